@@ -0,0 +1,92 @@
+//! Decode time of a 200-variant unit enum: the derived `match` against a lookup table of
+//! the variants indexed by tag, the jump table a derive could generate instead.
+//!
+//! Run with `cargo bench -p embedded-serialize-derive --bench enum_decode`. On x86-64 the
+//! two take about the same time per decode, as LLVM already lowers the contiguous `match`
+//! to a bounds check and a table lookup, so the derive keeps the `match`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use embedded_serialize::{Deserialize, DeserializeError};
+use embedded_serialize_derive::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+enum Wide {
+    V0, V1, V2, V3, V4, V5, V6, V7, V8, V9,
+    V10, V11, V12, V13, V14, V15, V16, V17, V18, V19,
+    V20, V21, V22, V23, V24, V25, V26, V27, V28, V29,
+    V30, V31, V32, V33, V34, V35, V36, V37, V38, V39,
+    V40, V41, V42, V43, V44, V45, V46, V47, V48, V49,
+    V50, V51, V52, V53, V54, V55, V56, V57, V58, V59,
+    V60, V61, V62, V63, V64, V65, V66, V67, V68, V69,
+    V70, V71, V72, V73, V74, V75, V76, V77, V78, V79,
+    V80, V81, V82, V83, V84, V85, V86, V87, V88, V89,
+    V90, V91, V92, V93, V94, V95, V96, V97, V98, V99,
+    V100, V101, V102, V103, V104, V105, V106, V107, V108, V109,
+    V110, V111, V112, V113, V114, V115, V116, V117, V118, V119,
+    V120, V121, V122, V123, V124, V125, V126, V127, V128, V129,
+    V130, V131, V132, V133, V134, V135, V136, V137, V138, V139,
+    V140, V141, V142, V143, V144, V145, V146, V147, V148, V149,
+    V150, V151, V152, V153, V154, V155, V156, V157, V158, V159,
+    V160, V161, V162, V163, V164, V165, V166, V167, V168, V169,
+    V170, V171, V172, V173, V174, V175, V176, V177, V178, V179,
+    V180, V181, V182, V183, V184, V185, V186, V187, V188, V189,
+    V190, V191, V192, V193, V194, V195, V196, V197, V198, V199,
+}
+
+const DECODES: usize = 20_000_000;
+
+/// The variants in tag order
+fn table() -> [Wide; 200] {
+    let mut table = [Wide::V0; 200];
+    for (tag, slot) in table.iter_mut().enumerate() {
+        *slot = Wide::deserialize(&[tag as u8]).unwrap();
+    }
+    table
+}
+
+fn decode_table(table: &[Wide; 200], buf: &[u8]) -> Result<Wide, DeserializeError> {
+    let tag = *buf.first().ok_or(DeserializeError::BufferTooSmall)?;
+    table
+        .get(tag as usize)
+        .copied()
+        .ok_or(DeserializeError::unknown_discriminant("Wide", tag as u32))
+}
+
+/// Tags from a xorshift generator, a few of them out of range.
+fn tags() -> Vec<u8> {
+    let mut state = 0x2545_F491u32;
+    (0..4096)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % 210) as u8
+        })
+        .collect()
+}
+
+fn time(name: &str, mut decode: impl FnMut(&[u8]) -> Result<Wide, DeserializeError>) -> Duration {
+    let tags = tags();
+    let start = Instant::now();
+    let mut valid = 0usize;
+    for index in 0..DECODES {
+        let tag = &tags[index % tags.len()..][..1];
+        valid += decode(black_box(tag)).is_ok() as usize;
+    }
+    let elapsed = start.elapsed();
+    black_box(valid);
+    println!("{name:>12}: {:.2} ns per decode", elapsed.as_nanos() as f64 / DECODES as f64);
+    elapsed
+}
+
+fn main() {
+    let table = table();
+    for tag in 0..=255u8 {
+        let matched = Wide::deserialize(&[tag]).ok();
+        assert_eq!(matched, decode_table(&table, &[tag]).ok());
+    }
+    time("match", Wide::deserialize);
+    time("jump table", |buf| decode_table(&table, buf));
+}
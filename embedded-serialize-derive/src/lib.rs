@@ -1,11 +1,2192 @@
 use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
 use quote::quote;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
 
+/// How a field that is not on the wire gets its value
+enum FieldDefault {
+    /// `Default::default()`
+    Trait,
+    /// A user function called with no arguments
+    Path(syn::ExprPath),
+}
+
+/// Conversions between a field and the type written in its place
+struct FieldMap {
+    /// `fn(&Field) -> Wire`
+    serialize: syn::ExprPath,
+    /// `fn(Wire) -> Result<Field, DeserializeError>`
+    deserialize: syn::ExprPath,
+}
+
+/// Containers a `count` field can size
+enum Counted<'a> {
+    /// `[T; N]`, of which the first `count` elements are on the wire
+    Array(&'a syn::TypeArray),
+    /// `heapless::Vec<T, N>`, holding exactly `count` elements
+    Vec(&'a syn::Type, &'a syn::GenericArgument),
+    /// `&[u8]`, borrowed from the buffer when decoding
+    Bytes,
+}
+
+impl<'a> Counted<'a> {
+    fn of(ty: &'a syn::Type) -> Option<Self> {
+        match ty {
+            syn::Type::Array(array) => Some(Counted::Array(array)),
+            syn::Type::Reference(reference) => match &*reference.elem {
+                syn::Type::Slice(slice) if is_ident_type(&slice.elem, &["u8"]) => Some(Counted::Bytes),
+                _ => None,
+            },
+            syn::Type::Path(path) => {
+                let segment = path.path.segments.last()?;
+                match &segment.arguments {
+                    // A named capacity parses as a type argument.
+                    syn::PathArguments::AngleBracketed(args) if segment.ident == "Vec" && args.args.len() == 2 => {
+                        match (&args.args[0], &args.args[1]) {
+                            (syn::GenericArgument::Type(item), cap) => Some(Counted::Vec(item, cap)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The element type, which a byte slice does not spell out
+    fn item(&self) -> Option<&'a syn::Type> {
+        match self {
+            Counted::Array(array) => Some(&array.elem),
+            Counted::Vec(item, _) => Some(item),
+            Counted::Bytes => None,
+        }
+    }
+
+    /// Expression for the largest count, which a byte slice does not have
+    fn max(&self) -> Option<TokenStream2> {
+        match self {
+            Counted::Array(array) => {
+                let len = &array.len;
+                Some(quote! { #len })
+            }
+            Counted::Vec(_, cap) => Some(quote! { #cap }),
+            Counted::Bytes => None,
+        }
+    }
+}
+
+/// Whether `ty` is a bare path naming one of `names`
+fn is_ident_type(ty: &syn::Type, names: &[&str]) -> bool {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .get_ident()
+            .is_some_and(|ident| names.iter().any(|name| ident == name)),
+        _ => false,
+    }
+}
+
+/// Field options given through `#[es(...)]`
+#[derive(Default)]
+struct FieldAttrs {
+    /// Name of an earlier field holding the element count of this field
+    count: Option<syn::Ident>,
+    /// `no_prefix` was given, spelling out that a counted field has no length prefix
+    no_prefix: bool,
+    /// Leave the field off the wire
+    skip: bool,
+    /// Value used when the field is not on the wire
+    default: Option<FieldDefault>,
+    /// Entry tag of the field in a TLV container
+    tag: Option<u8>,
+    /// Largest `len()` the field may have on the wire
+    max_len: Option<syn::LitInt>,
+    /// Value standing for `None` in an `Option` field written with `option::Sentinel`
+    sentinel: Option<syn::LitInt>,
+    /// Decode the field with the container's context through `DeserializeWith`
+    with_context: bool,
+    /// Write the field as another type, converting at the boundary
+    map: Option<FieldMap>,
+    /// `fn(&Self) -> bool` leaving the field off the wire when it returns `true`
+    skip_if: Option<syn::ExprPath>,
+    /// `fn(&[u8]) -> Field` computing the field from the bytes written before it
+    crc: Option<syn::ExprPath>,
+    /// Name of an earlier field whose number of set bits is written in place of this one
+    popcount_of: Option<syn::Ident>,
+}
+
+/// Container options given through `#[es(...)]`
+#[derive(Default)]
+struct ContainerAttrs {
+    /// Encode every field as a TLV entry
+    tlv: bool,
+    /// Generate `write_csv` for a comma-separated text dump
+    text_dump: bool,
+    /// Write the `bool` fields as one bitfield at the position of the first of them
+    pack_bools: bool,
+    /// Context type for fields marked `with_context`; deserialization implements
+    /// `DeserializeWith<context>` instead of `Deserialize`
+    context: Option<syn::Type>,
+    /// Generate a `<Name>View` that decodes fields on demand from a borrowed buffer
+    view: bool,
+    /// Write a `u16` payload length after each enum variant's tag
+    length_prefixed: bool,
+    /// Pad after an enum variant's tag so that its fields start at a multiple of this
+    payload_align: Option<usize>,
+    /// Generate `write_kv` and `parse_kv` for the key-value text form of an enum
+    text_kv: bool,
+    /// Bytes written before everything else, checked and consumed when decoding
+    magic: Vec<u8>,
+}
+
+/// One item of a container's `#[es(...)]`
+enum ContainerArg {
+    /// `magic = [...]`, which is not a meta item
+    Magic(syn::ExprArray),
+    Meta(NestedMeta),
+}
+
+impl syn::parse::Parse for ContainerArg {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::Ident) && input.peek2(syn::Token![=]) && input.peek3(syn::token::Bracket) {
+            let ident: syn::Ident = input.parse()?;
+            if ident != "magic" {
+                return Err(syn::Error::new_spanned(ident, "unknown es attribute"));
+            }
+            input.parse::<syn::Token![=]>()?;
+            return Ok(ContainerArg::Magic(input.parse()?));
+        }
+        input.parse().map(ContainerArg::Meta)
+    }
+}
+
+/// Parses the bytes of `magic = [...]`.
+fn parse_magic(array: &syn::ExprArray) -> syn::Result<Vec<u8>> {
+    if array.elems.is_empty() || array.elems.len() > MAX_MAGIC_LEN {
+        return Err(syn::Error::new_spanned(array, "magic must be 1 to 4 bytes"));
+    }
+    array
+        .elems
+        .iter()
+        .map(|elem| match elem {
+            syn::Expr::Lit(syn::ExprLit { lit: Lit::Int(lit), .. }) => lit.base10_parse(),
+            elem => Err(syn::Error::new_spanned(elem, "expected a byte literal")),
+        })
+        .collect()
+}
+
+/// Longest magic, matching `embedded_serialize::magic::MAX_MAGIC_LEN`
+const MAX_MAGIC_LEN: usize = 4;
+
+fn parse_container_attrs(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut attrs = ContainerAttrs::default();
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("es")) {
+        let args = attr
+            .parse_args_with(syn::punctuated::Punctuated::<ContainerArg, syn::Token![,]>::parse_terminated)?;
+        for arg in args.iter() {
+            let nested = match arg {
+                ContainerArg::Magic(array) => {
+                    attrs.magic = parse_magic(array)?;
+                    continue;
+                }
+                ContainerArg::Meta(nested) => nested,
+            };
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("tlv") => attrs.tlv = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("text_dump") => attrs.text_dump = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("pack_bools") => attrs.pack_bools = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("view") => attrs.view = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("text_kv") => attrs.text_kv = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("length_prefixed") => {
+                    attrs.length_prefixed = true
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("payload_align") => match &nv.lit {
+                    Lit::Int(lit) if lit.base10_parse::<usize>()? > 0 => attrs.payload_align = Some(lit.base10_parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a positive alignment")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("context") => match &nv.lit {
+                    Lit::Str(lit) => attrs.context = Some(lit.parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a type string")),
+                },
+                _ => return Err(syn::Error::new_spanned(nested, "unknown es attribute")),
+            }
+        }
+    }
+    if attrs.tlv && attrs.pack_bools {
+        return Err(syn::Error::new_spanned(&input.ident, "pack_bools is not supported in tlv containers"));
+    }
+    if let (true, Some(context)) = (attrs.tlv, &attrs.context) {
+        return Err(syn::Error::new_spanned(context, "context is not supported in tlv containers"));
+    }
+    if (attrs.length_prefixed || attrs.payload_align.is_some() || attrs.text_kv) && !matches!(input.data, Data::Enum(_)) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "length_prefixed, payload_align and text_kv are only supported on enums",
+        ));
+    }
+    if attrs.length_prefixed && attrs.payload_align.is_some() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "payload_align is not supported on length-prefixed enums",
+        ));
+    }
+    Ok(attrs)
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    let mut option = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("es")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[es(...)]")),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("no_prefix") => attrs.no_prefix = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => attrs.skip = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("with_context") => attrs.with_context = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+                    attrs.default = Some(FieldDefault::Trait)
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("count") => match &nv.lit {
+                    Lit::Str(lit) => attrs.count = Some(lit.parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a field name string")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("tag") => match &nv.lit {
+                    Lit::Int(lit) => attrs.tag = Some(lit.base10_parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a u8 tag")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("max_len") => match &nv.lit {
+                    Lit::Int(lit) => attrs.max_len = Some(lit.clone()),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a length")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("option") => match &nv.lit {
+                    Lit::Str(lit) if lit.value() == "presence" || lit.value() == "sentinel" => {
+                        option = Some(lit.clone())
+                    }
+                    lit => return Err(syn::Error::new_spanned(lit, "expected \"presence\" or \"sentinel\"")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("sentinel") => match &nv.lit {
+                    Lit::Int(lit) => attrs.sentinel = Some(lit.clone()),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected an integer sentinel")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("default") => match &nv.lit {
+                    Lit::Str(lit) => attrs.default = Some(FieldDefault::Path(lit.parse()?)),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a function path string")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("skip_if") => match &nv.lit {
+                    Lit::Str(lit) => attrs.skip_if = Some(lit.parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a function path string")),
+                },
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("map") => attrs.map = Some(parse_field_map(list)?),
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("popcount_of") => match &nv.lit {
+                    Lit::Str(lit) => attrs.popcount_of = Some(lit.parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a field name string")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("crc") => match &nv.lit {
+                    Lit::Str(lit) => attrs.crc = Some(parse_checksum(lit)?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected a checksum name or function path string")),
+                },
+                _ => return Err(syn::Error::new_spanned(nested, "unknown es attribute")),
+            }
+        }
+    }
+    if attrs.no_prefix && attrs.count.is_none() {
+        return Err(syn::Error::new_spanned(field, "no_prefix needs a count naming the element count"));
+    }
+    if attrs.count.is_some() {
+        if field.ident.is_none() {
+            return Err(syn::Error::new_spanned(field, "count is only supported on named fields"));
+        }
+        if Counted::of(&field.ty).is_none() {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "count is only supported on array, heapless::Vec and &[u8] fields",
+            ));
+        }
+    }
+    match (&option, &attrs.sentinel) {
+        (Some(option), None) if option.value() == "sentinel" => {
+            return Err(syn::Error::new_spanned(option, "option = \"sentinel\" needs a sentinel value"));
+        }
+        (Some(option), Some(_)) if option.value() == "presence" => {
+            return Err(syn::Error::new_spanned(option, "a sentinel needs option = \"sentinel\""));
+        }
+        (None, Some(sentinel)) => {
+            return Err(syn::Error::new_spanned(sentinel, "a sentinel needs option = \"sentinel\""));
+        }
+        _ => {}
+    }
+    if attrs.sentinel.is_some() && attrs.count.is_some() {
+        return Err(syn::Error::new_spanned(field, "a counted field cannot have a sentinel"));
+    }
+    if attrs.skip && attrs.count.is_some() {
+        return Err(syn::Error::new_spanned(field, "a skipped field cannot have a count"));
+    }
+    if attrs.skip && attrs.skip_if.is_some() {
+        return Err(syn::Error::new_spanned(field, "skip and skip_if cannot be combined"));
+    }
+    if attrs.with_context && (attrs.skip || attrs.count.is_some() || attrs.sentinel.is_some()) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "with_context cannot be combined with skip, count or sentinel",
+        ));
+    }
+    if attrs.map.is_some() && (attrs.skip || attrs.count.is_some() || attrs.sentinel.is_some() || attrs.with_context) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "map cannot be combined with skip, count, sentinel or with_context",
+        ));
+    }
+    if attrs.crc.is_some()
+        && (attrs.skip
+            || attrs.count.is_some()
+            || attrs.sentinel.is_some()
+            || attrs.with_context
+            || attrs.map.is_some()
+            || attrs.skip_if.is_some())
+    {
+        return Err(syn::Error::new_spanned(
+            field,
+            "crc cannot be combined with skip, count, sentinel, with_context, map or skip_if",
+        ));
+    }
+    if attrs.popcount_of.is_some() {
+        if !is_ident_type(&field.ty, &["u8"]) {
+            return Err(syn::Error::new_spanned(&field.ty, "popcount_of is only supported on u8 fields"));
+        }
+        if attrs.skip
+            || attrs.count.is_some()
+            || attrs.sentinel.is_some()
+            || attrs.with_context
+            || attrs.map.is_some()
+            || attrs.skip_if.is_some()
+            || attrs.crc.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "popcount_of cannot be combined with skip, count, sentinel, with_context, map, skip_if or crc",
+            ));
+        }
+    }
+    Ok(attrs)
+}
+
+/// Resolves the checksum of `crc = "..."`: a built-in algorithm name or a function path.
+fn parse_checksum(lit: &syn::LitStr) -> syn::Result<syn::ExprPath> {
+    let path = match lit.value().as_str() {
+        "crc16_ccitt" => "embedded_serialize::crc::crc16",
+        "crc8" => "embedded_serialize::crc::crc8",
+        "fletcher16" => "embedded_serialize::fletcher::fletcher16",
+        _ => return lit.parse(),
+    };
+    syn::LitStr::new(path, lit.span()).parse()
+}
+
+/// Parses `map(serialize = "path", deserialize = "path")`.
+fn parse_field_map(list: &syn::MetaList) -> syn::Result<FieldMap> {
+    let (mut serialize, mut deserialize) = (None, None);
+    for nested in list.nested.iter() {
+        let (slot, lit) = match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("serialize") => (&mut serialize, &nv.lit),
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("deserialize") => (&mut deserialize, &nv.lit),
+            _ => return Err(syn::Error::new_spanned(nested, "expected serialize or deserialize")),
+        };
+        match lit {
+            Lit::Str(lit) => *slot = Some(lit.parse()?),
+            lit => return Err(syn::Error::new_spanned(lit, "expected a function path string")),
+        }
+    }
+    match (serialize, deserialize) {
+        (Some(serialize), Some(deserialize)) => Ok(FieldMap { serialize, deserialize }),
+        _ => Err(syn::Error::new_spanned(list, "map needs both serialize and deserialize")),
+    }
+}
+
+/// A struct field together with the names the generated code uses for it
+struct FieldInfo<'a> {
+    field: &'a syn::Field,
+    /// Position of the field in declaration order
+    index: usize,
+    /// `self.#member` accesses the field
+    member: syn::Member,
+    /// Local variable holding the field while deserializing
+    binding: syn::Ident,
+    attrs: FieldAttrs,
+    /// The field belongs to an enum variant and is bound by reference in a match arm
+    in_variant: bool,
+    /// The field's `#[cfg(...)]` attributes, repeated on all generated code touching it
+    cfgs: Vec<syn::Attribute>,
+}
+
+impl FieldInfo<'_> {
+    fn cfg(&self) -> TokenStream2 {
+        let cfgs = &self.cfgs;
+        quote! { #(#cfgs)* }
+    }
+
+    /// Expression for the field's value while serializing
+    fn value(&self) -> TokenStream2 {
+        if self.in_variant {
+            let binding = &self.binding;
+            quote! { (*#binding) }
+        } else {
+            let member = &self.member;
+            quote! { self.#member }
+        }
+    }
+
+    /// Expression for the value of the field when it is not on the wire
+    fn default_value(&self) -> TokenStream2 {
+        match &self.attrs.default {
+            Some(FieldDefault::Path(path)) => quote! { #path() },
+            Some(FieldDefault::Trait) | None => quote! { Default::default() },
+        }
+    }
+
+    /// Expression for the value of the earlier field `name` while serializing
+    fn sibling_value(&self, name: &syn::Ident) -> TokenStream2 {
+        if self.in_variant {
+            quote! { (*#name) }
+        } else {
+            quote! { self.#name }
+        }
+    }
+}
+
+fn field_infos(fields: &Fields, in_variant: bool) -> syn::Result<Vec<FieldInfo<'_>>> {
+    let mut infos: Vec<FieldInfo> = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let (member, binding) = match &field.ident {
+            Some(ident) => (syn::Member::Named(ident.clone()), ident.clone()),
+            None => (
+                syn::Member::Unnamed(syn::Index::from(index)),
+                syn::Ident::new(&format!("field_{}", index), field.span()),
+            ),
+        };
+        let attrs = parse_field_attrs(field)?;
+        let cfgs: Vec<syn::Attribute> = field.attrs.iter().filter(|attr| attr.path.is_ident("cfg")).cloned().collect();
+        if !cfgs.is_empty() && field.ident.is_none() {
+            return Err(syn::Error::new_spanned(&cfgs[0], "cfg is only supported on named fields"));
+        }
+        if let Some(count) = &attrs.count {
+            match infos.iter().find(|earlier| earlier.field.ident.as_ref() == Some(count)) {
+                None => return Err(syn::Error::new_spanned(count, "count must name an earlier field")),
+                Some(earlier) if earlier.attrs.skip_if.is_some() => {
+                    return Err(syn::Error::new_spanned(count, "count cannot name a skip_if field"))
+                }
+                Some(earlier) if !is_ident_type(&earlier.field.ty, &["u8", "u16", "u32", "u64", "usize"]) => {
+                    return Err(syn::Error::new_spanned(count, "count must name an unsigned integer field"))
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(source) = &attrs.popcount_of {
+            if !infos.iter().any(|earlier| earlier.field.ident.as_ref() == Some(source)) {
+                return Err(syn::Error::new_spanned(source, "popcount_of must name an earlier field"));
+            }
+        }
+        if let Some(crc) = &attrs.crc {
+            if in_variant {
+                return Err(syn::Error::new_spanned(crc, "crc is only supported on struct fields"));
+            }
+            if index + 1 != fields.len() {
+                return Err(syn::Error::new_spanned(crc, "crc is only supported on the last field"));
+            }
+            if !cfgs.is_empty() {
+                return Err(syn::Error::new_spanned(&cfgs[0], "cfg is not supported on crc fields"));
+            }
+        }
+        if let Some(skip_if) = &attrs.skip_if {
+            if in_variant {
+                return Err(syn::Error::new_spanned(skip_if, "skip_if is only supported on struct fields"));
+            }
+            if !cfgs.is_empty() {
+                return Err(syn::Error::new_spanned(&cfgs[0], "cfg is not supported on skip_if fields"));
+            }
+        }
+        infos.push(FieldInfo {
+            field,
+            index,
+            member,
+            binding,
+            attrs,
+            in_variant,
+            cfgs,
+        });
+    }
+    Ok(infos)
+}
+
+/// Rejects `cfg` fields in containers whose layout does not allow leaving fields out.
+fn check_cfg_fields(container: &ContainerAttrs, infos: &[FieldInfo]) -> syn::Result<()> {
+    match infos.iter().find(|info| !info.cfgs.is_empty()) {
+        Some(info) if container.tlv || container.pack_bools => Err(syn::Error::new_spanned(
+            &info.cfgs[0],
+            "cfg fields are not supported in tlv or pack_bools containers",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Expression for the sum of `terms`, each paired with the `cfg` attributes it is counted under.
+fn cfg_sum(terms: Vec<(TokenStream2, TokenStream2)>) -> TokenStream2 {
+    if terms.iter().all(|(cfg, _)| cfg.is_empty()) {
+        let terms = terms.iter().map(|(_, term)| term);
+        return quote! { 0 #(+ #terms)* };
+    }
+    let (cfgs, terms): (Vec<_>, Vec<_>) = terms.into_iter().unzip();
+    quote! {
+        {
+            let mut total = 0usize;
+            #(#cfgs { total += #terms; })*
+            total
+        }
+    }
+}
+
+/// The serialized `bool` fields of a `pack_bools` container, in declaration order.
+/// Bool `i` is bit `i % 8` (least significant first) of byte `i / 8` of the bitfield.
+fn packed_bools<'a, 'f>(container: &ContainerAttrs, infos: &'a [FieldInfo<'f>]) -> Vec<&'a FieldInfo<'f>> {
+    if !container.pack_bools {
+        return Vec::new();
+    }
+    infos
+        .iter()
+        .filter(|info| !info.attrs.skip && info.attrs.skip_if.is_none())
+        .filter(|info| matches!(&info.field.ty, syn::Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident("bool")))
+        .collect()
+}
+
+/// Sets the bits of the packed bools in the zeroed bitfield `packed`.
+fn packed_bool_sets(bools: &[&FieldInfo]) -> TokenStream2 {
+    let sets = bools.iter().enumerate().map(|(index, info)| {
+        let value = info.value();
+        let (byte, bit) = (index / 8, (index % 8) as u8);
+        quote! {
+            if #value {
+                packed[#byte] |= 1 << #bit;
+            }
+        }
+    });
+    quote! { #(#sets)* }
+}
+
+/// Writes the packed bools as a bitfield at `buf[offset..]` and advances `offset`.
+fn serialize_packed_bools(bools: &[&FieldInfo]) -> TokenStream2 {
+    let len = bools.len().div_ceil(8);
+    let sets = packed_bool_sets(bools);
+    quote! {
+        let packed = buf
+            .get_mut(offset..offset + #len)
+            .ok_or(embedded_serialize::SerializeError::BufferTooSmall)?;
+        packed.fill(0);
+        #sets
+        offset += #len;
+    }
+}
+
+/// Reads the packed bools' bitfield at `buf[offset..]` into their bindings and advances `offset`.
+/// Bits past the last bool must be clear.
+fn deserialize_packed_bools(bools: &[&FieldInfo]) -> TokenStream2 {
+    let len = bools.len().div_ceil(8);
+    let unused = match bools.len() % 8 {
+        0 => quote! {},
+        used => {
+            let last = len - 1;
+            let used = used as u8;
+            quote! {
+                if packed[#last] >> #used != 0 {
+                    return Err(embedded_serialize::DeserializeError::InvalidData);
+                }
+            }
+        }
+    };
+    let gets = bools.iter().enumerate().map(|(index, info)| {
+        let binding = &info.binding;
+        let (byte, bit) = (index / 8, (index % 8) as u8);
+        quote! {
+            let #binding = packed[#byte] & (1 << #bit) != 0;
+        }
+    });
+    quote! {
+        let packed = buf
+            .get(offset..offset + #len)
+            .ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+        #unused
+        #(#gets)*
+        offset += #len;
+    }
+}
+
+/// The `skip_if` fields of a struct, in declaration order.
+/// Field `i` is present when bit `i % 8` (least significant first) of byte `i / 8` of the
+/// presence bitmap at the start of the struct is set.
+fn conditional_fields<'a, 'f>(infos: &'a [FieldInfo<'f>]) -> Vec<&'a FieldInfo<'f>> {
+    infos.iter().filter(|info| info.attrs.skip_if.is_some()).collect()
+}
+
+/// Sets the bits of the conditional fields present in the zeroed bitmap `presence`.
+fn presence_sets(conditional: &[&FieldInfo]) -> TokenStream2 {
+    let sets = conditional.iter().enumerate().map(|(index, info)| {
+        let skip_if = &info.attrs.skip_if;
+        let (byte, bit) = (index / 8, (index % 8) as u8);
+        quote! {
+            if !#skip_if(self) {
+                presence[#byte] |= 1 << #bit;
+            }
+        }
+    });
+    quote! { #(#sets)* }
+}
+
+/// Writes the presence bitmap of the conditional fields at `buf[offset..]` and advances `offset`.
+fn serialize_presence(conditional: &[&FieldInfo]) -> TokenStream2 {
+    let len = conditional.len().div_ceil(8);
+    let sets = presence_sets(conditional);
+    quote! {
+        let presence = buf
+            .get_mut(offset..offset + #len)
+            .ok_or(embedded_serialize::SerializeError::BufferTooSmall)?;
+        presence.fill(0);
+        #sets
+        offset += #len;
+    }
+}
+
+/// Checks the presence bitmap at `buf[offset..]`, binds its position to `presence` and
+/// advances `offset` past it. Bits past the last conditional field must be clear.
+fn deserialize_presence(conditional: &[&FieldInfo]) -> TokenStream2 {
+    let len = conditional.len().div_ceil(8);
+    let unused = match conditional.len() % 8 {
+        0 => quote! {},
+        used => {
+            let last = len - 1;
+            let used = used as u8;
+            quote! {
+                if buf[presence + #last] >> #used != 0 {
+                    return Err(embedded_serialize::DeserializeError::InvalidData);
+                }
+            }
+        }
+    };
+    quote! {
+        let presence = offset;
+        if buf.len() < presence + #len {
+            return Err(embedded_serialize::DeserializeError::BufferTooSmall);
+        }
+        #unused
+        offset += #len;
+    }
+}
+
+/// One block per serialized field, each writing it at `buf[offset..]` and advancing `offset`,
+/// together with the `cfg` attributes it runs under.
+/// Packed bools share a single block at the position of the first of them, and the
+/// presence bitmap of `skip_if` fields comes first.
+fn serialize_field_stmts(container: &ContainerAttrs, infos: &[FieldInfo]) -> Vec<(TokenStream2, TokenStream2)> {
+    let bools = packed_bools(container, infos);
+    let conditional = conditional_fields(infos);
+    let mut stmts = Vec::new();
+    if !container.magic.is_empty() {
+        let magic = &container.magic;
+        stmts.push((
+            quote! {},
+            quote! {
+                {
+                    offset += embedded_serialize::magic::write_magic(&[#(#magic),*], &mut buf[offset..])?;
+                }
+            },
+        ));
+    }
+    if container.tlv {
+        let len = tlv_entries_len(infos);
+        stmts.push((
+            quote! {},
+            quote! {
+                {
+                    let len = u16::try_from(#len).map_err(|_| embedded_serialize::SerializeError::InvalidData)?;
+                    offset += embedded_serialize::Serialize::serialize(&len, &mut buf[offset..])?;
+                }
+            },
+        ));
+    }
+    if !conditional.is_empty() {
+        let stmt = serialize_presence(&conditional);
+        stmts.push((quote! {}, quote! { { #stmt } }));
+    }
+    for info in infos.iter() {
+        if info.attrs.skip {
+            continue;
+        }
+        if let Some(first) = bools.first() {
+            if core::ptr::eq(*first, info) {
+                let stmt = serialize_packed_bools(&bools);
+                stmts.push((quote! {}, quote! { { #stmt } }));
+            }
+            if bools.iter().any(|packed| core::ptr::eq(*packed, info)) {
+                continue;
+            }
+        }
+        let value = info.value();
+        let check = match &info.attrs.max_len {
+            Some(max_len) => quote! {
+                if #value.len() > #max_len {
+                    return Err(embedded_serialize::SerializeError::InvalidData);
+                }
+            },
+            None => quote! {},
+        };
+        let stmt = serialize_field_stmt(container, info, stmts.len());
+        let stmt = match &info.attrs.skip_if {
+            Some(skip_if) => quote! {
+                if !#skip_if(self) {
+                    #check
+                    #stmt
+                }
+            },
+            None => quote! {
+                {
+                    #check
+                    #stmt
+                }
+            },
+        };
+        stmts.push((info.cfg(), stmt));
+    }
+    stmts
+}
+
+/// Writes one field at `buf[offset..]` and advances `offset`.
+/// `index` is the position of the field among the serialized ones, for overrun errors.
+fn serialize_field_stmt(container: &ContainerAttrs, info: &FieldInfo, index: usize) -> TokenStream2 {
+    let value = info.value();
+    if container.tlv {
+        let tag = info.attrs.tag;
+        return quote! {
+            let mut writer = embedded_serialize::tlv::TlvWriter::new(&mut buf[offset..]);
+            writer.push_value(#tag, &#value)?;
+            offset += writer.len();
+        };
+    }
+    if let Some(count) = &info.attrs.count {
+        let count = info.sibling_value(count);
+        let items = match Counted::of(&info.field.ty) {
+            Some(Counted::Array(_)) => quote! {
+                #value
+                    .get(..#count as usize)
+                    .ok_or(embedded_serialize::SerializeError::InvalidData)?
+            },
+            _ => quote! {
+                match &#value[..] {
+                    items if items.len() == #count as usize => items,
+                    _ => return Err(embedded_serialize::SerializeError::InvalidData),
+                }
+            },
+        };
+        return quote! {
+            let items = #items;
+            offset += embedded_serialize::serialize_raw(items, &mut buf[offset..])?;
+        };
+    }
+    if let Some(sentinel) = &info.attrs.sentinel {
+        return quote! {
+            offset += <embedded_serialize::option::Sentinel<#sentinel> as embedded_serialize::option::OptionEncoding<_>>::serialize_option(
+                &#value,
+                &mut buf[offset..],
+            )?;
+        };
+    }
+    if let Some(source) = &info.attrs.popcount_of {
+        let source = info.sibling_value(source);
+        return quote! {
+            let popcount = #source.count_ones() as u8;
+            let size = embedded_serialize::serialize_guarded(&popcount, &mut buf[offset..], #index)?;
+            offset += size;
+        };
+    }
+    if let Some(crc) = &info.attrs.crc {
+        let field_type = &info.field.ty;
+        return quote! {
+            let checksum: #field_type = #crc(&buf[..offset]);
+            let size = embedded_serialize::serialize_guarded(&checksum, &mut buf[offset..], #index)?;
+            offset += size;
+        };
+    }
+    if let Some(map) = &info.attrs.map {
+        let serialize = &map.serialize;
+        return quote! {
+            let wire = #serialize(&#value);
+            let size = embedded_serialize::serialize_guarded(&wire, &mut buf[offset..], #index)?;
+            offset += size;
+        };
+    }
+    quote! {
+        let size = embedded_serialize::serialize_guarded(&#value, &mut buf[offset..], #index)?;
+        offset += size;
+    }
+}
+
+/// One block per serialized field, each appending it to the `UninitWriter` `writer`,
+/// together with the `cfg` attributes it runs under. The layout is that of
+/// `serialize_field_stmts`.
+fn serialize_uninit_field_stmts(container: &ContainerAttrs, infos: &[FieldInfo]) -> Vec<(TokenStream2, TokenStream2)> {
+    let bools = packed_bools(container, infos);
+    let conditional = conditional_fields(infos);
+    let mut stmts = Vec::new();
+    if !container.magic.is_empty() {
+        let magic = &container.magic;
+        let len = magic.len();
+        stmts.push((
+            quote! {},
+            quote! {
+                writer.write_zeroed(#len, |bytes| embedded_serialize::magic::write_magic(&[#(#magic),*], bytes))?;
+            },
+        ));
+    }
+    if container.tlv {
+        let len = tlv_entries_len(infos);
+        stmts.push((
+            quote! {},
+            quote! {
+                let len = u16::try_from(#len).map_err(|_| embedded_serialize::SerializeError::InvalidData)?;
+                writer.write(&len)?;
+            },
+        ));
+    }
+    if !conditional.is_empty() {
+        let len = conditional.len().div_ceil(8);
+        let sets = presence_sets(&conditional);
+        stmts.push((
+            quote! {},
+            quote! {
+                writer.write_zeroed(#len, |presence| {
+                    #sets
+                    Ok(#len)
+                })?;
+            },
+        ));
+    }
+    for info in infos.iter() {
+        if info.attrs.skip {
+            continue;
+        }
+        if let Some(first) = bools.first() {
+            if core::ptr::eq(*first, info) {
+                let len = bools.len().div_ceil(8);
+                let sets = packed_bool_sets(&bools);
+                stmts.push((
+                    quote! {},
+                    quote! {
+                        writer.write_zeroed(#len, |packed| {
+                            #sets
+                            Ok(#len)
+                        })?;
+                    },
+                ));
+            }
+            if bools.iter().any(|packed| core::ptr::eq(*packed, info)) {
+                continue;
+            }
+        }
+        let value = info.value();
+        let check = match &info.attrs.max_len {
+            Some(max_len) => quote! {
+                if #value.len() > #max_len {
+                    return Err(embedded_serialize::SerializeError::InvalidData);
+                }
+            },
+            None => quote! {},
+        };
+        let stmt = serialize_uninit_field_stmt(container, info);
+        let stmt = match &info.attrs.skip_if {
+            Some(skip_if) => quote! {
+                if !#skip_if(self) {
+                    #check
+                    #stmt
+                }
+            },
+            None => quote! {
+                {
+                    #check
+                    #stmt
+                }
+            },
+        };
+        stmts.push((info.cfg(), stmt));
+    }
+    stmts
+}
+
+/// Appends one field to the `UninitWriter` `writer`.
+fn serialize_uninit_field_stmt(container: &ContainerAttrs, info: &FieldInfo) -> TokenStream2 {
+    let value = info.value();
+    if container.tlv {
+        // The entry encoder needs initialized bytes, so exactly those of the entry are zeroed.
+        let tag = info.attrs.tag;
+        return quote! {
+            let len = 2 + embedded_serialize::Serialize::serialized_size(&#value);
+            writer.write_zeroed(len, |bytes| {
+                let mut entries = embedded_serialize::tlv::TlvWriter::new(bytes);
+                entries.push_value(#tag, &#value)?;
+                Ok(entries.len())
+            })?;
+        };
+    }
+    if let Some(count) = &info.attrs.count {
+        let count = info.sibling_value(count);
+        let items = match Counted::of(&info.field.ty) {
+            Some(Counted::Array(_)) => quote! {
+                #value
+                    .get(..#count as usize)
+                    .ok_or(embedded_serialize::SerializeError::InvalidData)?
+            },
+            _ => quote! {
+                match &#value[..] {
+                    items if items.len() == #count as usize => items,
+                    _ => return Err(embedded_serialize::SerializeError::InvalidData),
+                }
+            },
+        };
+        return quote! {
+            let items = #items;
+            writer.write(items)?;
+        };
+    }
+    if let Some(sentinel) = &info.attrs.sentinel {
+        return quote! {
+            writer.write_with(|rest| {
+                <embedded_serialize::option::Sentinel<#sentinel> as embedded_serialize::option::OptionEncoding<_>>::serialize_option_uninit(
+                    &#value,
+                    rest,
+                )
+            })?;
+        };
+    }
+    if let Some(source) = &info.attrs.popcount_of {
+        let source = info.sibling_value(source);
+        return quote! {
+            let popcount = #source.count_ones() as u8;
+            writer.write(&popcount)?;
+        };
+    }
+    if let Some(crc) = &info.attrs.crc {
+        let field_type = &info.field.ty;
+        return quote! {
+            let checksum: #field_type = #crc(writer.written());
+            writer.write(&checksum)?;
+        };
+    }
+    if let Some(map) = &info.attrs.map {
+        let serialize = &map.serialize;
+        return quote! {
+            let wire = #serialize(&#value);
+            writer.write(&wire)?;
+        };
+    }
+    quote! {
+        writer.write(&#value)?;
+    }
+}
+
+/// `Serialize::serialize_uninit`, appending the blocks of `serialize_uninit_field_stmts`
+/// or the arms of `enum_serialize_uninit_body` to an `UninitWriter` so that only the
+/// bytes written are initialized.
+fn serialize_uninit_fn(body: TokenStream2) -> TokenStream2 {
+    quote! {
+        fn serialize_uninit<'__buf>(
+            &self,
+            buf: &'__buf mut [embedded_serialize::core::mem::MaybeUninit<u8>],
+        ) -> Result<&'__buf mut [u8], embedded_serialize::SerializeError> {
+            let mut writer = embedded_serialize::writer::UninitWriter::new(buf);
+            #body
+        }
+    }
+}
+
+/// Expression for the number of bytes the entries of a TLV container serialize to, after
+/// its `u16` length.
+fn tlv_entries_len(infos: &[FieldInfo]) -> TokenStream2 {
+    let entries = infos.iter().filter(|info| !info.attrs.skip).map(|info| {
+        let value = info.value();
+        quote! { 2 + embedded_serialize::Serialize::serialized_size(&#value) }
+    });
+    quote! { (0usize #(+ #entries)*) }
+}
+
+/// Expression for the number of bytes the fields serialize to.
+fn fields_size(container: &ContainerAttrs, infos: &[FieldInfo]) -> TokenStream2 {
+    if container.tlv {
+        let header = container.magic.len() + 2;
+        let len = tlv_entries_len(infos);
+        return quote! { #header + #len };
+    }
+    let bools = packed_bools(container, infos);
+    let packed = container.magic.len() + bools.len().div_ceil(8) + conditional_fields(infos).len().div_ceil(8);
+    let sizes = infos
+        .iter()
+        .filter(|info| !info.attrs.skip)
+        .filter(|info| !bools.iter().any(|packed| core::ptr::eq(*packed, *info)))
+        .map(|info| {
+            let value = info.value();
+            let size = match &info.attrs.count {
+                None if info.attrs.map.is_some() => {
+                    let serialize = info.attrs.map.as_ref().map(|map| &map.serialize);
+                    quote! { embedded_serialize::Serialize::serialized_size(&#serialize(&#value)) }
+                }
+                Some(count) if matches!(Counted::of(&info.field.ty), Some(Counted::Array(_))) => {
+                    let count = info.sibling_value(count);
+                    quote! {
+                        #value
+                            .get(..#count as usize)
+                            .map_or(0, embedded_serialize::Serialize::serialized_size)
+                    }
+                }
+                Some(_) => quote! { embedded_serialize::Serialize::serialized_size(&#value[..]) },
+                None => match &info.attrs.sentinel {
+                    Some(sentinel) => quote! {
+                        <embedded_serialize::option::Sentinel<#sentinel> as embedded_serialize::option::OptionEncoding<_>>::serialized_size_option(&#value)
+                    },
+                    None => quote! { embedded_serialize::Serialize::serialized_size(&#value) },
+                },
+            };
+            let size = match &info.attrs.skip_if {
+                Some(skip_if) => quote! { if #skip_if(self) { 0 } else { #size } },
+                None => size,
+            };
+            (info.cfg(), size)
+        });
+    let terms = core::iter::once((quote! {}, quote! { #packed })).chain(sizes).collect();
+    cfg_sum(terms)
+}
+
+/// Serializes the fields back to back in declaration order.
+fn serialize_body(stmts: &[(TokenStream2, TokenStream2)]) -> TokenStream2 {
+    let (cfgs, stmts): (Vec<_>, Vec<_>) = stmts.iter().cloned().unzip();
+    quote! {
+        let mut offset = 0;
+        #(#cfgs #stmts)*
+        Ok(offset)
+    }
+}
+
+/// Implements `SerializeFields` so the value can be written one field at a time.
+fn serialize_fields_impl(
+    input: &DeriveInput,
+    generics: &syn::Generics,
+    stmts: &[(TokenStream2, TokenStream2)],
+) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let count = cfg_sum(stmts.iter().map(|(cfg, _)| (cfg.clone(), quote! { 1 })).collect());
+    let (cfgs, stmts): (Vec<_>, Vec<_>) = stmts.iter().cloned().unzip();
+    let indices = 0..stmts.len();
+    let body = if stmts.is_empty() {
+        quote! {
+            let _ = buf;
+            Err(embedded_serialize::SerializeError::InvalidData)
+        }
+    } else if cfgs.iter().all(|cfg| cfg.is_empty()) {
+        quote! {
+            let mut offset = 0;
+            match index {
+                #(#indices => #stmts)*
+                _ => return Err(embedded_serialize::SerializeError::InvalidData),
+            }
+            Ok(offset)
+        }
+    } else {
+        // Indices count only the fields compiled in, so they are found at run time.
+        quote! {
+            let mut offset = 0;
+            let mut remaining = index;
+            #(
+                #cfgs {
+                    if remaining == 0 {
+                        #stmts
+                        return Ok(offset);
+                    }
+                    remaining -= 1;
+                }
+            )*
+            let _ = (buf, &mut offset, remaining);
+            Err(embedded_serialize::SerializeError::InvalidData)
+        }
+    };
+    quote! {
+        impl #impl_generics embedded_serialize::resume::SerializeFields for #name #ty_generics #where_clause {
+            const FIELD_COUNT: usize = #count;
+
+            fn serialize_field(&self, index: usize, buf: &mut [u8]) -> Result<usize, embedded_serialize::SerializeError> {
+                #body
+            }
+        }
+    }
+}
+
+/// Rejects a decoded field longer than its `max_len`.
+fn max_len_check(info: &FieldInfo) -> TokenStream2 {
+    let binding = &info.binding;
+    match &info.attrs.max_len {
+        Some(max_len) => quote! {
+            if #binding.len() > #max_len {
+                return Err(embedded_serialize::DeserializeError::InvalidData);
+            }
+        },
+        None => quote! {},
+    }
+}
+
+/// Deserializes the fields back to back in declaration order.
+/// With a `lifetime`, fields are decoded through `DeserializeBorrowed` and may borrow from `buf`.
+fn deserialize_body(
+    container: &ContainerAttrs,
+    fields: &Fields,
+    infos: &[FieldInfo],
+    lifetime: Option<&syn::Lifetime>,
+) -> TokenStream2 {
+    let deserialize_fields = deserialize_field_stmts(container, infos, lifetime);
+    let construct = construct(quote!(Self), fields, infos);
+    quote! {
+        let mut offset = 0;
+        #deserialize_fields
+        Ok((#construct, offset))
+    }
+}
+
+/// Binds each field to a local, decoding the serialized ones at `buf[offset..]` and advancing `offset`.
+fn deserialize_field_stmts(
+    container: &ContainerAttrs,
+    infos: &[FieldInfo],
+    lifetime: Option<&syn::Lifetime>,
+) -> TokenStream2 {
+    let bools = packed_bools(container, infos);
+    let conditional = conditional_fields(infos);
+    let mut deserialize_fields = quote! {};
+    if !container.magic.is_empty() {
+        let magic = &container.magic;
+        deserialize_fields.extend(quote! {
+            offset += embedded_serialize::magic::check_magic(&[#(#magic),*], &buf[offset..])?;
+        });
+    }
+    if !conditional.is_empty() {
+        deserialize_fields.extend(deserialize_presence(&conditional));
+    }
+
+    for info in infos.iter() {
+        if let Some(first) = bools.first() {
+            if core::ptr::eq(*first, info) {
+                deserialize_fields.extend(deserialize_packed_bools(&bools));
+            }
+            if bools.iter().any(|packed| core::ptr::eq(*packed, info)) {
+                continue;
+            }
+        }
+        let stmts = deserialize_field_stmt(container, info, lifetime);
+        if let Some(index) = conditional.iter().position(|field| core::ptr::eq(*field, info)) {
+            let field_name = &info.binding;
+            let field_type = &info.field.ty;
+            let default = info.default_value();
+            let (byte, bit) = (index / 8, (index % 8) as u8);
+            deserialize_fields.extend(quote! {
+                let #field_name: #field_type = if buf[presence + #byte] & (1 << #bit) != 0 {
+                    #stmts
+                    #field_name
+                } else {
+                    #default
+                };
+            });
+        } else if info.cfgs.is_empty() {
+            deserialize_fields.extend(stmts);
+        } else {
+            let cfg = info.cfg();
+            let field_name = &info.binding;
+            let field_type = &info.field.ty;
+            deserialize_fields.extend(quote! {
+                #cfg
+                let #field_name: #field_type = {
+                    #stmts
+                    #field_name
+                };
+            });
+        }
+    }
+    deserialize_fields
+}
+
+/// Binds one field to a local, decoding it at `buf[offset..]` and advancing `offset` if it is serialized.
+fn deserialize_field_stmt(container: &ContainerAttrs, info: &FieldInfo, lifetime: Option<&syn::Lifetime>) -> TokenStream2 {
+    let field_name = &info.binding;
+    let field_type = &info.field.ty;
+    if info.attrs.skip {
+        let value = info.default_value();
+        return quote! {
+            let #field_name: #field_type = #value;
+        };
+    }
+    if let Some(count) = &info.attrs.count {
+        return match Counted::of(field_type) {
+            Some(Counted::Vec(..)) => quote! {
+                let (#field_name, size) = embedded_serialize::deserialize_counted_vec(
+                    buf.get(offset..).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?,
+                    #count as usize,
+                )?;
+                offset += size;
+            },
+            Some(Counted::Bytes) => quote! {
+                let #field_name: #field_type = buf
+                    .get(offset..offset + #count as usize)
+                    .ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+                offset += #count as usize;
+            },
+            _ => quote! {
+                let mut #field_name: #field_type =
+                    embedded_serialize::core::array::from_fn(|_| Default::default());
+                offset += embedded_serialize::deserialize_raw_into(
+                    buf.get(offset..).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?,
+                    &mut #field_name,
+                    #count as usize,
+                )?;
+            },
+        };
+    }
+    if let Some(source) = &info.attrs.popcount_of {
+        return quote! {
+            let (_, size) = <u8 as embedded_serialize::Deserialize>::deserialize_consumed(
+                buf.get(offset..).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?,
+            )?;
+            let #field_name: u8 = #source.count_ones() as u8;
+            offset += size;
+        };
+    }
+    if let Some(crc) = &info.attrs.crc {
+        return quote! {
+            let (#field_name, size) = <#field_type as embedded_serialize::Deserialize>::deserialize_consumed(
+                buf.get(offset..).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?,
+            )?;
+            if #field_name != #crc(&buf[..offset]) {
+                return Err(embedded_serialize::DeserializeError::ChecksumMismatch);
+            }
+            offset += size;
+        };
+    }
+    if let Some(map) = &info.attrs.map {
+        let deserialize = &map.deserialize;
+        let index = info.index;
+        let check = max_len_check(info);
+        return quote! {
+            let (#field_name, size) = embedded_serialize::deserialize_mapped(&buf[offset..], #index, #deserialize)?;
+            offset += size;
+            #check
+        };
+    }
+    let decode = match (&info.attrs.sentinel, lifetime) {
+        _ if info.attrs.with_context => {
+            let context = &container.context;
+            quote! {
+                (|buf| <#field_type as embedded_serialize::DeserializeWith<#context>>::deserialize_with(context, buf))
+            }
+        }
+        (Some(sentinel), _) => quote! {
+            <embedded_serialize::option::Sentinel<#sentinel> as embedded_serialize::option::OptionEncoding<_>>::deserialize_option
+        },
+        (None, Some(lifetime)) => quote! {
+            <#field_type as embedded_serialize::DeserializeBorrowed<#lifetime>>::deserialize_borrowed
+        },
+        (None, None) => quote! { <#field_type as embedded_serialize::Deserialize>::deserialize_consumed },
+    };
+    let check = max_len_check(info);
+    quote! {
+        let (#field_name, size) = #decode(&buf[offset..])?;
+        offset += size;
+        #check
+    }
+}
+
+/// Checks that every field of a TLV container has a tag of its own.
+fn check_tlv_fields(infos: &[FieldInfo]) -> syn::Result<()> {
+    let mut seen = Vec::new();
+    for info in infos.iter().filter(|info| !info.attrs.skip) {
+        let tag = match info.attrs.tag {
+            Some(tag) => tag,
+            None => return Err(syn::Error::new_spanned(info.field, "tlv fields need #[es(tag = N)]")),
+        };
+        if info.attrs.count.is_some() {
+            return Err(syn::Error::new_spanned(info.field, "count is not supported in tlv containers"));
+        }
+        if info.attrs.sentinel.is_some() {
+            return Err(syn::Error::new_spanned(info.field, "sentinel is not supported in tlv containers"));
+        }
+        if info.attrs.map.is_some() {
+            return Err(syn::Error::new_spanned(info.field, "map is not supported in tlv containers"));
+        }
+        if info.attrs.skip_if.is_some() {
+            return Err(syn::Error::new_spanned(info.field, "skip_if is not supported in tlv containers"));
+        }
+        if info.attrs.crc.is_some() {
+            return Err(syn::Error::new_spanned(info.field, "crc is not supported in tlv containers"));
+        }
+        if info.attrs.popcount_of.is_some() {
+            return Err(syn::Error::new_spanned(info.field, "popcount_of is not supported in tlv containers"));
+        }
+        if seen.contains(&tag) {
+            return Err(syn::Error::new_spanned(info.field, "duplicate tlv tag"));
+        }
+        seen.push(tag);
+    }
+    Ok(())
+}
+
+/// Fills fields from the TLV entries within the container's `u16` length, in any order,
+/// skipping unknown tags.
+/// The container consumes the whole buffer.
+fn tlv_deserialize_body(fields: &Fields, infos: &[FieldInfo]) -> TokenStream2 {
+    let mut slots = quote! {};
+    let mut arms = quote! {};
+    let mut finish = quote! {};
+    for info in infos.iter() {
+        let binding = &info.binding;
+        let ty = &info.field.ty;
+        let default = match &info.attrs.default {
+            Some(FieldDefault::Path(path)) => quote! { #path() },
+            Some(FieldDefault::Trait) => quote! { Default::default() },
+            None if info.attrs.skip => quote! { Default::default() },
+            None => {
+                let tag = info.attrs.tag;
+                quote! { return Err(embedded_serialize::DeserializeError::MissingTag(#tag)) }
+            }
+        };
+        if let Some(tag) = info.attrs.tag.filter(|_| !info.attrs.skip) {
+            slots.extend(quote! { let mut #binding: Option<#ty> = None; });
+            arms.extend(quote! {
+                #tag => {
+                    if #binding.is_some() {
+                        return Err(embedded_serialize::DeserializeError::DuplicateTag(#tag));
+                    }
+                    #binding = Some(embedded_serialize::tlv::decode_value(value)?);
+                }
+            });
+            let check = max_len_check(info);
+            finish.extend(quote! {
+                let #binding: #ty = match #binding {
+                    Some(value) => value,
+                    None => #default,
+                };
+                #check
+            });
+        } else {
+            finish.extend(quote! { let #binding: #ty = #default; });
+        }
+    }
+    let construct = construct(quote!(Self), fields, infos);
+    quote! {
+        // Named apart from the field bindings declared below.
+        let (__len, __header) = <u16 as embedded_serialize::Deserialize>::deserialize_consumed(buf)?;
+        let __end = __header + __len as usize;
+        let __entries = buf.get(__header..__end).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+        #slots
+        for entry in embedded_serialize::tlv::TlvIter::new(__entries) {
+            let (tag, value) = entry?;
+            match tag {
+                #arms
+                _ => {}
+            }
+        }
+        #finish
+        Ok((#construct, __end))
+    }
+}
+
+/// Generates `write_csv`, writing each serialized field's `Display` form separated by commas.
+fn text_dump_impl(input: &DeriveInput, infos: &[FieldInfo]) -> TokenStream2 {
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    let fields: Vec<_> = infos.iter().filter(|info| !info.attrs.skip).collect();
+    let gated = fields.iter().any(|info| !info.cfgs.is_empty());
+    let mut writes = quote! {};
+    for (index, info) in fields.iter().enumerate() {
+        let member = &info.member;
+        let ty = &info.field.ty;
+        if info.cfgs.is_empty() || mentions_type_param(ty, &input.generics) {
+            where_clause
+                .predicates
+                .push(syn::parse_quote_spanned!(ty.span()=> #ty: embedded_serialize::core::fmt::Display));
+        }
+        if gated {
+            // Whether a separator is due depends on which earlier fields are compiled in.
+            let cfg = info.cfg();
+            writes.extend(quote! {
+                #cfg
+                {
+                    w.write_str(separator)?;
+                    separator = ",";
+                    write!(w, "{}", self.#member)?;
+                }
+            });
+            continue;
+        }
+        if index > 0 {
+            writes.extend(quote! { w.write_char(',')?; });
+        }
+        writes.extend(quote! { write!(w, "{}", self.#member)?; });
+    }
+    if gated {
+        writes = quote! {
+            let mut separator = "";
+            #writes
+            let _ = separator;
+        };
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Writes the fields as one comma-separated line of text, without a line ending.
+            #[allow(unused_assignments)]
+            pub fn write_csv(&self, w: &mut dyn embedded_serialize::core::fmt::Write) -> embedded_serialize::core::fmt::Result {
+                use embedded_serialize::core::fmt::Write as _;
+                #writes
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Rejects variants that have no key-value text form.
+fn check_text_kv_variants(variants: &[VariantInfo]) -> syn::Result<()> {
+    for variant in variants {
+        if matches!(&variant.variant.fields, Fields::Unnamed(fields) if !fields.unnamed.is_empty()) {
+            return Err(syn::Error::new_spanned(variant.variant, "text_kv needs unit or named-field variants"));
+        }
+        if let Some(info) = variant.fields.iter().find(|info| !info.cfgs.is_empty()) {
+            return Err(syn::Error::new_spanned(&info.cfgs[0], "cfg fields are not supported with text_kv"));
+        }
+    }
+    Ok(())
+}
+
+/// Generates `write_kv`, which writes the variant name and its fields in the `text::kv` form.
+fn write_kv_impl(input: &DeriveInput, variants: &[VariantInfo]) -> TokenStream2 {
+    let name = &input.ident;
+    let fields = variants.iter().flat_map(|variant| variant.fields.iter());
+    let generics = bounded_generics(&input.generics, fields, quote!(embedded_serialize::text::kv::KvValue));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = variants.iter().map(|variant| {
+        let pattern = variant_pattern(variant);
+        let variant_name = variant.variant.ident.to_string();
+        let writes = variant.fields.iter().filter(|info| !info.attrs.skip).map(|info| {
+            let binding = &info.binding;
+            let key = binding.to_string();
+            quote! { embedded_serialize::text::kv::write_field(w, #key, #binding)?; }
+        });
+        quote! {
+            #pattern => {
+                embedded_serialize::text::kv::write_start(w, #variant_name)?;
+                #(#writes)*
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Writes the value as a `{"type":...}` object of the `text::kv` form.
+            pub fn write_kv(&self, w: &mut dyn embedded_serialize::core::fmt::Write) -> embedded_serialize::core::fmt::Result {
+                match *self {
+                    #(#arms)*
+                }
+                embedded_serialize::text::kv::write_end(w)
+            }
+        }
+    }
+}
 
+/// Generates `parse_kv`, which reads the `text::kv` form back. Fields may come in any
+/// order, keys the variant does not have are ignored, and skipped fields take their default.
+fn parse_kv_impl(input: &DeriveInput, variants: &[VariantInfo]) -> TokenStream2 {
+    let name = &input.ident;
+    let fields = variants.iter().flat_map(|variant| variant.fields.iter());
+    let generics = bounded_generics(&input.generics, fields, quote!(embedded_serialize::text::kv::KvValue));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let arms = variants.iter().map(|variant| {
+        let ident = &variant.variant.ident;
+        let variant_name = ident.to_string();
+        let (skipped, parsed): (Vec<&FieldInfo>, Vec<&FieldInfo>) =
+            variant.fields.iter().partition(|info| info.attrs.skip);
+        let declares = parsed.iter().map(|info| {
+            let binding = &info.binding;
+            let ty = &info.field.ty;
+            quote! { let mut #binding: Option<#ty> = None; }
+        });
+        let matches = parsed.iter().map(|info| {
+            let binding = &info.binding;
+            let ty = &info.field.ty;
+            let key = binding.to_string();
+            quote! {
+                #key => #binding = Some(<#ty as embedded_serialize::text::kv::KvValue>::parse_value(value)?),
+            }
+        });
+        let unwraps = parsed.iter().map(|info| {
+            let binding = &info.binding;
+            quote! { let #binding = #binding.ok_or(embedded_serialize::DeserializeError::InvalidData)?; }
+        });
+        let defaults = skipped.iter().map(|info| {
+            let binding = &info.binding;
+            let ty = &info.field.ty;
+            let value = info.default_value();
+            quote! { let #binding: #ty = #value; }
+        });
+        let construct = construct(quote!(Self::#ident), &variant.variant.fields, &variant.fields);
+        quote! {
+            #variant_name => {
+                #(#declares)*
+                while let Some((key, value)) = reader.next_field()? {
+                    match key {
+                        #(#matches)*
+                        _ => {}
+                    }
+                }
+                #(#unwraps)*
+                #(#defaults)*
+                Ok(#construct)
+            }
+        }
+    });
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Parses a `{"type":...}` object of the `text::kv` form, failing with
+            /// `UnknownLabel` on a variant name this enum does not have and with
+            /// `InvalidData` on a missing field or malformed text.
+            pub fn parse_kv(text: &str) -> Result<Self, embedded_serialize::DeserializeError> {
+                let (mut reader, variant) = embedded_serialize::text::kv::KvReader::new(text)?;
+                match variant {
+                    #(#arms)*
+                    _ => Err(embedded_serialize::DeserializeError::UnknownLabel),
+                }
+            }
+        }
+    }
+}
+
+/// Whether `ty` names one of the type parameters of `generics`.
+fn mentions_type_param(ty: &syn::Type, generics: &syn::Generics) -> bool {
+    fn mentions(tokens: TokenStream2, params: &[&syn::Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => params.contains(&&ident),
+            TokenTree::Group(group) => mentions(group.stream(), params),
+            _ => false,
+        })
+    }
+    let params: Vec<_> = generics.type_params().map(|param| &param.ident).collect();
+    mentions(quote! { #ty }, &params)
+}
+
+/// Builds `path` (`Self` or a variant) from the deserialized field bindings.
+fn construct(path: TokenStream2, fields: &Fields, infos: &[FieldInfo]) -> TokenStream2 {
+    let bindings = infos.iter().map(|info| {
+        let cfg = info.cfg();
+        let binding = &info.binding;
+        quote! { #cfg #binding }
+    });
+    match fields {
+        Fields::Named(_) => quote! { #path { #(#bindings),* } },
+        Fields::Unnamed(_) => quote! { #path(#(#bindings),*) },
+        Fields::Unit => quote! { #path },
+    }
+}
+
+/// Gives every token in `tokens` the span `span`.
+fn respan(tokens: TokenStream2, span: Span) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut respanned = proc_macro2::Group::new(group.delimiter(), respan(group.stream(), span));
+                respanned.set_span(span);
+                return TokenTree::Group(respanned);
+            }
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+/// Requires every serialized field type to implement `bound`, spanned on the field so
+/// that a missing impl is reported against the offending field rather than the derive.
+/// Fields behind `cfg` are only bounded when their type involves a type parameter, since
+/// the bound applies whether or not the field is compiled in.
+fn bounded_generics<'a, 'f: 'a>(
+    generics: &syn::Generics,
+    infos: impl IntoIterator<Item = &'a FieldInfo<'f>>,
+    bound: TokenStream2,
+) -> syn::Generics {
+    let params = generics;
+    let mut generics = generics.clone();
+    let where_clause = generics.make_where_clause();
+    for info in infos.into_iter().filter(|info| !info.attrs.skip && info.attrs.map.is_none()) {
+        // Counted fields are written element by element; byte slices need no bound.
+        let ty = match (&info.attrs.count, Counted::of(&info.field.ty)) {
+            (Some(_), Some(counted)) => match counted.item() {
+                Some(item) => item,
+                None => continue,
+            },
+            _ => &info.field.ty,
+        };
+        if !info.cfgs.is_empty() && !mentions_type_param(ty, params) {
+            continue;
+        }
+        let bound = respan(bound.clone(), ty.span());
+        where_clause
+            .predicates
+            .push(syn::parse_quote_spanned!(ty.span()=> #ty: #bound));
+    }
+    generics
+}
+
+/// An enum variant together with its wire tag
+struct VariantInfo<'a> {
+    variant: &'a syn::Variant,
+    /// Byte written before the variant's fields
+    tag: u8,
+    fields: Vec<FieldInfo<'a>>,
+}
+
+/// Reads the tag given by `#[es(tag = N)]` or, failing that, an explicit discriminant.
+fn parse_variant_tag(variant: &syn::Variant) -> syn::Result<Option<u64>> {
+    let mut tag = None;
+    for attr in variant.attrs.iter().filter(|attr| attr.path.is_ident("es")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[es(...)]")),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("tag") => match &nv.lit {
+                    Lit::Int(lit) => tag = Some(lit.base10_parse()?),
+                    lit => return Err(syn::Error::new_spanned(lit, "expected an integer tag")),
+                },
+                _ => return Err(syn::Error::new_spanned(nested, "unknown es attribute")),
+            }
+        }
+    }
+    if tag.is_none() {
+        if let Some((_, expr)) = &variant.discriminant {
+            match expr {
+                syn::Expr::Lit(syn::ExprLit { lit: Lit::Int(lit), .. }) => tag = Some(lit.base10_parse()?),
+                expr => return Err(syn::Error::new_spanned(expr, "discriminant must be an integer literal")),
+            }
+        }
+    }
+    Ok(tag)
+}
+
+/// Assigns each variant its tag; variants without one continue from the previous tag, starting at 0.
+fn variant_infos(data: &syn::DataEnum) -> syn::Result<Vec<VariantInfo<'_>>> {
+    let mut variants: Vec<VariantInfo> = Vec::new();
+    let mut next = 0;
+    for variant in data.variants.iter() {
+        let tag = parse_variant_tag(variant)?.unwrap_or(next);
+        let tag = u8::try_from(tag).map_err(|_| syn::Error::new_spanned(variant, "variant tag does not fit in a u8"))?;
+        if variants.iter().any(|earlier| earlier.tag == tag) {
+            return Err(syn::Error::new_spanned(variant, "duplicate variant tag"));
+        }
+        next = u64::from(tag) + 1;
+        variants.push(VariantInfo {
+            variant,
+            tag,
+            fields: field_infos(&variant.fields, true)?,
+        });
+    }
+    Ok(variants)
+}
+
+/// Matches `*self` against the variant, binding its serialized fields by reference.
+fn variant_pattern(variant: &VariantInfo) -> TokenStream2 {
+    let ident = &variant.variant.ident;
+    let bindings = variant.fields.iter().map(|info| {
+        let binding = &info.binding;
+        let cfg = info.cfg();
+        match (&info.member, info.attrs.skip) {
+            (syn::Member::Named(member), true) => quote! { #cfg #member: _ },
+            (syn::Member::Named(member), false) => quote! { #cfg #member: ref #binding },
+            (syn::Member::Unnamed(_), true) => quote! { _ },
+            (syn::Member::Unnamed(_), false) => quote! { ref #binding },
+        }
+    });
+    match &variant.variant.fields {
+        Fields::Named(_) => quote! { Self::#ident { #(#bindings),* } },
+        Fields::Unnamed(_) => quote! { Self::#ident(#(#bindings),*) },
+        Fields::Unit => quote! { Self::#ident },
+    }
+}
+
+/// Number of padding bytes between a variant's `header` bytes and its fields.
+/// Only variants with serialized fields are padded.
+fn payload_padding(container: &ContainerAttrs, variant: &VariantInfo, header: usize) -> usize {
+    match container.payload_align {
+        Some(align) if variant.fields.iter().any(|info| !info.attrs.skip) => {
+            (align - (container.magic.len() + header) % align) % align
+        }
+        _ => 0,
+    }
+}
+
+/// Writes the variant's tag byte followed by its fields, with the `u16` length of the
+/// fields in between for length-prefixed enums.
+fn enum_serialize_body(container: &ContainerAttrs, variants: &[VariantInfo]) -> TokenStream2 {
+    let arms = variants.iter().map(|variant| {
+        let pattern = variant_pattern(variant);
+        let tag = variant.tag;
+        let stmts = serialize_field_stmts(&ContainerAttrs::default(), &variant.fields);
+        if container.length_prefixed {
+            let mutability = (!stmts.is_empty()).then(|| quote!(mut));
+            let (cfgs, stmts): (Vec<_>, Vec<_>) = stmts.into_iter().unzip();
+            // The length is written once the fields are, when it is known.
+            return quote! {
+                #pattern => {
+                    let #mutability offset = embedded_serialize::Serialize::serialize(&#tag, buf)? + 2;
+                    buf.get(..offset).ok_or(embedded_serialize::SerializeError::BufferTooSmall)?;
+                    #(#cfgs #stmts)*
+                    let len = u16::try_from(offset - 3).map_err(|_| embedded_serialize::SerializeError::InvalidData)?;
+                    embedded_serialize::Serialize::serialize(&len, &mut buf[1..])?;
+                    Ok(offset)
+                }
+            };
+        }
+        if stmts.is_empty() {
+            return quote! {
+                #pattern => embedded_serialize::Serialize::serialize(&#tag, buf),
+            };
+        }
+        let (cfgs, stmts): (Vec<_>, Vec<_>) = stmts.into_iter().unzip();
+        let padding = match payload_padding(container, variant, 1) {
+            0 => quote! {},
+            pad => quote! {
+                buf.get_mut(offset..offset + #pad)
+                    .ok_or(embedded_serialize::SerializeError::BufferTooSmall)?
+                    .fill(0);
+                offset += #pad;
+            },
+        };
+        quote! {
+            #pattern => {
+                let mut offset = embedded_serialize::Serialize::serialize(&#tag, buf)?;
+                #padding
+                #(#cfgs #stmts)*
+                Ok(offset)
+            }
+        }
+    });
+    let body = quote! {
+        match *self {
+            #(#arms)*
+        }
+    };
+    match container.magic.as_slice() {
+        [] => body,
+        magic => quote! {
+            embedded_serialize::magic::serialize_after_magic(&[#(#magic),*], buf, |buf| { #body })
+        },
+    }
+}
+
+/// Appends the magic, then the variant's tag byte and its fields to `writer` like
+/// `enum_serialize_body` writes them, returning the written bytes.
+fn enum_serialize_uninit_body(container: &ContainerAttrs, variants: &[VariantInfo]) -> TokenStream2 {
+    let magic = &container.magic;
+    let magic_len = magic.len();
+    let write_magic = match magic.as_slice() {
+        [] => quote! {},
+        magic => quote! {
+            writer.write_zeroed(#magic_len, |bytes| embedded_serialize::magic::write_magic(&[#(#magic),*], bytes))?;
+        },
+    };
+    let arms = variants.iter().map(|variant| {
+        let pattern = variant_pattern(variant);
+        let tag = variant.tag;
+        let (cfgs, stmts): (Vec<_>, Vec<_>) = serialize_uninit_field_stmts(&ContainerAttrs::default(), &variant.fields)
+            .into_iter()
+            .unzip();
+        if container.length_prefixed {
+            // The length is patched in once the fields are written, when it is known.
+            return quote! {
+                #pattern => {
+                    writer.write(&#tag)?;
+                    writer.write(&0u16)?;
+                    #(#cfgs #stmts)*
+                    let written = writer.finish();
+                    let len = u16::try_from(written.len() - (#magic_len + 3))
+                        .map_err(|_| embedded_serialize::SerializeError::InvalidData)?;
+                    embedded_serialize::Serialize::serialize(&len, &mut written[#magic_len + 1..])?;
+                    Ok(written)
+                }
+            };
+        }
+        let padding = match payload_padding(container, variant, 1) {
+            0 => quote! {},
+            pad => quote! {
+                writer.write_zeroed(#pad, |_| Ok(#pad))?;
+            },
+        };
+        quote! {
+            #pattern => {
+                writer.write(&#tag)?;
+                #padding
+                #(#cfgs #stmts)*
+                Ok(writer.finish())
+            }
+        }
+    });
+    quote! {
+        #write_magic
+        match *self {
+            #(#arms)*
+        }
+    }
+}
 
-#[proc_macro_derive(Serialize)]
+/// Wraps a deserialization body so that it runs after the container's magic is checked
+/// and consumed.
+fn magic_deserialize_body(container: &ContainerAttrs, body: TokenStream2) -> TokenStream2 {
+    match container.magic.as_slice() {
+        [] => body,
+        magic => quote! {
+            embedded_serialize::magic::deserialize_after_magic(&[#(#magic),*], buf, |buf| { #body })
+        },
+    }
+}
+
+/// Expression for the number of bytes the tag and fields of `*self` serialize to.
+fn enum_size(container: &ContainerAttrs, variants: &[VariantInfo]) -> TokenStream2 {
+    let header: usize = if container.length_prefixed { 3 } else { 1 };
+    let mut arms = Vec::new();
+    for variant in variants {
+        let pattern = variant_pattern(variant);
+        let size = fields_size(&ContainerAttrs::default(), &variant.fields);
+        let header = container.magic.len() + header + payload_padding(container, variant, header);
+        arms.push(quote! { #pattern => #header + #size, });
+    }
+    quote! {
+        match *self {
+            #(#arms)*
+        }
+    }
+}
+
+/// Matches `tag` (a `u32`) against the variant tags and decodes the matching variant's
+/// fields from `buf[start..]`, after any payload padding. An unknown tag fails with
+/// `UnknownDiscriminant` naming the enum `name`.
+fn enum_variant_match(
+    name: &syn::Ident,
+    container: &ContainerAttrs,
+    variants: &[VariantInfo],
+    lifetime: Option<&syn::Lifetime>,
+    start: usize,
+) -> TokenStream2 {
+    let arms = variants.iter().map(|variant| {
+        let ident = &variant.variant.ident;
+        let tag = u32::from(variant.tag);
+        let stmts = deserialize_field_stmts(&ContainerAttrs::default(), &variant.fields, lifetime);
+        let construct = construct(quote!(Self::#ident), &variant.variant.fields, &variant.fields);
+        if variant.fields.iter().all(|info| info.attrs.skip) {
+            return quote! {
+                #tag => {
+                    #stmts
+                    Ok((#construct, #start))
+                }
+            };
+        }
+        let skip_padding = match payload_padding(container, variant, start) {
+            0 => quote! {},
+            pad => quote! {
+                buf.get(..#start + #pad).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+                offset += #pad;
+            },
+        };
+        quote! {
+            #tag => {
+                let mut offset = #start;
+                #skip_padding
+                #stmts
+                Ok((#construct, offset))
+            }
+        }
+    });
+    let name = name.to_string();
+    quote! {
+        match tag {
+            #(#arms)*
+            tag => Err(embedded_serialize::DeserializeError::unknown_discriminant(#name, tag)),
+        }
+    }
+}
+
+/// Reads the tag byte and decodes the fields of the matching variant.
+///
+/// A length-prefixed variant is decoded from its declared payload only, and consumes all
+/// of it: payload bytes after the fields this build knows of, appended by a newer sender,
+/// are skipped.
+fn enum_deserialize_body(
+    name: &syn::Ident,
+    container: &ContainerAttrs,
+    variants: &[VariantInfo],
+    lifetime: Option<&syn::Lifetime>,
+) -> TokenStream2 {
+    if container.length_prefixed {
+        let variant_match = enum_variant_match(name, container, variants, lifetime, 3);
+        return quote! {
+            let tag = *buf.first().ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+            let tag = tag as u32;
+            let len = <u16 as embedded_serialize::Deserialize>::deserialize(&buf[1..])?;
+            let end = 3 + len as usize;
+            let buf = buf.get(..end).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+            let result: Result<(Self, usize), embedded_serialize::DeserializeError> = #variant_match;
+            result.map(|(value, _)| (value, end))
+        };
+    }
+    let variant_match = enum_variant_match(name, container, variants, lifetime, 1);
+    quote! {
+        let tag = *buf.first().ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+        let tag = tag as u32;
+        #variant_match
+    }
+}
+
+/// Generates `deserialize_with_tag_fn`, for tags that are not a leading byte.
+fn tag_fn_impl(
+    input: &DeriveInput,
+    generics: &syn::Generics,
+    variants: &[VariantInfo],
+    lifetime: Option<&syn::Lifetime>,
+) -> TokenStream2 {
+    let name = &input.ident;
+    let variant_match = enum_variant_match(name, &ContainerAttrs::default(), variants, lifetime, 0);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Decodes the variant whose tag `tag_fn` extracts from `buf`, reading its fields
+            /// from the start of `buf`. Returns the value and the number of bytes consumed.
+            pub fn deserialize_with_tag_fn<F>(
+                buf: &#lifetime [u8],
+                tag_fn: F,
+            ) -> Result<(Self, usize), embedded_serialize::DeserializeError>
+            where
+                F: FnOnce(&[u8]) -> Result<u32, embedded_serialize::DeserializeError>,
+            {
+                let tag = tag_fn(buf)?;
+                #variant_match
+            }
+        }
+    }
+}
+
+/// Generates `deserialize_tagged`, for callers that act on the tag without matching on
+/// the decoded value.
+fn tagged_impl(
+    input: &DeriveInput,
+    container: &ContainerAttrs,
+    generics: &syn::Generics,
+    lifetime: Option<&syn::Lifetime>,
+) -> TokenStream2 {
+    let name = &input.ident;
+    let tag_offset = container.magic.len();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Decodes a value like `Deserialize` does, also returning the wire tag of its
+            /// variant. Returns the value, the tag and the number of bytes consumed.
+            pub fn deserialize_tagged(
+                buf: &#lifetime [u8],
+            ) -> Result<(Self, u32, usize), embedded_serialize::DeserializeError> {
+                let tag = *buf.get(#tag_offset).ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+                <Self as embedded_serialize::DeserializeBorrowed<'_>>::deserialize_borrowed(buf)
+                    .map(|(value, consumed)| (value, tag as u32, consumed))
+            }
+        }
+    }
+}
+
+/// Implements `VariantNames` when the `names` feature is enabled.
+fn variant_names_impl(input: &DeriveInput, variants: &[VariantInfo]) -> TokenStream2 {
+    if !cfg!(feature = "names") {
+        return quote! {};
+    }
+    let name = &input.ident;
+    let idents: Vec<_> = variants.iter().map(|variant| &variant.variant.ident).collect();
+    let names: Vec<_> = idents.iter().map(|ident| ident.to_string()).collect();
+    let tags = variants.iter().map(|variant| u32::from(variant.tag));
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics embedded_serialize::names::VariantNames for #name #ty_generics #where_clause {
+            const VARIANT_NAMES: &'static [(&'static str, u32)] = &[#((#names, #tags)),*];
+
+            fn variant_name(&self) -> &'static str {
+                match *self {
+                    #(Self::#idents { .. } => #names,)*
+                }
+            }
+        }
+    }
+}
+
+/// Rejects container attributes that only apply to structs.
+fn check_enum_container(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let container = parse_container_attrs(input)?;
+    if container.tlv || container.text_dump || container.pack_bools || container.context.is_some() || container.view {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "tlv, text_dump, pack_bools, context and view are only supported on structs",
+        ));
+    }
+    Ok(container)
+}
+
+/// Rejects `with_context` fields in containers without a context type.
+fn check_context_fields<'a, 'f: 'a>(
+    container: &ContainerAttrs,
+    infos: impl IntoIterator<Item = &'a FieldInfo<'f>>,
+) -> syn::Result<()> {
+    match infos.into_iter().find(|info| info.attrs.with_context) {
+        Some(info) if container.context.is_none() => Err(syn::Error::new_spanned(
+            info.field,
+            "with_context needs #[es(context = \"...\")] on the container",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Derives `Serialize`, writing the fields back to back in declaration order.
+///
+/// # `cfg`-gated fields
+///
+/// Named fields may carry `#[cfg(...)]`; every generated statement touching such a field
+/// is gated the same way, so the impls compile under any combination of features.
+///
+/// **A `cfg`-gated field changes the wire format.** Firmware built with the feature on
+/// cannot exchange messages with firmware built with it off, and neither side can tell
+/// from the bytes alone. Derive `Describe` as well and compare `T::SCHEMA.hash()` between
+/// peers: the schema only lists the fields compiled in, so the hashes differ.
+///
+/// `cfg` is not supported on tuple fields or in `tlv` and `pack_bools` containers.
+///
+/// # Counted fields
+///
+/// `#[es(count = "n")]` on a named array, `heapless::Vec` or `&[u8]` field writes its
+/// elements without a length prefix, taking their number from the earlier unsigned
+/// integer field `n`. An array writes its first `n` elements and decodes the rest as
+/// `Default::default()`; a `Vec` or slice must hold exactly `n` elements, or serializing
+/// fails with `InvalidData`. Decoding a `Vec` fails with `InvalidData` if `n` exceeds its
+/// capacity, and a slice borrows its bytes from the buffer. `#[es(no_prefix, count = "n")]`
+/// spells the same out; `no_prefix` without a `count` is rejected, since every other
+/// slice or `Vec` field needs its length prefix to be decoded.
+///
+/// # Mapped fields
+///
+/// `#[es(map(serialize = "to_wire", deserialize = "from_wire"))]` writes a field as
+/// another type, such as a rich enum sent as its legacy `u8` code. `to_wire` is a
+/// `fn(&Field) -> Wire` and `from_wire` a `fn(Wire) -> Result<Field, DeserializeError>`,
+/// where `Wire` implements `Serialize` and `Deserialize`. An `InvalidData` returned by
+/// `from_wire` is reported as `InvalidDataAt` with the field's position in declaration
+/// order. Mapped fields cannot be combined with `skip`, `count`, `sentinel` or
+/// `with_context`, and are not supported in `tlv` containers, views or `FixedLayout`.
+///
+/// # Conditional fields
+///
+/// `#[es(skip_if = "path")]` on a struct field leaves it off the wire whenever
+/// `path(&self)` returns `true`. The struct then starts, after any magic, with a presence
+/// bitmap of `ceil(k / 8)` bytes for its `k` such fields, where bit `i % 8` (least
+/// significant first) of byte `i / 8` is set when the `i`th of them was written. Decoding reads the
+/// bitmap and gives absent fields `Default::default()`, or the result of the function
+/// named by `#[es(default = "path")]`. `skip_if` is not supported on enum variant fields,
+/// `cfg`-gated fields, fields named by a `count`, or in `tlv` containers, views or
+/// `FixedLayout`.
+///
+/// # Checksum fields
+///
+/// `#[es(crc = "crc16_ccitt")]` on the last field of a struct writes the checksum of all
+/// bytes the struct wrote before it in place of the field's value, and decoding fails with
+/// `ChecksumMismatch` unless the checksum read matches, keeping it in the field. Besides the
+/// built-in `crc16_ccitt`, `crc8` and `fletcher16`, the name may be the path of any
+/// `fn(&[u8]) -> Field`. Such a struct cannot be written field by field with
+/// `SerializeFields`, whose checksum field fails with `InvalidData`. `crc` is not
+/// supported on enum variant fields, `cfg`-gated fields, or in `tlv` containers or views.
+///
+/// # Popcount fields
+///
+/// `#[es(popcount_of = "faults")]` on a `u8` field writes the number of bits set in the
+/// earlier field `faults` in place of the field's value, for a compact health summary.
+/// Decoding skips the byte and recomputes the count from the decoded `faults`:
+///
+/// ```
+/// use embedded_serialize::{Deserialize, Serialize};
+/// use embedded_serialize_derive::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct FaultMirror {
+///     faults: u16,
+///     #[es(popcount_of = "faults")]
+///     active: u8,
+/// }
+///
+/// let mut buf = [0u8; 3];
+/// FaultMirror { faults: 0b1000_0000_0110_0001, active: 0 }.serialize(&mut buf).unwrap();
+/// assert_eq!(buf, [0x80, 0x61, 4]);
+/// buf[2] = 9;
+/// assert_eq!(FaultMirror::deserialize(&buf).unwrap().active, 4);
+/// ```
+///
+/// `popcount_of` is not supported in `tlv` containers or views.
+///
+/// # Uninitialized buffers
+///
+/// The generated `serialize_uninit` writes the fields one at a time through
+/// `embedded_serialize::writer::UninitWriter`, so only the bytes the value serializes to
+/// are initialized; the rest of the buffer is left untouched.
+///
+/// # `#[non_exhaustive]` types
+///
+/// A derive expands in the crate that defines the type, where `#[non_exhaustive]` does not
+/// restrict construction or matching, so such structs, enums and variants derive as usual.
+/// Decoding an enum tag this build does not know fails with `UnknownDiscriminant`.
+///
+/// # Key-value text
+///
+/// With `#[es(text_kv)]` on an enum of unit and named-field variants, `Serialize` also
+/// generates `write_kv` and `Deserialize` generates `parse_kv`, for the JSON-like
+/// `{"type":"Variant","field":1}` form of `embedded_serialize::text::kv`. Field types
+/// must implement `KvValue`.
+///
+/// # TLV structs
+///
+/// With `#[es(tlv)]` on a struct, each field is written as an entry of its `#[es(tag = N)]`,
+/// a length byte and the value, after the `u16` length of all the entries. Decoding
+/// accepts the entries in any order within that length and skips unknown tags, so the
+/// struct can be nested in another or followed by more data.
+///
+/// # Magic
+///
+/// With `#[es(magic = [0xAA, 0x55])]` on a struct or enum, the 1 to 4 given bytes are
+/// written before everything else, an enum's tag included, and decoding fails with
+/// `BadMagic` holding the bytes found if they differ; see `embedded_serialize::magic`.
+/// Magic is not supported on views or `FixedLayout` types, and `Describe` reports such
+/// types as opaque.
+///
+/// # Length-prefixed enums
+///
+/// With `#[es(length_prefixed)]` on an enum, each variant's tag is followed by the `u16`
+/// length of its fields. A receiver decodes the fields it knows and skips the rest of the
+/// declared length, so new fields can be appended to a variant without breaking older
+/// receivers.
+///
+/// # Aligned enum payloads
+///
+/// With `#[es(payload_align = K)]` on an enum, zero bytes are written after the tag of
+/// each variant with fields so that its fields start at a multiple of `K`, as in a C
+/// tagged union; decoding skips them. Offsets count from the start of the enum, so the
+/// payload is aligned within a frame when the enum itself starts at a multiple of `K`,
+/// such as at `ByteWriter::len()` of that alignment.
+#[proc_macro_derive(Serialize, attributes(es))]
 pub fn derive_serialize(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
@@ -13,54 +2194,112 @@ pub fn derive_serialize(input: TokenStream) -> TokenStream {
 
     let serialize_impl = match input.data {
         Data::Struct(ref data_struct) => {
-            let mut serialize_fields = quote! {};
-            #[allow(unused_variables)]
-            let mut total_size = 0usize;
-
-            match data_struct.fields {
-                Fields::Named(ref fields_named) => {
-                    for field in fields_named.named.iter() {
-                        let field_name = &field.ident;
-                        #[allow(unused)]
-                        let field_name_str = field_name.as_ref().unwrap().to_string();
-                        serialize_fields.extend(quote! {
-                            {
-                                let size = self.#field_name.serialize(&mut buf[offset..])?;
-                                offset += size;
-                            }
-                        });
-                        total_size += 0;
-                    }
+            let infos = match field_infos(&data_struct.fields, false) {
+                Ok(infos) => infos,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let container = match parse_container_attrs(&input) {
+                Ok(container) => container,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            if let Err(err) = check_cfg_fields(&container, &infos) {
+                return err.to_compile_error().into();
+            }
+            if container.tlv {
+                if let Err(err) = check_tlv_fields(&infos) {
+                    return err.to_compile_error().into();
                 }
-                Fields::Unnamed(ref fields_unnamed) => {
-                    for (index, _field) in fields_unnamed.unnamed.iter().enumerate() {
-                        let index = syn::Index::from(index);
-                        serialize_fields.extend(quote! {
-                            {
-                                let size = self.#index.serialize(&mut buf[offset..])?;
-                                offset += size;
-                            }
-                        });
-                        total_size += 0;
+            }
+            let stmts = serialize_field_stmts(&container, &infos);
+            let body = serialize_body(&stmts);
+            let text_dump = if container.text_dump {
+                text_dump_impl(&input, &infos)
+            } else {
+                quote! {}
+            };
+
+            let generics = bounded_generics(&input.generics, &infos, quote!(embedded_serialize::Serialize));
+            let mut field_stmts = stmts.clone();
+            if infos.iter().any(|info| info.attrs.crc.is_some()) {
+                // Written on its own, the checksum field has none of the bytes it covers.
+                if let Some((_, stmt)) = field_stmts.last_mut() {
+                    *stmt = quote! {
+                        {
+                            Err::<(), _>(embedded_serialize::SerializeError::InvalidData)?;
+                        }
+                    };
+                }
+            }
+            let serialize_fields = serialize_fields_impl(&input, &generics, &field_stmts);
+            let (cfgs, uninit_stmts): (Vec<_>, Vec<_>) =
+                serialize_uninit_field_stmts(&container, &infos).into_iter().unzip();
+            let serialize_uninit = serialize_uninit_fn(quote! {
+                #(#cfgs #uninit_stmts)*
+                Ok(writer.finish())
+            });
+            let size = fields_size(&container, &infos);
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+            quote! {
+                impl #impl_generics embedded_serialize::Serialize for #name #ty_generics #where_clause {
+                    fn serialize(&self, buf: &mut [u8]) -> Result<usize, embedded_serialize::SerializeError> {
+                        #body
+                    }
+
+                    #serialize_uninit
+
+                    fn serialized_size(&self) -> usize {
+                        #size
                     }
                 }
-                Fields::Unit => {}
+
+                #serialize_fields
+
+                #text_dump
             }
+        }
+        Data::Enum(ref data_enum) => {
+            let (container, variants) = match check_enum_container(&input)
+                .and_then(|container| Ok((container, variant_infos(data_enum)?)))
+            {
+                Ok(parsed) => parsed,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let body = enum_serialize_body(&container, &variants);
+            let serialize_uninit = serialize_uninit_fn(enum_serialize_uninit_body(&container, &variants));
+            let text_kv = if container.text_kv {
+                match check_text_kv_variants(&variants) {
+                    Ok(()) => write_kv_impl(&input, &variants),
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            } else {
+                quote! {}
+            };
+            let size = enum_size(&container, &variants);
+            let fields = variants.iter().flat_map(|variant| variant.fields.iter());
+            let generics = bounded_generics(&input.generics, fields, quote!(embedded_serialize::Serialize));
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
             quote! {
-                impl embedded_serialize::Serialize for #name {
+                impl #impl_generics embedded_serialize::Serialize for #name #ty_generics #where_clause {
                     fn serialize(&self, buf: &mut [u8]) -> Result<usize, embedded_serialize::SerializeError> {
-                        let mut offset = 0;
-                        #serialize_fields
-                        Ok(offset)
+                        #body
+                    }
+
+                    #serialize_uninit
+
+                    fn serialized_size(&self) -> usize {
+                        #size
                     }
                 }
+
+                #text_kv
             }
         }
         _ => {
             return syn::Error::new_spanned(
                 input.ident,
-                "Serialize can only be derived for structs",
+                "Serialize can only be derived for structs and enums",
             )
             .to_compile_error()
             .into();
@@ -71,76 +2310,464 @@ pub fn derive_serialize(input: TokenStream) -> TokenStream {
 }
 
 
-#[proc_macro_derive(Deserialize)]
+#[proc_macro_derive(Deserialize, attributes(es))]
 pub fn derive_deserialize(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let name = input.ident.clone();
 
-    let deserialize_impl = match input.data {
+    let mut lifetimes = input.generics.lifetimes().map(|def| &def.lifetime);
+    let lifetime = lifetimes.next();
+    if let Some(extra) = lifetimes.next() {
+        return syn::Error::new_spanned(extra, "Deserialize supports at most one lifetime parameter")
+            .to_compile_error()
+            .into();
+    }
+
+    let (body, generics, extra) = match input.data {
         Data::Struct(ref data_struct) => {
-            let mut deserialize_fields = quote! {};
-            let mut field_initializations = quote! {};
-            #[allow(unused)]
-            let mut total_size = 0usize;
-
-            match data_struct.fields {
-                Fields::Named(ref fields_named) => {
-                    for field in fields_named.named.iter() {
-                        let field_name = &field.ident;
-                        let field_type = &field.ty;
-                        deserialize_fields.extend(quote! {
-                            let #field_name = embedded_serialize::Deserialize::deserialize(&buf[offset..])?;
-                            offset += embedded_serialize::core::mem::size_of::<#field_type>();
-                        });
-                        field_initializations.extend(quote! {
-                            #field_name,
-                        });
-                        total_size += 0;
-                    }
+            let infos = match field_infos(&data_struct.fields, false) {
+                Ok(infos) => infos,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let container = match parse_container_attrs(&input) {
+                Ok(container) => container,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            if let Err(err) = check_context_fields(&container, &infos).and_then(|_| check_cfg_fields(&container, &infos)) {
+                return err.to_compile_error().into();
+            }
+            if let (Some(_), Some(lifetime)) = (&container.context, lifetime) {
+                return syn::Error::new_spanned(lifetime, "a container with a context cannot borrow from the input")
+                    .to_compile_error()
+                    .into();
+            }
+            let body = if container.tlv {
+                if let Err(err) = check_tlv_fields(&infos) {
+                    return err.to_compile_error().into();
                 }
-                Fields::Unnamed(ref fields_unnamed) => {
-                    let mut field_names = Vec::new();
-                    for (index, field) in fields_unnamed.unnamed.iter().enumerate() {
-                        let field_name = syn::Ident::new(&format!("field_{}", index), field.span());
-                        let field_type = &field.ty;
-                        deserialize_fields.extend(quote! {
-                            let #field_name = embedded_serialize::Deserialize::deserialize(&buf[offset..])?;
-                            offset += embedded_serialize::core::mem::size_of::<#field_type>();
-                        });
-                        field_names.push(field_name);
-                        total_size += 0; 
-                    }
+                if let Some(lifetime) = lifetime {
+                    return syn::Error::new_spanned(lifetime, "tlv containers cannot borrow from the input")
+                        .to_compile_error()
+                        .into();
+                }
+                magic_deserialize_body(&container, tlv_deserialize_body(&data_struct.fields, &infos))
+            } else {
+                deserialize_body(&container, &data_struct.fields, &infos, lifetime)
+            };
+            let view = if container.view {
+                match view_impl(&input, &container, &infos) {
+                    Ok(view) => view,
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            } else {
+                quote! {}
+            };
+            if let Some(context) = container.context {
+                let deserialize_with = context_deserialize_impl(&input, &infos, &context, &body);
+                return quote!(#deserialize_with #view).into();
+            }
+            let generics = deserialize_generics(&input.generics, &infos, lifetime);
+            (body, generics, view)
+        }
+        Data::Enum(ref data_enum) => {
+            let (container, variants) = match check_enum_container(&input)
+                .and_then(|container| Ok((container, variant_infos(data_enum)?)))
+            {
+                Ok(parsed) => parsed,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let fields = variants.iter().flat_map(|variant| variant.fields.iter());
+            if let Err(err) = check_context_fields(&ContainerAttrs::default(), fields) {
+                return err.to_compile_error().into();
+            }
+            let body = magic_deserialize_body(&container, enum_deserialize_body(&name, &container, &variants, lifetime));
+            let fields = variants.iter().flat_map(|variant| variant.fields.iter());
+            let generics = deserialize_generics(&input.generics, fields, lifetime);
+            let tag_fn = tag_fn_impl(&input, &generics, &variants, lifetime);
+            let tagged = tagged_impl(&input, &container, &generics, lifetime);
+            let names = variant_names_impl(&input, &variants);
+            let text_kv = if container.text_kv {
+                match check_text_kv_variants(&variants) {
+                    Ok(()) => parse_kv_impl(&input, &variants),
+                    Err(err) => return err.to_compile_error().into(),
+                }
+            } else {
+                quote! {}
+            };
+            (body, generics, quote! { #tag_fn #tagged #names #text_kv })
+        }
+        _ => {
+            return syn::Error::new_spanned(
+                input.ident,
+                "Deserialize can only be derived for structs and enums",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
 
-                    field_initializations = quote! {
-                        (#(#field_names),*)
-                    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let deserialize_impl = match lifetime {
+        // A borrowing type can only be decoded from a buffer that outlives it.
+        Some(lifetime) => quote! {
+            impl #impl_generics embedded_serialize::DeserializeBorrowed<#lifetime> for #name #ty_generics #where_clause {
+                fn deserialize_borrowed(buf: &#lifetime [u8]) -> Result<(Self, usize), embedded_serialize::DeserializeError> {
+                    #body
                 }
-                Fields::Unit => {}
             }
 
+            #extra
+        },
+        None => quote! {
+            impl #impl_generics embedded_serialize::Deserialize for #name #ty_generics #where_clause {
+                fn deserialize(buf: &[u8]) -> Result<Self, embedded_serialize::DeserializeError> {
+                    Self::deserialize_consumed(buf).map(|(value, _)| value)
+                }
+
+                fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), embedded_serialize::DeserializeError> {
+                    #body
+                }
+            }
+
+            #extra
+        },
+    };
+
+    deserialize_impl.into()
+}
+
+/// Generates `<Name>View<'a>`, which checks the buffer length once and then decodes
+/// each field from its fixed offset when its accessor is called.
+fn view_impl(input: &DeriveInput, container: &ContainerAttrs, infos: &[FieldInfo]) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&input.generics, "view is not supported on generic structs"));
+    }
+    if container.tlv || container.pack_bools || container.context.is_some() || !container.magic.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "view needs a fixed layout, without tlv, pack_bools, context or magic",
+        ));
+    }
+    let fields: Vec<&FieldInfo> = infos.iter().filter(|info| !info.attrs.skip).collect();
+    if let Some(info) = fields
+        .iter()
+        .find(|info| {
+            info.attrs.count.is_some()
+                || info.attrs.sentinel.is_some()
+                || info.attrs.with_context
+                || info.attrs.map.is_some()
+                || info.attrs.skip_if.is_some()
+                || info.attrs.crc.is_some()
+                || info.attrs.popcount_of.is_some()
+        })
+    {
+        return Err(syn::Error::new_spanned(info.field, "view fields need a fixed layout"));
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let view = syn::Ident::new(&format!("{}View", name), name.span());
+    let size = |info: &&FieldInfo| {
+        let ty = &info.field.ty;
+        (info.cfg(), quote! { <#ty as embedded_serialize::layout::FixedLayout>::SIZE })
+    };
+    let sizes: Vec<(TokenStream2, TokenStream2)> = fields.iter().map(size).collect();
+    let size = cfg_sum(sizes.clone());
+    let accessors = fields.iter().enumerate().map(|(index, info)| {
+        let accessor = &info.binding;
+        let ty = &info.field.ty;
+        let cfg = info.cfg();
+        let offset = cfg_sum(sizes[..index].to_vec());
+        let doc = format!("Decodes `{}` from the buffer.", accessor);
+        quote! {
+            #cfg
+            #[doc = #doc]
+            #vis fn #accessor(&self) -> Result<#ty, embedded_serialize::DeserializeError> {
+                const OFFSET: usize = #offset;
+                <#ty as embedded_serialize::Deserialize>::deserialize(&self.buf[OFFSET..])
+            }
+        }
+    });
+    let doc = format!("A `{}` read in place, decoding each field on access.", name);
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #view<'a> {
+            buf: &'a [u8],
+        }
+
+        impl<'a> #view<'a> {
+            /// Serialized size of the whole struct
+            #vis const SIZE: usize = #size;
+
+            /// Views the struct at the front of `buf`, failing if `buf` is too short to hold it.
+            #vis fn new(buf: &'a [u8]) -> Result<Self, embedded_serialize::DeserializeError> {
+                let buf = buf
+                    .get(..Self::SIZE)
+                    .ok_or(embedded_serialize::DeserializeError::BufferTooSmall)?;
+                Ok(#view { buf })
+            }
+
+            /// The serialized struct
+            #vis fn as_bytes(&self) -> &'a [u8] {
+                self.buf
+            }
+
+            #(#accessors)*
+        }
+    })
+}
+
+/// Implements `DeserializeWith<context>` for a struct with a context type.
+fn context_deserialize_impl(
+    input: &DeriveInput,
+    infos: &[FieldInfo],
+    context: &syn::Type,
+    body: &TokenStream2,
+) -> TokenStream2 {
+    let name = &input.ident;
+    let (plain, contextual): (Vec<&FieldInfo>, Vec<&FieldInfo>) =
+        infos.iter().partition(|info| !info.attrs.with_context);
+    let generics = deserialize_generics(&input.generics, plain, None);
+    let bound = quote!(embedded_serialize::DeserializeWith<#context>);
+    let generics = bounded_generics(&generics, contextual, bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics embedded_serialize::DeserializeWith<#context> for #name #ty_generics #where_clause {
+            fn deserialize_with(
+                context: &#context,
+                buf: &[u8],
+            ) -> Result<(Self, usize), embedded_serialize::DeserializeError> {
+                #body
+            }
+        }
+    }
+}
+
+/// Bounds the field types by `DeserializeBorrowed<'lifetime>` for borrowing types, `Deserialize` otherwise.
+fn deserialize_generics<'a, 'f: 'a>(
+    generics: &syn::Generics,
+    infos: impl IntoIterator<Item = &'a FieldInfo<'f>>,
+    lifetime: Option<&syn::Lifetime>,
+) -> syn::Generics {
+    let bound = match lifetime {
+        Some(lifetime) => quote!(embedded_serialize::DeserializeBorrowed<#lifetime>),
+        None => quote!(embedded_serialize::Deserialize),
+    };
+    bounded_generics(generics, infos, bound)
+}
+
+#[proc_macro_derive(FixedLayout, attributes(es))]
+pub fn derive_fixed_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident.clone();
+
+    let layout_impl = match input.data {
+        Data::Struct(ref data_struct) => {
+            let infos = match field_infos(&data_struct.fields, false) {
+                Ok(infos) => infos,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            if let Some(info) = infos.iter().find(|info| info.attrs.count.is_some()) {
+                return syn::Error::new_spanned(info.field, "counted fields have no fixed layout")
+                    .to_compile_error()
+                    .into();
+            }
+            if let Some(info) = infos.iter().find(|info| info.attrs.map.is_some()) {
+                return syn::Error::new_spanned(info.field, "mapped fields have no fixed layout")
+                    .to_compile_error()
+                    .into();
+            }
+            if let Some(info) = infos.iter().find(|info| info.attrs.skip_if.is_some()) {
+                return syn::Error::new_spanned(info.field, "skip_if fields have no fixed layout")
+                    .to_compile_error()
+                    .into();
+            }
+            match parse_container_attrs(&input) {
+                Ok(container) if container.pack_bools => {
+                    return syn::Error::new_spanned(&input.ident, "packed bools have no fixed layout")
+                        .to_compile_error()
+                        .into();
+                }
+                Ok(container) if !container.magic.is_empty() => {
+                    return syn::Error::new_spanned(&input.ident, "magic is not supported with FixedLayout")
+                        .to_compile_error()
+                        .into();
+                }
+                Ok(_) => {}
+                Err(err) => return err.to_compile_error().into(),
+            }
+            let fields: Vec<_> = infos.iter().filter(|info| !info.attrs.skip).collect();
+            let cfgs: Vec<_> = fields.iter().map(|info| info.cfg()).collect();
+            let types: Vec<_> = fields.iter().map(|info| &info.field.ty).collect();
+            let size = cfg_sum(
+                cfgs.iter()
+                    .cloned()
+                    .zip(types.iter().map(|ty| quote! { <#ty as embedded_serialize::layout::FixedLayout>::SIZE }))
+                    .collect(),
+            );
+
+            let generics = bounded_generics(&input.generics, &infos, quote!(embedded_serialize::layout::FixedLayout));
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
             quote! {
-                impl embedded_serialize::Deserialize for #name {
-                    fn deserialize(buf: &[u8]) -> Result<Self, embedded_serialize::DeserializeError> {
+                impl #impl_generics embedded_serialize::layout::FixedLayout for #name #ty_generics #where_clause {
+                    const SIZE: usize = #size;
+
+                    fn swap_bytes_in_place(record: &mut [u8]) {
                         let mut offset = 0;
-                        #deserialize_fields
-                        Ok(Self {
-                            #field_initializations
-                        })
+                        #(
+                            #cfgs
+                            {
+                                let size = <#types as embedded_serialize::layout::FixedLayout>::SIZE;
+                                <#types as embedded_serialize::layout::FixedLayout>::swap_bytes_in_place(&mut record[offset..offset + size]);
+                                offset += size;
+                            }
+                        )*
+                        let _ = (record, offset);
                     }
                 }
             }
         }
         _ => {
-           
             return syn::Error::new_spanned(
                 input.ident,
-                "Deserialize can only be derived for structs",
+                "FixedLayout can only be derived for structs",
             )
             .to_compile_error()
             .into();
         }
     };
 
-    deserialize_impl.into()
+    layout_impl.into()
+}
+
+/// A `schema::Field` for one serialized field.
+/// Sentinel options and counted byte slices are opaque; other counted fields are described
+/// through their element type.
+fn field_schema(info: &FieldInfo) -> TokenStream2 {
+    let name = info.binding.to_string();
+    let ty = &info.field.ty;
+    let counted = Counted::of(ty).and_then(|counted| Some((counted.item()?, counted.max()?)));
+    let schema = match (&info.attrs.count, &info.attrs.sentinel, counted) {
+        (Some(count), _, Some((item, max))) => {
+            let count = count.to_string();
+            quote! {
+                &embedded_serialize::schema::Schema::Counted {
+                    item: <#item as embedded_serialize::schema::Describe>::SCHEMA,
+                    max: #max,
+                    count: #count,
+                }
+            }
+        }
+        (Some(_), _, None) => quote! { &embedded_serialize::schema::Schema::Opaque { name: "counted byte slice" } },
+        (None, Some(_), _) => quote! { &embedded_serialize::schema::Schema::Opaque { name: "sentinel option" } },
+        _ if info.attrs.map.is_some() => quote! { &embedded_serialize::schema::Schema::Opaque { name: "mapped field" } },
+        _ => quote! { <#ty as embedded_serialize::schema::Describe>::SCHEMA },
+    };
+    let cfg = info.cfg();
+    quote! {
+        #cfg
+        embedded_serialize::schema::Field { name: #name, schema: #schema }
+    }
+}
+
+/// The fields whose types must implement `Describe`.
+fn described_fields<'a, 'f>(infos: &'a [FieldInfo<'f>]) -> impl Iterator<Item = &'a FieldInfo<'f>> {
+    infos
+        .iter()
+        .filter(|info| info.attrs.sentinel.is_none() && info.attrs.map.is_none())
+}
+
+#[proc_macro_derive(Describe, attributes(es))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident.clone();
+    let name_str = name.to_string();
+
+    let (schema, generics) = match input.data {
+        Data::Struct(ref data_struct) => {
+            let infos = match field_infos(&data_struct.fields, false) {
+                Ok(infos) => infos,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let container = match parse_container_attrs(&input) {
+                Ok(container) => container,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let fields = infos.iter().filter(|info| !info.attrs.skip).map(field_schema);
+            // `Schema::Struct` has no room for the bitfields, conditional fields or magic.
+            let schema = if container.tlv
+                || container.pack_bools
+                || !conditional_fields(&infos).is_empty()
+                || !container.magic.is_empty()
+            {
+                quote! { embedded_serialize::schema::Schema::Opaque { name: #name_str } }
+            } else {
+                quote! {
+                    embedded_serialize::schema::Schema::Struct {
+                        name: #name_str,
+                        fields: &[#(#fields),*],
+                    }
+                }
+            };
+            let bound = quote!(embedded_serialize::schema::Describe);
+            (schema, bounded_generics(&input.generics, described_fields(&infos), bound))
+        }
+        Data::Enum(ref data_enum) => {
+            let (container, variants) = match check_enum_container(&input)
+                .and_then(|container| Ok((container, variant_infos(data_enum)?)))
+            {
+                Ok(parsed) => parsed,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let descriptions = variants.iter().map(|variant| {
+                let variant_name = variant.variant.ident.to_string();
+                let tag = u32::from(variant.tag);
+                let fields = variant.fields.iter().filter(|info| !info.attrs.skip).map(field_schema);
+                quote! {
+                    embedded_serialize::schema::Variant {
+                        name: #variant_name,
+                        tag: #tag,
+                        fields: &[#(#fields),*],
+                    }
+                }
+            });
+            // `Schema::Enum` has no room for the length prefix, padding or magic.
+            let schema = if container.length_prefixed || container.payload_align.is_some() || !container.magic.is_empty() {
+                quote! { embedded_serialize::schema::Schema::Opaque { name: #name_str } }
+            } else {
+                quote! {
+                    embedded_serialize::schema::Schema::Enum {
+                        name: #name_str,
+                        variants: &[#(#descriptions),*],
+                    }
+                }
+            };
+            let fields = variants.iter().flat_map(|variant| described_fields(&variant.fields));
+            let bound = quote!(embedded_serialize::schema::Describe);
+            (schema, bounded_generics(&input.generics, fields, bound))
+        }
+        _ => {
+            return syn::Error::new_spanned(
+                input.ident,
+                "Describe can only be derived for structs and enums",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let describe_impl = quote! {
+        impl #impl_generics embedded_serialize::schema::Describe for #name #ty_generics #where_clause {
+            const SCHEMA: &'static embedded_serialize::schema::Schema = &#schema;
+        }
+    };
+
+    describe_impl.into()
 }
@@ -0,0 +1,40 @@
+use embedded_serialize::{DeserializeBorrowed, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Calibration<'a> {
+    version: u8,
+    blob: &'a [u8; 256],
+    crc: u16,
+}
+
+static BLOB: [u8; 256] = {
+    let mut blob = [0u8; 256];
+    let mut index = 0;
+    while index < 256 {
+        blob[index] = index as u8;
+        index += 1;
+    }
+    blob
+};
+
+#[test]
+fn decoded_reference_aliases_the_input() {
+    let calibration = Calibration { version: 3, blob: &BLOB, crc: 0xABCD };
+    let mut buf = [0u8; 300];
+    let size = calibration.serialize(&mut buf).unwrap();
+    assert_eq!(size, 259);
+    assert_eq!(calibration.serialized_size(), size);
+    assert_eq!(buf[1..257], BLOB);
+    assert_eq!(buf[257..259], [0xAB, 0xCD]);
+
+    let (decoded, consumed) = Calibration::deserialize_borrowed(&buf[..size]).unwrap();
+    assert_eq!((&decoded, consumed), (&calibration, size));
+    assert!(core::ptr::eq(decoded.blob.as_ptr(), buf[1..].as_ptr()));
+}
+
+#[test]
+fn short_blob_is_rejected() {
+    let buf = [3u8; 200];
+    assert!(matches!(Calibration::deserialize_borrowed(&buf), Err(DeserializeError::BufferTooSmall)));
+}
@@ -0,0 +1,55 @@
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Packet {
+    header: u16,
+    samples: [u16; 4],
+}
+
+/// Its wire size (2 or 3 bytes) differs from its size in memory.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+struct SmallStruct {
+    id: u8,
+    reading: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Batch {
+    items: [SmallStruct; 3],
+    trailer: u8,
+}
+
+#[test]
+fn packet_round_trip() {
+    let packet = Packet { header: 0xA55A, samples: [1, 2, 3, 4] };
+    let mut buf = [0u8; 16];
+    let size = packet.serialize(&mut buf).unwrap();
+    assert_eq!(size, 10);
+    assert_eq!(buf[..size], [0xA5, 0x5A, 0, 1, 0, 2, 0, 3, 0, 4]);
+    assert_eq!(Packet::deserialize_consumed(&buf).unwrap(), (packet, 10));
+}
+
+#[test]
+fn nested_struct_array_advances_by_consumed_bytes() {
+    let batch = Batch {
+        items: [
+            SmallStruct { id: 1, reading: None },
+            SmallStruct { id: 2, reading: Some(20) },
+            SmallStruct { id: 3, reading: None },
+        ],
+        trailer: 0xEE,
+    };
+    let mut buf = [0u8; 16];
+    let size = batch.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [1, 0, 2, 1, 20, 3, 0, 0xEE]);
+    assert_eq!(Batch::deserialize_consumed(&buf).unwrap(), (batch, 8));
+}
+
+#[test]
+fn slice_overflow_names_the_record() {
+    let records: [SmallStruct; 5] = core::array::from_fn(|id| SmallStruct { id: id as u8, reading: Some(0) });
+    let mut buf = [0u8; 8];
+    let err = embedded_serialize::serialize_all(&records, &mut buf).unwrap_err();
+    assert!(matches!(err, embedded_serialize::SerializeError::BufferTooSmallAt(2)));
+}
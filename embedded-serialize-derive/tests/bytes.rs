@@ -0,0 +1,50 @@
+use embedded_serialize::bytes::{ByteBuf, Bytes};
+use embedded_serialize::{Deserialize, DeserializeBorrowed, DeserializeError, Serialize, SerializeError};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Chunk<'a> {
+    id: u8,
+    #[es(max_len = 4)]
+    payload: Bytes<'a>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct OwnedChunk {
+    id: u8,
+    #[es(max_len = 2)]
+    payload: ByteBuf<8>,
+}
+
+#[test]
+fn borrowed_field_aliases_the_input() {
+    let mut buf = [0u8; 16];
+    let size = Chunk { id: 1, payload: Bytes(&[5, 6, 7]) }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [1, 0, 3, 5, 6, 7]);
+
+    let (chunk, consumed) = Chunk::deserialize_borrowed(&buf[..size]).unwrap();
+    assert_eq!(consumed, size);
+    assert!(core::ptr::eq(chunk.payload.0.as_ptr(), buf[3..].as_ptr()));
+}
+
+#[test]
+fn max_len_is_enforced_both_ways() {
+    let mut buf = [0u8; 16];
+    let long = Chunk { id: 1, payload: Bytes(&[0; 5]) };
+    assert!(matches!(long.serialize(&mut buf), Err(SerializeError::InvalidData)));
+
+    let wire = [1, 0, 5, 0, 0, 0, 0, 0];
+    assert!(matches!(Chunk::deserialize_borrowed(&wire), Err(DeserializeError::InvalidData)));
+}
+
+#[test]
+fn owned_round_trip_with_max_len() {
+    let mut buf = [0u8; 16];
+    let chunk = OwnedChunk { id: 2, payload: ByteBuf::from_slice(&[1, 2]).unwrap() };
+    let size = chunk.serialize(&mut buf).unwrap();
+    assert_eq!(OwnedChunk::deserialize(&buf[..size]).unwrap(), chunk);
+
+    let long = OwnedChunk { id: 2, payload: ByteBuf::from_slice(&[1, 2, 3]).unwrap() };
+    assert!(matches!(long.serialize(&mut buf), Err(SerializeError::InvalidData)));
+    assert!(matches!(OwnedChunk::deserialize(&[2, 0, 3, 1, 2, 3]), Err(DeserializeError::InvalidData)));
+}
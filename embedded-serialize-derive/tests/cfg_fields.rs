@@ -0,0 +1,80 @@
+use embedded_serialize::schema::Describe;
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Describe, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Describe, Debug, Clone, Copy, PartialEq)]
+pub struct GpsFix {
+    lat: i32,
+    lon: i32,
+}
+
+/// Declares `Report` with its `position` field under `#[cfg($gps)]`, so one build can
+/// check the derives with the field compiled in (`all()`) and compiled out (`any()`),
+/// as a hardware feature would select it.
+macro_rules! report {
+    ($gps:meta) => {
+        #[derive(Serialize, Deserialize, Describe, Debug, PartialEq)]
+        pub struct Report {
+            pub seq: u16,
+            #[cfg($gps)]
+            pub position: crate::GpsFix,
+            pub battery: u8,
+        }
+    };
+}
+
+mod with_gps {
+    use super::*;
+
+    report!(all());
+}
+
+mod without_gps {
+    use super::*;
+
+    report!(any());
+}
+
+mod never_had_gps {
+    use super::*;
+
+    #[derive(Serialize, Describe)]
+    pub struct Report {
+        pub seq: u16,
+        pub battery: u8,
+    }
+}
+
+#[test]
+fn round_trip_with_field_compiled_in() {
+    let report = with_gps::Report { seq: 1, position: GpsFix { lat: -5, lon: 7 }, battery: 90 };
+    let mut buf = [0u8; 16];
+    let size = report.serialize(&mut buf).unwrap();
+    assert_eq!(size, 11);
+    assert_eq!(report.serialized_size(), size);
+    assert_eq!(buf[..size], [0, 1, 0xFF, 0xFF, 0xFF, 0xFB, 0, 0, 0, 7, 90]);
+    assert_eq!(with_gps::Report::deserialize(&buf[..size]).unwrap(), report);
+}
+
+#[test]
+fn round_trip_with_field_compiled_out() {
+    let report = without_gps::Report { seq: 1, battery: 90 };
+    let mut buf = [0u8; 16];
+    let size = report.serialize(&mut buf).unwrap();
+    assert_eq!(size, 3);
+    assert_eq!(report.serialized_size(), size);
+    assert_eq!(buf[..size], [0, 1, 90]);
+    assert_eq!(without_gps::Report::deserialize(&buf[..size]).unwrap(), report);
+
+    let mut plain = [0u8; 16];
+    let plain_size = never_had_gps::Report { seq: 1, battery: 90 }.serialize(&mut plain).unwrap();
+    assert_eq!(plain[..plain_size], buf[..size]);
+}
+
+#[test]
+fn schema_hash_follows_the_cfg() {
+    let with = with_gps::Report::SCHEMA.hash();
+    let without = without_gps::Report::SCHEMA.hash();
+    assert_ne!(with, without);
+    assert_eq!(without, never_had_gps::Report::SCHEMA.hash());
+}
@@ -0,0 +1,84 @@
+use embedded_serialize::codec::{Codec, CobsCodec, FramedCrcCodec, LineHexCodec};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+enum Request {
+    ReadRegister { address: u16 },
+    WriteRegister { address: u16, value: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Response {
+    status: u8,
+    value: Option<u32>,
+}
+
+/// Zero bytes and `\n` (0x0A) that the framings must carry through
+fn exchanges() -> [(Request, Response); 3] {
+    [
+        (Request::ReadRegister { address: 0x000A }, Response { status: 0, value: Some(0x0A00_0000) }),
+        (Request::WriteRegister { address: 0x0100, value: 0 }, Response { status: 0, value: None }),
+        (Request::ReadRegister { address: 0xFFFF }, Response { status: 2, value: None }),
+    ]
+}
+
+/// Moves `bytes` across the link in 3-byte chunks, so frames arrive split, and returns
+/// the one message they carry.
+fn transfer<C: Codec>(receiver: &mut C, bytes: &[u8]) -> C::Rx {
+    let mut received = None;
+    for chunk in bytes.chunks(3) {
+        if let Some(msg) = receiver.feed(chunk).unwrap() {
+            assert!(received.is_none(), "more than one frame decoded");
+            received = Some(msg);
+        }
+    }
+    assert!(receiver.feed(&[]).unwrap().is_none());
+    received.expect("frame not decoded")
+}
+
+/// Sends each request from `host` to `device` and each response back.
+fn duplex<H, D>(mut host: H, mut device: D)
+where
+    H: Codec<Tx = Request, Rx = Response>,
+    D: Codec<Tx = Response, Rx = Request>,
+{
+    let mut wire = [0u8; 64];
+    for (request, response) in exchanges() {
+        let size = host.encode(&request, &mut wire).unwrap();
+        assert_eq!(transfer(&mut device, &wire[..size]), request);
+        let size = device.encode(&response, &mut wire).unwrap();
+        assert_eq!(transfer(&mut host, &wire[..size]), response);
+    }
+}
+
+#[test]
+fn cobs_duplex() {
+    duplex(CobsCodec::<Request, Response, 64>::new(), CobsCodec::<Response, Request, 64>::new());
+}
+
+#[test]
+fn framed_crc_duplex() {
+    duplex(FramedCrcCodec::<Request, Response, 64>::new(), FramedCrcCodec::<Response, Request, 64>::new());
+    duplex(
+        FramedCrcCodec::<Request, Response, 64>::with_whitening(0x1D0F),
+        FramedCrcCodec::<Response, Request, 64>::with_whitening(0x1D0F),
+    );
+}
+
+#[test]
+fn line_hex_duplex() {
+    duplex(LineHexCodec::<Request, Response, 64>::new(), LineHexCodec::<Response, Request, 64>::new());
+}
+
+#[test]
+fn corrupted_frame_is_dropped_and_the_link_recovers() {
+    let mut host = FramedCrcCodec::<Request, Response, 64>::new();
+    let mut device = FramedCrcCodec::<Response, Request, 64>::new();
+    let mut wire = [0u8; 64];
+    let request = Request::ReadRegister { address: 7 };
+    let size = host.encode(&request, &mut wire).unwrap();
+    wire[3] ^= 0x01;
+    assert!(device.feed(&wire[..size]).is_err());
+    wire[3] ^= 0x01;
+    assert_eq!(device.feed(&wire[..size]).unwrap(), Some(request));
+}
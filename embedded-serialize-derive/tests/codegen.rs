@@ -0,0 +1,123 @@
+use embedded_serialize::codegen::{c_header, emit_python, CodegenError, Message};
+use embedded_serialize::fixed_str::FixedStr;
+use embedded_serialize::frame::MessageId;
+use embedded_serialize::schema::Describe;
+use embedded_serialize::Serialize as _;
+use embedded_serialize_derive::{Describe, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Describe)]
+#[repr(u8)]
+enum Mode {
+    Idle = 1,
+    Sampling = 4,
+    Fault = 0x80,
+}
+
+#[derive(Serialize, Deserialize, Describe)]
+struct Calibration {
+    bias: i16,
+    gains: [u16; 3],
+}
+
+#[derive(Serialize, Deserialize, Describe)]
+struct Status {
+    mode: Mode,
+    name: FixedStr<8>,
+    uptime: u32,
+    calibration: Calibration,
+    history: [[i16; 2]; 2],
+    flags: [bool; 4],
+}
+
+impl MessageId for Status {
+    const ID: u8 = 0x21;
+}
+
+#[derive(Serialize, Deserialize, Describe)]
+struct Ack {
+    seq: u8,
+}
+
+impl MessageId for Ack {
+    const ID: u8 = 0x7F;
+}
+
+#[derive(Serialize, Deserialize, Describe)]
+struct Log {
+    len: u8,
+    #[es(count = "len")]
+    entries: [u16; 4],
+}
+
+#[derive(Serialize, Deserialize, Describe)]
+enum Command {
+    Reboot,
+    SetRate(u16),
+}
+
+/// Regenerate with `UPDATE_GOLDEN=1 cargo test -p embedded-serialize-derive --test codegen`.
+#[test]
+fn header_matches_golden_file() {
+    let header = c_header("MESSAGES_H", &[Message::with_id::<Status>(), Message::with_id::<Ack>()]).unwrap();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/messages.h");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, &header).unwrap();
+    }
+    assert_eq!(header, std::fs::read_to_string(path).unwrap());
+}
+
+#[test]
+fn unsupported_constructs_are_reported() {
+    let err = c_header("LOG_H", &[Message::new::<Log>()]).unwrap_err();
+    assert!(matches!(err, CodegenError::Unsupported { ref path, .. } if path == "Log.entries"), "{err}");
+    let err = c_header("COMMAND_H", &[Message::new::<Command>()]).unwrap_err();
+    assert!(matches!(err, CodegenError::Unsupported { ref path, .. } if path == "Command"), "{err}");
+}
+
+#[derive(Serialize, Deserialize, Describe)]
+struct Report {
+    status: Status,
+    command: Command,
+    len: u8,
+    #[es(count = "len")]
+    readings: [i16; 4],
+}
+
+/// Bytes of `report()`, also decoded by `golden/decode_report.py`.
+const REPORT_HEX: &str = "0473656e736f7200000000012c0064000100020003fff6000a000a00140100010101012c02fffe0007";
+
+fn report() -> Report {
+    Report {
+        status: Status {
+            mode: Mode::Sampling,
+            name: FixedStr::new("sensor"),
+            uptime: 300,
+            calibration: Calibration { bias: 100, gains: [1, 2, 3] },
+            history: [[-10, 10], [10, 20]],
+            flags: [true, false, true, true],
+        },
+        command: Command::SetRate(300),
+        len: 2,
+        readings: [-2, 7, 0, 0],
+    }
+}
+
+/// Regenerate with `UPDATE_GOLDEN=1 cargo test -p embedded-serialize-derive --test codegen`.
+#[test]
+fn python_matches_golden_file() {
+    let mut python = String::new();
+    emit_python(<Report as Describe>::SCHEMA, &mut python).unwrap();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/report.py");
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, &python).unwrap();
+    }
+    assert_eq!(python, std::fs::read_to_string(path).unwrap());
+}
+
+#[test]
+fn python_example_bytes_come_from_rust() {
+    let mut buf = [0u8; 64];
+    let size = report().serialize(&mut buf).unwrap();
+    let hex: String = buf[..size].iter().map(|byte| format!("{byte:02x}")).collect();
+    assert_eq!(hex, REPORT_HEX);
+}
@@ -0,0 +1,84 @@
+use embedded_serialize::{Deserialize, DeserializeError, DeserializeWith, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// Payload layout negotiated when the session opened
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    A,
+    B,
+}
+
+struct SessionCtx {
+    mode: Mode,
+}
+
+#[derive(Debug, PartialEq)]
+enum Payload {
+    /// Mode A: one 16-bit reading
+    Reading(u16),
+    /// Mode B: two signed 8-bit offsets
+    Offsets(i8, i8),
+}
+
+impl DeserializeWith<SessionCtx> for Payload {
+    fn deserialize_with(context: &SessionCtx, buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        match context.mode {
+            Mode::A => u16::deserialize_consumed(buf).map(|(reading, size)| (Payload::Reading(reading), size)),
+            Mode::B => {
+                let (x, _) = i8::deserialize_consumed(buf)?;
+                let (y, _) = i8::deserialize_consumed(buf.get(1..).ok_or(DeserializeError::BufferTooSmall)?)?;
+                Ok((Payload::Offsets(x, y), 2))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[es(context = "SessionCtx")]
+struct Message {
+    seq: u8,
+    #[es(with_context)]
+    payload: Payload,
+    flags: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Plain {
+    id: u8,
+    value: u16,
+}
+
+#[test]
+fn same_bytes_decode_differently_per_context() {
+    let bytes = [7, 0xFF, 0x02, 0x80];
+
+    let a = SessionCtx { mode: Mode::A };
+    assert_eq!(
+        Message::deserialize_with(&a, &bytes).unwrap(),
+        (Message { seq: 7, payload: Payload::Reading(0xFF02), flags: 0x80 }, 4)
+    );
+
+    let b = SessionCtx { mode: Mode::B };
+    assert_eq!(
+        Message::deserialize_with(&b, &bytes).unwrap(),
+        (Message { seq: 7, payload: Payload::Offsets(-1, 2), flags: 0x80 }, 4)
+    );
+}
+
+#[test]
+fn context_errors_propagate() {
+    let a = SessionCtx { mode: Mode::A };
+    assert!(matches!(Message::deserialize_with(&a, &[7, 0xFF]), Err(DeserializeError::BufferTooSmall)));
+}
+
+#[test]
+fn context_free_types_ignore_the_context() {
+    let plain = Plain { id: 3, value: 0x0102 };
+    let mut buf = [0u8; 3];
+    plain.serialize(&mut buf).unwrap();
+
+    let b = SessionCtx { mode: Mode::B };
+    assert_eq!(Plain::deserialize_with(&b, &buf).unwrap(), (Plain { id: 3, value: 0x0102 }, 3));
+    assert_eq!(Plain::deserialize_with(&(), &buf).unwrap(), Plain::deserialize_consumed(&buf).unwrap());
+    assert_eq!(u16::deserialize_with(&b, &[1, 2]).unwrap(), (0x0102, 2));
+}
@@ -0,0 +1,109 @@
+use embedded_serialize::{Deserialize, DeserializeBorrowed, DeserializeError, Serialize, SerializeError};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Block {
+    n_samples: u8,
+    #[es(no_prefix, count = "n_samples")]
+    samples: [u16; 4],
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct VecBlock {
+    n: u16,
+    #[es(count = "n")]
+    items: heapless::Vec<u8, 4>,
+}
+
+#[test]
+fn counted_array_round_trip() {
+    let block = Block { n_samples: 2, samples: [0x0102, 0x0304, 0, 0] };
+    let mut buf = [0u8; 16];
+    let size = block.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [2, 0x01, 0x02, 0x03, 0x04]);
+    assert_eq!(block.serialized_size(), size);
+    assert_eq!(Block::deserialize(&buf[..size]).unwrap(), block);
+}
+
+#[test]
+fn counted_array_errors() {
+    let block = Block { n_samples: 5, samples: [0; 4] };
+    let mut buf = [0u8; 16];
+    assert!(matches!(block.serialize(&mut buf), Err(SerializeError::InvalidData)));
+
+    assert!(matches!(Block::deserialize(&[2, 0x01, 0x02, 0x03]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(Block::deserialize(&[5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]), Err(DeserializeError::InvalidData)));
+}
+
+#[test]
+fn counted_vec_round_trip() {
+    let block = VecBlock { n: 3, items: heapless::Vec::from_slice(&[7, 8, 9]).unwrap() };
+    let mut buf = [0u8; 8];
+    let size = block.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0, 3, 7, 8, 9]);
+    assert_eq!(VecBlock::deserialize(&buf[..size]).unwrap(), block);
+
+    let mismatched = VecBlock { n: 2, items: block.items.clone() };
+    assert!(matches!(mismatched.serialize(&mut buf), Err(SerializeError::InvalidData)));
+    assert!(matches!(VecBlock::deserialize(&[0, 5, 1, 2, 3, 4, 5]), Err(DeserializeError::InvalidData)));
+}
+
+/// A Modbus "read holding registers" response: unit address, function code, byte count,
+/// then the register bytes and a CRC the transport checks separately
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ReadRegistersResponse<'a> {
+    address: u8,
+    function: u8,
+    byte_count: u8,
+    #[es(count = "byte_count")]
+    data: &'a [u8],
+    crc: u16,
+}
+
+/// A sensor hub report with the channel count ahead of an unrelated field
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ChannelReport {
+    n_channels: u8,
+    status: u8,
+    #[es(count = "n_channels")]
+    channels: heapless::Vec<i16, 4>,
+}
+
+#[test]
+fn reference_modbus_frame() {
+    // Unit 0x11 answering with registers 0x022B and 0x0000. Modbus sends its CRC-16
+    // (0x429A here) low byte first, so this big-endian field reads it as 0x9A42.
+    let frame = [0x11, 0x03, 0x04, 0x02, 0x2B, 0x00, 0x00, 0x9A, 0x42];
+    let (response, consumed) = ReadRegistersResponse::deserialize_borrowed(&frame).unwrap();
+    assert_eq!(consumed, frame.len());
+    assert_eq!(
+        response,
+        ReadRegistersResponse { address: 0x11, function: 0x03, byte_count: 4, data: &frame[3..7], crc: 0x9A42 }
+    );
+    assert!(core::ptr::eq(response.data, &frame[3..7]));
+
+    let mut buf = [0u8; 16];
+    let size = response.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], frame);
+
+    let wrong = ReadRegistersResponse { byte_count: 3, ..response };
+    assert!(matches!(wrong.serialize(&mut buf), Err(SerializeError::InvalidData)));
+    assert!(matches!(ReadRegistersResponse::deserialize_borrowed(&frame[..6]), Err(DeserializeError::BufferTooSmall)));
+}
+
+#[test]
+fn reference_channel_report() {
+    let frame = [3, 0x80, 0x00, 0x10, 0xFF, 0xF0, 0x7F, 0xFF];
+    let report = ChannelReport::deserialize(&frame).unwrap();
+    assert_eq!((report.n_channels, report.status), (3, 0x80));
+    assert_eq!(report.channels[..], [16, -16, i16::MAX]);
+
+    let mut buf = [0u8; 16];
+    let size = report.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], frame);
+    assert_eq!(report.serialized_size(), size);
+
+    // Five channels announced for a four-channel container.
+    let frame = [5, 0, 0, 1, 0, 2, 0, 3, 0, 4, 0, 5];
+    assert!(matches!(ChannelReport::deserialize(&frame), Err(DeserializeError::InvalidData)));
+}
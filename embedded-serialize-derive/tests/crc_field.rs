@@ -0,0 +1,83 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Check {
+    text: [u8; 9],
+    #[es(crc = "crc16_ccitt")]
+    crc: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(magic = [0x7E])]
+struct Small {
+    text: [u8; 9],
+    #[es(crc = "crc8")]
+    crc: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Sum {
+    text: [u8; 5],
+    #[es(crc = "fletcher16")]
+    sum: u16,
+}
+
+/// XOR of every byte, as a user-supplied checksum
+fn xor(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |acc, byte| acc ^ byte)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Custom {
+    a: u8,
+    b: u16,
+    #[es(crc = "xor")]
+    check: u8,
+}
+
+#[test]
+fn correct_frames_carry_the_reference_checksums() {
+    let mut buf = [0u8; 16];
+    let size = Check { text: *b"123456789", crc: 0 }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], *b"123456789\x29\xB1");
+    assert_eq!(Check::deserialize(&buf[..size]).unwrap(), Check { text: *b"123456789", crc: 0x29B1 });
+
+    // The magic is written by the struct, so it is covered too.
+    let size = Small { text: *b"123456789", crc: 0 }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[size - 1], embedded_serialize::crc::crc8(b"\x7E123456789"));
+    assert_eq!(embedded_serialize::crc::crc8(b"123456789"), 0xF4);
+    assert!(Small::deserialize(&buf[..size]).is_ok());
+
+    let size = Sum { text: *b"abcde", sum: 0 }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[size - 2..size], [0xC8, 0xF0]);
+
+    let size = Custom { a: 0x0F, b: 0xF0FF, check: 0 }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0x0F, 0xF0, 0xFF, 0x00]);
+    assert_eq!(Custom::deserialize(&buf[..size]).unwrap().check, 0);
+}
+
+#[test]
+fn in_memory_value_is_ignored() {
+    let mut zero = [0u8; 16];
+    let mut stale = [0u8; 16];
+    let size = Check { text: *b"123456789", crc: 0 }.serialize(&mut zero).unwrap();
+    assert_eq!(Check { text: *b"123456789", crc: 0xDEAD }.serialize(&mut stale).unwrap(), size);
+    assert_eq!(zero, stale);
+    assert_eq!(Check { text: *b"123456789", crc: 0xDEAD }.serialized_size(), 11);
+}
+
+#[test]
+fn corrupted_bytes_fail_the_check() {
+    let mut buf = [0u8; 16];
+    let size = Check { text: *b"123456789", crc: 0 }.serialize(&mut buf).unwrap();
+    for index in 0..size {
+        let mut corrupted = buf;
+        corrupted[index] ^= 0x01;
+        assert!(matches!(
+            Check::deserialize(&corrupted[..size]),
+            Err(DeserializeError::ChecksumMismatch)
+        ));
+    }
+    assert!(matches!(Check::deserialize(&buf[..size - 1]), Err(DeserializeError::BufferTooSmall)));
+}
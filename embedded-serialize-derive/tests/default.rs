@@ -0,0 +1,28 @@
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+mod defaults {
+    pub fn unset_channel() -> u8 {
+        0xFF
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Config {
+    rate: u16,
+    #[es(skip, default = "defaults::unset_channel")]
+    channel: u8,
+    #[es(skip)]
+    cached: u32,
+}
+
+#[test]
+fn skipped_field_takes_custom_default() {
+    let config = Config { rate: 500, channel: 3, cached: 9 };
+    let mut buf = [0u8; 8];
+    let size = config.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0x01, 0xF4]);
+
+    let decoded = Config::deserialize(&buf[..size]).unwrap();
+    assert_eq!(decoded, Config { rate: 500, channel: 0xFF, cached: 0 });
+}
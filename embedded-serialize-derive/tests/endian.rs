@@ -0,0 +1,30 @@
+use embedded_serialize::endian::{U16Be, U16Le, U32Be, U32Le};
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// A register image whose fields use different byte orders
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Registers {
+    status: U16Be,
+    control: U16Le,
+    counter: U32Le,
+    id: U32Be,
+}
+
+#[test]
+fn mixed_endian_struct_round_trip() {
+    let registers = Registers {
+        status: 0x0102.into(),
+        control: 0x0304.into(),
+        counter: 0x0506_0708.into(),
+        id: 0x090A_0B0C.into(),
+    };
+    let mut buf = [0u8; 12];
+    assert_eq!(registers.serialize(&mut buf).unwrap(), 12);
+    assert_eq!(buf, [0x01, 0x02, 0x04, 0x03, 0x08, 0x07, 0x06, 0x05, 0x09, 0x0A, 0x0B, 0x0C]);
+
+    let decoded = Registers::deserialize(&buf).unwrap();
+    assert_eq!(decoded, registers);
+    assert_eq!(decoded.control.get(), 0x0304);
+    assert_eq!(core::mem::size_of::<Registers>(), 12);
+}
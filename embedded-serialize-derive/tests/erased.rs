@@ -0,0 +1,101 @@
+use embedded_serialize::erased::{serialize_dyn_all, SerializeDyn};
+use embedded_serialize::Serialize;
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+struct Ack {
+    seq: u8,
+}
+
+#[derive(Serialize)]
+#[es(tlv)]
+struct Provisioning {
+    #[es(tag = 1)]
+    node: u16,
+    #[es(tag = 2)]
+    name: [u8; 4],
+}
+
+#[derive(Serialize)]
+struct Reading {
+    #[es(option = "sentinel", sentinel = 0xFFFF)]
+    channel: Option<u16>,
+    value: i32,
+}
+
+#[derive(Serialize)]
+#[es(length_prefixed, magic = [0xA5])]
+enum Command {
+    Reboot,
+    SetRate { rate: u16, limit: Option<u8> },
+}
+
+#[derive(Serialize)]
+#[es(payload_align = 4)]
+enum Aligned {
+    Empty,
+    Value(u16),
+}
+
+/// Larger than the scratch buffer the default `serialized_size` measures in
+#[derive(Serialize)]
+struct Dump {
+    id: u8,
+    data: [u8; 300],
+}
+
+fn assert_exact_size(value: &dyn SerializeDyn) {
+    let mut buf = [0u8; 512];
+    let written = value.serialize_dyn(&mut buf).unwrap();
+    assert_eq!(value.serialized_size_dyn(), written);
+}
+
+#[test]
+fn queue_of_different_messages_drains_into_one_buffer() {
+    let ack = Ack { seq: 9 };
+    let provisioning = Provisioning { node: 0x0102, name: *b"abcd" };
+    let reading = Reading { channel: None, value: -1 };
+    let mut queue: heapless::Deque<&dyn SerializeDyn, 4> = heapless::Deque::new();
+    queue.push_back(&ack).ok().unwrap();
+    queue.push_back(&provisioning).ok().unwrap();
+    queue.push_back(&reading).ok().unwrap();
+
+    let total: usize = queue.iter().map(|message| message.serialized_size_dyn()).sum();
+    let mut buf = [0u8; 32];
+    let mut offset = 0;
+    while let Some(message) = queue.pop_front() {
+        offset += message.serialize_dyn(&mut buf[offset..]).unwrap();
+    }
+    assert_eq!(offset, total);
+    assert_eq!(
+        buf[..offset],
+        [9, 0, 10, 1, 2, 0x01, 0x02, 2, 4, b'a', b'b', b'c', b'd', 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+    );
+
+    let values: [&dyn SerializeDyn; 3] = [&ack, &provisioning, &reading];
+    let mut again = [0u8; 32];
+    assert_eq!(serialize_dyn_all(&values, &mut again).unwrap(), offset);
+    assert_eq!(again, buf);
+    assert_ne!(ack.type_id16(), reading.type_id16());
+}
+
+#[test]
+fn derived_sizes_are_exact_without_serializing() {
+    assert_exact_size(&Provisioning { node: 1, name: [0; 4] });
+    assert_exact_size(&Reading { channel: Some(3), value: 0 });
+    assert_exact_size(&Reading { channel: None, value: 0 });
+    assert_exact_size(&Command::Reboot);
+    assert_exact_size(&Command::SetRate { rate: 10, limit: None });
+    assert_exact_size(&Command::SetRate { rate: 10, limit: Some(1) });
+    assert_exact_size(&Aligned::Empty);
+    assert_exact_size(&Aligned::Value(5));
+    assert_exact_size(&Dump { id: 1, data: [0; 300] });
+}
+
+#[test]
+fn size_of_a_value_that_fails_to_serialize_does_not_panic() {
+    // `Some` of the sentinel cannot be written, but its size is still known.
+    let reading = Reading { channel: Some(0xFFFF), value: 0 };
+    assert_eq!(reading.serialized_size(), 6);
+    assert!(reading.serialize(&mut [0u8; 8]).is_err());
+}
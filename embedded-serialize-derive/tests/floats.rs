@@ -0,0 +1,34 @@
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// One accelerometer reading, in g
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Reading {
+    x: f32,
+    y: f32,
+    z: f32,
+    temperature: f64,
+}
+
+#[test]
+fn float_fields_round_trip() {
+    let reading = Reading { x: 0.5, y: -1.0, z: 9.81, temperature: 21.25 };
+    let mut buf = [0u8; 20];
+    assert_eq!(reading.serialize(&mut buf).unwrap(), 20);
+    assert_eq!(reading.serialized_size(), 20);
+    assert_eq!(buf[4..8], (-1.0f32).to_bits().to_be_bytes());
+    assert_eq!(buf[12..], 21.25f64.to_bits().to_be_bytes());
+    assert_eq!(Reading::deserialize(&buf).unwrap(), reading);
+}
+
+#[test]
+fn nan_fields_keep_their_bits() {
+    let reading = Reading { x: f32::NAN, y: -0.0, z: f32::INFINITY, temperature: f64::NAN };
+    let mut buf = [0u8; 20];
+    reading.serialize(&mut buf).unwrap();
+    let decoded = Reading::deserialize(&buf).unwrap();
+    assert_eq!(decoded.x.to_bits(), f32::NAN.to_bits());
+    assert_eq!(decoded.y.to_bits(), (-0.0f32).to_bits());
+    assert_eq!(decoded.z, f32::INFINITY);
+    assert_eq!(decoded.temperature.to_bits(), f64::NAN.to_bits());
+}
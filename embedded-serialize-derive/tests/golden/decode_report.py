@@ -0,0 +1,22 @@
+"""Decodes a Report serialized by the Rust side with the generated report.py.
+
+Run from this directory: python3 decode_report.py
+The bytes are REPORT_HEX in ../codegen.rs, which checks that Rust still produces them.
+"""
+
+from report import Report
+
+REPORT_HEX = "0473656e736f7200000000012c0064000100020003fff6000a000a00140100010101012c02fffe0007"
+
+report = Report.parse(bytes.fromhex(REPORT_HEX))
+print(report)
+
+status = report.status
+assert status.mode.variant == "Sampling"
+assert status.name == "sensor"
+assert status.uptime == 300
+assert status.calibration.bias == 100 and status.calibration.gains == [1, 2, 3]
+assert status.history == [[-10, 10], [10, 20]]
+assert status.flags == [True, False, True, True]
+assert report.command.variant == "SetRate" and report.command.field_0 == 300
+assert report.readings == [-2, 7]
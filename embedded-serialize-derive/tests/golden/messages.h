@@ -0,0 +1,56 @@
+#ifndef MESSAGES_H
+#define MESSAGES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+static inline uint16_t es_get_u16(const uint8_t *p) { return (uint16_t)((p[0] << 8) | p[1]); }
+static inline uint32_t es_get_u32(const uint8_t *p) {
+    return ((uint32_t)p[0] << 24) | ((uint32_t)p[1] << 16) | ((uint32_t)p[2] << 8) | p[3];
+}
+static inline void es_put_u16(uint8_t *p, uint16_t v) { p[0] = (uint8_t)(v >> 8); p[1] = (uint8_t)v; }
+static inline void es_put_u32(uint8_t *p, uint32_t v) {
+    p[0] = (uint8_t)(v >> 24); p[1] = (uint8_t)(v >> 16); p[2] = (uint8_t)(v >> 8); p[3] = (uint8_t)v;
+}
+
+typedef uint8_t Mode;
+enum {
+    Mode_Idle = 1,
+    Mode_Sampling = 4,
+    Mode_Fault = 128,
+};
+
+typedef struct __attribute__((packed)) {
+    uint8_t bias[2];
+    uint8_t gains[3][2];
+} Calibration;
+
+static inline int16_t Calibration_get_bias(const Calibration *m) { return (int16_t)es_get_u16(m->bias); }
+static inline void Calibration_set_bias(Calibration *m, int16_t v) { es_put_u16(m->bias, (uint16_t)v); }
+static inline uint16_t Calibration_get_gains(const Calibration *m, size_t i0) { return (uint16_t)es_get_u16(m->gains[i0]); }
+static inline void Calibration_set_gains(Calibration *m, size_t i0, uint16_t v) { es_put_u16(m->gains[i0], (uint16_t)v); }
+
+typedef struct __attribute__((packed)) {
+    Mode mode;
+    char name[8];
+    uint8_t uptime[4];
+    Calibration calibration;
+    uint8_t history[2][2][2];
+    uint8_t flags[4];
+} Status;
+
+static inline uint32_t Status_get_uptime(const Status *m) { return (uint32_t)es_get_u32(m->uptime); }
+static inline void Status_set_uptime(Status *m, uint32_t v) { es_put_u32(m->uptime, (uint32_t)v); }
+static inline int16_t Status_get_history(const Status *m, size_t i0, size_t i1) { return (int16_t)es_get_u16(m->history[i0][i1]); }
+static inline void Status_set_history(Status *m, size_t i0, size_t i1, int16_t v) { es_put_u16(m->history[i0][i1], (uint16_t)v); }
+
+typedef struct __attribute__((packed)) {
+    uint8_t seq;
+} Ack;
+
+#define STATUS_SIZE 33
+#define STATUS_ID 0x21
+#define ACK_SIZE 1
+#define ACK_ID 0x7F
+
+#endif /* MESSAGES_H */
@@ -0,0 +1,183 @@
+import struct
+
+
+def _scalar(fmt):
+    size = struct.calcsize(fmt)
+
+    def read(data, offset):
+        return struct.unpack_from(fmt, data, offset)[0], offset + size
+
+    return read
+
+
+_u8, _u16, _u32 = _scalar(">B"), _scalar(">H"), _scalar(">I")
+_i8, _i16, _i32 = _scalar(">b"), _scalar(">h"), _scalar(">i")
+
+
+def _bool(data, offset):
+    value, offset = _u8(data, offset)
+    if value > 1:
+        raise ValueError("invalid bool %d" % value)
+    return value == 1, offset
+
+
+def _array(item, count):
+    def read(data, offset):
+        items = []
+        for _ in range(count):
+            value, offset = item(data, offset)
+            items.append(value)
+        return items, offset
+
+    return read
+
+
+def _str(size):
+    def read(data, offset):
+        raw = bytes(data[offset:offset + size])
+        if len(raw) < size:
+            raise ValueError("buffer too small")
+        return raw.rstrip(b"\0").decode("utf-8"), offset + size
+
+    return read
+
+
+def _fields(value, names):
+    return ", ".join("%s=%r" % (name, getattr(value, name)) for name in names)
+
+
+class Mode:
+    Idle = 1
+    Sampling = 4
+    Fault = 128
+
+    def __init__(self, variant, names, **fields):
+        self.variant = variant
+        self.names = names
+        self.__dict__.update(fields)
+
+    def __repr__(self):
+        if not self.names:
+            return "Mode.%s" % self.variant
+        return "Mode.%s(%s)" % (self.variant, _fields(self, self.names))
+
+    @classmethod
+    def parse(cls, data):
+        return cls.parse_from(data, 0)[0]
+
+    @classmethod
+    def parse_from(cls, data, offset):
+        tag, offset = _u8(data, offset)
+        if tag == 1:
+            fields = {}
+            return cls("Idle", (), **fields), offset
+        if tag == 4:
+            fields = {}
+            return cls("Sampling", (), **fields), offset
+        if tag == 128:
+            fields = {}
+            return cls("Fault", (), **fields), offset
+        raise ValueError("unknown Mode tag %d" % tag)
+
+
+class Calibration:
+    FIELDS = ("bias", "gains")
+    SIZE = 8
+    FORMAT = ">h3H"
+
+    def __init__(self, **fields):
+        self.__dict__.update(fields)
+
+    def __repr__(self):
+        return "Calibration(%s)" % _fields(self, self.FIELDS)
+
+    @classmethod
+    def parse(cls, data):
+        return cls.parse_from(data, 0)[0]
+
+    @classmethod
+    def parse_from(cls, data, offset):
+        fields = {}
+        fields["bias"], offset = _i16(data, offset)
+        fields["gains"], offset = _array(_u16, 3)(data, offset)
+        return cls(**fields), offset
+
+
+class Status:
+    FIELDS = ("mode", "name", "uptime", "calibration", "history", "flags")
+    SIZE = 33
+
+    def __init__(self, **fields):
+        self.__dict__.update(fields)
+
+    def __repr__(self):
+        return "Status(%s)" % _fields(self, self.FIELDS)
+
+    @classmethod
+    def parse(cls, data):
+        return cls.parse_from(data, 0)[0]
+
+    @classmethod
+    def parse_from(cls, data, offset):
+        fields = {}
+        fields["mode"], offset = Mode.parse_from(data, offset)
+        fields["name"], offset = _str(8)(data, offset)
+        fields["uptime"], offset = _u32(data, offset)
+        fields["calibration"], offset = Calibration.parse_from(data, offset)
+        fields["history"], offset = _array(_array(_i16, 2), 2)(data, offset)
+        fields["flags"], offset = _array(_bool, 4)(data, offset)
+        return cls(**fields), offset
+
+
+class Command:
+    Reboot = 0
+    SetRate = 1
+
+    def __init__(self, variant, names, **fields):
+        self.variant = variant
+        self.names = names
+        self.__dict__.update(fields)
+
+    def __repr__(self):
+        if not self.names:
+            return "Command.%s" % self.variant
+        return "Command.%s(%s)" % (self.variant, _fields(self, self.names))
+
+    @classmethod
+    def parse(cls, data):
+        return cls.parse_from(data, 0)[0]
+
+    @classmethod
+    def parse_from(cls, data, offset):
+        tag, offset = _u8(data, offset)
+        if tag == 0:
+            fields = {}
+            return cls("Reboot", (), **fields), offset
+        if tag == 1:
+            fields = {}
+            fields["field_0"], offset = _u16(data, offset)
+            return cls("SetRate", ("field_0",), **fields), offset
+        raise ValueError("unknown Command tag %d" % tag)
+
+
+class Report:
+    FIELDS = ("status", "command", "len", "readings")
+
+    def __init__(self, **fields):
+        self.__dict__.update(fields)
+
+    def __repr__(self):
+        return "Report(%s)" % _fields(self, self.FIELDS)
+
+    @classmethod
+    def parse(cls, data):
+        return cls.parse_from(data, 0)[0]
+
+    @classmethod
+    def parse_from(cls, data, offset):
+        fields = {}
+        fields["status"], offset = Status.parse_from(data, offset)
+        fields["command"], offset = Command.parse_from(data, offset)
+        fields["len"], offset = _u8(data, offset)
+        fields["readings"], offset = _array(_i16, fields["len"])(data, offset)
+        return cls(**fields), offset
@@ -0,0 +1,62 @@
+use embedded_serialize::layout::{native_view, swap_endianness_in_place, FixedLayout, Plain};
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, FixedLayout, Serialize};
+
+#[derive(Serialize, Deserialize, FixedLayout, Debug, PartialEq, Clone, Copy)]
+#[repr(C)]
+struct Sample {
+    timestamp: u32,
+    channel: u16,
+    value: i16,
+}
+
+// SAFETY: `repr(C)` with fields in wire order and no padding; any bit pattern is valid.
+unsafe impl Plain for Sample {}
+
+const COUNT: usize = 16;
+
+#[repr(C, align(4))]
+struct Aligned([u8; COUNT * Sample::SIZE + 4]);
+
+fn block() -> Aligned {
+    let mut buf = Aligned([0; COUNT * Sample::SIZE + 4]);
+    for index in 0..COUNT {
+        let sample = Sample {
+            timestamp: 0x0100_0000 + index as u32,
+            channel: index as u16,
+            value: -(index as i16),
+        };
+        sample.serialize(&mut buf.0[index * Sample::SIZE..]).unwrap();
+    }
+    buf
+}
+
+#[test]
+fn converted_block_matches_element_wise_decode() {
+    let mut buf = block();
+    let wire = buf.0;
+    swap_endianness_in_place::<Sample>(&mut buf.0, COUNT).unwrap();
+    let view = native_view::<Sample>(&buf.0, COUNT).unwrap();
+    assert_eq!(view.len(), COUNT);
+    for (index, sample) in view.iter().enumerate() {
+        let expected = Sample::deserialize(&wire[index * Sample::SIZE..]).unwrap();
+        assert_eq!(*sample, expected);
+    }
+
+    swap_endianness_in_place::<Sample>(&mut buf.0, COUNT).unwrap();
+    assert_eq!(buf.0, wire);
+}
+
+#[test]
+fn short_buffer_fails() {
+    let mut buf = block();
+    let short = &mut buf.0[..COUNT * Sample::SIZE - 1];
+    assert!(matches!(swap_endianness_in_place::<Sample>(short, COUNT), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(native_view::<Sample>(short, COUNT), Err(DeserializeError::BufferTooSmall)));
+}
+
+#[test]
+fn misaligned_view_fails() {
+    let buf = block();
+    assert!(matches!(native_view::<Sample>(&buf.0[2..], COUNT), Err(DeserializeError::Misaligned)));
+}
@@ -0,0 +1,58 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// The variants as an older receiver knows them
+mod v1 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[es(length_prefixed)]
+    pub enum Event {
+        Ping,
+        Status { level: u8 },
+    }
+}
+
+/// A newer sender, which appended a field to `Status`
+mod v2 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[es(length_prefixed)]
+    pub enum Event {
+        Ping,
+        Status { level: u8, uptime: u16 },
+    }
+}
+
+#[test]
+fn length_follows_the_tag() {
+    let mut buf = [0u8; 8];
+    let size = v2::Event::Status { level: 7, uptime: 0x0102 }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [1, 0, 3, 7, 0x01, 0x02]);
+    assert_eq!(v2::Event::deserialize_consumed(&buf).unwrap(), (v2::Event::Status { level: 7, uptime: 0x0102 }, 6));
+
+    let size = v2::Event::Ping.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0, 0, 0]);
+    assert_eq!(v2::Event::Ping.serialized_size(), 3);
+}
+
+#[test]
+fn older_receiver_skips_extra_trailing_bytes() {
+    let mut buf = [0u8; 16];
+    let first = v2::Event::Status { level: 7, uptime: 0xBEEF }.serialize(&mut buf).unwrap();
+    let second = v2::Event::Ping.serialize(&mut buf[first..]).unwrap();
+
+    let (event, size) = v1::Event::deserialize_consumed(&buf).unwrap();
+    assert_eq!((event, size), (v1::Event::Status { level: 7 }, first));
+    assert_eq!(v1::Event::deserialize_consumed(&buf[size..]).unwrap(), (v1::Event::Ping, second));
+}
+
+#[test]
+fn short_payloads_are_rejected() {
+    // The declared length runs past the buffer.
+    assert!(matches!(v1::Event::deserialize(&[1, 0, 3, 7]), Err(DeserializeError::BufferTooSmall)));
+    // The declared length is too short for the known fields.
+    assert!(matches!(v2::Event::deserialize(&[1, 0, 2, 7, 0x01, 0x02]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(v1::Event::deserialize(&[1, 0]), Err(DeserializeError::BufferTooSmall)));
+}
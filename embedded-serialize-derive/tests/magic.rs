@@ -0,0 +1,77 @@
+use embedded_serialize::codec::{Codec, FramedCrcCodec};
+use embedded_serialize::{Deserialize, DeserializeError, Serialize, SerializeError};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[es(magic = [0xAA, 0x55])]
+struct LogRecord {
+    seq: u16,
+    level: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(magic = [0xAA, 0x55])]
+enum Entry {
+    Empty,
+    #[es(tag = 0x20)]
+    Reading(i16),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(magic = [0xDE, 0xAD, 0xBE, 0xEF])]
+struct Header {
+    version: u8,
+}
+
+#[test]
+fn magic_is_written_first_and_counted() {
+    let record = LogRecord { seq: 0x0102, level: 3 };
+    let mut buf = [0u8; 8];
+    let size = record.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0xAA, 0x55, 0x01, 0x02, 3]);
+    assert_eq!(record.serialized_size(), 5);
+    assert_eq!(LogRecord::deserialize_consumed(&buf).unwrap(), (record, 5));
+
+    assert_eq!(Header { version: 1 }.serialized_size(), 5);
+    assert_eq!(Header::deserialize(&[0xDE, 0xAD, 0xBE, 0xEF, 1]).unwrap(), Header { version: 1 });
+}
+
+#[test]
+fn magic_comes_before_the_tag() {
+    let mut buf = [0u8; 8];
+    let size = Entry::Reading(-2).serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0xAA, 0x55, 0x20, 0xFF, 0xFE]);
+    assert_eq!(Entry::Reading(-2).serialized_size(), size);
+    assert_eq!(Entry::deserialize(&buf[..size]).unwrap(), Entry::Reading(-2));
+
+    let size = Entry::Empty.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0xAA, 0x55, 0x00]);
+    // A known tag without the magic is still rejected.
+    assert!(matches!(Entry::deserialize(&[0x20, 0xFF, 0xFE]), Err(DeserializeError::BadMagic(0x20FF))));
+    assert!(matches!(
+        Entry::deserialize(&[0xAA, 0x55, 0x07]),
+        Err(DeserializeError::UnknownDiscriminant { value: 7, .. })
+    ));
+}
+
+#[test]
+fn mismatch_reports_the_bytes_found() {
+    assert!(matches!(LogRecord::deserialize(&[0xAA, 0x56, 0, 1, 3]), Err(DeserializeError::BadMagic(0xAA56))));
+    assert!(matches!(
+        Header::deserialize(&[0xDE, 0xAD, 0xC0, 0xDE, 1]),
+        Err(DeserializeError::BadMagic(0xDEAD_C0DE))
+    ));
+    assert!(matches!(LogRecord::deserialize(&[0xAA]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(LogRecord { seq: 0, level: 0 }.serialize(&mut [0u8; 1]), Err(SerializeError::BufferTooSmall)));
+}
+
+#[test]
+fn magic_travels_inside_a_crc_frame() {
+    let mut codec = FramedCrcCodec::<LogRecord>::new();
+    let record = LogRecord { seq: 9, level: 1 };
+    let mut frame = [0u8; 16];
+    let size = codec.encode(&record, &mut frame).unwrap();
+    assert_eq!(size, 2 + 5 + 2);
+    assert_eq!(frame[..4], [0, 5, 0xAA, 0x55]);
+    assert_eq!(codec.feed(&frame[..size]).unwrap(), Some(record));
+}
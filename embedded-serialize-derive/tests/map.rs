@@ -0,0 +1,72 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// Held as a rich type in RAM, sent as its legacy one-byte code
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Idle,
+    Tracking { gain: u8 },
+    Fault,
+}
+
+fn mode_to_code(mode: &Mode) -> u8 {
+    match *mode {
+        Mode::Idle => 0,
+        Mode::Tracking { gain } => 0x10 | (gain & 0x0F),
+        Mode::Fault => 0xFF,
+    }
+}
+
+fn mode_from_code(code: u8) -> Result<Mode, DeserializeError> {
+    match code {
+        0 => Ok(Mode::Idle),
+        0x10..=0x1F => Ok(Mode::Tracking { gain: code & 0x0F }),
+        0xFF => Ok(Mode::Fault),
+        _ => Err(DeserializeError::InvalidData),
+    }
+}
+
+fn index_to_wire(index: &usize) -> u16 {
+    *index as u16
+}
+
+fn index_from_wire(wire: u16) -> Result<usize, DeserializeError> {
+    Ok(wire as usize)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Status {
+    seq: u8,
+    #[es(map(serialize = "mode_to_code", deserialize = "mode_from_code"))]
+    mode: Mode,
+    #[es(map(serialize = "index_to_wire", deserialize = "index_from_wire"))]
+    sensor: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Event {
+    Changed(#[es(map(serialize = "mode_to_code", deserialize = "mode_from_code"))] Mode),
+}
+
+#[test]
+fn fields_are_written_as_the_wire_type() {
+    let status = Status { seq: 4, mode: Mode::Tracking { gain: 3 }, sensor: 0x0102 };
+    let mut buf = [0u8; 8];
+    let size = status.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [4, 0x13, 0x01, 0x02]);
+    assert_eq!(status.serialized_size(), size);
+    assert_eq!(Status::deserialize(&buf[..size]).unwrap(), status);
+
+    for mode in [Mode::Idle, Mode::Fault] {
+        let size = Event::Changed(mode).serialize(&mut buf).unwrap();
+        assert_eq!(size, 2);
+        assert_eq!(Event::deserialize(&buf[..size]).unwrap(), Event::Changed(mode));
+    }
+}
+
+#[test]
+fn invalid_wire_value_names_the_field() {
+    assert!(matches!(Status::deserialize(&[4, 0x20, 0, 1]), Err(DeserializeError::InvalidDataAt(1))));
+    assert!(matches!(Event::deserialize(&[0, 0x42]), Err(DeserializeError::InvalidDataAt(0))));
+    assert!(matches!(Status::deserialize(&[4, 0x13, 0]), Err(DeserializeError::BufferTooSmall)));
+}
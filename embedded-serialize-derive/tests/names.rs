@@ -0,0 +1,42 @@
+//! Run with `cargo test -p embedded-serialize-derive --features names`.
+#![cfg(feature = "names")]
+
+use embedded_serialize::names::VariantNames;
+use embedded_serialize::{Deserialize as _, DeserializeError};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[repr(u8)]
+enum Mode {
+    Idle = 1,
+    Sampling { rate: u16 } = 4,
+    #[es(tag = 9)]
+    Fault(u8),
+    Reset,
+}
+
+#[test]
+fn table_lists_variants_in_declaration_order() {
+    assert_eq!(Mode::VARIANT_NAMES, &[("Idle", 1), ("Sampling", 4), ("Fault", 9), ("Reset", 10)]);
+}
+
+#[test]
+fn variant_name_of_each_variant() {
+    assert_eq!(Mode::Idle.variant_name(), "Idle");
+    assert_eq!(Mode::Sampling { rate: 100 }.variant_name(), "Sampling");
+    assert_eq!(Mode::Fault(3).variant_name(), "Fault");
+    assert_eq!(Mode::Reset.variant_name(), "Reset");
+}
+
+#[test]
+fn name_of_discriminant_lookups() {
+    assert_eq!(Mode::name_of_discriminant(4), Some("Sampling"));
+    assert_eq!(Mode::name_of_discriminant(10), Some("Reset"));
+    assert_eq!(Mode::name_of_discriminant(2), None);
+    assert_eq!(Mode::name_of_discriminant(7), None);
+}
+
+#[test]
+fn unknown_tag_still_fails_to_decode() {
+    assert!(matches!(Mode::deserialize(&[7]), Err(DeserializeError::UnknownDiscriminant { .. })));
+}
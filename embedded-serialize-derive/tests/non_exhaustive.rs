@@ -0,0 +1,67 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Header {
+    pub version: u8,
+    pub length: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Id(pub u32);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Command {
+    Stop,
+    #[non_exhaustive]
+    Move { speed: i16 },
+    #[es(tag = 7)]
+    Reset(u8),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[non_exhaustive]
+#[es(length_prefixed)]
+pub enum Event {
+    Ping,
+    #[non_exhaustive]
+    Level(u16),
+}
+
+fn round_trip<T: Serialize + Deserialize + PartialEq + core::fmt::Debug>(value: T, expected: &[u8]) {
+    let mut buf = [0u8; 16];
+    let size = value.serialize(&mut buf).unwrap();
+    assert_eq!(&buf[..size], expected);
+    assert_eq!(value.serialized_size(), size);
+    assert_eq!(T::deserialize(&buf[..size]).unwrap(), value);
+}
+
+#[test]
+fn structs_round_trip() {
+    round_trip(Header { version: 2, length: 0x0102 }, &[2, 1, 2]);
+    round_trip(Id(0xDEAD_BEEF), &[0xDE, 0xAD, 0xBE, 0xEF]);
+}
+
+#[test]
+fn enums_round_trip() {
+    round_trip(Command::Stop, &[0]);
+    round_trip(Command::Move { speed: -2 }, &[1, 0xFF, 0xFE]);
+    round_trip(Command::Reset(9), &[7, 9]);
+    round_trip(Event::Ping, &[0, 0, 0]);
+    round_trip(Event::Level(0x0304), &[1, 0, 2, 3, 4]);
+}
+
+#[test]
+fn unknown_tags_are_still_rejected() {
+    assert!(matches!(
+        Command::deserialize(&[2, 0, 0]),
+        Err(DeserializeError::UnknownDiscriminant { value: 2, .. })
+    ));
+    assert!(matches!(
+        Event::deserialize(&[5, 0, 0]),
+        Err(DeserializeError::UnknownDiscriminant { value: 5, .. })
+    ));
+}
@@ -0,0 +1,31 @@
+use embedded_serialize::{Deserialize, Serialize, SerializeError};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Telemetry {
+    #[es(option = "sentinel", sentinel = 0xFFFF)]
+    reading: Option<u16>,
+    #[es(option = "presence")]
+    battery: Option<u8>,
+}
+
+#[test]
+fn sentinel_field_round_trip() {
+    let mut buf = [0u8; 8];
+    let value = Telemetry { reading: None, battery: Some(90) };
+    let size = value.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0xFF, 0xFF, 1, 90]);
+    assert_eq!(value.serialized_size(), size);
+    assert_eq!(Telemetry::deserialize(&buf[..size]).unwrap(), value);
+
+    let value = Telemetry { reading: Some(300), battery: None };
+    let size = value.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0x01, 0x2C, 0]);
+    assert_eq!(Telemetry::deserialize(&buf[..size]).unwrap(), value);
+}
+
+#[test]
+fn sentinel_field_rejects_some_sentinel() {
+    let value = Telemetry { reading: Some(0xFFFF), battery: None };
+    assert!(matches!(value.serialize(&mut [0u8; 8]), Err(SerializeError::InvalidData)));
+}
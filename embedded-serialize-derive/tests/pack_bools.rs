@@ -0,0 +1,73 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+#[es(pack_bools)]
+struct Settings {
+    version: u8,
+    wifi: bool,
+    bluetooth: bool,
+    interval: u16,
+    gps: bool,
+    led: bool,
+    buzzer: bool,
+    #[es(skip)]
+    dirty: bool,
+    logging: bool,
+    sleep: bool,
+    name: [u8; 2],
+    debug: bool,
+    ota: bool,
+}
+
+#[test]
+fn nine_bools_take_two_bytes() {
+    let settings = Settings { version: 1, interval: 0x0203, name: *b"ab", ..Default::default() };
+    let mut buf = [0u8; 16];
+    // version, the 2-byte bitfield at the first bool's position, interval, name
+    assert_eq!(settings.serialize(&mut buf).unwrap(), 1 + 2 + 2 + 2);
+    assert_eq!(buf[..7], [1, 0, 0, 0x02, 0x03, b'a', b'b']);
+}
+
+#[test]
+fn every_bit_round_trips_alone() {
+    let setters: [fn(&mut Settings); 9] = [
+        |s| s.wifi = true,
+        |s| s.bluetooth = true,
+        |s| s.gps = true,
+        |s| s.led = true,
+        |s| s.buzzer = true,
+        |s| s.logging = true,
+        |s| s.sleep = true,
+        |s| s.debug = true,
+        |s| s.ota = true,
+    ];
+    for (index, set) in setters.iter().enumerate() {
+        let mut settings = Settings { version: 7, interval: 0xBEEF, name: *b"xy", ..Default::default() };
+        set(&mut settings);
+        let mut buf = [0u8; 16];
+        let size = settings.serialize(&mut buf).unwrap();
+        let mut bits = [0u8; 2];
+        bits[index / 8] = 1 << (index % 8);
+        assert_eq!(buf[1..3], bits, "bool {index}");
+        assert_eq!(buf[3..size], [0xBE, 0xEF, b'x', b'y']);
+        assert_eq!(Settings::deserialize(&buf[..size]).unwrap(), settings);
+    }
+}
+
+#[test]
+fn skipped_bool_is_not_packed() {
+    let settings = Settings { dirty: true, logging: true, ..Default::default() };
+    let mut buf = [0u8; 16];
+    let size = settings.serialize(&mut buf).unwrap();
+    assert_eq!(buf[1..3], [1 << 5, 0]);
+    let decoded = Settings::deserialize(&buf[..size]).unwrap();
+    assert!(!decoded.dirty);
+    assert!(decoded.logging);
+}
+
+#[test]
+fn unused_bits_are_rejected() {
+    let buf = [0, 0, 0b10, 0, 0, 0, 0];
+    assert!(matches!(Settings::deserialize(&buf), Err(DeserializeError::InvalidData)));
+}
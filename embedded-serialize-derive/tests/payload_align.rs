@@ -0,0 +1,48 @@
+use embedded_serialize::writer::ByteWriter;
+use embedded_serialize::{Deserialize, DeserializeError, Serialize, SerializeError};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// Mirrors a C `struct { uint8_t tag; union { uint32_t counter; uint16_t pair[2]; }; }`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(payload_align = 4)]
+enum Register {
+    Idle,
+    Counter(u32),
+    Pair { low: u16, high: u16 },
+}
+
+#[test]
+fn payload_lands_on_a_four_byte_boundary() {
+    let mut buf = [0xEE; 12];
+    let size = Register::Counter(0x0102_0304).serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [1, 0, 0, 0, 1, 2, 3, 4]);
+    assert_eq!(Register::Counter(0).serialized_size(), 8);
+    assert_eq!(Register::deserialize_consumed(&buf).unwrap(), (Register::Counter(0x0102_0304), 8));
+
+    let size = Register::Pair { low: 5, high: 6 }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [2, 0, 0, 0, 0, 5, 0, 6]);
+    assert_eq!(Register::deserialize(&buf[..size]).unwrap(), Register::Pair { low: 5, high: 6 });
+}
+
+#[test]
+fn variants_without_fields_are_not_padded() {
+    let mut buf = [0u8; 4];
+    assert_eq!(Register::Idle.serialize(&mut buf).unwrap(), 1);
+    assert_eq!(Register::deserialize_consumed(&buf).unwrap(), (Register::Idle, 1));
+}
+
+#[test]
+fn aligned_within_a_frame_from_an_aligned_start() {
+    let mut buf = [0u8; 16];
+    let mut writer = ByteWriter::<1>::new(&mut buf);
+    writer.write(&0xCAFE_F00Du32).unwrap();
+    assert_eq!(writer.len() % 4, 0);
+    writer.write(&Register::Counter(7)).unwrap();
+    assert_eq!(writer.written()[8..12], [0, 0, 0, 7]);
+}
+
+#[test]
+fn truncated_padding_is_rejected() {
+    assert!(matches!(Register::deserialize(&[1, 0, 0]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(Register::Counter(1).serialize(&mut [0u8; 3]), Err(SerializeError::BufferTooSmall)));
+}
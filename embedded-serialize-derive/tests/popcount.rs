@@ -0,0 +1,38 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Health {
+    faults: u32,
+    warnings: u8,
+    #[es(popcount_of = "faults")]
+    active_faults: u8,
+    #[es(popcount_of = "warnings")]
+    active_warnings: u8,
+}
+
+fn encode(health: &Health) -> ([u8; 8], usize) {
+    let mut buf = [0u8; 8];
+    let size = health.serialize(&mut buf).unwrap();
+    assert_eq!(health.serialized_size(), size);
+    (buf, size)
+}
+
+#[test]
+fn count_reflects_the_source_bits() {
+    let (buf, size) = encode(&Health { faults: 0x8000_0101, warnings: 0xFF, active_faults: 0, active_warnings: 0 });
+    assert_eq!(buf[..size], [0x80, 0x00, 0x01, 0x01, 0xFF, 3, 8]);
+
+    let (buf, size) = encode(&Health { faults: 0, warnings: 0, active_faults: 7, active_warnings: 7 });
+    assert_eq!(buf[..size], [0, 0, 0, 0, 0, 0, 0]);
+
+    let (buf, _) = encode(&Health { faults: u32::MAX, warnings: 0b1010, active_faults: 0, active_warnings: 0 });
+    assert_eq!(buf[5..7], [32, 2]);
+}
+
+#[test]
+fn decode_recomputes_the_count() {
+    let decoded = Health::deserialize(&[0x00, 0x00, 0x00, 0x0F, 0x01, 99, 99]).unwrap();
+    assert_eq!(decoded, Health { faults: 0x0F, warnings: 1, active_faults: 4, active_warnings: 1 });
+    assert!(matches!(Health::deserialize(&[0, 0, 0, 0, 0, 0]), Err(DeserializeError::BufferTooSmall)));
+}
@@ -0,0 +1,57 @@
+use embedded_serialize::layout::{swap_endianness_in_place, FixedLayout};
+use embedded_serialize::reserved::{Reserved, ReservedZero};
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, FixedLayout, Serialize};
+
+/// A register block with "bytes 1–3: reserved" and "byte 6: reserved, must be zero"
+#[derive(Serialize, Deserialize, FixedLayout, Debug, Clone, Copy, PartialEq)]
+#[es(view)]
+struct Registers {
+    mode: u8,
+    reserved: Reserved<3>,
+    threshold: u16,
+    must_be_zero: ReservedZero<1>,
+    count: u32,
+}
+
+fn registers() -> Registers {
+    Registers {
+        mode: 2,
+        reserved: Reserved,
+        threshold: 0x0102,
+        must_be_zero: ReservedZero,
+        count: 0x0A0B_0C0D,
+    }
+}
+
+#[test]
+fn following_fields_sit_after_the_reserved_bytes() {
+    let mut buf = [0xFFu8; 12];
+    let size = registers().serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [2, 0, 0, 0, 0x01, 0x02, 0, 0x0A, 0x0B, 0x0C, 0x0D]);
+    assert_eq!((size, Registers::SIZE, RegistersView::SIZE), (11, 11, 11));
+    assert_eq!(registers().serialized_size(), size);
+
+    let view = RegistersView::new(&buf[..size]).unwrap();
+    assert_eq!(view.threshold().unwrap(), 0x0102);
+    assert_eq!(view.count().unwrap(), 0x0A0B_0C0D);
+}
+
+#[test]
+fn decoding_skips_or_verifies() {
+    let mut buf = [0u8; 11];
+    registers().serialize(&mut buf).unwrap();
+    buf[1..4].copy_from_slice(&[0xDE, 0xAD, 0x01]);
+    assert_eq!(Registers::deserialize(&buf).unwrap(), registers());
+
+    buf[6] = 0x80;
+    assert!(matches!(Registers::deserialize(&buf), Err(DeserializeError::InvalidValue(0x80))));
+    assert!(matches!(RegistersView::new(&buf[..10]), Err(DeserializeError::BufferTooSmall)));
+}
+
+#[test]
+fn byte_order_swap_leaves_reserved_bytes_alone() {
+    let mut buf = [2, 7, 8, 9, 0x02, 0x01, 0, 0x0D, 0x0C, 0x0B, 0x0A];
+    swap_endianness_in_place::<Registers>(&mut buf, 1).unwrap();
+    assert_eq!(buf, [2, 7, 8, 9, 0x01, 0x02, 0, 0x0A, 0x0B, 0x0C, 0x0D]);
+}
@@ -0,0 +1,41 @@
+use embedded_serialize::resume::{Progress, ResumableSerializer};
+use embedded_serialize::{Serialize, SerializeError};
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize, Clone)]
+struct Record {
+    id: u16,
+    samples: [u8; 5],
+    flags: u8,
+}
+
+#[test]
+fn one_byte_chunks_reassemble_the_value() {
+    let record = Record { id: 0x0102, samples: [3, 4, 5, 6, 7], flags: 8 };
+    let mut expected = [0u8; 16];
+    let size = record.serialize(&mut expected).unwrap();
+
+    let mut serializer = ResumableSerializer::<_, 8>::new(record.clone());
+    let mut out = [0u8; 16];
+    let mut len = 0;
+    loop {
+        let mut chunk = [0u8; 1];
+        let progress = serializer.serialize_chunk(&mut chunk).unwrap();
+        out[len..len + progress.written()].copy_from_slice(&chunk[..progress.written()]);
+        len += progress.written();
+        if let Progress::Done(_) = progress {
+            break;
+        }
+    }
+    assert!(serializer.is_done());
+    assert_eq!(out[..len], expected[..size]);
+    assert_eq!(serializer.serialize_chunk(&mut [0u8; 4]).unwrap(), Progress::Done(0));
+}
+
+#[test]
+fn field_larger_than_scratch_fails() {
+    let mut serializer = ResumableSerializer::<_, 4>::new(Record { id: 1, samples: [0; 5], flags: 0 });
+    let mut chunk = [0u8; 1];
+    assert_eq!(serializer.serialize_chunk(&mut chunk).unwrap(), Progress::More(1));
+    assert!(matches!(serializer.serialize_chunk(&mut chunk), Err(SerializeError::BufferTooSmall)));
+}
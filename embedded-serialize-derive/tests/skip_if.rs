@@ -0,0 +1,69 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+mod pred {
+    pub fn no_errors(sample: &super::Sample) -> bool {
+        sample.error_count == 0
+    }
+
+    pub fn no_note(sample: &super::Sample) -> bool {
+        sample.note == 0
+    }
+}
+
+fn unset() -> u16 {
+    0xFFFF
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Sample {
+    id: u8,
+    error_count: u8,
+    #[es(skip_if = "pred::no_errors")]
+    last_error: u32,
+    #[es(skip_if = "pred::no_note", default = "unset")]
+    note: u16,
+}
+
+fn encode(sample: &Sample) -> ([u8; 16], usize) {
+    let mut buf = [0u8; 16];
+    let size = sample.serialize(&mut buf).unwrap();
+    assert_eq!(sample.serialized_size(), size);
+    (buf, size)
+}
+
+#[test]
+fn field_is_written_when_the_sibling_is_non_zero() {
+    let sample = Sample { id: 7, error_count: 2, last_error: 0xDEAD_BEEF, note: 5 };
+    let (buf, size) = encode(&sample);
+    assert_eq!(buf[..size], [0b11, 7, 2, 0xDE, 0xAD, 0xBE, 0xEF, 0, 5]);
+    assert_eq!(Sample::deserialize(&buf[..size]).unwrap(), sample);
+}
+
+#[test]
+fn field_is_skipped_when_the_sibling_is_zero() {
+    let sample = Sample { id: 7, error_count: 0, last_error: 0xDEAD_BEEF, note: 5 };
+    let (buf, size) = encode(&sample);
+    assert_eq!(buf[..size], [0b10, 7, 0, 0, 5]);
+    assert_eq!(
+        Sample::deserialize(&buf[..size]).unwrap(),
+        Sample { id: 7, error_count: 0, last_error: 0, note: 5 }
+    );
+}
+
+#[test]
+fn absent_field_takes_its_default_function() {
+    let sample = Sample { id: 1, error_count: 1, last_error: 9, note: 0 };
+    let (buf, size) = encode(&sample);
+    assert_eq!(buf[..size], [0b01, 1, 1, 0, 0, 0, 9]);
+    assert_eq!(Sample::deserialize(&buf[..size]).unwrap().note, 0xFFFF);
+}
+
+#[test]
+fn presence_bitmap_is_checked() {
+    // A bit past the last conditional field.
+    assert!(matches!(Sample::deserialize(&[0b100, 1, 0]), Err(DeserializeError::InvalidData)));
+    // The bitmap promises a field the buffer does not hold.
+    assert!(matches!(Sample::deserialize(&[0b01, 1, 1, 0]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(Sample::deserialize(&[]), Err(DeserializeError::BufferTooSmall)));
+}
@@ -0,0 +1,31 @@
+use embedded_serialize::DeserializeError;
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Reading {
+    Temperature(i16),
+    Humidity { percent: u8, tenths: u8 },
+}
+
+/// The variant is picked by the high bit of the status byte after the two payload bytes.
+fn status_bit(buf: &[u8]) -> Result<u32, DeserializeError> {
+    let status = buf.get(2).ok_or(DeserializeError::BufferTooSmall)?;
+    Ok(u32::from(status >> 7))
+}
+
+#[test]
+fn tag_read_from_a_later_byte() {
+    let (reading, size) = Reading::deserialize_with_tag_fn(&[0xFF, 0x38, 0x00], status_bit).unwrap();
+    assert_eq!((reading, size), (Reading::Temperature(-200), 2));
+    let (reading, size) = Reading::deserialize_with_tag_fn(&[55, 3, 0x80], status_bit).unwrap();
+    assert_eq!((reading, size), (Reading::Humidity { percent: 55, tenths: 3 }, 2));
+}
+
+#[test]
+fn closure_errors_and_unknown_tags_fail() {
+    assert!(matches!(Reading::deserialize_with_tag_fn(&[0, 0], status_bit), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(
+        Reading::deserialize_with_tag_fn(&[0, 0], |_| Ok(5)),
+        Err(DeserializeError::UnknownDiscriminant { .. })
+    ));
+}
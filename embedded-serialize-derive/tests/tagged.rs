@@ -0,0 +1,61 @@
+use embedded_serialize::bytes::Bytes;
+use embedded_serialize::{DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Message {
+    Ping,
+    Ack(u8),
+    #[es(tag = 3)]
+    Reading { channel: u8, value: i16 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(magic = [0xCA, 0xFE])]
+enum Framed {
+    Idle,
+    #[es(tag = 3)]
+    Level(u16),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Chunk<'a> {
+    Empty,
+    Data(#[es(max_len = 8)] Bytes<'a>),
+}
+
+#[test]
+fn reports_the_decoded_tag() {
+    let mut buf = [0u8; 8];
+    let message = Message::Reading { channel: 2, value: -1 };
+    let size = message.serialize(&mut buf).unwrap();
+    assert_eq!(Message::deserialize_tagged(&buf).unwrap(), (message, 3, size));
+
+    let size = Message::Ack(9).serialize(&mut buf).unwrap();
+    assert_eq!(Message::deserialize_tagged(&buf).unwrap(), (Message::Ack(9), 1, size));
+}
+
+#[test]
+fn tag_follows_the_magic() {
+    let mut buf = [0u8; 8];
+    let size = Framed::Level(0x0102).serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0xCA, 0xFE, 3, 1, 2]);
+    assert_eq!(Framed::deserialize_tagged(&buf).unwrap(), (Framed::Level(0x0102), 3, 5));
+}
+
+#[test]
+fn borrowing_enums_report_the_tag() {
+    let wire = [1, 0, 2, 7, 8];
+    let (chunk, tag, size) = Chunk::deserialize_tagged(&wire).unwrap();
+    assert_eq!((chunk, tag, size), (Chunk::Data(Bytes(&[7, 8])), 1, 5));
+}
+
+#[test]
+fn decode_errors_are_unchanged() {
+    assert!(matches!(Message::deserialize_tagged(&[]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(Message::deserialize_tagged(&[3, 2]), Err(DeserializeError::BufferTooSmall)));
+    assert!(matches!(
+        Message::deserialize_tagged(&[7]),
+        Err(DeserializeError::UnknownDiscriminant { value: 7, .. })
+    ));
+}
@@ -0,0 +1,20 @@
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+#[es(text_dump)]
+struct Status {
+    level: u8,
+    armed: bool,
+    code: u8,
+    #[es(skip)]
+    #[allow(dead_code)]
+    scratch: u32,
+}
+
+#[test]
+fn three_fields_as_csv() {
+    let status = Status { level: 10, armed: true, code: 255, scratch: 7 };
+    let mut line = String::new();
+    status.write_csv(&mut line).unwrap();
+    assert_eq!(line, "10,true,255");
+}
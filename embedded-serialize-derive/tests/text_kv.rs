@@ -0,0 +1,69 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(text_kv)]
+enum Command {
+    Stop,
+    Start {
+        speed: u16,
+        reverse: bool,
+        #[es(skip)]
+        retries: u8,
+        offset_mm: i32,
+    },
+}
+
+fn to_text(command: &Command) -> String {
+    let mut text = String::new();
+    command.write_kv(&mut text).unwrap();
+    text
+}
+
+#[test]
+fn variants_round_trip_through_text() {
+    let start = Command::Start { speed: 1200, reverse: true, retries: 3, offset_mm: -40 };
+    let text = to_text(&start);
+    assert_eq!(text, r#"{"type":"Start","speed":1200,"reverse":true,"offset_mm":-40}"#);
+    assert_eq!(
+        Command::parse_kv(&text).unwrap(),
+        Command::Start { speed: 1200, reverse: true, retries: 0, offset_mm: -40 }
+    );
+
+    assert_eq!(to_text(&Command::Stop), r#"{"type":"Stop"}"#);
+    assert_eq!(Command::parse_kv(&to_text(&Command::Stop)).unwrap(), Command::Stop);
+
+    // The binary form is unaffected.
+    let mut buf = [0u8; 16];
+    let size = start.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [1, 0x04, 0xB0, 1, 0xFF, 0xFF, 0xFF, 0xD8]);
+    assert_eq!(
+        Command::deserialize(&buf[..size]).unwrap(),
+        Command::Start { speed: 1200, reverse: true, retries: 0, offset_mm: -40 }
+    );
+}
+
+#[test]
+fn gateway_text_is_parsed_leniently() {
+    let text = " { \"type\" : \"Start\", \"offset_mm\": 7, \"note\": \"hi\", \"reverse\": false, \"speed\": 5 } ";
+    assert_eq!(
+        Command::parse_kv(text).unwrap(),
+        Command::Start { speed: 5, reverse: false, retries: 0, offset_mm: 7 }
+    );
+}
+
+#[test]
+fn malformed_text_is_rejected() {
+    assert!(matches!(Command::parse_kv(r#"{"type":"Jump"}"#), Err(DeserializeError::UnknownLabel)));
+    assert!(matches!(
+        Command::parse_kv(r#"{"type":"Start","speed":5,"reverse":true}"#),
+        Err(DeserializeError::InvalidData)
+    ));
+    assert!(matches!(
+        Command::parse_kv(r#"{"type":"Start","speed":70000,"reverse":true,"offset_mm":0}"#),
+        Err(DeserializeError::InvalidData)
+    ));
+    assert!(matches!(Command::parse_kv(r#"{"kind":"Stop"}"#), Err(DeserializeError::InvalidData)));
+    assert!(matches!(Command::parse_kv(r#"{"type":"Stop"} x"#), Err(DeserializeError::InvalidData)));
+    assert!(matches!(Command::parse_kv(r#"{"type":"Stop""#), Err(DeserializeError::InvalidData)));
+}
@@ -0,0 +1,79 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(tlv)]
+struct Provisioning {
+    #[es(tag = 1)]
+    id: u16,
+    #[es(tag = 2, default)]
+    retries: u8,
+}
+
+/// A TLV struct between plain fields
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Envelope {
+    seq: u8,
+    provisioning: Provisioning,
+    crc: u16,
+}
+
+#[test]
+fn round_trip() {
+    let value = Provisioning { id: 0x0102, retries: 3 };
+    let mut buf = [0u8; 16];
+    let size = value.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [0, 7, 1, 2, 0x01, 0x02, 2, 1, 3]);
+    assert_eq!(value.serialized_size(), size);
+    assert_eq!(Provisioning::deserialize(&buf[..size]).unwrap(), value);
+}
+
+#[test]
+fn entries_in_any_order() {
+    let blob = [0, 7, 2, 1, 3, 1, 2, 0x01, 0x02];
+    assert_eq!(Provisioning::deserialize(&blob).unwrap(), Provisioning { id: 0x0102, retries: 3 });
+}
+
+#[test]
+fn unknown_tags_are_skipped() {
+    let blob = [0, 12, 9, 3, 0xAA, 0xBB, 0xCC, 1, 2, 0x01, 0x02, 2, 1, 3];
+    assert_eq!(Provisioning::deserialize(&blob).unwrap(), Provisioning { id: 0x0102, retries: 3 });
+}
+
+#[test]
+fn missing_optional_takes_default() {
+    let blob = [0, 4, 1, 2, 0x01, 0x02];
+    assert_eq!(Provisioning::deserialize(&blob).unwrap(), Provisioning { id: 0x0102, retries: 0 });
+}
+
+#[test]
+fn missing_required_fails() {
+    let blob = [0, 3, 2, 1, 3];
+    assert!(matches!(Provisioning::deserialize(&blob), Err(DeserializeError::MissingTag(1))));
+}
+
+#[test]
+fn duplicate_tag_fails() {
+    let blob = [0, 8, 1, 2, 0x01, 0x02, 1, 2, 0x03, 0x04];
+    assert!(matches!(Provisioning::deserialize(&blob), Err(DeserializeError::DuplicateTag(1))));
+}
+
+#[test]
+fn entries_end_at_the_length() {
+    // The id entry lies past the declared length, so it is not read.
+    let blob = [0, 3, 2, 1, 3, 1, 2, 0x01, 0x02];
+    assert!(matches!(Provisioning::deserialize(&blob), Err(DeserializeError::MissingTag(1))));
+    let (value, consumed) = Provisioning::deserialize_consumed(&[0, 4, 1, 2, 0x01, 0x02, 0xEE]).unwrap();
+    assert_eq!((value, consumed), (Provisioning { id: 0x0102, retries: 0 }, 6));
+    assert!(matches!(Provisioning::deserialize(&[0, 5, 1, 2, 0x01, 0x02]), Err(DeserializeError::BufferTooSmall)));
+}
+
+#[test]
+fn nests_between_other_fields() {
+    let value = Envelope { seq: 7, provisioning: Provisioning { id: 0x0102, retries: 3 }, crc: 0xBEEF };
+    let mut buf = [0u8; 16];
+    let size = value.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [7, 0, 7, 1, 2, 0x01, 0x02, 2, 1, 3, 0xBE, 0xEF]);
+    assert_eq!(value.serialized_size(), size);
+    assert_eq!(Envelope::deserialize_consumed(&buf).unwrap(), (value, size));
+}
@@ -0,0 +1,13 @@
+use embedded_serialize::Deserialize;
+use embedded_serialize_derive::Deserialize;
+
+// `&[u8; N]` fields only borrow from the input, so the struct has no owned `Deserialize`.
+#[derive(Deserialize)]
+struct Calibration<'a> {
+    version: u8,
+    blob: &'a [u8; 256],
+}
+
+fn main() {
+    let _ = Calibration::deserialize(&[0; 257]);
+}
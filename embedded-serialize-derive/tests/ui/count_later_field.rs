@@ -0,0 +1,10 @@
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+struct Report {
+    #[es(count = "n_channels")]
+    channels: heapless::Vec<u16, 4>,
+    n_channels: u8,
+}
+
+fn main() {}
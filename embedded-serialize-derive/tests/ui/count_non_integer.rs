@@ -0,0 +1,10 @@
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+struct Report {
+    n_channels: bool,
+    #[es(count = "n_channels")]
+    channels: heapless::Vec<u16, 4>,
+}
+
+fn main() {}
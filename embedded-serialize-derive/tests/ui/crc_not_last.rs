@@ -0,0 +1,11 @@
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+struct Frame {
+    id: u8,
+    #[es(crc = "crc8")]
+    crc: u8,
+    payload: u16,
+}
+
+fn main() {}
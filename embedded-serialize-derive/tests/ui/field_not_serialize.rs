@@ -0,0 +1,11 @@
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+struct NotWire;
+
+#[derive(Serialize, Deserialize)]
+struct Reading {
+    channel: u8,
+    raw: NotWire,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use embedded_serialize_derive::Serialize;
+
+fn to_code(value: &u32) -> u8 {
+    *value as u8
+}
+
+#[derive(Serialize)]
+struct Status {
+    #[es(map(serialize = "to_code"))]
+    code: u32,
+}
+
+fn main() {}
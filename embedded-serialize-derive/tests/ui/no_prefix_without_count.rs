@@ -0,0 +1,9 @@
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+struct Block {
+    #[es(no_prefix)]
+    samples: [u16; 4],
+}
+
+fn main() {}
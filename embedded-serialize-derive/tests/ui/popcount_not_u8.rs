@@ -0,0 +1,10 @@
+use embedded_serialize_derive::Serialize;
+
+#[derive(Serialize)]
+struct Health {
+    faults: u32,
+    #[es(popcount_of = "faults")]
+    active: u16,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use embedded_serialize_derive::Serialize;
+
+fn never(_: &u8) -> bool {
+    false
+}
+
+#[derive(Serialize)]
+enum Event {
+    Reading {
+        #[es(skip_if = "never")]
+        value: u8,
+    },
+}
+
+fn main() {}
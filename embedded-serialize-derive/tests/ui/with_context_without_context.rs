@@ -0,0 +1,10 @@
+use embedded_serialize_derive::Deserialize;
+
+#[derive(Deserialize)]
+struct Message {
+    seq: u8,
+    #[es(with_context)]
+    payload: u16,
+}
+
+fn main() {}
@@ -0,0 +1,130 @@
+use core::mem::MaybeUninit;
+
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+struct Plain {
+    id: u8,
+    value: u32,
+    samples: [u16; 2],
+}
+
+#[derive(Serialize)]
+#[es(magic = [0xAA, 0x55], pack_bools)]
+struct Packed {
+    armed: bool,
+    level: u16,
+    fault: bool,
+}
+
+fn skip_reading(value: &Conditional) -> bool {
+    value.flags & 1 == 0
+}
+
+#[derive(Serialize, Deserialize)]
+struct Conditional {
+    flags: u8,
+    #[es(skip_if = "skip_reading")]
+    reading: u16,
+    #[es(option = "sentinel", sentinel = 0xFF)]
+    channel: Option<u8>,
+    #[es(popcount_of = "flags")]
+    set: u8,
+    #[es(crc = "crc8")]
+    crc: u8,
+}
+
+#[derive(Serialize)]
+struct Counted {
+    n: u8,
+    #[es(count = "n")]
+    items: [u8; 4],
+}
+
+#[derive(Serialize)]
+#[es(tlv)]
+struct Tagged {
+    #[es(tag = 1)]
+    a: u8,
+    #[es(tag = 2)]
+    b: u32,
+}
+
+#[derive(Serialize)]
+enum Command {
+    Stop,
+    Move { x: i16, y: i16 },
+}
+
+#[derive(Serialize)]
+#[es(length_prefixed, magic = [0x7E])]
+enum Framed {
+    Ping,
+    Data(u8, u16),
+}
+
+#[derive(Serialize)]
+#[es(payload_align = 4)]
+enum Aligned {
+    Word(u32),
+}
+
+/// Serializes `value` both ways and checks that `serialize_uninit` writes the same bytes
+/// as `serialize` and leaves everything after them alone.
+fn check_uninit<T: Serialize>(value: &T) {
+    let mut expected = [0u8; 64];
+    let size = value.serialize(&mut expected).unwrap();
+
+    let mut buf = [MaybeUninit::new(0xEEu8); 64];
+    assert_eq!(value.serialize_uninit(&mut buf).unwrap(), &expected[..size]);
+    for byte in &buf[size..] {
+        // SAFETY: the buffer was initialized to 0xEE above.
+        assert_eq!(unsafe { byte.assume_init() }, 0xEE);
+    }
+
+    // Under Miri, reading a byte of this buffer that was not written fails the test.
+    let mut buf = [MaybeUninit::<u8>::uninit(); 64];
+    let written = value.serialize_uninit(&mut buf).unwrap();
+    assert_eq!(written, &expected[..size]);
+}
+
+#[test]
+fn structs_write_only_their_bytes() {
+    check_uninit(&Plain { id: 1, value: 0x0102_0304, samples: [5, 6] });
+    check_uninit(&Packed { armed: true, level: 0x1234, fault: true });
+    check_uninit(&Conditional { flags: 0b11, reading: 0x0A0B, channel: None, set: 0, crc: 0 });
+    check_uninit(&Conditional { flags: 0b10, reading: 0, channel: Some(3), set: 0, crc: 0 });
+
+    // The popcount and checksum are computed while writing.
+    let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+    let written = Conditional { flags: 0b111, reading: 1, channel: None, set: 0, crc: 0 }
+        .serialize_uninit(&mut buf)
+        .unwrap();
+    let decoded = Conditional::deserialize(written).unwrap();
+    assert_eq!(decoded.set, 3);
+    assert_eq!(decoded.crc, embedded_serialize::crc::crc8(&written[..written.len() - 1]));
+    check_uninit(&Counted { n: 2, items: [9, 8, 7, 6] });
+    check_uninit(&Tagged { a: 1, b: 2 });
+}
+
+#[test]
+fn enums_write_only_their_bytes() {
+    check_uninit(&Command::Stop);
+    check_uninit(&Command::Move { x: -1, y: 2 });
+    check_uninit(&Framed::Ping);
+    check_uninit(&Framed::Data(1, 0x0203));
+    check_uninit(&Aligned::Word(0xDEAD_BEEF));
+}
+
+#[test]
+fn uninit_errors_match_serialize() {
+    let mut buf = [MaybeUninit::<u8>::uninit(); 4];
+    assert!(Plain { id: 1, value: 2, samples: [3, 4] }.serialize_uninit(&mut buf).is_err());
+
+    let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+    let counted = Counted { n: 5, items: [0; 4] };
+    assert!(counted.serialize_uninit(&mut buf).is_err());
+    let sentinel = Conditional { flags: 0, reading: 0, channel: Some(0xFF), set: 0, crc: 0 };
+    assert!(sentinel.serialize_uninit(&mut buf).is_err());
+}
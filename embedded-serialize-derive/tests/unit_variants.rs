@@ -0,0 +1,54 @@
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+enum Command {
+    Stop,
+    Start,
+    Move { x: i16, y: i16 },
+    Home,
+    Speed(u8),
+    Reset,
+}
+
+/// Serializes `command`, checking the returned length against `serialized_size` and
+/// decoding it back.
+fn encode(command: &Command) -> ([u8; 8], usize) {
+    let mut buf = [0xEE; 8];
+    let size = command.serialize(&mut buf).unwrap();
+    assert_eq!(command.serialized_size(), size);
+    assert_eq!(Command::deserialize_consumed(&buf).unwrap(), (command.clone(), size));
+    (buf, size)
+}
+
+#[test]
+fn unit_variants_are_the_tag_alone() {
+    for (command, tag) in [(Command::Stop, 0), (Command::Start, 1), (Command::Home, 3), (Command::Reset, 5)] {
+        let (buf, size) = encode(&command);
+        assert_eq!(size, 1);
+        assert_eq!(buf[0], tag);
+    }
+}
+
+#[test]
+fn data_variants_are_the_tag_and_payload() {
+    let (buf, size) = encode(&Command::Move { x: -2, y: 0x0102 });
+    assert_eq!(buf[..size], [2, 0xFF, 0xFE, 0x01, 0x02]);
+
+    let (buf, size) = encode(&Command::Speed(40));
+    assert_eq!(buf[..size], [4, 40]);
+}
+
+#[test]
+fn unit_variant_decode_consumes_one_byte() {
+    let wire = [0, 1, 4, 9, 5];
+    let mut offset = 0;
+    let mut decoded = [Command::Reset, Command::Reset, Command::Stop, Command::Reset];
+    for slot in decoded.iter_mut() {
+        let (command, size) = Command::deserialize_consumed(&wire[offset..]).unwrap();
+        *slot = command;
+        offset += size;
+    }
+    assert_eq!(offset, wire.len());
+    assert_eq!(decoded, [Command::Stop, Command::Start, Command::Speed(9), Command::Reset]);
+}
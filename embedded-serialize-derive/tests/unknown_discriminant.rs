@@ -0,0 +1,34 @@
+use embedded_serialize::{Deserialize, DeserializeError};
+use embedded_serialize_derive::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+enum Command {
+    Stop,
+    Go(u8),
+    #[es(tag = 0x40)]
+    Reset,
+}
+
+#[test]
+fn unknown_tag_is_reported() {
+    assert_eq!(Command::deserialize(&[0x40]).unwrap(), Command::Reset);
+    assert!(matches!(
+        Command::deserialize(&[0x07]),
+        Err(DeserializeError::UnknownDiscriminant { value: 0x07, .. })
+    ));
+    assert!(matches!(
+        Command::deserialize(&[0x02, 1]),
+        Err(DeserializeError::UnknownDiscriminant { value: 0x02, .. })
+    ));
+}
+
+#[test]
+fn variant_field_errors_carry_their_value() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Switch {
+        Set(bool),
+    }
+
+    assert_eq!(Switch::deserialize(&[0, 1]).unwrap(), Switch::Set(true));
+    assert!(matches!(Switch::deserialize(&[0, 7]), Err(DeserializeError::InvalidValue(7))));
+}
@@ -0,0 +1,68 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, FixedLayout, Serialize};
+
+#[derive(Serialize, Deserialize, FixedLayout, Debug, Clone, Copy, PartialEq)]
+struct Point {
+    x: i16,
+    y: i16,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[es(view)]
+struct Record {
+    id: u16,
+    temperature: f32,
+    #[es(skip)]
+    cached: u8,
+    flags: [u8; 3],
+    ok: bool,
+    position: Point,
+    timestamp: u64,
+}
+
+fn record() -> Record {
+    Record {
+        id: 0x1234,
+        temperature: -12.5,
+        cached: 0,
+        flags: [1, 2, 3],
+        ok: true,
+        position: Point { x: -3, y: 700 },
+        timestamp: 0x0102_0304_0506_0708,
+    }
+}
+
+#[test]
+fn accessors_match_full_deserialize() {
+    let mut buf = [0u8; 32];
+    let size = record().serialize(&mut buf).unwrap();
+    assert_eq!(RecordView::SIZE, size);
+
+    let full = Record::deserialize(&buf).unwrap();
+    let view = RecordView::new(&buf).unwrap();
+    assert_eq!(view.id().unwrap(), full.id);
+    assert_eq!(view.temperature().unwrap(), full.temperature);
+    assert_eq!(view.flags().unwrap(), full.flags);
+    assert_eq!(view.ok().unwrap(), full.ok);
+    assert_eq!(view.position().unwrap(), full.position);
+    assert_eq!(view.timestamp().unwrap(), full.timestamp);
+    assert_eq!(view.as_bytes(), &buf[..size]);
+}
+
+#[test]
+fn short_buffer_is_rejected_up_front() {
+    let mut buf = [0u8; 32];
+    let size = record().serialize(&mut buf).unwrap();
+    assert!(matches!(RecordView::new(&buf[..size - 1]), Err(DeserializeError::BufferTooSmall)));
+}
+
+#[test]
+fn invalid_field_fails_only_its_accessor() {
+    let mut buf = [0u8; 32];
+    record().serialize(&mut buf).unwrap();
+    // `ok` follows id (2), temperature (4) and flags (3).
+    buf[9] = 2;
+    let view = RecordView::new(&buf).unwrap();
+    assert!(matches!(view.ok(), Err(DeserializeError::InvalidValue(2))));
+    assert_eq!(view.timestamp().unwrap(), 0x0102_0304_0506_0708);
+}
@@ -0,0 +1,33 @@
+use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+/// A log record with a 64-bit monotonic timestamp, no longer split into two `u32`s
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct LogRecord {
+    timestamp_ms: u64,
+    offset_us: i64,
+    level: u8,
+}
+
+#[test]
+fn boundary_values_round_trip() {
+    for (timestamp_ms, offset_us) in [(0, 0), (u64::MAX, i64::MIN), (1, i64::MAX), (u64::MAX - 1, -1)] {
+        let record = LogRecord { timestamp_ms, offset_us, level: 3 };
+        let mut buf = [0u8; 17];
+        assert_eq!(record.serialize(&mut buf).unwrap(), 17);
+        assert_eq!(record.serialized_size(), 17);
+        assert_eq!(buf[..8], timestamp_ms.to_be_bytes());
+        assert_eq!(buf[8..16], offset_us.to_be_bytes());
+        assert_eq!(LogRecord::deserialize(&buf).unwrap(), record);
+    }
+}
+
+#[test]
+fn short_timestamps_are_rejected() {
+    let record = LogRecord { timestamp_ms: u64::MAX, offset_us: i64::MIN, level: 0 };
+    let mut buf = [0u8; 17];
+    record.serialize(&mut buf).unwrap();
+    for len in 0..buf.len() {
+        assert!(matches!(LogRecord::deserialize(&buf[..len]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,149 @@
+//! Packed binary-coded decimal, two decimal digits per byte, as reported by RTC chips and
+//! many other peripherals.
+//!
+//! The most significant digits come first. Decoding rejects a byte with a nibble above 9
+//! with `InvalidValue`, and encoding a value with more digits than fit with `InvalidData`.
+
+use crate::layout::FixedLayout;
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Writes `value` as packed BCD filling `out`.
+pub fn write_bcd(mut value: u64, out: &mut [u8]) -> Result<(), SerializeError> {
+    for byte in out.iter_mut().rev() {
+        *byte = ((value / 10 % 10) << 4 | (value % 10)) as u8;
+        value /= 100;
+    }
+    if value != 0 {
+        return Err(SerializeError::InvalidData);
+    }
+    Ok(())
+}
+
+/// Reads the packed BCD value of `bytes`.
+/// Fails with `InvalidValue` holding the first byte with a nibble above 9, or with
+/// `InvalidData` on a value that does not fit in a `u64`.
+pub fn read_bcd(bytes: &[u8]) -> Result<u64, DeserializeError> {
+    let mut value = 0u64;
+    for &byte in bytes {
+        let (high, low) = (byte >> 4, byte & 0xF);
+        if high > 9 || low > 9 {
+            return Err(DeserializeError::InvalidValue(byte as u32));
+        }
+        value = value
+            .checked_mul(100)
+            .and_then(|value| value.checked_add((high * 10 + low) as u64))
+            .ok_or(DeserializeError::InvalidData)?;
+    }
+    Ok(value)
+}
+
+/// Integers with a packed BCD form of the same width
+pub trait BcdCode: Copy {
+    /// The BCD form, or `None` if the value has more digits than fit.
+    fn to_bcd(self) -> Option<Self>;
+    /// The value of a BCD form.
+    /// Fails with `InvalidValue` holding the first byte with a nibble above 9.
+    fn from_bcd(bcd: Self) -> Result<Self, DeserializeError>;
+}
+
+macro_rules! impl_bcd_code {
+    ($($ty:ty),*) => {
+        $(
+            impl BcdCode for $ty {
+                fn to_bcd(self) -> Option<Self> {
+                    let mut bytes = [0u8; core::mem::size_of::<$ty>()];
+                    write_bcd(self as u64, &mut bytes).ok()?;
+                    Some(<$ty>::from_be_bytes(bytes))
+                }
+
+                fn from_bcd(bcd: Self) -> Result<Self, DeserializeError> {
+                    read_bcd(&bcd.to_be_bytes()).map(|value| value as $ty)
+                }
+            }
+        )*
+    };
+}
+
+impl_bcd_code!(u8, u16, u32);
+
+/// A decimal value written as packed BCD: 0–99 for `u8`, 0–9999 for `u16`, and
+/// 0–99999999 for `u32`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Bcd<T>(pub T);
+
+impl<T: BcdCode + Serialize> Serialize for Bcd<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.0.to_bcd().ok_or(SerializeError::InvalidData)?.serialize(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.0.serialized_size()
+    }
+}
+
+impl<T: BcdCode + Deserialize> Deserialize for Bcd<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (bcd, size) = T::deserialize_consumed(buf)?;
+        let value = T::from_bcd(bcd)?;
+        Ok((Bcd(value), size))
+    }
+}
+
+impl<T: BcdCode + FixedLayout> FixedLayout for Bcd<T> {
+    const SIZE: usize = T::SIZE;
+
+    fn swap_bytes_in_place(record: &mut [u8]) {
+        T::swap_bytes_in_place(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forty_two_is_0x42() {
+        let mut buf = [0u8; 1];
+        assert_eq!(Bcd(42u8).serialize(&mut buf).unwrap(), 1);
+        assert_eq!(buf, [0x42]);
+        assert_eq!(Bcd::<u8>::deserialize(&[0x42]).unwrap(), Bcd(42));
+    }
+
+    #[test]
+    fn multi_byte_values_round_trip() {
+        let mut buf = [0u8; 4];
+        Bcd(1234u16).serialize(&mut buf).unwrap();
+        assert_eq!(buf[..2], [0x12, 0x34]);
+        assert_eq!(Bcd::<u16>::deserialize(&buf).unwrap(), Bcd(1234));
+
+        Bcd(9_876_543u32).serialize(&mut buf).unwrap();
+        assert_eq!(buf, [0x09, 0x87, 0x65, 0x43]);
+        assert_eq!(Bcd::<u32>::deserialize_consumed(&buf).unwrap(), (Bcd(9_876_543), 4));
+
+        let mut clock = [0u8; 3];
+        write_bcd(235_959, &mut clock).unwrap();
+        assert_eq!(clock, [0x23, 0x59, 0x59]);
+        assert_eq!(read_bcd(&clock).unwrap(), 235_959);
+    }
+
+    #[test]
+    fn invalid_nibbles_are_rejected() {
+        assert!(matches!(Bcd::<u8>::deserialize(&[0x4A]), Err(DeserializeError::InvalidValue(0x4A))));
+        assert!(matches!(Bcd::<u8>::deserialize(&[0xA4]), Err(DeserializeError::InvalidValue(0xA4))));
+        assert!(matches!(Bcd::<u16>::deserialize(&[0x12, 0x3F]), Err(DeserializeError::InvalidValue(0x3F))));
+        assert!(matches!(read_bcd(&[0x99; 11]), Err(DeserializeError::InvalidData)));
+    }
+
+    #[test]
+    fn values_with_too_many_digits_are_rejected() {
+        let mut buf = [0u8; 2];
+        assert!(matches!(Bcd(100u8).serialize(&mut buf), Err(SerializeError::InvalidData)));
+        assert!(matches!(Bcd(10_000u16).serialize(&mut buf), Err(SerializeError::InvalidData)));
+        assert_eq!(Bcd(99u8).serialize(&mut buf).unwrap(), 1);
+        assert!(matches!(Bcd::<u16>::deserialize(&[0x12]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
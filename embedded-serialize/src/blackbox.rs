@@ -0,0 +1,427 @@
+//! An append-only event log in a ring of NOR flash sectors, for keeping the last events
+//! before a crash.
+//!
+//! A record is a 9-byte header — a big-endian `u16` payload length, a `u16` type ID, a
+//! `u32` sequence number and the CRC-8 of the header fields — followed by the payload and
+//! its CRC-16. Records start at multiples of the flash write size and never cross a sector
+//! boundary; the rest of a sector that cannot hold the next record is left erased.
+//!
+//! When the log is full, the sector holding the oldest records is erased and reused.
+//! A record torn by a power loss fails its CRC and is skipped, as is everything after a
+//! corrupt header in the same sector, since its length cannot be trusted.
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::crc::{crc16, crc8};
+use crate::flash::FlashError;
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Bytes before the payload of a record
+const HEADER_LEN: usize = 9;
+
+/// Bytes of a record besides its payload
+const OVERHEAD: usize = HEADER_LEN + 2;
+
+/// Writes `value` as a record with the given sequence number and type ID.
+/// Returns the number of bytes written.
+pub fn encode_record<T: Serialize + ?Sized>(
+    seq: u32,
+    type_id: u16,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    let payload = buf.get_mut(HEADER_LEN..).ok_or(SerializeError::BufferTooSmall)?;
+    let len = value.serialize(payload)?;
+    let len16 = u16::try_from(len).map_err(|_| SerializeError::InvalidData)?;
+    len16.serialize(buf)?;
+    type_id.serialize(&mut buf[2..])?;
+    seq.serialize(&mut buf[4..])?;
+    buf[8] = crc8(&buf[..8]);
+    let end = HEADER_LEN + len;
+    let crc = crc16(&buf[HEADER_LEN..end]);
+    Ok(end + crc.serialize(&mut buf[end..])?)
+}
+
+/// Reads a record, failing with `ChecksumMismatch` if either CRC does not match.
+/// Returns its sequence number, type ID, payload and size.
+pub fn decode_record(buf: &[u8]) -> Result<(u32, u16, &[u8], usize), DeserializeError> {
+    let header = decode_header(buf)?;
+    let end = HEADER_LEN + header.len;
+    let payload = buf.get(HEADER_LEN..end).ok_or(DeserializeError::BufferTooSmall)?;
+    if u16::deserialize(&buf[end..])? != crc16(payload) {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    Ok((header.seq, header.type_id, payload, end + 2))
+}
+
+/// Fields of a record header whose CRC matches
+struct Header {
+    len: usize,
+    type_id: u16,
+    seq: u32,
+}
+
+fn decode_header(buf: &[u8]) -> Result<Header, DeserializeError> {
+    let header = buf.get(..HEADER_LEN).ok_or(DeserializeError::BufferTooSmall)?;
+    if header[8] != crc8(&header[..8]) {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    Ok(Header {
+        len: u16::deserialize(header)? as usize,
+        type_id: u16::deserialize(&header[2..])?,
+        seq: u32::deserialize(&header[4..])?,
+    })
+}
+
+/// What was found at a record position
+enum Slot {
+    /// A record with a valid header; `valid` is false if its payload is torn
+    Record { header: Header, valid: bool, next: u32 },
+    /// Erased flash: no record was written here or after it in the sector
+    Erased,
+    /// Neither a valid header nor erased flash
+    Corrupt,
+}
+
+/// Reads the record at `pos` into `buf`, for a sector ending at `sector_end`.
+fn read_slot<S: NorFlash, const N: usize>(
+    storage: &mut S,
+    pos: u32,
+    sector_end: u32,
+    buf: &mut [u8; N],
+) -> Result<Slot, S::Error> {
+    let available = (sector_end - pos) as usize;
+    if available < OVERHEAD {
+        return Ok(Slot::Erased);
+    }
+    let chunk = &mut buf[..available.min(N)];
+    storage.read(pos, chunk)?;
+    if chunk[..HEADER_LEN].iter().all(|&byte| byte == 0xFF) {
+        return Ok(Slot::Erased);
+    }
+    let header = match decode_header(chunk) {
+        Ok(header) => header,
+        Err(_) => return Ok(Slot::Corrupt),
+    };
+    let size = (OVERHEAD + header.len).next_multiple_of(S::WRITE_SIZE);
+    if size > chunk.len() {
+        return Ok(Slot::Corrupt);
+    }
+    Ok(Slot::Record {
+        valid: decode_record(chunk).is_ok(),
+        next: pos + size as u32,
+        header,
+    })
+}
+
+/// An append-only log of serialized records in a ring of flash sectors.
+///
+/// `N` is the record buffer size, bounding the size of a record: it must be a multiple of
+/// the flash read and write sizes and at most the erase size, and the write size must be
+/// a multiple of the read size.
+pub struct Blackbox<S, const N: usize = 256> {
+    storage: S,
+    start: u32,
+    sectors: u32,
+    /// Offset the next record is written at
+    head: u32,
+    next_seq: u32,
+}
+
+impl<S: NorFlash, const N: usize> Blackbox<S, N> {
+    /// Opens the log in the erase-aligned region from `start` to `end`, finding where the
+    /// newest record ends so that appending continues after it.
+    pub fn open(storage: S, start: u32, end: u32) -> Result<Self, S::Error> {
+        let mut blackbox = Blackbox {
+            storage,
+            start,
+            sectors: (end - start) / S::ERASE_SIZE as u32,
+            head: start,
+            next_seq: 0,
+        };
+        let mut buf = [0u8; N];
+        let mut newest: Option<u32> = None;
+        for sector in 0..blackbox.sectors {
+            let sector_start = blackbox.sector_start(sector);
+            let sector_end = sector_start + S::ERASE_SIZE as u32;
+            let mut pos = sector_start;
+            let mut last = None;
+            loop {
+                match read_slot(&mut blackbox.storage, pos, sector_end, &mut buf)? {
+                    Slot::Record { header, next, .. } => {
+                        last = Some(header.seq);
+                        pos = next;
+                    }
+                    Slot::Erased => break,
+                    Slot::Corrupt => {
+                        // The space after a corrupt header cannot be written; continue in
+                        // the next sector if this one turns out to be the newest.
+                        pos = sector_end;
+                        break;
+                    }
+                }
+            }
+            if let Some(seq) = last {
+                // Compare sequence numbers as a wrapping sequence.
+                let is_newer = match newest {
+                    Some(current) => seq.wrapping_sub(current) as i32 > 0,
+                    None => true,
+                };
+                if is_newer {
+                    newest = Some(seq);
+                    blackbox.head = pos;
+                }
+            }
+        }
+        if let Some(seq) = newest {
+            blackbox.next_seq = seq.wrapping_add(1);
+        }
+        if blackbox.head == blackbox.sector_start(blackbox.sectors) {
+            blackbox.head = start;
+        }
+        Ok(blackbox)
+    }
+
+    fn sector_start(&self, sector: u32) -> u32 {
+        self.start + sector * S::ERASE_SIZE as u32
+    }
+
+    /// Index of the sector holding `offset`.
+    fn sector_of(&self, offset: u32) -> u32 {
+        (offset - self.start) / S::ERASE_SIZE as u32
+    }
+
+    /// Sequence number the next record gets.
+    pub fn next_seq(&self) -> u32 {
+        self.next_seq
+    }
+
+    /// Appends `value` as a record of type `type_id`, erasing the sector with the oldest
+    /// records if the current one is full.
+    /// Returns the record's sequence number.
+    pub fn append<T: Serialize + ?Sized>(&mut self, type_id: u16, value: &T) -> Result<u32, FlashError<S::Error>> {
+        let seq = self.next_seq;
+        let mut record = [0xFFu8; N];
+        let len = encode_record(seq, type_id, value, &mut record).map_err(FlashError::Serialize)?;
+        let len = len.next_multiple_of(S::WRITE_SIZE);
+        if len > N || len > S::ERASE_SIZE {
+            return Err(FlashError::Serialize(SerializeError::BufferTooSmall));
+        }
+        let sector_end = self.sector_start(self.sector_of(self.head)) + S::ERASE_SIZE as u32;
+        if self.head + len as u32 > sector_end {
+            self.head = self.sector_start((self.sector_of(self.head) + 1) % self.sectors);
+        }
+        if (self.head - self.start).is_multiple_of(S::ERASE_SIZE as u32) {
+            let from = self.head;
+            self.storage
+                .erase(from, from + S::ERASE_SIZE as u32)
+                .map_err(FlashError::Flash)?;
+        }
+        self.storage.write(self.head, &record[..len]).map_err(FlashError::Flash)?;
+        self.head += len as u32;
+        if self.head == self.sector_start(self.sectors) {
+            self.head = self.start;
+        }
+        self.next_seq = seq.wrapping_add(1);
+        Ok(seq)
+    }
+
+    /// Returns a reader over the intact records, oldest first.
+    pub fn reader(&mut self) -> BlackboxReader<'_, S, N> {
+        // Unless the log has not wrapped yet, the sector after the newest one holds the
+        // oldest records; an erased sector there just yields nothing.
+        let newest = match self.head {
+            head if head == self.start => self.sectors - 1,
+            head => self.sector_of(head - 1),
+        };
+        let first = (newest + 1) % self.sectors;
+        BlackboxReader {
+            pos: self.sector_start(first),
+            sector: first,
+            remaining: self.sectors,
+            buf: [0; N],
+            blackbox: self,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+/// A record read back from a `Blackbox`
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    pub seq: u32,
+    pub type_id: u16,
+    /// The serialized value, to be decoded as the type `type_id` names
+    pub payload: &'a [u8],
+}
+
+/// Scans a `Blackbox` forward, skipping torn and corrupt records.
+pub struct BlackboxReader<'a, S, const N: usize> {
+    blackbox: &'a mut Blackbox<S, N>,
+    buf: [u8; N],
+    sector: u32,
+    /// Sectors left to scan, the current one included
+    remaining: u32,
+    pos: u32,
+}
+
+impl<S: NorFlash, const N: usize> BlackboxReader<'_, S, N> {
+    /// Reads the next intact record, or `None` at the end of the log.
+    pub fn next_record(&mut self) -> Result<Option<Record<'_>>, S::Error> {
+        while self.remaining > 0 {
+            let sector_end = self.blackbox.sector_start(self.sector) + S::ERASE_SIZE as u32;
+            match read_slot(&mut self.blackbox.storage, self.pos, sector_end, &mut self.buf)? {
+                Slot::Record { header, valid, next } => {
+                    self.pos = next;
+                    if valid {
+                        return Ok(Some(Record {
+                            seq: header.seq,
+                            type_id: header.type_id,
+                            payload: &self.buf[HEADER_LEN..HEADER_LEN + header.len],
+                        }));
+                    }
+                }
+                Slot::Erased | Slot::Corrupt => {
+                    self.sector = (self.sector + 1) % self.blackbox.sectors;
+                    self.pos = self.blackbox.sector_start(self.sector);
+                    self.remaining -= 1;
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::mock::MockFlash;
+
+    /// Four 64-byte sectors; a `u32` record takes 16 bytes, so a sector holds four.
+    type Log = Blackbox<MockFlash<256>, 64>;
+
+    const EVENT: u16 = 0x0E;
+
+    /// Sequence numbers and values of the intact records, oldest first
+    struct Contents {
+        records: [(u32, u32); 16],
+        len: usize,
+    }
+
+    impl Contents {
+        fn seqs(&self) -> impl Iterator<Item = u32> + '_ {
+            self.records[..self.len].iter().map(|&(seq, _)| seq)
+        }
+    }
+
+    fn read_all(log: &mut Log) -> Contents {
+        let mut contents = Contents { records: [(0, 0); 16], len: 0 };
+        let mut reader = log.reader();
+        while let Some(record) = reader.next_record().unwrap() {
+            assert_eq!(record.type_id, EVENT);
+            contents.records[contents.len] = (record.seq, u32::deserialize(record.payload).unwrap());
+            contents.len += 1;
+        }
+        contents
+    }
+
+    /// A log holding records `0..count`, each with the value `1000 + seq`
+    fn log_with(count: u32) -> Log {
+        let mut log = Log::open(MockFlash::new(), 0, 256).unwrap();
+        for seq in 0..count {
+            assert_eq!(log.append(EVENT, &(1000 + seq)).unwrap(), seq);
+        }
+        log
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut buf = [0u8; 32];
+        let size = encode_record(5, EVENT, &0x0102u16, &mut buf).unwrap();
+        assert_eq!(size, 13);
+        assert_eq!(decode_record(&buf).unwrap(), (5, EVENT, &[1u8, 2][..], 13));
+        buf[10] ^= 1;
+        assert!(matches!(decode_record(&buf), Err(DeserializeError::ChecksumMismatch)));
+        buf[3] ^= 1;
+        assert!(matches!(decode_record(&buf), Err(DeserializeError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn records_come_back_in_order_after_reopening() {
+        let mut log = log_with(6);
+        let contents = read_all(&mut log);
+        assert!(contents.seqs().eq(0..6));
+        assert_eq!(contents.records[5].1, 1005);
+
+        let mut log = Log::open(log.into_inner(), 0, 256).unwrap();
+        assert_eq!(log.next_seq(), 6);
+        assert_eq!(log.append(EVENT, &1006u32).unwrap(), 6);
+        assert!(read_all(&mut log).seqs().eq(0..7));
+    }
+
+    #[test]
+    fn wraps_around_by_erasing_the_oldest_sector() {
+        let mut log = log_with(22);
+        // Record 16 reused sector 0, dropping records 0 to 3; 20 and 21 reused sector 1.
+        let contents = read_all(&mut log);
+        assert!(contents.seqs().eq(8..22));
+        assert!(contents.records[..contents.len].iter().all(|&(seq, value)| value == 1000 + seq));
+
+        let mut log = Log::open(log.into_inner(), 0, 256).unwrap();
+        assert_eq!(log.next_seq(), 22);
+        assert!(read_all(&mut log).seqs().eq(8..22));
+    }
+
+    #[test]
+    fn corrupt_record_is_skipped() {
+        let log = log_with(6);
+        let mut flash = log.into_inner();
+        // The payload of record 1, the second in sector 0.
+        flash.data[16 + HEADER_LEN] ^= 0x40;
+        let mut log = Log::open(flash, 0, 256).unwrap();
+        let contents = read_all(&mut log);
+        assert!(contents.seqs().eq([0, 2, 3, 4, 5]));
+    }
+
+    /// Cuts power after every possible number of bytes while appending record `count`,
+    /// and checks that reopening recovers every earlier record and keeps appending.
+    fn check_torn_append(count: u32) {
+        for budget in 0.. {
+            let mut log = log_with(count);
+            let mut flash = log.into_inner();
+            flash.cut_power_after(budget);
+            log = Log::open(flash, 0, 256).unwrap();
+            let completed = log.append(EVENT, &(1000 + count)).is_ok();
+            let mut flash = log.into_inner();
+            flash.restore_power();
+
+            let mut log = Log::open(flash, 0, 256).unwrap();
+            let contents = read_all(&mut log);
+            // Power lost while writing only the padding leaves the record intact.
+            let intact = contents.len as u32;
+            assert!(intact == count + 1 || (intact == count && !completed), "budget {}", budget);
+            assert!(contents.seqs().eq(0..intact), "budget {}", budget);
+
+            let seq = log.append(EVENT, &7u32).unwrap();
+            let contents = read_all(&mut log);
+            assert_eq!(contents.records[contents.len - 1], (seq, 7), "budget {}", budget);
+            assert!(contents.seqs().take(intact as usize).eq(0..intact));
+            if completed {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn torn_record_mid_sector_is_skipped() {
+        check_torn_append(6);
+    }
+
+    #[test]
+    fn torn_record_at_a_sector_start_is_skipped() {
+        check_torn_append(8);
+    }
+}
@@ -0,0 +1,72 @@
+//! Runtime-length bool sequences, written as a big-endian `u16` count followed by
+//! `ceil(count / 8)` bytes with bool `i` in bit `i % 8` (least significant first) of byte `i / 8`.
+
+use crate::{Serialize, SerializeError};
+#[cfg(feature = "heapless")]
+use crate::{Deserialize, DeserializeError};
+
+/// Serializes `bools` as a count and a packed bitmap.
+/// Returns the number of bytes written.
+pub fn serialize_bool_seq(bools: &[bool], buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let count = u16::try_from(bools.len()).map_err(|_| SerializeError::InvalidData)?;
+    let prefix = count.serialize(buf)?;
+    let end = prefix + bools.len().div_ceil(8);
+    let packed = buf.get_mut(prefix..end).ok_or(SerializeError::BufferTooSmall)?;
+    packed.fill(0);
+    for (index, _) in bools.iter().enumerate().filter(|(_, &set)| set) {
+        packed[index / 8] |= 1 << (index % 8);
+    }
+    Ok(end)
+}
+
+/// Deserializes a count and packed bitmap, rejecting counts above `CAP` and set bits past the count.
+/// Returns the bools and the number of bytes consumed.
+#[cfg(feature = "heapless")]
+pub fn deserialize_bool_seq<const CAP: usize>(
+    buf: &[u8],
+) -> Result<(heapless::Vec<bool, CAP>, usize), DeserializeError> {
+    let (count, prefix) = u16::deserialize_consumed(buf)?;
+    let count = count as usize;
+    if count > CAP {
+        return Err(DeserializeError::InvalidData);
+    }
+    let end = prefix + count.div_ceil(8);
+    let packed = buf.get(prefix..end).ok_or(DeserializeError::BufferTooSmall)?;
+    if !count.is_multiple_of(8) && packed[packed.len() - 1] >> (count % 8) != 0 {
+        return Err(DeserializeError::InvalidData);
+    }
+    let bools = (0..count).map(|index| packed[index / 8] & (1 << (index % 8)) != 0).collect();
+    Ok((bools, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAGS: [bool; 10] = [true, false, false, true, false, false, false, false, false, true];
+
+    #[test]
+    fn ten_bools_take_two_data_bytes() {
+        let mut buf = [0xAAu8; 8];
+        assert_eq!(serialize_bool_seq(&FLAGS, &mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], [0, 10, 0b0000_1001, 0b10]);
+        assert!(matches!(serialize_bool_seq(&FLAGS, &mut buf[..3]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn ten_bools_round_trip() {
+        let (bools, size) = deserialize_bool_seq::<16>(&[0, 10, 0b0000_1001, 0b10]).unwrap();
+        assert_eq!((&bools[..], size), (&FLAGS[..], 4));
+        let (empty, size) = deserialize_bool_seq::<16>(&[0, 0]).unwrap();
+        assert_eq!((empty.len(), size), (0, 2));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn count_above_capacity_and_stray_bits_are_rejected() {
+        assert!(matches!(deserialize_bool_seq::<8>(&[0, 10, 0, 0]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_bool_seq::<16>(&[0, 10, 0, 0b100]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_bool_seq::<16>(&[0, 10, 0]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
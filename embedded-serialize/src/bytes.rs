@@ -0,0 +1,134 @@
+//! Opaque byte payloads, written as a big-endian `u16` length followed by the bytes.
+
+use core::ops::Deref;
+
+use crate::{DeserializeBorrowed, DeserializeError, Serialize, SerializeError};
+
+/// Writes the `u16` length prefix and `bytes`.
+fn write_prefixed(bytes: &[u8], buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let len = u16::try_from(bytes.len()).map_err(|_| SerializeError::InvalidData)?;
+    let end = 2 + bytes.len();
+    let dst = buf.get_mut(..end).ok_or(SerializeError::BufferTooSmall)?;
+    len.serialize(dst)?;
+    dst[2..].copy_from_slice(bytes);
+    Ok(end)
+}
+
+/// Reads the `u16` length prefix and returns the bytes it covers.
+fn read_prefixed(buf: &[u8]) -> Result<(&[u8], usize), DeserializeError> {
+    let (len, prefix) = u16::deserialize_borrowed(buf)?;
+    let end = prefix + len as usize;
+    let bytes = buf.get(prefix..end).ok_or(DeserializeError::BufferTooSmall)?;
+    Ok((bytes, end))
+}
+
+/// A byte payload borrowed from the surrounding buffer.
+/// Deserializing it through `DeserializeBorrowed` points into the input without copying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bytes<'a>(pub &'a [u8]);
+
+impl<'a> Deref for Bytes<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_prefixed(self.0, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        2 + self.0.len()
+    }
+}
+
+impl<'de> DeserializeBorrowed<'de> for Bytes<'de> {
+    fn deserialize_borrowed(buf: &'de [u8]) -> Result<(Self, usize), DeserializeError> {
+        read_prefixed(buf).map(|(bytes, size)| (Bytes(bytes), size))
+    }
+}
+
+/// An owned byte payload of at most `N` bytes, for when the input can't be borrowed.
+#[cfg(feature = "heapless")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ByteBuf<const N: usize>(pub heapless::Vec<u8, N>);
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> ByteBuf<N> {
+    /// Copies `bytes`, or returns `None` if they exceed the capacity.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        heapless::Vec::from_slice(bytes).ok().map(ByteBuf)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Deref for ByteBuf<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Serialize for ByteBuf<N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_prefixed(&self.0, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        2 + self.0.len()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> crate::Deserialize for ByteBuf<N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(bytes, _)| bytes)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (bytes, size) = read_prefixed(buf)?;
+        let bytes = Self::from_slice(bytes).ok_or(DeserializeError::InvalidData)?;
+        Ok((bytes, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_decode_aliases_the_input() {
+        let mut buf = [0u8; 8];
+        assert_eq!(Bytes(&[1, 2, 3]).serialize(&mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], [0, 3, 1, 2, 3]);
+
+        let (bytes, size) = Bytes::deserialize_borrowed(&buf).unwrap();
+        assert_eq!(size, 5);
+        assert_eq!(*bytes, [1, 2, 3]);
+        assert!(core::ptr::eq(bytes.0.as_ptr(), buf[2..].as_ptr()));
+    }
+
+    #[test]
+    fn short_payload_fails() {
+        assert!(matches!(Bytes::deserialize_borrowed(&[0, 3, 1, 2]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(Bytes(&[1, 2]).serialize(&mut [0u8; 3]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn owned_round_trip() {
+        use crate::Deserialize;
+
+        let bytes = ByteBuf::<4>::from_slice(&[7, 8, 9]).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(bytes.serialize(&mut buf).unwrap(), 5);
+        assert_eq!(ByteBuf::<4>::deserialize(&buf).unwrap(), bytes);
+        assert!(matches!(ByteBuf::<2>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        assert!(ByteBuf::<2>::from_slice(&[1, 2, 3]).is_none());
+    }
+}
@@ -0,0 +1,199 @@
+//! A small subset of CBOR (RFC 8949): unsigned integers, byte strings and arrays.
+//!
+//! Encoding always uses the shortest head; decoding accepts any head width but
+//! rejects values that do not fit the target type, indefinite lengths and reserved
+//! additional information.
+
+use crate::{DeserializeError, SerializeError};
+
+/// Major type 0: unsigned integer
+const MAJOR_UNSIGNED: u8 = 0;
+/// Major type 2: byte string
+const MAJOR_BYTES: u8 = 2;
+/// Major type 4: array
+const MAJOR_ARRAY: u8 = 4;
+
+/// Encodes a value as CBOR
+pub trait CborSerialize {
+    /// Writes the CBOR encoding of the value into `buf`.
+    /// Returns the number of bytes written.
+    fn serialize_cbor(&self, buf: &mut [u8]) -> Result<usize, SerializeError>;
+}
+
+/// Decodes a value from CBOR
+pub trait CborDeserialize: Sized {
+    /// Reads a CBOR encoded value from the front of `buf`.
+    /// Returns the value and the number of bytes consumed.
+    fn deserialize_cbor(buf: &[u8]) -> Result<(Self, usize), DeserializeError>;
+}
+
+/// Writes the CBOR encoding of `value` into `buf`.
+pub fn serialize_cbor<T: CborSerialize + ?Sized>(value: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    value.serialize_cbor(buf)
+}
+
+/// Reads a CBOR encoded value from the front of `buf`.
+pub fn deserialize_cbor<T: CborDeserialize>(buf: &[u8]) -> Result<(T, usize), DeserializeError> {
+    T::deserialize_cbor(buf)
+}
+
+/// Reads a CBOR byte string from the front of `buf` without copying it.
+pub fn deserialize_cbor_bytes(buf: &[u8]) -> Result<(&[u8], usize), DeserializeError> {
+    let (len, head) = read_head(buf, MAJOR_BYTES)?;
+    let len = usize::try_from(len).map_err(|_| DeserializeError::InvalidData)?;
+    let bytes = buf
+        .get(head..)
+        .and_then(|rest| rest.get(..len))
+        .ok_or(DeserializeError::BufferTooSmall)?;
+    Ok((bytes, head + len))
+}
+
+/// Writes an initial byte for `major` with the argument `value` in its shortest form.
+fn write_head(major: u8, value: u64, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let (info, width) = match value {
+        0..=23 => (value as u8, 0),
+        24..=0xff => (24, 1),
+        0x100..=0xffff => (25, 2),
+        0x1_0000..=0xffff_ffff => (26, 4),
+        _ => (27, 8),
+    };
+    let head = buf.get_mut(..1 + width).ok_or(SerializeError::BufferTooSmall)?;
+    head[0] = (major << 5) | info;
+    head[1..].copy_from_slice(&value.to_be_bytes()[8 - width..]);
+    Ok(1 + width)
+}
+
+/// Reads an initial byte that must carry `major`, returning its argument and the head length.
+fn read_head(buf: &[u8], major: u8) -> Result<(u64, usize), DeserializeError> {
+    let initial = *buf.first().ok_or(DeserializeError::BufferTooSmall)?;
+    if initial >> 5 != major {
+        return Err(DeserializeError::InvalidData);
+    }
+    let width = match initial & 0x1f {
+        info @ 0..=23 => return Ok((info as u64, 1)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err(DeserializeError::InvalidData),
+    };
+    let bytes = buf.get(1..1 + width).ok_or(DeserializeError::BufferTooSmall)?;
+    let value = bytes.iter().fold(0u64, |acc, &byte| (acc << 8) | byte as u64);
+    Ok((value, 1 + width))
+}
+
+macro_rules! impl_cbor_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl CborSerialize for $ty {
+                fn serialize_cbor(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+                    write_head(MAJOR_UNSIGNED, *self as u64, buf)
+                }
+            }
+
+            impl CborDeserialize for $ty {
+                fn deserialize_cbor(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+                    let (value, size) = read_head(buf, MAJOR_UNSIGNED)?;
+                    let value = <$ty>::try_from(value).map_err(|_| DeserializeError::InvalidData)?;
+                    Ok((value, size))
+                }
+            }
+        )*
+    };
+}
+
+impl_cbor_unsigned!(u8, u16, u32, u64);
+
+impl CborSerialize for [u8] {
+    fn serialize_cbor(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let head = write_head(MAJOR_BYTES, self.len() as u64, buf)?;
+        let dst = buf
+            .get_mut(head..head + self.len())
+            .ok_or(SerializeError::BufferTooSmall)?;
+        dst.copy_from_slice(self);
+        Ok(head + self.len())
+    }
+}
+
+/// Arrays encode as CBOR arrays of their elements; use a `[u8]` slice for a byte string.
+impl<T: CborSerialize, const N: usize> CborSerialize for [T; N] {
+    fn serialize_cbor(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut offset = write_head(MAJOR_ARRAY, N as u64, buf)?;
+        for item in self.iter() {
+            let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+            offset += item.serialize_cbor(rest)?;
+        }
+        Ok(offset)
+    }
+}
+
+impl<T: CborDeserialize, const N: usize> CborDeserialize for [T; N] {
+    fn deserialize_cbor(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (len, mut offset) = read_head(buf, MAJOR_ARRAY)?;
+        if len != N as u64 {
+            return Err(DeserializeError::InvalidData);
+        }
+        let mut failed = None;
+        let array = core::array::from_fn(|_| {
+            if failed.is_some() {
+                return None;
+            }
+            let item = buf
+                .get(offset..)
+                .ok_or(DeserializeError::BufferTooSmall)
+                .and_then(T::deserialize_cbor);
+            match item {
+                Ok((item, size)) => {
+                    offset += size;
+                    Some(item)
+                }
+                Err(err) => {
+                    failed = Some(err);
+                    None
+                }
+            }
+        });
+        if let Some(err) = failed {
+            return Err(err);
+        }
+        Ok((array.map(|item: Option<T>| item.unwrap()), offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsigned_heads() {
+        let mut buf = [0u8; 9];
+        assert_eq!(serialize_cbor(&10u8, &mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 0x0A);
+        assert_eq!(serialize_cbor(&1000u16, &mut buf).unwrap(), 3);
+        assert_eq!(buf[..3], [0x19, 0x03, 0xE8]);
+        assert_eq!(deserialize_cbor::<u16>(&buf).unwrap(), (1000, 3));
+        assert_eq!(serialize_cbor(&24u8, &mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [0x18, 24]);
+    }
+
+    #[test]
+    fn byte_strings_and_arrays() {
+        let mut buf = [0u8; 8];
+        assert_eq!(serialize_cbor(&[1u8, 2, 3][..], &mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], [0x43, 1, 2, 3]);
+        assert_eq!(deserialize_cbor_bytes(&buf).unwrap(), (&[1, 2, 3][..], 4));
+
+        assert_eq!(serialize_cbor(&[1u16, 500], &mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], [0x82, 0x01, 0x19, 0x01, 0xF4]);
+        assert_eq!(deserialize_cbor::<[u16; 2]>(&buf).unwrap(), ([1, 500], 5));
+    }
+
+    #[test]
+    fn rejects_mismatched_input() {
+        assert!(matches!(deserialize_cbor::<u8>(&[0x19, 0x01, 0x00]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_cbor::<u8>(&[0x43]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_cbor::<u16>(&[0x19, 0x01]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(deserialize_cbor::<u8>(&[0x1F]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_cbor::<[u8; 2]>(&[0x83, 1, 2, 3]), Err(DeserializeError::InvalidData)));
+    }
+}
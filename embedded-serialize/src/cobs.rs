@@ -0,0 +1,62 @@
+//! Consistent Overhead Byte Stuffing, which removes every `0x00` from a payload so
+//! that `0x00` can delimit frames on a byte stream.
+//!
+//! The functions here encode and decode a single frame without its delimiter.
+
+use crate::{DeserializeError, SerializeError};
+
+/// Largest encoded size of `len` payload bytes.
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// Encodes `src` into `dst`.
+/// Returns the number of bytes written, none of which are `0x00`.
+pub fn encode(src: &[u8], dst: &mut [u8]) -> Result<usize, SerializeError> {
+    let mut code_index = 0;
+    let mut code = 1u8;
+    let mut out = 1;
+    for &byte in src {
+        if byte != 0 {
+            *dst.get_mut(out).ok_or(SerializeError::BufferTooSmall)? = byte;
+            out += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            *dst.get_mut(code_index).ok_or(SerializeError::BufferTooSmall)? = code;
+            code_index = out;
+            out += 1;
+            code = 1;
+        }
+    }
+    *dst.get_mut(code_index).ok_or(SerializeError::BufferTooSmall)? = code;
+    Ok(out)
+}
+
+/// Decodes the frame in `buf` in place, writing the payload to the front of `buf`.
+/// Returns the payload length.
+pub fn decode_in_place(buf: &mut [u8]) -> Result<usize, DeserializeError> {
+    let mut read = 0;
+    let mut write = 0;
+    while read < buf.len() {
+        let code = buf[read] as usize;
+        if code == 0 || read + code > buf.len() {
+            return Err(DeserializeError::InvalidData);
+        }
+        read += 1;
+        for _ in 1..code {
+            if buf[read] == 0 {
+                return Err(DeserializeError::InvalidData);
+            }
+            buf[write] = buf[read];
+            read += 1;
+            write += 1;
+        }
+        // A full block carries no implied zero, nor does the last block.
+        if code != 0xFF && read < buf.len() {
+            buf[write] = 0;
+            write += 1;
+        }
+    }
+    Ok(write)
+}
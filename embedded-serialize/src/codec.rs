@@ -0,0 +1,358 @@
+//! Codecs bundling message serialization with a framing, so that a driver can turn
+//! messages into bytes and a received byte stream back into messages.
+
+use core::marker::PhantomData;
+
+use crate::cobs;
+use crate::crc::{deserialize_frame, deserialize_with_crc16, serialize_frame, serialize_with_crc16};
+use crate::ecc::{decode_ecc, encode_ecc};
+use crate::whitening::{dewhiten, whiten};
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Framed message transport over a byte stream
+pub trait Codec {
+    /// Messages sent
+    type Tx: Serialize;
+    /// Messages received
+    type Rx: Deserialize;
+
+    /// Writes `msg` as one frame into `out`.
+    /// Returns the number of bytes written.
+    fn encode(&mut self, msg: &Self::Tx, out: &mut [u8]) -> Result<usize, SerializeError>;
+
+    /// Appends received `bytes` and decodes the first complete frame, if there is one.
+    /// Frames still buffered are returned by later calls, which may pass no new bytes.
+    /// A frame that fails to decode is dropped and its error returned.
+    fn feed(&mut self, bytes: &[u8]) -> Result<Option<Self::Rx>, DeserializeError>;
+}
+
+/// Received bytes not yet consumed by a complete frame
+pub(crate) struct RxBuffer<const N: usize> {
+    pub(crate) bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> RxBuffer<N> {
+    pub(crate) fn new() -> Self {
+        RxBuffer { bytes: [0; N], len: 0 }
+    }
+
+    /// Appends `bytes`, discarding everything buffered if they don't fit.
+    pub(crate) fn extend(&mut self, bytes: &[u8]) -> Result<(), DeserializeError> {
+        let end = self.len + bytes.len();
+        match self.bytes.get_mut(self.len..end) {
+            Some(dst) => {
+                dst.copy_from_slice(bytes);
+                self.len = end;
+                Ok(())
+            }
+            None => {
+                self.len = 0;
+                Err(DeserializeError::BufferTooSmall)
+            }
+        }
+    }
+
+    pub(crate) fn filled(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Drops the first `count` buffered bytes.
+    pub(crate) fn consume(&mut self, count: usize) {
+        self.bytes.copy_within(count..self.len, 0);
+        self.len -= count;
+    }
+
+    /// Position of the first buffered `delimiter`, skipping any empty frames before it.
+    pub(crate) fn find_frame(&mut self, delimiter: u8) -> Option<usize> {
+        while self.filled().first() == Some(&delimiter) {
+            self.consume(1);
+        }
+        self.filled().iter().position(|&byte| byte == delimiter)
+    }
+}
+
+/// COBS-encoded frames, each terminated by `0x00`.
+/// `N` bounds both the serialized message size and the receive buffer.
+pub struct CobsCodec<Tx, Rx = Tx, const N: usize = 256> {
+    rx: RxBuffer<N>,
+    messages: PhantomData<fn(&Tx) -> Rx>,
+}
+
+impl<Tx, Rx, const N: usize> CobsCodec<Tx, Rx, N> {
+    pub fn new() -> Self {
+        CobsCodec {
+            rx: RxBuffer::new(),
+            messages: PhantomData,
+        }
+    }
+}
+
+impl<Tx, Rx, const N: usize> Default for CobsCodec<Tx, Rx, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tx: Serialize, Rx: Deserialize, const N: usize> Codec for CobsCodec<Tx, Rx, N> {
+    type Tx = Tx;
+    type Rx = Rx;
+
+    fn encode(&mut self, msg: &Tx, out: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut scratch = [0u8; N];
+        let size = msg.serialize(&mut scratch)?;
+        let encoded = cobs::encode(&scratch[..size], out)?;
+        *out.get_mut(encoded).ok_or(SerializeError::BufferTooSmall)? = 0;
+        Ok(encoded + 1)
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Result<Option<Rx>, DeserializeError> {
+        self.rx.extend(bytes)?;
+        let end = match self.rx.find_frame(0) {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let frame = &mut self.rx.bytes[..end];
+        let result = cobs::decode_in_place(frame).and_then(|len| Rx::deserialize(&frame[..len]));
+        self.rx.consume(end + 1);
+        result.map(Some)
+    }
+}
+
+/// Frames written by `crc::serialize_frame`: a big-endian `u16` payload length, the payload,
+/// and the CRC-16 of the payload.
+/// `N` bounds the receive buffer, which must hold a whole frame.
+///
+/// With whitening on, each whole frame is whitened from the same seed, for radios that
+/// send their own preamble and sync word ahead of it. The seed is a hook for the link:
+/// call `set_whitening` whenever the channel or whatever else it is derived from changes.
+pub struct FramedCrcCodec<Tx, Rx = Tx, const N: usize = 256> {
+    rx: RxBuffer<N>,
+    whitening: Option<u16>,
+    messages: PhantomData<fn(&Tx) -> Rx>,
+}
+
+impl<Tx, Rx, const N: usize> FramedCrcCodec<Tx, Rx, N> {
+    pub fn new() -> Self {
+        FramedCrcCodec {
+            rx: RxBuffer::new(),
+            whitening: None,
+            messages: PhantomData,
+        }
+    }
+
+    /// A codec whitening frames with `seed`, as with `whitening::whiten`.
+    pub fn with_whitening(seed: u16) -> Self {
+        let mut codec = Self::new();
+        codec.set_whitening(Some(seed));
+        codec
+    }
+
+    /// Sets the whitening seed of the frames encoded and decoded from now on, or turns
+    /// whitening off with `None`.
+    pub fn set_whitening(&mut self, seed: Option<u16>) {
+        self.whitening = seed;
+    }
+}
+
+impl<Tx, Rx, const N: usize> Default for FramedCrcCodec<Tx, Rx, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tx: Serialize, Rx: Deserialize, const N: usize> Codec for FramedCrcCodec<Tx, Rx, N> {
+    type Tx = Tx;
+    type Rx = Rx;
+
+    fn encode(&mut self, msg: &Tx, out: &mut [u8]) -> Result<usize, SerializeError> {
+        let size = serialize_frame(msg, out)?;
+        if let Some(seed) = self.whitening {
+            whiten(&mut out[..size], seed);
+        }
+        Ok(size)
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Result<Option<Rx>, DeserializeError> {
+        self.rx.extend(bytes)?;
+        let mut header = match self.rx.filled().get(..2) {
+            Some(header) => [header[0], header[1]],
+            None => return Ok(None),
+        };
+        if let Some(seed) = self.whitening {
+            dewhiten(&mut header, seed);
+        }
+        let len = u16::from_be_bytes(header) as usize;
+        let total = 2 + len + 2;
+        if total > N {
+            self.rx.consume(self.rx.len);
+            return Err(DeserializeError::BufferTooSmall);
+        }
+        if self.rx.len < total {
+            return Ok(None);
+        }
+        let result = match self.whitening {
+            Some(seed) => {
+                let mut frame = [0u8; N];
+                frame[..total].copy_from_slice(&self.rx.filled()[..total]);
+                dewhiten(&mut frame[..total], seed);
+                deserialize_frame::<Rx>(&frame[..total])
+            }
+            None => deserialize_frame::<Rx>(&self.rx.filled()[..total]),
+        };
+        let result = result.map(|(msg, _)| msg);
+        self.rx.consume(total);
+        result.map(Some)
+    }
+}
+
+/// Frames like those of `FramedCrcCodec`, with the payload and CRC-16 protected by
+/// the `ecc` code beneath the CRC; the length prefix counts the encoded bytes.
+/// `N` bounds the receive buffer, which must hold a whole encoded frame.
+pub struct EccFramedCrcCodec<Tx, Rx = Tx, const N: usize = 256> {
+    rx: RxBuffer<N>,
+    corrected: usize,
+    messages: PhantomData<fn(&Tx) -> Rx>,
+}
+
+impl<Tx, Rx, const N: usize> EccFramedCrcCodec<Tx, Rx, N> {
+    pub fn new() -> Self {
+        EccFramedCrcCodec {
+            rx: RxBuffer::new(),
+            corrected: 0,
+            messages: PhantomData,
+        }
+    }
+
+    /// Number of code words corrected in received frames so far.
+    pub fn corrected(&self) -> usize {
+        self.corrected
+    }
+}
+
+impl<Tx, Rx, const N: usize> Default for EccFramedCrcCodec<Tx, Rx, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tx: Serialize, Rx: Deserialize, const N: usize> Codec for EccFramedCrcCodec<Tx, Rx, N> {
+    type Tx = Tx;
+    type Rx = Rx;
+
+    fn encode(&mut self, msg: &Tx, out: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut scratch = [0u8; N];
+        let size = serialize_with_crc16(msg, &mut scratch)?;
+        let body = out.get_mut(2..).ok_or(SerializeError::BufferTooSmall)?;
+        let encoded = encode_ecc(&scratch[..size], body)?;
+        let len = u16::try_from(encoded).map_err(|_| SerializeError::InvalidData)?;
+        len.serialize(out)?;
+        Ok(2 + encoded)
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Result<Option<Rx>, DeserializeError> {
+        self.rx.extend(bytes)?;
+        let len = match u16::deserialize(self.rx.filled()) {
+            Ok(len) => len as usize,
+            Err(_) => return Ok(None),
+        };
+        let total = 2 + len;
+        if total > N {
+            self.rx.consume(self.rx.len);
+            return Err(DeserializeError::BufferTooSmall);
+        }
+        if self.rx.len < total {
+            return Ok(None);
+        }
+        let mut decoded = [0u8; N];
+        let result = decode_ecc(&self.rx.filled()[2..total], &mut decoded).and_then(|(size, corrected)| {
+            self.corrected += corrected;
+            match deserialize_with_crc16::<Rx>(&decoded[..size])? {
+                (msg, consumed) if consumed == size => Ok(msg),
+                _ => Err(DeserializeError::InvalidData),
+            }
+        });
+        self.rx.consume(total);
+        result.map(Some)
+    }
+}
+
+/// Lines of uppercase hex, each terminated by `\n`; a `\r` before the `\n` is ignored.
+/// `N` bounds both the serialized message size and the receive buffer.
+pub struct LineHexCodec<Tx, Rx = Tx, const N: usize = 256> {
+    rx: RxBuffer<N>,
+    messages: PhantomData<fn(&Tx) -> Rx>,
+}
+
+impl<Tx, Rx, const N: usize> LineHexCodec<Tx, Rx, N> {
+    pub fn new() -> Self {
+        LineHexCodec {
+            rx: RxBuffer::new(),
+            messages: PhantomData,
+        }
+    }
+}
+
+impl<Tx, Rx, const N: usize> Default for LineHexCodec<Tx, Rx, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tx: Serialize, Rx: Deserialize, const N: usize> Codec for LineHexCodec<Tx, Rx, N> {
+    type Tx = Tx;
+    type Rx = Rx;
+
+    fn encode(&mut self, msg: &Tx, out: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut scratch = [0u8; N];
+        let size = msg.serialize(&mut scratch)?;
+        let line = out.get_mut(..2 * size + 1).ok_or(SerializeError::BufferTooSmall)?;
+        for (digits, byte) in line.chunks_exact_mut(2).zip(&scratch[..size]) {
+            digits[0] = HEX_DIGITS[(byte >> 4) as usize];
+            digits[1] = HEX_DIGITS[(byte & 0xF) as usize];
+        }
+        line[2 * size] = b'\n';
+        Ok(line.len())
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Result<Option<Rx>, DeserializeError> {
+        self.rx.extend(bytes)?;
+        let end = match self.rx.find_frame(b'\n') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let line = &mut self.rx.bytes[..end];
+        let line = match line.split_last_mut() {
+            Some((b'\r', rest)) => rest,
+            _ => line,
+        };
+        let result = decode_hex_in_place(line).and_then(|len| Rx::deserialize(&line[..len]));
+        self.rx.consume(end + 1);
+        result.map(Some)
+    }
+}
+
+pub(crate) const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+pub(crate) fn hex_value(digit: u8) -> Result<u8, DeserializeError> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(DeserializeError::InvalidData),
+    }
+}
+
+/// Decodes the hex digits in `buf` in place, writing the bytes to the front of `buf`.
+/// Returns the number of bytes.
+pub(crate) fn decode_hex_in_place(buf: &mut [u8]) -> Result<usize, DeserializeError> {
+    if !buf.len().is_multiple_of(2) {
+        return Err(DeserializeError::InvalidData);
+    }
+    for index in 0..buf.len() / 2 {
+        let high = hex_value(buf[2 * index])?;
+        let low = hex_value(buf[2 * index + 1])?;
+        buf[index] = high << 4 | low;
+    }
+    Ok(buf.len() / 2)
+}
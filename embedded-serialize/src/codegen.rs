@@ -0,0 +1,455 @@
+//! C header and Python decoder generation from schema descriptors.
+//!
+//! In C headers, each message becomes a packed struct whose layout matches the wire format byte for byte.
+//! Multi-byte integers are stored as big-endian byte arrays and read and written through
+//! generated inline functions, so the header is correct on hosts of either endianness.
+
+use std::fmt::{self, Write};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::frame::MessageId;
+use crate::schema::{Describe, Field, Schema};
+
+/// A type to emit in a header, with its message ID if it has one
+pub struct Message {
+    pub schema: &'static Schema,
+    pub id: Option<u8>,
+}
+
+impl Message {
+    pub fn new<T: Describe>() -> Self {
+        Message { schema: T::SCHEMA, id: None }
+    }
+
+    pub fn with_id<T: Describe + MessageId>() -> Self {
+        Message {
+            schema: T::SCHEMA,
+            id: Some(T::ID),
+        }
+    }
+}
+
+/// Errors from generating code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    /// The schema at `path` has no C representation
+    Unsupported { path: String, reason: &'static str },
+    /// The output could not be written
+    Write,
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Unsupported { path, reason } => write!(f, "unsupported at {}: {}", path, reason),
+            CodegenError::Write => f.write_str("failed to write output"),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+fn unsupported(path: &str, reason: &'static str) -> CodegenError {
+    CodegenError::Unsupported {
+        path: String::from(path),
+        reason,
+    }
+}
+
+/// Generates a C header declaring every message, the struct and enum types they use,
+/// accessors for their multi-byte integers, and `_SIZE` and `_ID` constants.
+/// `guard` names the include guard macro.
+pub fn c_header(guard: &str, messages: &[Message]) -> Result<String, CodegenError> {
+    let mut header = Header::default();
+    for message in messages {
+        match message.schema {
+            Schema::Struct { name, .. } | Schema::Enum { name, .. } => header.define(message.schema, name)?,
+            Schema::Opaque { name } => return Err(unsupported(name, "opaque wire format")),
+            _ => return Err(unsupported("<message>", "messages must be structs or enums")),
+        };
+    }
+
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "#ifndef {guard}\n#define {guard}\n\n#include <stddef.h>\n#include <stdint.h>\n\n{HELPERS}\n"
+    );
+    out.push_str(&header.body);
+    for message in messages {
+        let (Schema::Struct { name, .. } | Schema::Enum { name, .. }) = message.schema else {
+            continue;
+        };
+        let constant = upper_snake(name);
+        // Every type that reached this point has a fixed size.
+        let size = message.schema.fixed_size().unwrap_or(0);
+        let _ = writeln!(out, "#define {constant}_SIZE {size}");
+        if let Some(id) = message.id {
+            let _ = writeln!(out, "#define {constant}_ID 0x{id:02X}");
+        }
+    }
+    let _ = write!(out, "\n#endif /* {guard} */\n");
+    Ok(out)
+}
+
+const HELPERS: &str = "\
+static inline uint16_t es_get_u16(const uint8_t *p) { return (uint16_t)((p[0] << 8) | p[1]); }
+static inline uint32_t es_get_u32(const uint8_t *p) {
+    return ((uint32_t)p[0] << 24) | ((uint32_t)p[1] << 16) | ((uint32_t)p[2] << 8) | p[3];
+}
+static inline void es_put_u16(uint8_t *p, uint16_t v) { p[0] = (uint8_t)(v >> 8); p[1] = (uint8_t)v; }
+static inline void es_put_u32(uint8_t *p, uint32_t v) {
+    p[0] = (uint8_t)(v >> 24); p[1] = (uint8_t)(v >> 16); p[2] = (uint8_t)(v >> 8); p[3] = (uint8_t)v;
+}
+";
+
+/// Type definitions emitted so far, each before its first use
+#[derive(Default)]
+struct Header {
+    defined: Vec<&'static str>,
+    body: String,
+}
+
+impl Header {
+    /// Emits the definition of a named struct or enum, and those it depends on, once.
+    fn define(&mut self, schema: &'static Schema, path: &str) -> Result<(), CodegenError> {
+        match schema {
+            Schema::Struct { name, fields } => {
+                if self.defined.contains(name) {
+                    return Ok(());
+                }
+                if fields.is_empty() {
+                    return Err(unsupported(path, "empty structs have no C representation"));
+                }
+                let mut members = String::new();
+                let mut accessors = String::new();
+                for field in fields.iter() {
+                    let field_path = std::format!("{}.{}", path, field.name);
+                    let (ty, dims) = self.member(field.schema, &field_path)?;
+                    let _ = writeln!(members, "    {} {}{};", ty, field.name, dims);
+                    accessors_for(&mut accessors, name, field);
+                }
+                let _ = write!(
+                    self.body,
+                    "typedef struct __attribute__((packed)) {{\n{members}}} {name};\n\n{accessors}"
+                );
+                if !accessors.is_empty() {
+                    self.body.push('\n');
+                }
+                self.defined.push(name);
+            }
+            Schema::Enum { name, variants } => {
+                if self.defined.contains(name) {
+                    return Ok(());
+                }
+                if variants.iter().any(|variant| !variant.fields.is_empty()) {
+                    return Err(unsupported(path, "enums with fields have no C representation"));
+                }
+                let _ = writeln!(self.body, "typedef uint8_t {name};\nenum {{");
+                for variant in variants.iter() {
+                    let _ = writeln!(self.body, "    {}_{} = {},", name, variant.name, variant.tag);
+                }
+                self.body.push_str("};\n\n");
+                self.defined.push(name);
+            }
+            _ => unreachable!("only structs and enums are defined"),
+        }
+        Ok(())
+    }
+
+    /// C type and array dimensions of a struct member.
+    fn member(&mut self, schema: &'static Schema, path: &str) -> Result<(&'static str, String), CodegenError> {
+        Ok(match schema {
+            Schema::U8 | Schema::Bool => ("uint8_t", String::new()),
+            Schema::I8 => ("int8_t", String::new()),
+            Schema::U16 | Schema::I16 => ("uint8_t", String::from("[2]")),
+            Schema::U32 | Schema::I32 => ("uint8_t", String::from("[4]")),
+            Schema::FixedStr { len } => ("char", std::format!("[{}]", len)),
+            Schema::Array { item, len } => {
+                let (ty, dims) = self.member(item, path)?;
+                (ty, std::format!("[{}]{}", len, dims))
+            }
+            Schema::Struct { name, .. } | Schema::Enum { name, .. } => {
+                self.define(schema, path)?;
+                (name, String::new())
+            }
+            Schema::Counted { .. } => return Err(unsupported(path, "counted arrays have no fixed layout")),
+            Schema::Opaque { .. } => return Err(unsupported(path, "opaque wire format")),
+        })
+    }
+}
+
+/// Emits getter and setter functions for a field that holds multi-byte integers.
+fn accessors_for(out: &mut String, owner: &str, field: &Field) {
+    let mut schema = field.schema;
+    let mut depth = 0;
+    while let Schema::Array { item, .. } = schema {
+        schema = item;
+        depth += 1;
+    }
+    let (bits, signed) = match schema {
+        Schema::U16 => (16, false),
+        Schema::I16 => (16, true),
+        Schema::U32 => (32, false),
+        Schema::I32 => (32, true),
+        _ => return,
+    };
+    let value_ty = std::format!("{}int{}_t", if signed { "" } else { "u" }, bits);
+    let params: String = (0..depth).map(|index| std::format!(", size_t i{}", index)).collect();
+    let indices: String = (0..depth).map(|index| std::format!("[i{}]", index)).collect();
+    let name = field.name;
+    let _ = writeln!(
+        out,
+        "static inline {value_ty} {owner}_get_{name}(const {owner} *m{params}) {{ \
+         return ({value_ty})es_get_u{bits}(m->{name}{indices}); }}"
+    );
+    let _ = writeln!(
+        out,
+        "static inline void {owner}_set_{name}({owner} *m{params}, {value_ty} v) {{ \
+         es_put_u{bits}(m->{name}{indices}, (uint{bits}_t)v); }}"
+    );
+}
+
+/// `CamelCase` to `UPPER_SNAKE`.
+fn upper_snake(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            out.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
+
+/// Generates a Python module with a class per struct and enum in `schema`, each with a
+/// `parse(data)` classmethod returning an instance and a `parse_from(data, offset)`
+/// classmethod also returning the offset just past it.
+///
+/// Struct instances have an attribute per field. Enum classes have a constant per variant
+/// tag, and enum instances have a `variant` name and an attribute per field of that variant.
+/// Structs of only scalars, fixed strings and scalar arrays also get a `FORMAT` string for
+/// the `struct` module, under which arrays unpack flattened.
+pub fn emit_python(schema: &'static Schema, out: &mut impl Write) -> Result<(), CodegenError> {
+    let name = match schema {
+        Schema::Struct { name, .. } | Schema::Enum { name, .. } => name,
+        Schema::Opaque { name } => return Err(unsupported(name, "opaque wire format")),
+        _ => return Err(unsupported("<message>", "messages must be structs or enums")),
+    };
+    let mut module = PythonModule::default();
+    module.define(schema, name)?;
+    out.write_str(PYTHON_HELPERS)?;
+    out.write_str(&module.body)?;
+    Ok(())
+}
+
+impl From<fmt::Error> for CodegenError {
+    fn from(_: fmt::Error) -> Self {
+        CodegenError::Write
+    }
+}
+
+const PYTHON_HELPERS: &str = r#"import struct
+
+
+def _scalar(fmt):
+    size = struct.calcsize(fmt)
+
+    def read(data, offset):
+        return struct.unpack_from(fmt, data, offset)[0], offset + size
+
+    return read
+
+
+_u8, _u16, _u32 = _scalar(">B"), _scalar(">H"), _scalar(">I")
+_i8, _i16, _i32 = _scalar(">b"), _scalar(">h"), _scalar(">i")
+
+
+def _bool(data, offset):
+    value, offset = _u8(data, offset)
+    if value > 1:
+        raise ValueError("invalid bool %d" % value)
+    return value == 1, offset
+
+
+def _array(item, count):
+    def read(data, offset):
+        items = []
+        for _ in range(count):
+            value, offset = item(data, offset)
+            items.append(value)
+        return items, offset
+
+    return read
+
+
+def _str(size):
+    def read(data, offset):
+        raw = bytes(data[offset:offset + size])
+        if len(raw) < size:
+            raise ValueError("buffer too small")
+        return raw.rstrip(b"\0").decode("utf-8"), offset + size
+
+    return read
+
+
+def _fields(value, names):
+    return ", ".join("%s=%r" % (name, getattr(value, name)) for name in names)
+"#;
+
+/// Classes emitted so far, each after those it refers to
+#[derive(Default)]
+struct PythonModule {
+    defined: Vec<&'static str>,
+    body: String,
+}
+
+impl PythonModule {
+    /// Emits the class of a named struct or enum, and those it depends on, once.
+    fn define(&mut self, schema: &'static Schema, path: &str) -> Result<(), CodegenError> {
+        match schema {
+            Schema::Struct { name, fields } => {
+                if self.defined.contains(name) {
+                    return Ok(());
+                }
+                let parse = self.parse_fields(fields, path, "        ")?;
+                let names = python_tuple(fields.iter().map(|field| field.name));
+                let _ = write!(self.body, "\n\nclass {name}:\n    FIELDS = {names}\n");
+                if let Some(size) = schema.fixed_size() {
+                    let _ = writeln!(self.body, "    SIZE = {size}");
+                }
+                if let Some(format) = struct_format(fields) {
+                    let _ = writeln!(self.body, "    FORMAT = \">{format}\"");
+                }
+                let _ = write!(
+                    self.body,
+                    "\n    def __init__(self, **fields):\n        self.__dict__.update(fields)\n\
+                     \n    def __repr__(self):\n        return \"{name}(%s)\" % _fields(self, self.FIELDS)\n\
+                     \n    @classmethod\n    def parse(cls, data):\n        return cls.parse_from(data, 0)[0]\n\
+                     \n    @classmethod\n    def parse_from(cls, data, offset):\n        fields = {{}}\n\
+                     {parse}        return cls(**fields), offset\n"
+                );
+                self.defined.push(name);
+            }
+            Schema::Enum { name, variants } => {
+                if self.defined.contains(name) {
+                    return Ok(());
+                }
+                let mut arms = String::new();
+                for variant in variants.iter() {
+                    let variant_path = std::format!("{}::{}", path, variant.name);
+                    let parse = self.parse_fields(variant.fields, &variant_path, "            ")?;
+                    let names = python_tuple(variant.fields.iter().map(|field| field.name));
+                    let _ = write!(
+                        arms,
+                        "        if tag == {}:\n            fields = {{}}\n{parse}            \
+                         return cls(\"{}\", {names}, **fields), offset\n",
+                        variant.tag, variant.name
+                    );
+                }
+                let _ = write!(self.body, "\n\nclass {name}:\n");
+                for variant in variants.iter() {
+                    let _ = writeln!(self.body, "    {} = {}", variant.name, variant.tag);
+                }
+                let _ = write!(
+                    self.body,
+                    "\n    def __init__(self, variant, names, **fields):\n        self.variant = variant\n\
+                     \x20       self.names = names\n        self.__dict__.update(fields)\n\
+                     \n    def __repr__(self):\n        if not self.names:\n            \
+                     return \"{name}.%s\" % self.variant\n        \
+                     return \"{name}.%s(%s)\" % (self.variant, _fields(self, self.names))\n\
+                     \n    @classmethod\n    def parse(cls, data):\n        return cls.parse_from(data, 0)[0]\n\
+                     \n    @classmethod\n    def parse_from(cls, data, offset):\n        tag, offset = _u8(data, offset)\n\
+                     {arms}        raise ValueError(\"unknown {name} tag %d\" % tag)\n"
+                );
+                self.defined.push(name);
+            }
+            _ => unreachable!("only structs and enums are defined"),
+        }
+        Ok(())
+    }
+
+    /// Statements reading `fields` into the `fields` dict, advancing `offset`.
+    fn parse_fields(&mut self, fields: &[Field], path: &str, indent: &str) -> Result<String, CodegenError> {
+        let mut parse = String::new();
+        for field in fields {
+            let field_path = std::format!("{}.{}", path, field.name);
+            let reader = match field.schema {
+                Schema::Counted { item, count, .. } => {
+                    std::format!("_array({}, fields[\"{}\"])", self.reader(item, &field_path)?, count)
+                }
+                schema => self.reader(schema, &field_path)?,
+            };
+            let _ = writeln!(
+                parse,
+                "{indent}fields[\"{}\"], offset = {reader}(data, offset)",
+                field.name
+            );
+        }
+        Ok(parse)
+    }
+
+    /// Expression for a function reading `schema` from `(data, offset)`.
+    fn reader(&mut self, schema: &'static Schema, path: &str) -> Result<String, CodegenError> {
+        Ok(match schema {
+            Schema::U8 => String::from("_u8"),
+            Schema::U16 => String::from("_u16"),
+            Schema::U32 => String::from("_u32"),
+            Schema::I8 => String::from("_i8"),
+            Schema::I16 => String::from("_i16"),
+            Schema::I32 => String::from("_i32"),
+            Schema::Bool => String::from("_bool"),
+            Schema::FixedStr { len } => std::format!("_str({})", len),
+            Schema::Array { item, len } => std::format!("_array({}, {})", self.reader(item, path)?, len),
+            Schema::Struct { name, .. } | Schema::Enum { name, .. } => {
+                self.define(schema, path)?;
+                std::format!("{}.parse_from", name)
+            }
+            Schema::Counted { .. } => return Err(unsupported(path, "counted arrays must be fields")),
+            Schema::Opaque { .. } => return Err(unsupported(path, "opaque wire format")),
+        })
+    }
+}
+
+/// A Python tuple literal of string constants.
+fn python_tuple<'a>(items: impl Iterator<Item = &'a str>) -> String {
+    let items: Vec<String> = items.map(|item| std::format!("\"{}\"", item)).collect();
+    match items.len() {
+        1 => std::format!("({},)", items[0]),
+        _ => std::format!("({})", items.join(", ")),
+    }
+}
+
+/// `struct` module format of a flat struct, without the byte order prefix.
+fn struct_format(fields: &[Field]) -> Option<String> {
+    let mut format = String::new();
+    for field in fields {
+        let (mut schema, mut count) = (field.schema, 1);
+        while let Schema::Array { item, len } = schema {
+            schema = item;
+            count *= len;
+        }
+        let code = match schema {
+            Schema::U8 => 'B',
+            Schema::U16 => 'H',
+            Schema::U32 => 'I',
+            Schema::I8 => 'b',
+            Schema::I16 => 'h',
+            Schema::I32 => 'i',
+            Schema::Bool => '?',
+            Schema::FixedStr { len } if count == 1 => {
+                let _ = write!(format, "{}s", len);
+                continue;
+            }
+            _ => return None,
+        };
+        if count == 1 {
+            format.push(code);
+        } else {
+            let _ = write!(format, "{}{}", count, code);
+        }
+    }
+    Some(format)
+}
@@ -0,0 +1,174 @@
+//! Compression of serialized bytes before transmission, with the algorithm supplied
+//! through the `Compressor` trait. `Rle` is a run-length encoding for payloads with
+//! long runs of one byte, such as mostly idle sensor frames.
+//!
+//! ```
+//! use embedded_serialize::compress::{deserialize_compressed, serialize_compressed, Rle};
+//! use embedded_serialize_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Frame {
+//!     channel: u8,
+//!     samples: [u16; 16],
+//! }
+//!
+//! let frame = Frame { channel: 3, samples: [0; 16] };
+//! let mut buf = [0u8; 64];
+//! let size = serialize_compressed::<_, _, 64>(&frame, &Rle, &mut buf).unwrap();
+//! assert_eq!(buf[..size], [1, 3, 32, 0]);
+//! assert_eq!(deserialize_compressed::<Frame, _, 64>(&buf[..size], &Rle).unwrap(), frame);
+//! ```
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A lossless compression algorithm over byte slices
+pub trait Compressor {
+    /// Compresses `src` into `dst`.
+    /// Returns the number of bytes written.
+    fn compress(&self, src: &[u8], dst: &mut [u8]) -> Result<usize, SerializeError>;
+
+    /// Decompresses the whole of `src` into `dst`.
+    /// Returns the number of bytes written.
+    fn decompress(&self, src: &[u8], dst: &mut [u8]) -> Result<usize, DeserializeError>;
+}
+
+/// Run-length encoding as pairs of a count (1 to 255) and the byte repeated.
+/// Input without runs doubles in size.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rle;
+
+impl Compressor for Rle {
+    fn compress(&self, src: &[u8], dst: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut written = 0;
+        let mut rest = src;
+        while let Some(&byte) = rest.first() {
+            let run = rest.iter().take(255).take_while(|&&next| next == byte).count();
+            let pair = dst.get_mut(written..written + 2).ok_or(SerializeError::BufferTooSmall)?;
+            pair.copy_from_slice(&[run as u8, byte]);
+            written += 2;
+            rest = &rest[run..];
+        }
+        Ok(written)
+    }
+
+    /// Fails with `InvalidData` on an odd length or a zero count.
+    fn decompress(&self, src: &[u8], dst: &mut [u8]) -> Result<usize, DeserializeError> {
+        let pairs = src.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return Err(DeserializeError::InvalidData);
+        }
+        let mut written = 0;
+        for pair in pairs {
+            let run = pair[0] as usize;
+            if run == 0 {
+                return Err(DeserializeError::InvalidData);
+            }
+            let out = dst.get_mut(written..written + run).ok_or(DeserializeError::BufferTooSmall)?;
+            out.fill(pair[1]);
+            written += run;
+        }
+        Ok(written)
+    }
+}
+
+/// Serializes `value` into a stack buffer of `N` bytes, then compresses it into `buf`.
+/// Returns the number of bytes written.
+pub fn serialize_compressed<T: Serialize + ?Sized, C: Compressor + ?Sized, const N: usize>(
+    value: &T,
+    compressor: &C,
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    let mut scratch = [0u8; N];
+    let size = value.serialize(&mut scratch)?;
+    compressor.compress(&scratch[..size], buf)
+}
+
+/// Decompresses the whole of `buf` into a stack buffer of `N` bytes, then deserializes
+/// a value from it.
+pub fn deserialize_compressed<T: Deserialize, C: Compressor + ?Sized, const N: usize>(
+    buf: &[u8],
+    compressor: &C,
+) -> Result<T, DeserializeError> {
+    let mut scratch = [0u8; N];
+    let size = compressor.decompress(buf, &mut scratch)?;
+    T::deserialize(&scratch[..size])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Passes the bytes through unchanged, standing in for a user-supplied codec
+    struct Identity;
+
+    impl Compressor for Identity {
+        fn compress(&self, src: &[u8], dst: &mut [u8]) -> Result<usize, SerializeError> {
+            let dst = dst.get_mut(..src.len()).ok_or(SerializeError::BufferTooSmall)?;
+            dst.copy_from_slice(src);
+            Ok(src.len())
+        }
+
+        fn decompress(&self, src: &[u8], dst: &mut [u8]) -> Result<usize, DeserializeError> {
+            let dst = dst.get_mut(..src.len()).ok_or(DeserializeError::BufferTooSmall)?;
+            dst.copy_from_slice(src);
+            Ok(src.len())
+        }
+    }
+
+    #[test]
+    fn repetitive_values_shrink_and_round_trip() {
+        let samples = [[0x0101u16; 8], [0; 8], [0xFFFF; 8]];
+        let mut buf = [0u8; 64];
+        let size = serialize_compressed::<_, _, 48>(&samples, &Rle, &mut buf).unwrap();
+        assert_eq!(buf[..size], [16, 0x01, 16, 0x00, 16, 0xFF]);
+        assert!(size < samples.serialized_size());
+        assert_eq!(deserialize_compressed::<[[u16; 8]; 3], _, 48>(&buf[..size], &Rle).unwrap(), samples);
+    }
+
+    #[test]
+    fn long_runs_are_split_at_255() {
+        let mut buf = [0u8; 8];
+        assert_eq!(Rle.compress(&[7; 600], &mut buf).unwrap(), 6);
+        assert_eq!(buf[..6], [255, 7, 255, 7, 90, 7]);
+
+        let mut out = [0u8; 600];
+        assert_eq!(Rle.decompress(&buf[..6], &mut out).unwrap(), 600);
+        assert_eq!(out, [7; 600]);
+    }
+
+    #[test]
+    fn input_without_runs_doubles() {
+        let mut buf = [0u8; 8];
+        assert_eq!(Rle.compress(&[1, 2, 3, 4], &mut buf).unwrap(), 8);
+        assert_eq!(buf, [1, 1, 1, 2, 1, 3, 1, 4]);
+        assert!(Rle.compress(&[1, 2, 3, 4, 5], &mut buf).is_err());
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let mut out = [0u8; 8];
+        assert!(matches!(Rle.decompress(&[2, 7, 3], &mut out), Err(DeserializeError::InvalidData)));
+        assert!(matches!(Rle.decompress(&[0, 7], &mut out), Err(DeserializeError::InvalidData)));
+        assert!(matches!(Rle.decompress(&[9, 7], &mut out), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn scratch_bounds_the_uncompressed_size() {
+        let mut buf = [0u8; 16];
+        assert!(serialize_compressed::<_, _, 3>(&0u32, &Rle, &mut buf).is_err());
+        let size = serialize_compressed::<_, _, 4>(&0u32, &Rle, &mut buf).unwrap();
+        assert!(matches!(
+            deserialize_compressed::<u32, _, 3>(&buf[..size], &Rle),
+            Err(DeserializeError::BufferTooSmall)
+        ));
+    }
+
+    #[test]
+    fn any_compressor_can_be_plugged_in() {
+        let mut buf = [0u8; 4];
+        assert_eq!(serialize_compressed::<_, _, 4>(&0x0102_0304u32, &Identity, &mut buf).unwrap(), 4);
+        assert_eq!(buf, [1, 2, 3, 4]);
+        let codec: &dyn Compressor = &Identity;
+        assert_eq!(deserialize_compressed::<u32, _, 4>(&buf, codec).unwrap(), 0x0102_0304);
+    }
+}
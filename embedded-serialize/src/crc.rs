@@ -0,0 +1,171 @@
+//! CRC-16 checksums over serialized values.
+//!
+//! The checksum is CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF),
+//! appended big-endian after the bytes it covers. A CRC-8 is provided for short headers.
+//!
+//! A struct whose specification lists the checksum as a field can name it with
+//! `#[es(crc = "crc16_ccitt")]` on its last field instead; the value it holds is ignored
+//! when serializing:
+//!
+//! ```
+//! use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+//! use embedded_serialize_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Telemetry {
+//!     id: u8,
+//!     level: u16,
+//!     #[es(crc = "crc16_ccitt")]
+//!     crc: u16,
+//! }
+//!
+//! let mut buf = [0u8; 8];
+//! let size = Telemetry { id: 1, level: 0x0203, crc: 0 }.serialize(&mut buf).unwrap();
+//! assert_eq!(buf[..size], [0x01, 0x02, 0x03, 0xAD, 0xAD]);
+//!
+//! let decoded = Telemetry::deserialize(&buf[..size]).unwrap();
+//! assert_eq!(decoded, Telemetry { id: 1, level: 0x0203, crc: 0xADAD });
+//!
+//! buf[1] ^= 0x10;
+//! assert!(matches!(Telemetry::deserialize(&buf[..size]), Err(DeserializeError::ChecksumMismatch)));
+//! ```
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Computes the CRC-16/CCITT-FALSE of `data`.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Computes the CRC-8/SMBUS (polynomial 0x07, initial value 0x00) of `data`.
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Serializes `value` followed by the CRC-16 of its bytes.
+/// Returns the number of bytes written.
+pub fn serialize_with_crc16<T: Serialize + ?Sized>(value: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let size = value.serialize(buf)?;
+    let crc = crc16(&buf[..size]);
+    Ok(size + crc.serialize(&mut buf[size..])?)
+}
+
+/// Deserializes a value followed by the CRC-16 of its bytes, failing with
+/// `ChecksumMismatch` if the checksum does not match.
+/// Returns the value and the number of bytes consumed, checksum included.
+pub fn deserialize_with_crc16<T: Deserialize>(buf: &[u8]) -> Result<(T, usize), DeserializeError> {
+    let (value, size) = T::deserialize_consumed(buf)?;
+    let rest = buf.get(size..).ok_or(DeserializeError::BufferTooSmall)?;
+    let (crc, crc_size) = u16::deserialize_consumed(rest)?;
+    if crc != crc16(&buf[..size]) {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    Ok((value, size + crc_size))
+}
+
+/// Serializes `value` as a frame: a big-endian `u16` payload length, the payload,
+/// and the CRC-16 of the payload.
+/// Returns the number of bytes written.
+pub fn serialize_frame<T: Serialize + ?Sized>(value: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let body = buf.get_mut(2..).ok_or(SerializeError::BufferTooSmall)?;
+    let size = serialize_with_crc16(value, body)?;
+    let len = u16::try_from(size - 2).map_err(|_| SerializeError::InvalidData)?;
+    len.serialize(buf)?;
+    Ok(2 + size)
+}
+
+/// Deserializes a frame written by `serialize_frame`, failing with `ChecksumMismatch` if
+/// the CRC does not match and `InvalidData` if the payload is not exactly the given length.
+/// Returns the value and the number of bytes consumed, the whole frame.
+pub fn deserialize_frame<T: Deserialize>(buf: &[u8]) -> Result<(T, usize), DeserializeError> {
+    let len = u16::deserialize(buf)? as usize;
+    let frame = buf.get(..2 + len + 2).ok_or(DeserializeError::BufferTooSmall)?;
+    let (payload, crc) = frame[2..].split_at(len);
+    if u16::deserialize(crc)? != crc16(payload) {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    let (value, size) = T::deserialize_consumed(payload)?;
+    if size != len {
+        return Err(DeserializeError::InvalidData);
+    }
+    Ok((value, frame.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reports consuming more bytes than it was given
+    struct Overreports;
+
+    impl Deserialize for Overreports {
+        fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+            Self::deserialize_consumed(buf).map(|(value, _)| value)
+        }
+
+        fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+            Ok((Overreports, buf.len() + 1))
+        }
+    }
+
+    #[test]
+    fn crc16_round_trip() {
+        let mut buf = [0u8; 4];
+        assert_eq!(serialize_with_crc16(&0x1234u16, &mut buf).unwrap(), 4);
+        assert_eq!(deserialize_with_crc16::<u16>(&buf).unwrap(), (0x1234, 4));
+        buf[0] ^= 0x80;
+        assert!(matches!(deserialize_with_crc16::<u16>(&buf), Err(DeserializeError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn overreported_size_fails_instead_of_panicking() {
+        assert!(matches!(deserialize_with_crc16::<Overreports>(&[1, 2]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let mut buf = [0u8; 16];
+        let size = serialize_frame(&0x0102_0304u32, &mut buf).unwrap();
+        assert_eq!(size, 8);
+        assert_eq!(buf[..6], [0, 4, 1, 2, 3, 4]);
+        assert_eq!(buf[6..8], crc16(&[1, 2, 3, 4]).to_be_bytes());
+        assert_eq!(deserialize_frame::<u32>(&buf).unwrap(), (0x0102_0304, 8));
+    }
+
+    #[test]
+    fn frame_with_bad_length_is_rejected() {
+        // The length covers a trailing byte the payload does not consume, under a valid CRC.
+        let mut buf = [0, 5, 1, 2, 3, 4, 9, 0, 0];
+        let crc = crc16(&buf[2..7]);
+        buf[7..].copy_from_slice(&crc.to_be_bytes());
+        assert!(matches!(deserialize_frame::<u32>(&buf), Err(DeserializeError::InvalidData)));
+
+        // The length runs past the end of the buffer.
+        let mut buf = [0u8; 16];
+        let size = serialize_frame(&0x0102_0304u32, &mut buf).unwrap();
+        buf[1] = 40;
+        assert!(matches!(deserialize_frame::<u32>(&buf[..size]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn frame_with_bad_crc_is_rejected() {
+        let mut buf = [0u8; 16];
+        let size = serialize_frame(&0x0102_0304u32, &mut buf).unwrap();
+        buf[3] ^= 0x01;
+        assert!(matches!(deserialize_frame::<u32>(&buf[..size]), Err(DeserializeError::ChecksumMismatch)));
+    }
+}
@@ -0,0 +1,109 @@
+//! Fixed-point decimal values stored as scaled integers.
+
+use core::fmt;
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A decimal with two fractional digits, stored as a whole number of hundredths
+/// and written as 8 big-endian bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Decimal2(i64);
+
+impl Decimal2 {
+    pub const fn from_hundredths(hundredths: i64) -> Self {
+        Decimal2(hundredths)
+    }
+
+    /// Builds `whole.fraction`, e.g. `new(12, 34)` for 12.34 and `new(-12, 34)` for -12.34.
+    /// Returns `None` if `fraction` is 100 or more or the value overflows.
+    /// Values between -1 and 0 can only be built with `from_hundredths`.
+    pub fn new(whole: i64, fraction: u8) -> Option<Self> {
+        if fraction >= 100 {
+            return None;
+        }
+        let scaled = whole.checked_mul(100)?;
+        let hundredths = if whole < 0 {
+            scaled.checked_sub(fraction as i64)?
+        } else {
+            scaled.checked_add(fraction as i64)?
+        };
+        Some(Decimal2(hundredths))
+    }
+
+    pub const fn hundredths(&self) -> i64 {
+        self.0
+    }
+
+    /// Integer part, rounded toward zero.
+    pub const fn whole(&self) -> i64 {
+        self.0 / 100
+    }
+
+    /// Fractional digits, without sign.
+    pub const fn fraction(&self) -> u8 {
+        (self.0 % 100).unsigned_abs() as u8
+    }
+}
+
+impl fmt::Display for Decimal2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}{}.{:02}", sign, self.whole().unsigned_abs(), self.fraction())
+    }
+}
+
+impl Serialize for Decimal2 {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let dst = buf.get_mut(..8).ok_or(SerializeError::BufferTooSmall)?;
+        dst.copy_from_slice(&self.0.to_be_bytes());
+        Ok(8)
+    }
+
+    fn serialized_size(&self) -> usize {
+        8
+    }
+}
+
+impl Deserialize for Decimal2 {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let bytes = buf.get(..8).ok_or(DeserializeError::BufferTooSmall)?;
+        let mut raw = [0; 8];
+        raw.copy_from_slice(bytes);
+        Ok((Decimal2(i64::from_be_bytes(raw)), 8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn twelve_point_three_four_round_trip() {
+        let value = Decimal2::new(12, 34).unwrap();
+        assert_eq!((value.hundredths(), value.whole(), value.fraction()), (1234, 12, 34));
+        let mut buf = [0u8; 8];
+        assert_eq!(value.serialize(&mut buf).unwrap(), 8);
+        assert_eq!(buf, 1234i64.to_be_bytes());
+        assert_eq!(Decimal2::deserialize(&buf).unwrap(), value);
+    }
+
+    #[test]
+    fn negative_values_keep_the_fraction_unsigned() {
+        let value = Decimal2::new(-12, 34).unwrap();
+        assert_eq!((value.hundredths(), value.whole(), value.fraction()), (-1234, -12, 34));
+        assert_eq!(Decimal2::from_hundredths(-5).fraction(), 5);
+    }
+
+    #[test]
+    fn bad_fraction_or_overflow_is_rejected() {
+        assert_eq!(Decimal2::new(1, 100), None);
+        assert_eq!(Decimal2::new(i64::MAX / 100 + 1, 0), None);
+        assert_eq!(Decimal2::new(i64::MAX / 100, 99), None);
+        assert!(matches!(Decimal2::deserialize(&[0; 7]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(Decimal2::new(1, 0).unwrap().serialize(&mut [0; 7]), Err(SerializeError::BufferTooSmall)));
+    }
+}
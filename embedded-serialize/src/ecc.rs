@@ -0,0 +1,145 @@
+//! Forward error correction with an extended Hamming(8,4) SECDED code.
+//!
+//! Every 4-bit nibble, high nibble first, becomes one 8-bit code word, so the encoded
+//! form is twice the size of the input. Decoding corrects any single flipped bit in a
+//! code word and detects any two.
+
+use crate::{DeserializeError, SerializeError};
+
+/// Code word of the low 4 bits of `nibble`.
+/// Bits 0 to 6 hold Hamming positions 1 to 7 (parity at 1, 2 and 4); bit 7 is overall parity.
+fn encode_nibble(nibble: u8) -> u8 {
+    let bit = |index: u8| (nibble >> index) & 1;
+    let (d1, d2, d3, d4) = (bit(0), bit(1), bit(2), bit(3));
+    let p1 = d1 ^ d2 ^ d4;
+    let p2 = d1 ^ d3 ^ d4;
+    let p3 = d2 ^ d3 ^ d4;
+    let word = p1 | p2 << 1 | d1 << 2 | p3 << 3 | d2 << 4 | d3 << 5 | d4 << 6;
+    word | ((word.count_ones() as u8 & 1) << 7)
+}
+
+/// Nibble carried by `word`, and whether a bit had to be corrected.
+fn decode_word(mut word: u8) -> Result<(u8, bool), DeserializeError> {
+    let bit = |word: u8, position: u8| (word >> (position - 1)) & 1;
+    let syndrome = (bit(word, 1) ^ bit(word, 3) ^ bit(word, 5) ^ bit(word, 7))
+        | (bit(word, 2) ^ bit(word, 3) ^ bit(word, 6) ^ bit(word, 7)) << 1
+        | (bit(word, 4) ^ bit(word, 5) ^ bit(word, 6) ^ bit(word, 7)) << 2;
+    let parity_error = !word.count_ones().is_multiple_of(2);
+    let corrected = match (syndrome, parity_error) {
+        (0, false) => false,
+        // Only the overall parity bit flipped.
+        (0, true) => true,
+        (position, true) => {
+            word ^= 1 << (position - 1);
+            true
+        }
+        (_, false) => return Err(DeserializeError::Uncorrectable),
+    };
+    let nibble = bit(word, 3) | bit(word, 5) << 1 | bit(word, 6) << 2 | bit(word, 7) << 3;
+    Ok((nibble, corrected))
+}
+
+/// Encodes `src` into `dst`.
+/// Returns the number of bytes written, always `2 * src.len()`.
+pub fn encode_ecc(src: &[u8], dst: &mut [u8]) -> Result<usize, SerializeError> {
+    let dst = dst.get_mut(..2 * src.len()).ok_or(SerializeError::BufferTooSmall)?;
+    for (words, &byte) in dst.chunks_exact_mut(2).zip(src) {
+        words[0] = encode_nibble(byte >> 4);
+        words[1] = encode_nibble(byte & 0xF);
+    }
+    Ok(dst.len())
+}
+
+/// Decodes `src` into `dst`, correcting single-bit errors and failing with
+/// `Uncorrectable` on a code word with two flipped bits.
+/// Returns the number of bytes written and the number of code words corrected.
+pub fn decode_ecc(src: &[u8], dst: &mut [u8]) -> Result<(usize, usize), DeserializeError> {
+    if !src.len().is_multiple_of(2) {
+        return Err(DeserializeError::InvalidData);
+    }
+    let dst = dst.get_mut(..src.len() / 2).ok_or(DeserializeError::BufferTooSmall)?;
+    let mut corrected = 0;
+    for (byte, words) in dst.iter_mut().zip(src.chunks_exact(2)) {
+        let (high, high_corrected) = decode_word(words[0])?;
+        let (low, low_corrected) = decode_word(words[1])?;
+        *byte = high << 4 | low;
+        corrected += high_corrected as usize + low_corrected as usize;
+    }
+    Ok((dst.len(), corrected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Codec, EccFramedCrcCodec};
+    use crate::{Deserialize, Serialize};
+
+    const PAYLOAD: [u8; 4] = [0x00, 0x5A, 0xC3, 0xFF];
+
+    #[test]
+    fn every_nibble_round_trips() {
+        for nibble in 0..16 {
+            assert_eq!(decode_word(encode_nibble(nibble)).unwrap(), (nibble, false));
+        }
+    }
+
+    #[test]
+    fn one_flipped_bit_per_word_is_corrected() {
+        let mut encoded = [0u8; 8];
+        assert_eq!(encode_ecc(&PAYLOAD, &mut encoded).unwrap(), 8);
+        for bit in 0..8 {
+            let mut noisy = encoded;
+            for word in noisy.iter_mut() {
+                *word ^= 1 << bit;
+            }
+            let mut decoded = [0u8; 4];
+            assert_eq!(decode_ecc(&noisy, &mut decoded).unwrap(), (4, 8), "bit {bit}");
+            assert_eq!(decoded, PAYLOAD);
+        }
+    }
+
+    #[test]
+    fn two_flipped_bits_in_a_word_are_detected() {
+        let mut encoded = [0u8; 8];
+        encode_ecc(&PAYLOAD, &mut encoded).unwrap();
+        for (first, second) in [(0, 1), (2, 6), (3, 7)] {
+            let mut noisy = encoded;
+            noisy[5] ^= 1 << first | 1 << second;
+            let mut decoded = [0u8; 4];
+            assert!(matches!(decode_ecc(&noisy, &mut decoded), Err(DeserializeError::Uncorrectable)));
+        }
+    }
+
+    #[test]
+    fn corrected_payload_deserializes_identically() {
+        let value = [0x1234u16, 0xBEEF, 0x0000];
+        let mut serialized = [0u8; 6];
+        value.serialize(&mut serialized).unwrap();
+        let mut encoded = [0u8; 12];
+        encode_ecc(&serialized, &mut encoded).unwrap();
+        encoded[0] ^= 0x04;
+        encoded[11] ^= 0x80;
+        let mut decoded = [0u8; 6];
+        assert_eq!(decode_ecc(&encoded, &mut decoded).unwrap(), (6, 2));
+        assert_eq!(<[u16; 3]>::deserialize(&decoded).unwrap(), value);
+    }
+
+    #[test]
+    fn codec_corrects_beneath_the_crc() {
+        let mut tx = EccFramedCrcCodec::<u32, u32, 64>::new();
+        let mut rx = EccFramedCrcCodec::<u32, u32, 64>::new();
+        let mut wire = [0u8; 64];
+        let size = tx.encode(&0xDEAD_BEEF, &mut wire).unwrap();
+        assert_eq!(size, 2 + 2 * (4 + 2));
+        wire[4] ^= 0x10;
+        assert_eq!(rx.feed(&wire[..size]).unwrap(), Some(0xDEAD_BEEF));
+        assert_eq!(rx.corrected(), 1);
+    }
+
+    #[test]
+    fn odd_length_and_short_output_fail() {
+        assert!(matches!(decode_ecc(&[0; 3], &mut [0; 4]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(decode_ecc(&[0; 4], &mut [0; 1]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(encode_ecc(&[0; 2], &mut [0; 3]), Err(SerializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,137 @@
+//! Integers stored in a fixed byte order, for register images and other structs that
+//! mix byte orders.
+//!
+//! Each type holds the bytes as they appear on the wire, so it has no alignment
+//! requirement and serializing it is a plain copy. `get` and `set` convert to and from
+//! the native integer.
+
+use crate::layout::{FixedLayout, Plain};
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+macro_rules! endian_int {
+    ($($name:ident($int:ty, $to_bytes:ident, $from_bytes:ident) => $order:literal;)*) => {
+        $(
+            #[doc = concat!("A `", stringify!($int), "` stored ", $order, ".")]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+            #[repr(transparent)]
+            pub struct $name([u8; core::mem::size_of::<$int>()]);
+
+            impl $name {
+                pub const fn new(value: $int) -> Self {
+                    $name(value.$to_bytes())
+                }
+
+                pub const fn get(self) -> $int {
+                    <$int>::$from_bytes(self.0)
+                }
+
+                pub fn set(&mut self, value: $int) {
+                    self.0 = value.$to_bytes();
+                }
+
+                /// The stored bytes
+                pub const fn to_bytes(self) -> [u8; core::mem::size_of::<$int>()] {
+                    self.0
+                }
+
+                pub const fn from_bytes(bytes: [u8; core::mem::size_of::<$int>()]) -> Self {
+                    $name(bytes)
+                }
+            }
+
+            impl From<$int> for $name {
+                fn from(value: $int) -> Self {
+                    Self::new(value)
+                }
+            }
+
+            impl From<$name> for $int {
+                fn from(value: $name) -> Self {
+                    value.get()
+                }
+            }
+
+            impl Serialize for $name {
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+                    let dst = buf.get_mut(..self.0.len()).ok_or(SerializeError::BufferTooSmall)?;
+                    dst.copy_from_slice(&self.0);
+                    Ok(self.0.len())
+                }
+
+                fn serialized_size(&self) -> usize {
+                    self.0.len()
+                }
+            }
+
+            impl Deserialize for $name {
+                fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+                    Self::deserialize_consumed(buf).map(|(value, _)| value)
+                }
+
+                fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+                    let mut bytes = [0; core::mem::size_of::<$int>()];
+                    let src = buf.get(..bytes.len()).ok_or(DeserializeError::BufferTooSmall)?;
+                    bytes.copy_from_slice(src);
+                    Ok(($name(bytes), bytes.len()))
+                }
+            }
+
+            /// The stored order is the wire order, so there is nothing to swap.
+            impl FixedLayout for $name {
+                const SIZE: usize = core::mem::size_of::<$int>();
+
+                fn swap_bytes_in_place(_record: &mut [u8]) {}
+            }
+
+            unsafe impl Plain for $name {}
+        )*
+    };
+}
+
+endian_int! {
+    U16Be(u16, to_be_bytes, from_be_bytes) => "big-endian";
+    U16Le(u16, to_le_bytes, from_le_bytes) => "little-endian";
+    U32Be(u32, to_be_bytes, from_be_bytes) => "big-endian";
+    U32Le(u32, to_le_bytes, from_le_bytes) => "little-endian";
+    U64Be(u64, to_be_bytes, from_be_bytes) => "big-endian";
+    U64Le(u64, to_le_bytes, from_le_bytes) => "little-endian";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::{align_of, size_of};
+
+    #[test]
+    fn stored_byte_order() {
+        assert_eq!(U16Be::new(0x0102).to_bytes(), [0x01, 0x02]);
+        assert_eq!(U16Le::new(0x0102).to_bytes(), [0x02, 0x01]);
+        assert_eq!(U32Be::new(0x0102_0304).to_bytes(), [1, 2, 3, 4]);
+        assert_eq!(U32Le::new(0x0102_0304).to_bytes(), [4, 3, 2, 1]);
+        assert_eq!(U64Be::new(0x0102_0304_0506_0708).to_bytes(), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(U64Le::new(0x0102_0304_0506_0708).to_bytes(), [8, 7, 6, 5, 4, 3, 2, 1]);
+
+        let mut value = U32Le::from_bytes([4, 3, 2, 1]);
+        assert_eq!(u32::from(value), 0x0102_0304);
+        value.set(0xAABB_CCDD);
+        assert_eq!(value.to_bytes(), [0xDD, 0xCC, 0xBB, 0xAA]);
+        assert_eq!(U32Le::from(0xAABB_CCDD), value);
+    }
+
+    #[test]
+    fn serializing_copies_the_stored_bytes() {
+        let mut buf = [0u8; 4];
+        assert_eq!(U32Le::new(0x0102_0304).serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [4, 3, 2, 1]);
+        assert_eq!(U32Le::deserialize(&buf).unwrap().get(), 0x0102_0304);
+        assert!(matches!(U32Le::deserialize(&buf[..3]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn raw_width_without_alignment() {
+        assert_eq!((size_of::<U16Be>(), size_of::<U16Le>()), (2, 2));
+        assert_eq!((size_of::<U32Be>(), size_of::<U32Le>()), (4, 4));
+        assert_eq!((size_of::<U64Be>(), size_of::<U64Le>()), (8, 8));
+        assert_eq!((align_of::<U32Be>(), align_of::<U64Le>()), (1, 1));
+    }
+}
@@ -0,0 +1,83 @@
+//! Object-safe serialization, for queues and tables of values of different types.
+//!
+//! Only serialization is erased. Deserialization stays statically typed: a receiver
+//! decodes each message as a concrete type chosen by a dispatcher, such as
+//! `frame::decode_message` keyed on the message ID.
+
+use core::any::type_name;
+
+use crate::crc::crc16;
+use crate::{Serialize, SerializeError};
+
+/// `Serialize` behind a `dyn` pointer.
+/// Implemented for every sized `Serialize` type.
+pub trait SerializeDyn {
+    /// Same as `Serialize::serialize`.
+    fn serialize_dyn(&self, buf: &mut [u8]) -> Result<usize, SerializeError>;
+
+    /// Same as `Serialize::serialized_size`.
+    fn serialized_size_dyn(&self) -> usize;
+
+    /// Identifies the concrete type, by the CRC-16 of its type name.
+    /// Only stable within one build, so it must not go on the wire.
+    fn type_id16(&self) -> u16;
+}
+
+impl<T: Serialize> SerializeDyn for T {
+    fn serialize_dyn(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.serialize(buf)
+    }
+
+    fn serialized_size_dyn(&self) -> usize {
+        self.serialized_size()
+    }
+
+    fn type_id16(&self) -> u16 {
+        crc16(type_name::<T>().as_bytes())
+    }
+}
+
+/// Writes `values` back to back into `buf`.
+/// Returns the number of bytes written.
+pub fn serialize_dyn_all(values: &[&dyn SerializeDyn], buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let mut offset = 0;
+    for value in values {
+        let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+        offset += value.serialize_dyn(rest)?;
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes::Bytes;
+    use crate::decimal::Decimal2;
+    use crate::fixed_str::FixedStr;
+    use crate::int24::I24;
+    use crate::masked::Masked;
+    use crate::option::SentinelOption;
+    use crate::padded::BlockPadded;
+    use crate::timestamp::Timestamped;
+
+    #[test]
+    fn sizes_are_exact_for_values_larger_than_the_scratch_buffer() {
+        let blob = [0x5Au8; 400];
+        let values: [&dyn SerializeDyn; 9] = [
+            &Bytes(&blob),
+            &Decimal2::from_hundredths(1),
+            &FixedStr::<12>::new("abc"),
+            &I24(-1),
+            &Masked([Some(1u16), None, Some(3)]),
+            &SentinelOption::<u16, 0xFFFF>::new(None),
+            &BlockPadded::<16, _>([0u8; 10]),
+            &Timestamped::new(5, 7u8),
+            &[0u32; 100],
+        ];
+        let mut buf = [0u8; 512];
+        for value in values {
+            let written = value.serialize_dyn(&mut buf).unwrap();
+            assert_eq!(value.serialized_size_dyn(), written);
+        }
+    }
+}
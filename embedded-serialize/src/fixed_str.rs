@@ -0,0 +1,112 @@
+//! Fixed-width, zero-padded UTF-8 strings, like C `char name[N]` fields.
+
+use core::fmt;
+use core::ops::Deref;
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A string of at most `N` bytes, always written as exactly `N` bytes padded with `\0`.
+/// Decoding trims the trailing `\0` bytes, so trailing NULs in the string itself are lost.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedStr<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FixedStr<N> {
+    /// Copies `s`, truncated to the longest prefix of at most `N` bytes that ends on a char boundary.
+    pub fn new(s: &str) -> Self {
+        let mut len = s.len().min(N);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0; N];
+        bytes[..len].copy_from_slice(&s.as_bytes()[..len]);
+        FixedStr { bytes, len }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // `new` and `deserialize` only accept valid UTF-8 cut at a char boundary.
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> Default for FixedStr<N> {
+    fn default() -> Self {
+        FixedStr { bytes: [0; N], len: 0 }
+    }
+}
+
+impl<const N: usize> Deref for FixedStr<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> Serialize for FixedStr<N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let dst = buf.get_mut(..N).ok_or(SerializeError::BufferTooSmall)?;
+        dst.copy_from_slice(&self.bytes);
+        Ok(N)
+    }
+
+    fn serialized_size(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Deserialize for FixedStr<N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(s, _)| s)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let src = buf.get(..N).ok_or(DeserializeError::BufferTooSmall)?;
+        let len = src.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+        core::str::from_utf8(&src[..len]).map_err(|_| DeserializeError::InvalidData)?;
+        let mut bytes = [0; N];
+        bytes[..len].copy_from_slice(&src[..len]);
+        Ok((FixedStr { bytes, len }, N))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abc_is_padded_to_eight_bytes() {
+        let mut buf = [0xAAu8; 8];
+        assert_eq!(FixedStr::<8>::new("abc").serialize(&mut buf).unwrap(), 8);
+        assert_eq!(buf, *b"abc\0\0\0\0\0");
+        let (decoded, size) = FixedStr::<8>::deserialize_consumed(&buf).unwrap();
+        assert_eq!((&*decoded, size), ("abc", 8));
+    }
+
+    #[test]
+    fn long_strings_are_cut_at_a_char_boundary() {
+        assert_eq!(&*FixedStr::<4>::new("sensor"), "sens");
+        // 'é' is two bytes and would straddle the end.
+        assert_eq!(&*FixedStr::<4>::new("abcé"), "abc");
+    }
+
+    #[test]
+    fn invalid_utf8_and_short_buffers_fail() {
+        assert!(matches!(FixedStr::<4>::deserialize(&[b'a', 0xFF, 0, 0]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(FixedStr::<4>::deserialize(b"abc"), Err(DeserializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,321 @@
+//! Serialized records in NOR flash, and a power-loss safe configuration store built on them.
+//!
+//! A record is a big-endian `u32` generation, a `u16` payload length, the payload, and the
+//! CRC-16 of everything before it.
+
+use core::marker::PhantomData;
+
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::crc::crc16;
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Bytes before the payload of a record
+const HEADER_LEN: usize = 6;
+
+/// Writes `value` as a record with the given generation.
+/// Returns the number of bytes written.
+pub fn encode_record<T: Serialize + ?Sized>(
+    generation: u32,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    let payload = buf.get_mut(HEADER_LEN..).ok_or(SerializeError::BufferTooSmall)?;
+    let len = value.serialize(payload)?;
+    let len16 = u16::try_from(len).map_err(|_| SerializeError::InvalidData)?;
+    generation.serialize(buf)?;
+    len16.serialize(&mut buf[4..])?;
+    let end = HEADER_LEN + len;
+    let crc = crc16(&buf[..end]);
+    Ok(end + crc.serialize(&mut buf[end..])?)
+}
+
+/// Reads a record, failing with `ChecksumMismatch` if it is corrupt, or `BufferTooSmall` if its
+/// length runs past `buf`, as it does in erased flash.
+/// Returns its generation and value.
+pub fn decode_record<T: Deserialize>(buf: &[u8]) -> Result<(u32, T), DeserializeError> {
+    let generation = u32::deserialize(buf)?;
+    let len = u16::deserialize(buf.get(4..).ok_or(DeserializeError::BufferTooSmall)?)?;
+    let end = HEADER_LEN + len as usize;
+    let payload = buf.get(HEADER_LEN..end).ok_or(DeserializeError::BufferTooSmall)?;
+    let crc = u16::deserialize(&buf[end..])?;
+    if crc != crc16(&buf[..end]) {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    Ok((generation, T::deserialize(payload)?))
+}
+
+/// Errors from saving a configuration
+#[derive(Debug)]
+pub enum FlashError<E> {
+    /// The flash driver failed
+    Flash(E),
+    /// The value does not fit in a record
+    Serialize(SerializeError),
+    /// The record read back differs from the one written
+    Verify,
+}
+
+/// A configuration value kept in two flash banks that are written alternately.
+///
+/// Each save goes to the bank not holding the newest valid record, with the next
+/// generation, and is read back before it counts. Losing power at any point leaves
+/// the previous record intact, so loading always finds the last completed save
+/// or, before the first one, nothing.
+///
+/// `N` is the record buffer size: it must be a multiple of the flash read and write
+/// sizes, and each bank is `N` rounded up to the erase size.
+pub struct DualBankConfig<T, S, const N: usize = 256> {
+    storage: S,
+    banks: [u32; 2],
+    value: PhantomData<fn(&T) -> T>,
+}
+
+impl<T: Serialize + Deserialize, S: NorFlash, const N: usize> DualBankConfig<T, S, N> {
+    /// Uses the banks starting at the erase-aligned offsets `bank_a` and `bank_b`.
+    pub fn new(storage: S, bank_a: u32, bank_b: u32) -> Self {
+        DualBankConfig {
+            storage,
+            banks: [bank_a, bank_b],
+            value: PhantomData,
+        }
+    }
+
+    fn bank_len() -> u32 {
+        N.next_multiple_of(S::ERASE_SIZE) as u32
+    }
+
+    /// Index, generation and value of the newest valid record, if either bank holds one.
+    fn newest(&mut self) -> Result<Option<(usize, u32, T)>, S::Error> {
+        let mut newest: Option<(usize, u32, T)> = None;
+        let mut record = [0u8; N];
+        for bank in 0..2 {
+            self.storage.read(self.banks[bank], &mut record)?;
+            if let Ok((generation, value)) = decode_record::<T>(&record) {
+                // Compare generations as a wrapping sequence number.
+                let is_newer = match &newest {
+                    Some((_, current, _)) => generation.wrapping_sub(*current) as i32 > 0,
+                    None => true,
+                };
+                if is_newer {
+                    newest = Some((bank, generation, value));
+                }
+            }
+        }
+        Ok(newest)
+    }
+
+    /// Loads the newest valid record, or `None` if neither bank holds one.
+    pub fn try_load(&mut self) -> Result<Option<T>, S::Error> {
+        Ok(self.newest()?.map(|(_, _, value)| value))
+    }
+
+    /// Loads the newest valid record, or `T::default()` if neither bank holds one.
+    pub fn load(&mut self) -> Result<T, S::Error>
+    where
+        T: Default,
+    {
+        Ok(self.try_load()?.unwrap_or_default())
+    }
+
+    /// Writes `value` to the bank not holding the newest valid record and verifies it.
+    pub fn save(&mut self, value: &T) -> Result<(), FlashError<S::Error>> {
+        let (bank, generation) = match self.newest().map_err(FlashError::Flash)? {
+            Some((bank, generation, _)) => (1 - bank, generation.wrapping_add(1)),
+            None => (0, 0),
+        };
+        let mut record = [0xFFu8; N];
+        let len = encode_record(generation, value, &mut record).map_err(FlashError::Serialize)?;
+        let len = len.next_multiple_of(S::WRITE_SIZE);
+        if len > N {
+            return Err(FlashError::Serialize(SerializeError::BufferTooSmall));
+        }
+        let offset = self.banks[bank];
+        self.storage
+            .erase(offset, offset + Self::bank_len())
+            .map_err(FlashError::Flash)?;
+        self.storage.write(offset, &record[..len]).map_err(FlashError::Flash)?;
+        let mut written = [0u8; N];
+        self.storage.read(offset, &mut written).map_err(FlashError::Flash)?;
+        if written[..len] != record[..len] {
+            return Err(FlashError::Verify);
+        }
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> S {
+        self.storage
+    }
+}
+
+/// In-memory NOR flash that can lose power partway through an operation, for the
+/// tests of this module and `blackbox`.
+#[cfg(test)]
+pub(crate) mod mock {
+    use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+    /// `SIZE` bytes of flash with 64-byte sectors and 4-byte write units.
+    /// Writes can only clear bits, as on real NOR flash.
+    pub(crate) struct MockFlash<const SIZE: usize> {
+        pub(crate) data: [u8; SIZE],
+        /// Bytes that may still be erased or written before power is lost, or `None` for no limit
+        budget: Option<usize>,
+    }
+
+    impl<const SIZE: usize> MockFlash<SIZE> {
+        pub(crate) fn new() -> Self {
+            MockFlash { data: [0xFF; SIZE], budget: None }
+        }
+
+        /// Loses power once `bytes` more bytes have been erased or written. The operation
+        /// in progress stops there and fails, as does every later erase or write.
+        pub(crate) fn cut_power_after(&mut self, bytes: usize) {
+            self.budget = Some(bytes);
+        }
+
+        /// Brings power back, leaving the contents as they were when it was lost.
+        pub(crate) fn restore_power(&mut self) {
+            self.budget = None;
+        }
+
+        /// Takes one byte from the budget, or fails if power has run out.
+        fn spend(&mut self) -> Result<(), NorFlashErrorKind> {
+            match &mut self.budget {
+                Some(0) => Err(NorFlashErrorKind::Other),
+                Some(budget) => {
+                    *budget -= 1;
+                    Ok(())
+                }
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl<const SIZE: usize> ErrorType for MockFlash<SIZE> {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl<const SIZE: usize> ReadNorFlash for MockFlash<SIZE> {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            let src = self.data.get(offset..offset + bytes.len()).ok_or(NorFlashErrorKind::OutOfBounds)?;
+            bytes.copy_from_slice(src);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            SIZE
+        }
+    }
+
+    impl<const SIZE: usize> NorFlash for MockFlash<SIZE> {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 64;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            let (from, to) = (from as usize, to as usize);
+            if !from.is_multiple_of(Self::ERASE_SIZE) || !to.is_multiple_of(Self::ERASE_SIZE) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+            if from > to || to > SIZE {
+                return Err(NorFlashErrorKind::OutOfBounds);
+            }
+            for index in from..to {
+                self.spend()?;
+                self.data[index] = 0xFF;
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            if !offset.is_multiple_of(Self::WRITE_SIZE) || !bytes.len().is_multiple_of(Self::WRITE_SIZE) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+            if offset + bytes.len() > SIZE {
+                return Err(NorFlashErrorKind::OutOfBounds);
+            }
+            for (index, byte) in bytes.iter().enumerate() {
+                self.spend()?;
+                self.data[offset + index] &= byte;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockFlash;
+    use super::*;
+
+    type Config = [u16; 3];
+    type Store = DualBankConfig<Config, MockFlash<128>, 64>;
+
+    fn store(flash: MockFlash<128>) -> Store {
+        DualBankConfig::new(flash, 0, 64)
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut buf = [0u8; 16];
+        assert_eq!(encode_record(7, &0xABCDu16, &mut buf).unwrap(), 10);
+        assert_eq!(buf[..6], [0, 0, 0, 7, 0, 2]);
+        assert_eq!(decode_record::<u16>(&buf).unwrap(), (7, 0xABCD));
+        buf[7] ^= 1;
+        assert!(matches!(decode_record::<u16>(&buf), Err(DeserializeError::ChecksumMismatch)));
+        assert!(matches!(decode_record::<u16>(&[0xFF; 16]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn saves_alternate_banks_and_load_the_newest() {
+        let mut config = store(MockFlash::new());
+        assert_eq!(config.try_load().unwrap(), None);
+        assert_eq!(config.load().unwrap(), Config::default());
+        for generation in 0..5u16 {
+            config.save(&[generation, 1, 2]).unwrap();
+            assert_eq!(config.load().unwrap(), [generation, 1, 2]);
+        }
+        let flash = config.into_inner();
+        // The fifth save (generation 4) went to bank 0, the fourth to bank 1.
+        assert_eq!((flash.data[3], flash.data[64 + 3]), (4, 3));
+    }
+
+    #[test]
+    fn corrupt_bank_falls_back_to_the_other() {
+        let mut config = store(MockFlash::new());
+        config.save(&[1, 1, 1]).unwrap();
+        config.save(&[2, 2, 2]).unwrap();
+        let mut flash = config.into_inner();
+        flash.data[64 + 7] ^= 0x10;
+        assert_eq!(store(flash).load().unwrap(), [1, 1, 1]);
+    }
+
+    #[test]
+    fn power_loss_at_every_step_keeps_a_valid_config() {
+        let (old, new) = ([1, 2, 3], [4, 5, 6]);
+        for cut in 0.. {
+            let mut config = store(MockFlash::new());
+            config.save(&[0, 0, 0]).unwrap();
+            config.save(&old).unwrap();
+            let mut flash = config.into_inner();
+            flash.cut_power_after(cut);
+
+            let mut config = store(flash);
+            let saved = config.save(&new);
+            let mut flash = config.into_inner();
+            flash.restore_power();
+            let loaded = store(flash).load().unwrap();
+            match saved {
+                Ok(()) => {
+                    assert_eq!(loaded, new);
+                    break;
+                }
+                Err(FlashError::Flash(_)) => assert!(loaded == old || loaded == new, "cut after {cut} bytes"),
+                Err(err) => panic!("cut after {cut} bytes: {err:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Fletcher-16 checksums over serialized values, for peers that do not use CRC-16.
+//!
+//! Both running sums are taken modulo 255 starting from zero. The checksum is
+//! `sum2 << 8 | sum1`, appended big-endian after the bytes it covers.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Computes the Fletcher-16 checksum of `data`.
+pub fn fletcher16(data: &[u8]) -> u16 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for &byte in data {
+        sum1 = (sum1 + byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    sum2 << 8 | sum1
+}
+
+/// Serializes `value` followed by the Fletcher-16 checksum of its bytes.
+/// Returns the number of bytes written.
+pub fn serialize_with_fletcher16<T: Serialize + ?Sized>(value: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let size = value.serialize(buf)?;
+    let checksum = fletcher16(&buf[..size]);
+    Ok(size + checksum.serialize(&mut buf[size..])?)
+}
+
+/// Deserializes a value followed by the Fletcher-16 checksum of its bytes, failing with
+/// `ChecksumMismatch` if the checksum does not match.
+/// Returns the value and the number of bytes consumed, checksum included.
+pub fn deserialize_with_fletcher16<T: Deserialize>(buf: &[u8]) -> Result<(T, usize), DeserializeError> {
+    let (value, size) = T::deserialize_consumed(buf)?;
+    let rest = buf.get(size..).ok_or(DeserializeError::BufferTooSmall)?;
+    let (checksum, checksum_size) = u16::deserialize_consumed(rest)?;
+    if checksum != fletcher16(&buf[..size]) {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    Ok((value, size + checksum_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_checksums() {
+        assert_eq!(fletcher16(b""), 0);
+        assert_eq!(fletcher16(b"abcde"), 0xC8F0);
+        assert_eq!(fletcher16(b"abcdef"), 0x2057);
+        assert_eq!(fletcher16(b"abcdefgh"), 0x0627);
+    }
+
+    #[test]
+    fn fletcher16_round_trip() {
+        let mut buf = [0u8; 6];
+        assert_eq!(serialize_with_fletcher16(&0x0102_0304u32, &mut buf).unwrap(), 6);
+        assert_eq!(buf[4..], fletcher16(&[1, 2, 3, 4]).to_be_bytes());
+        assert_eq!(deserialize_with_fletcher16::<u32>(&buf).unwrap(), (0x0102_0304, 6));
+    }
+
+    #[test]
+    fn corrupted_byte_is_rejected() {
+        let mut buf = [0u8; 6];
+        serialize_with_fletcher16(&0x0102_0304u32, &mut buf).unwrap();
+        for index in 0..buf.len() {
+            let mut corrupted = buf;
+            corrupted[index] ^= 0x10;
+            assert!(matches!(
+                deserialize_with_fletcher16::<u32>(&corrupted),
+                Err(DeserializeError::ChecksumMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    fn missing_checksum_is_too_small() {
+        assert!(matches!(deserialize_with_fletcher16::<u32>(&[1, 2, 3, 4, 5]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(serialize_with_fletcher16(&1u32, &mut [0u8; 5]), Err(SerializeError::BufferTooSmall)));
+    }
+}
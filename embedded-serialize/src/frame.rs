@@ -0,0 +1,334 @@
+//! Addressed frames for multi-drop buses, and message-ID dispatch of their payloads.
+//!
+//! An addressed frame is a two-byte header (source node, destination node) followed by
+//! the payload. A message payload is the message's ID byte followed by the message.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Destination that every node accepts
+pub const BROADCAST: u8 = 0xFF;
+
+/// Source and destination node of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub src: u8,
+    pub dst: u8,
+}
+
+impl Serialize for Address {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let header = buf.get_mut(..2).ok_or(SerializeError::BufferTooSmall)?;
+        header.copy_from_slice(&[self.src, self.dst]);
+        Ok(2)
+    }
+
+    fn serialized_size(&self) -> usize {
+        2
+    }
+}
+
+impl Deserialize for Address {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(address, _)| address)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let header = buf.get(..2).ok_or(DeserializeError::BufferTooSmall)?;
+        Ok((Address { src: header[0], dst: header[1] }, 2))
+    }
+}
+
+/// Writes the address header followed by `payload`.
+/// Returns the number of bytes written.
+pub fn write_addressed<T: Serialize + ?Sized>(
+    address: Address,
+    payload: &T,
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    let size = address.serialize(buf)?;
+    Ok(size + payload.serialize(&mut buf[size..])?)
+}
+
+/// A message type with a one-byte ID identifying it on the wire
+pub trait MessageId {
+    const ID: u8;
+}
+
+/// Writes `T::ID` followed by `message`.
+/// Returns the number of bytes written.
+pub fn write_message<T: MessageId + Serialize>(message: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let size = T::ID.serialize(buf)?;
+    Ok(size + message.serialize(&mut buf[size..])?)
+}
+
+/// ID of the message in `payload`.
+pub fn message_id(payload: &[u8]) -> Result<u8, DeserializeError> {
+    payload.first().copied().ok_or(DeserializeError::BufferTooSmall)
+}
+
+/// Decodes `payload` as a `T` if it carries `T::ID`, or returns `None` for other messages.
+pub fn decode_message<T: MessageId + Deserialize>(payload: &[u8]) -> Result<Option<T>, DeserializeError> {
+    if message_id(payload)? != T::ID {
+        return Ok(None);
+    }
+    T::deserialize(&payload[1..]).map(Some)
+}
+
+/// Accepts frames addressed to one node or broadcast, looking only at the header
+/// so that frames for other nodes cost two byte reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Router {
+    node: u8,
+}
+
+impl Router {
+    pub fn new(node: u8) -> Self {
+        Router { node }
+    }
+
+    pub fn node(&self) -> u8 {
+        self.node
+    }
+
+    /// Returns the header and payload of `frame` if this node should handle it,
+    /// or `None` if it is addressed to another node.
+    pub fn route<'a>(&self, frame: &'a [u8]) -> Result<Option<(Address, &'a [u8])>, DeserializeError> {
+        let (address, size) = Address::deserialize_consumed(frame)?;
+        if address.dst == self.node || address.dst == BROADCAST {
+            Ok(Some((address, &frame[size..])))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Decodes the payload of `frame` as a `T` if this node should handle it.
+    pub fn decode<T: Deserialize>(&self, frame: &[u8]) -> Result<Option<(Address, T)>, DeserializeError> {
+        match self.route(frame)? {
+            Some((address, payload)) => Ok(Some((address, T::deserialize(payload)?))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Declares an enum with one variant per message type, each wrapping the type of the
+/// same name and identified on the wire by the given ID.
+///
+/// The enum serializes as the ID byte followed by the message, deserializes by matching
+/// on the ID (failing with `UnknownDiscriminant` for unregistered IDs), and converts from
+/// each message type with `From`.
+///
+/// ```
+/// use embedded_serialize::{register_messages, Deserialize, DeserializeError, Serialize};
+/// use embedded_serialize_derive::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// pub struct Ping {
+///     seq: u16,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// pub struct Telemetry {
+///     voltage: u16,
+///     temperature: i8,
+/// }
+///
+/// register_messages! {
+///     #[derive(Debug, PartialEq)]
+///     pub enum AnyMessage {
+///         Ping = 0x01,
+///         Telemetry = 0x02,
+///     }
+/// }
+///
+/// let mut buf = [0u8; 8];
+/// for message in [
+///     AnyMessage::from(Ping { seq: 7 }),
+///     AnyMessage::from(Telemetry { voltage: 3300, temperature: -5 }),
+/// ] {
+///     let size = message.serialize(&mut buf).unwrap();
+///     assert_eq!(buf[0], message.id());
+///     assert_eq!(AnyMessage::deserialize(&buf[..size]).unwrap(), message);
+/// }
+///
+/// assert!(matches!(
+///     AnyMessage::deserialize(&[0x03, 0, 7]),
+///     Err(DeserializeError::UnknownDiscriminant { value: 3, .. })
+/// ));
+/// ```
+///
+/// Duplicate IDs fail to compile:
+///
+/// ```compile_fail
+/// # use embedded_serialize::register_messages;
+/// # use embedded_serialize_derive::{Deserialize, Serialize};
+/// # #[derive(Serialize, Deserialize)]
+/// # pub struct Ping { seq: u16 }
+/// # #[derive(Serialize, Deserialize)]
+/// # pub struct Pong { seq: u16 }
+/// register_messages! {
+///     pub enum AnyMessage {
+///         Ping = 0x01,
+///         Pong = 0x01,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! register_messages {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($message:ident = $id:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($message($message)),+
+        }
+
+        const _: () = {
+            let ids: &[u8] = &[$($id),+];
+            let mut index = 0;
+            while index < ids.len() {
+                let mut other = index + 1;
+                while other < ids.len() {
+                    if ids[index] == ids[other] {
+                        panic!(concat!("duplicate message ID in ", stringify!($name)));
+                    }
+                    other += 1;
+                }
+                index += 1;
+            }
+        };
+
+        impl $name {
+            /// ID of the wrapped message
+            pub fn id(&self) -> u8 {
+                match *self {
+                    $($name::$message(_) => $id),+
+                }
+            }
+        }
+
+        impl $crate::Serialize for $name {
+            fn serialize(&self, buf: &mut [u8]) -> Result<usize, $crate::SerializeError> {
+                let size = $crate::Serialize::serialize(&self.id(), buf)?;
+                let rest = buf.get_mut(size..).ok_or($crate::SerializeError::BufferTooSmall)?;
+                let payload = match *self {
+                    $($name::$message(ref message) => $crate::Serialize::serialize(message, rest)?),+
+                };
+                Ok(size + payload)
+            }
+
+            fn serialized_size(&self) -> usize {
+                1 + match *self {
+                    $($name::$message(ref message) => $crate::Serialize::serialized_size(message)),+
+                }
+            }
+        }
+
+        impl $crate::Deserialize for $name {
+            fn deserialize(buf: &[u8]) -> Result<Self, $crate::DeserializeError> {
+                Self::deserialize_consumed(buf).map(|(message, _)| message)
+            }
+
+            fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), $crate::DeserializeError> {
+                let id = $crate::frame::message_id(buf)?;
+                $(
+                    if id == $id {
+                        let (message, size) = <$message as $crate::Deserialize>::deserialize_consumed(&buf[1..])?;
+                        return Ok(($name::$message(message), 1 + size));
+                    }
+                )+
+                Err($crate::DeserializeError::unknown_discriminant(stringify!($name), id as u32))
+            }
+        }
+
+        $(
+            impl From<$message> for $name {
+                fn from(message: $message) -> Self {
+                    $name::$message(message)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static PING_DECODES: AtomicUsize = AtomicUsize::new(0);
+    static TELEMETRY_DECODES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, PartialEq)]
+    struct Ping(u8);
+
+    #[derive(Debug, PartialEq)]
+    struct Telemetry(u16);
+
+    impl MessageId for Ping {
+        const ID: u8 = 0x01;
+    }
+
+    impl MessageId for Telemetry {
+        const ID: u8 = 0x02;
+    }
+
+    impl Deserialize for Ping {
+        fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+            Self::deserialize_consumed(buf).map(|(message, _)| message)
+        }
+
+        fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+            PING_DECODES.fetch_add(1, Ordering::Relaxed);
+            u8::deserialize_consumed(buf).map(|(value, size)| (Ping(value), size))
+        }
+    }
+
+    impl Deserialize for Telemetry {
+        fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+            Self::deserialize_consumed(buf).map(|(message, _)| message)
+        }
+
+        fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+            TELEMETRY_DECODES.fetch_add(1, Ordering::Relaxed);
+            u16::deserialize_consumed(buf).map(|(value, size)| (Telemetry(value), size))
+        }
+    }
+
+    /// Routes `frame` and hands the payload to the handler for its message ID.
+    fn dispatch(router: &Router, frame: &[u8]) -> Result<Option<Address>, DeserializeError> {
+        let Some((address, payload)) = router.route(frame)? else {
+            return Ok(None);
+        };
+        if decode_message::<Ping>(payload)?.is_none() && decode_message::<Telemetry>(payload)?.is_none() {
+            return Err(DeserializeError::InvalidData);
+        }
+        Ok(Some(address))
+    }
+
+    /// Only this test decodes `Ping` and `Telemetry`, so the counts are its own.
+    #[test]
+    fn each_handler_fires_once_per_routed_frame() {
+        let router = Router::new(7);
+        assert_eq!(dispatch(&router, &[2, 7, 0x01, 9]).unwrap(), Some(Address { src: 2, dst: 7 }));
+        assert_eq!(dispatch(&router, &[2, 7, 0x02, 0, 5]).unwrap(), Some(Address { src: 2, dst: 7 }));
+        assert_eq!(dispatch(&router, &[3, BROADCAST, 0x01, 1]).unwrap(), Some(Address { src: 3, dst: BROADCAST }));
+        assert_eq!((PING_DECODES.load(Ordering::Relaxed), TELEMETRY_DECODES.load(Ordering::Relaxed)), (2, 1));
+
+        // Frames for other nodes are dropped on the header, even with a payload that would not decode.
+        assert_eq!(dispatch(&router, &[2, 8, 0x02, 0, 5]).unwrap(), None);
+        assert_eq!(dispatch(&router, &[2, 8, 0xEE]).unwrap(), None);
+        assert_eq!((PING_DECODES.load(Ordering::Relaxed), TELEMETRY_DECODES.load(Ordering::Relaxed)), (2, 1));
+
+        // A frame for us with an unknown message ID reaches no handler.
+        assert!(matches!(dispatch(&router, &[2, 7, 0xEE]), Err(DeserializeError::InvalidData)));
+        assert_eq!((PING_DECODES.load(Ordering::Relaxed), TELEMETRY_DECODES.load(Ordering::Relaxed)), (2, 1));
+    }
+
+    #[test]
+    fn short_header_is_rejected() {
+        assert!(matches!(Router::new(7).route(&[2]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,320 @@
+//! Golden byte vectors pinning the wire format, as promised by `WIRE_FORMAT_VERSION`.
+//!
+//! The tests of this module serialize a representative value of every type this crate
+//! implements `Serialize` for and compare the bytes with vectors checked in there, and
+//! the doc test below does the same for derived types, so a change to any encoding fails
+//! the tests until the vectors and `WIRE_FORMAT_VERSION` are updated together. None of
+//! the vectors are compiled into firmware.
+//!
+//! `assert_golden!` pins the encoding of other types the same way:
+//!
+//! ```
+//! use embedded_serialize::assert_golden;
+//! use embedded_serialize::{Deserialize, Serialize};
+//! use embedded_serialize_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Reading {
+//!     sensor: u8,
+//!     value: i16,
+//!     valid: bool,
+//!     samples: [u16; 2],
+//!     limit: Option<u8>,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Tuple(u16, i8);
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Batch {
+//!     count: u8,
+//!     #[es(count = "count")]
+//!     items: [u16; 4],
+//!     #[es(skip)]
+//!     cached: u32,
+//!     #[es(option = "sentinel", sentinel = 0xFFFF)]
+//!     channel: Option<u16>,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[es(pack_bools)]
+//! struct Flags {
+//!     id: u8,
+//!     armed: bool,
+//!     fault: bool,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[es(tlv)]
+//! struct Settings {
+//!     #[es(tag = 1)]
+//!     rate: u16,
+//!     #[es(tag = 2)]
+//!     mode: Option<u8>,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum Command {
+//!     Stop,
+//!     Move { speed: i16 },
+//!     #[es(tag = 9)]
+//!     Set(u8, u8),
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[es(magic = [0xAA, 0x55])]
+//! struct Record {
+//!     seq: u8,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[es(length_prefixed)]
+//! enum Extensible {
+//!     Level(u16),
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[es(payload_align = 4)]
+//! enum Aligned {
+//!     Word(u32),
+//! }
+//!
+//! assert_golden!(
+//!     Reading { sensor: 3, value: -200, valid: true, samples: [1, 2], limit: Some(9) },
+//!     "03 ff38 01 0001 0002 01 09"
+//! );
+//! assert_golden!(Tuple(0x1234, -1), "1234 ff");
+//! assert_golden!(Batch { count: 2, items: [5, 6, 7, 8], cached: 99, channel: None }, "02 0005 0006 ffff");
+//! assert_golden!(Flags { id: 7, armed: true, fault: false }, "07 01");
+//! assert_golden!(Settings { rate: 500, mode: None }, "0007 01 02 01f4 02 01 00");
+//! assert_golden!(Record { seq: 7 }, "aa55 07");
+//! assert_golden!(Command::Stop, "00");
+//! assert_golden!(Command::Move { speed: -2 }, "01 fffe");
+//! assert_golden!(Command::Set(4, 5), "09 04 05");
+//! assert_golden!(Extensible::Level(0x0102), "00 0002 0102");
+//! assert_golden!(Aligned::Word(0x0A0B0C0D), "00 000000 0a0b0c0d");
+//! ```
+
+use crate::codec::hex_value;
+use crate::{Serialize, SIZE_SCRATCH_LEN};
+
+/// Panics unless `value` serializes to the bytes written as hex digits in `hex`, which
+/// may be separated by whitespace. Values must fit in `SIZE_SCRATCH_LEN` bytes.
+#[macro_export]
+macro_rules! assert_golden {
+    ($value:expr, $hex:expr $(,)?) => {
+        $crate::golden::check(&$value, $hex)
+    };
+}
+
+/// Implementation of `assert_golden!`.
+#[doc(hidden)]
+#[track_caller]
+pub fn check<T: Serialize + ?Sized>(value: &T, hex: &str) {
+    let mut expected = [0u8; SIZE_SCRATCH_LEN];
+    let mut len = 0;
+    let mut digits = hex.bytes().filter(|digit| !digit.is_ascii_whitespace());
+    while let Some(high) = digits.next() {
+        let low = digits.next().expect("odd number of hex digits in golden vector");
+        let byte = hex_value(high).and_then(|high| Ok(high << 4 | hex_value(low)?));
+        expected[len] = byte.expect("invalid hex digit in golden vector");
+        len += 1;
+    }
+    let mut actual = [0u8; SIZE_SCRATCH_LEN];
+    let size = value
+        .serialize(&mut actual)
+        .expect("value does not serialize into the golden scratch buffer");
+    if actual[..size] != expected[..len] {
+        panic!(
+            "wire format changed: expected {:02x?}, got {:02x?}",
+            &expected[..len],
+            &actual[..size]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use core::num::{NonZeroI8, NonZeroU128, NonZeroU16};
+    use core::ops::{RangeFrom, RangeTo};
+
+    use crate::bcd::Bcd;
+    use crate::bytes::Bytes;
+    use crate::decimal::Decimal2;
+    use crate::endian::{U16Be, U16Le, U32Be, U32Le, U64Be, U64Le};
+    use crate::fixed_str::FixedStr;
+    use crate::frame::{Address, MessageId};
+    use crate::gray::Gray;
+    use crate::handle::{Handle, HandleTable};
+    use crate::hex_id::HexId;
+    use crate::int24::{I24, U24};
+    use crate::kvstore::{Key, KvStore};
+    use crate::masked::Masked;
+    use crate::matrix::Matrix;
+    use crate::nack::{ErrorCode, Nack};
+    use crate::option::{EncodedOption, PresenceByte, SentinelOption};
+    use crate::padded::BlockPadded;
+    use crate::reserved::{Reserved, ReservedZero};
+    use crate::ring::RingSnapshot;
+    use crate::timestamp::{Ticks, Timestamped};
+
+    /// Every `Serialize` impl in this crate, including those of enabled optional features
+    #[test]
+    fn builtin_vectors_match() {
+        struct Table;
+
+        impl HandleTable for Table {
+            const LEN: u32 = 16;
+        }
+
+        #[derive(Debug)]
+        struct Ping(u8);
+
+        impl Serialize for Ping {
+            fn serialize(&self, buf: &mut [u8]) -> Result<usize, crate::SerializeError> {
+                self.0.serialize(buf)
+            }
+
+            fn serialized_size(&self) -> usize {
+                1
+            }
+        }
+
+        impl crate::Deserialize for Ping {
+            fn deserialize(buf: &[u8]) -> Result<Self, crate::DeserializeError> {
+                u8::deserialize(buf).map(Ping)
+            }
+
+            fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), crate::DeserializeError> {
+                u8::deserialize_consumed(buf).map(|(value, size)| (Ping(value), size))
+            }
+        }
+
+        impl MessageId for Ping {
+            const ID: u8 = 0x21;
+        }
+
+        crate::register_messages! {
+            enum Message {
+                Ping = Ping::ID,
+            }
+        }
+
+        assert_golden!(0xA5u8, "a5");
+        assert_golden!(0x1234u16, "1234");
+        assert_golden!(0x1234_5678u32, "12345678");
+        assert_golden!(0x0102_0304_0506_0708u64, "0102030405060708");
+        assert_golden!(0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10u128, "0102030405060708090a0b0c0d0e0f10");
+        assert_golden!(-2i8, "fe");
+        assert_golden!(-2i16, "fffe");
+        assert_golden!(-2i32, "fffffffe");
+        assert_golden!(i64::MIN, "8000000000000000");
+        assert_golden!(-2i128, "fffffffffffffffffffffffffffffffe");
+        assert_golden!(-2.5f32, "c0200000");
+        assert_golden!(1.0f64, "3ff0000000000000");
+        assert_golden!(true, "01");
+        assert_golden!(false, "00");
+        assert_golden!('\u{e9}', "000000e9");
+        assert_golden!(NonZeroU16::new(0x1234).unwrap(), "1234");
+        assert_golden!(NonZeroI8::new(-2).unwrap(), "fe");
+        assert_golden!(NonZeroU128::new(1).unwrap(), "00000000000000000000000000000001");
+        assert_golden!(Some(0x1234u16), "01 1234");
+        assert_golden!(None::<u16>, "00");
+        assert_golden!(RangeFrom { start: 5u16 }, "0005");
+        assert_golden!(RangeTo { end: 5u16 }, "0005");
+        assert_golden!([1u16, 2][..], "0001 0002");
+        assert_golden!([1u8, 2, 3], "010203");
+        assert_golden!(&[0xABu8, 0xCD], "abcd");
+        assert_golden!(Bytes(&[1, 2, 3]), "0003 010203");
+        assert_golden!(FixedStr::<4>::new("ab"), "61620000");
+        assert_golden!(Masked::<u8, 3>([Some(1), None, Some(3)]), "05 01 03");
+        assert_golden!(BlockPadded::<4, u16>(0x1234), "1234 0202");
+        assert_golden!(HexId([0xAB, 0xCD]), "abcd");
+        assert_golden!(Decimal2::new(12, 34).unwrap(), "00000000000004d2");
+        assert_golden!(Decimal2::new(-1, 5).unwrap(), "ffffffffffffff97");
+        assert_golden!(U24::new(0x12_3456).unwrap(), "123456");
+        assert_golden!(I24::new(-2).unwrap(), "fffffe");
+        assert_golden!(Handle::<Table>::new(9).unwrap(), "00000009");
+        assert_golden!(Timestamped::new(0x0102_0304_0506_0708, 7u8), "0102030405060708 07");
+        assert_golden!(Ticks::<1000>::new(0x0102_0304_0506_0708), "0102030405060708");
+        assert_golden!(EncodedOption::<u16, PresenceByte>::new(Some(3)), "01 0003");
+        assert_golden!(SentinelOption::<u16, 0xFFFF>::new(None), "ffff");
+        assert_golden!(SentinelOption::<u16, 0xFFFF>::new(Some(3)), "0003");
+        assert_golden!(Address { src: 1, dst: 2 }, "01 02");
+        assert_golden!(Message::Ping(Ping(5)), "21 05");
+        assert_golden!(ErrorCode::ChecksumMismatch, "06");
+        assert_golden!(ErrorCode::UnknownDiscriminant, "0b");
+        assert_golden!(ErrorCode::BadMagic, "0d");
+        assert_golden!(ErrorCode::VersionTooOld, "0e");
+        assert_golden!(ErrorCode::Application(0x90), "90");
+        assert_golden!(Nack { code: ErrorCode::MissingTag, detail: 7 }, "04 0007");
+        assert_golden!(Reserved::<3>, "000000");
+        assert_golden!(ReservedZero::<2>, "0000");
+        assert_golden!(RingSnapshot::new([7u8, 8, 9], 1, 2).unwrap(), "0001 0002 070809");
+        assert_golden!(Bcd(42u8), "42");
+        assert_golden!(Bcd(1234u16), "1234");
+        assert_golden!(Gray(5u8), "07");
+        assert_golden!(Gray(0x1234u16), "1b2e");
+        assert_golden!(Matrix([[1.0, -2.0]]), "01 02 3f800000 c0000000");
+        assert_golden!(U16Be::new(0x1234), "1234");
+        assert_golden!(U16Le::new(0x1234), "3412");
+        assert_golden!(U32Be::new(0x1234_5678), "12345678");
+        assert_golden!(U32Le::new(0x1234_5678), "78563412");
+        assert_golden!(U64Be::new(0x0102_0304_0506_0708), "0102030405060708");
+        assert_golden!(U64Le::new(0x0102_0304_0506_0708), "0807060504030201");
+        assert_golden!(Ipv4Addr::new(192, 168, 1, 2), "c0a80102");
+        assert_golden!(Ipv6Addr::new(0x2001, 0xDB8, 0, 0, 0, 0, 0, 1), "20010db8000000000000000000000001");
+        assert_golden!(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), "04 0a000001");
+        let mut settings = KvStore::<16>::new();
+        settings.set(Key::new("volume"), &7u8).unwrap();
+        assert_golden!(settings, "0004 476f 01 07");
+
+        #[cfg(feature = "heapless")]
+        {
+            assert_golden!(crate::bytes::ByteBuf::<4>::from_slice(&[1, 2]).unwrap(), "0002 0102");
+            let mut map = heapless::LinearMap::<u8, u16, 4>::new();
+            map.insert(1, 0x0203).unwrap();
+            assert_golden!(map, "0001 01 0203");
+            let mut map = heapless::FnvIndexMap::<u8, u16, 4>::new();
+            map.insert(1, 0x0203).unwrap();
+            assert_golden!(map, "0001 01 0203");
+            let mut queue = heapless::spsc::Queue::<u8, 4>::new();
+            queue.enqueue(7).unwrap();
+            queue.enqueue(8).unwrap();
+            assert_golden!(queue, "0002 07 08");
+        }
+    }
+
+    #[test]
+    fn whitespace_between_digits_is_ignored() {
+        assert_golden!(0x0102_0304u32, "0102 0304");
+        assert_golden!(0x0102_0304u32, "01\n02\t03 04");
+        assert_golden!([0xABu8; 0], "");
+    }
+
+    #[test]
+    #[should_panic(expected = "wire format changed")]
+    fn changed_encoding_panics() {
+        assert_golden!(0x1234u16, "3412");
+    }
+
+    #[test]
+    #[should_panic(expected = "wire format changed")]
+    fn extra_bytes_panic() {
+        assert_golden!(0x1234u16, "1234 00");
+    }
+
+    #[test]
+    #[should_panic(expected = "odd number of hex digits")]
+    fn odd_digit_count_panics() {
+        assert_golden!(0x12u8, "123");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid hex digit")]
+    fn invalid_digit_panics() {
+        assert_golden!(0x12u8, "1g");
+    }
+}
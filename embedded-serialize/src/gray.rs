@@ -0,0 +1,114 @@
+//! Gray-coded unsigned integers, in which consecutive values differ in exactly one bit,
+//! so that a reading taken mid-transition is off by at most one.
+//!
+//! The Gray code is written in place of the binary value, big-endian like the integer.
+
+use crate::layout::FixedLayout;
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Integers with a Gray code of the same width
+pub trait GrayCode: Copy {
+    fn to_gray(self) -> Self;
+    fn from_gray(gray: Self) -> Self;
+}
+
+macro_rules! impl_gray_code {
+    ($($ty:ty),*) => {
+        $(
+            impl GrayCode for $ty {
+                fn to_gray(self) -> Self {
+                    self ^ (self >> 1)
+                }
+
+                fn from_gray(gray: Self) -> Self {
+                    // XOR of every right shift of the code, folded in doubling steps.
+                    let mut value = gray;
+                    let mut shift = 1;
+                    while shift < <$ty>::BITS {
+                        value ^= value >> shift;
+                        shift <<= 1;
+                    }
+                    value
+                }
+            }
+        )*
+    };
+}
+
+impl_gray_code!(u8, u16, u32);
+
+/// A binary value written as its Gray code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Gray<T>(pub T);
+
+impl<T: GrayCode + Serialize> Serialize for Gray<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.0.to_gray().serialize(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.0.serialized_size()
+    }
+}
+
+impl<T: GrayCode + Deserialize> Deserialize for Gray<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (gray, size) = T::deserialize_consumed(buf)?;
+        Ok((Gray(T::from_gray(gray)), size))
+    }
+}
+
+impl<T: GrayCode + FixedLayout> FixedLayout for Gray<T> {
+    const SIZE: usize = T::SIZE;
+
+    fn swap_bytes_in_place(record: &mut [u8]) {
+        T::swap_bytes_in_place(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_as_the_gray_code() {
+        let mut buf = [0u8; 4];
+        assert_eq!(Gray(2u16).serialize(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [0x00, 0x03]);
+        assert_eq!(Gray(0x8000u16).serialize(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [0xC0, 0x00]);
+        assert_eq!(Gray(0xFFFF_FFFFu32).serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0x80, 0, 0, 0]);
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let mut buf = [0u8; 4];
+        for value in [0u32, 1, 2, 3, 0x1234_5678, 0x8000_0000, u32::MAX - 1, u32::MAX] {
+            Gray(value).serialize(&mut buf).unwrap();
+            assert_eq!(Gray::<u32>::deserialize_consumed(&buf).unwrap(), (Gray(value), 4));
+        }
+        assert!(matches!(Gray::<u32>::deserialize(&buf[..3]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn conversion_is_exact_over_the_full_range() {
+        for value in 0..=u16::MAX {
+            let gray = value.to_gray();
+            assert_eq!(u16::from_gray(gray), value);
+            if value > 0 {
+                assert_eq!((gray ^ (value - 1).to_gray()).count_ones(), 1);
+            }
+        }
+        for value in 0..=u8::MAX {
+            assert_eq!(u8::from_gray(value.to_gray()), value);
+        }
+        for value in (0..=u32::MAX).step_by(16_777_259).chain([u32::MAX]) {
+            assert_eq!(u32::from_gray(value.to_gray()), value);
+        }
+    }
+}
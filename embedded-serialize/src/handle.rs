@@ -0,0 +1,108 @@
+//! Serializable handles that are indices into a static resource table.
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A static table that handles index into
+pub trait HandleTable {
+    /// Number of entries; decoded indices must be below it
+    const LEN: u32;
+}
+
+/// Index of an entry in the table `T`, serialized as a `u32`.
+/// Decoding rejects indices outside the table with `InvalidData`.
+pub struct Handle<T> {
+    index: u32,
+    table: PhantomData<fn() -> T>,
+}
+
+impl<T: HandleTable> Handle<T> {
+    /// Returns a handle for `index`, or `None` if it is outside the table.
+    pub fn new(index: u32) -> Option<Self> {
+        if index < T::LEN {
+            Some(Handle {
+                index,
+                table: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.index).finish()
+    }
+}
+
+impl<T> Serialize for Handle<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.index.serialize(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        4
+    }
+}
+
+impl<T: HandleTable> Deserialize for Handle<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(handle, _)| handle)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (index, size) = u32::deserialize_consumed(buf)?;
+        let handle = Self::new(index).ok_or(DeserializeError::InvalidData)?;
+        Ok((handle, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sensors;
+
+    impl HandleTable for Sensors {
+        const LEN: u32 = 4;
+    }
+
+    #[test]
+    fn handle_round_trip() {
+        let handle = Handle::<Sensors>::new(3).unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(handle.serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0, 0, 0, 3]);
+        assert_eq!(Handle::<Sensors>::deserialize(&buf).unwrap(), handle);
+    }
+
+    #[test]
+    fn out_of_range_index_is_rejected() {
+        assert!(Handle::<Sensors>::new(4).is_none());
+        assert!(matches!(Handle::<Sensors>::deserialize(&[0, 0, 0, 4]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(Handle::<Sensors>::deserialize(&[0, 0, 0]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,119 @@
+//! Fixed-size identifiers, such as MAC addresses, written raw and displayed as hex.
+
+use core::fmt;
+
+use crate::layout::FixedLayout;
+use crate::schema::{Describe, Schema};
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// An `N`-byte identifier, written as its bytes verbatim and displayed as
+/// colon-separated lowercase hex, like `aa:bb:cc:dd:ee:ff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HexId<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> HexId<N> {
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for HexId<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        HexId(bytes)
+    }
+}
+
+impl<const N: usize> fmt::Display for HexId<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Serialize for HexId<N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let dst = buf.get_mut(..N).ok_or(SerializeError::BufferTooSmall)?;
+        dst.copy_from_slice(&self.0);
+        Ok(N)
+    }
+
+    fn serialized_size(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Deserialize for HexId<N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(id, _)| id)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let src = buf.get(..N).ok_or(DeserializeError::BufferTooSmall)?;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(src);
+        Ok((HexId(bytes), N))
+    }
+}
+
+impl<const N: usize> FixedLayout for HexId<N> {
+    const SIZE: usize = N;
+
+    fn swap_bytes_in_place(_record: &mut [u8]) {}
+}
+
+impl<const N: usize> Describe for HexId<N> {
+    const SCHEMA: &'static Schema = <[u8; N] as Describe>::SCHEMA;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    /// Collects formatted text without an allocator
+    struct Text {
+        bytes: [u8; 32],
+        len: usize,
+    }
+
+    impl Write for Text {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let dst = self.bytes.get_mut(self.len..self.len + s.len()).ok_or(fmt::Error)?;
+            dst.copy_from_slice(s.as_bytes());
+            self.len += s.len();
+            Ok(())
+        }
+    }
+
+    fn display<T: fmt::Display>(value: &T) -> Text {
+        let mut text = Text { bytes: [0; 32], len: 0 };
+        write!(text, "{}", value).unwrap();
+        text
+    }
+
+    const MAC: HexId<6> = HexId([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+
+    #[test]
+    fn six_byte_id_is_written_verbatim() {
+        let mut buf = [0u8; 8];
+        assert_eq!(MAC.serialize(&mut buf).unwrap(), 6);
+        assert_eq!(buf[..6], MAC.0);
+        assert_eq!(HexId::<6>::deserialize_consumed(&buf).unwrap(), (MAC, 6));
+        assert!(matches!(HexId::<6>::deserialize(&buf[..5]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(MAC.serialize(&mut buf[..5]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn displays_as_colon_separated_hex() {
+        let text = display(&MAC);
+        assert_eq!(&text.bytes[..text.len], b"aa:bb:cc:dd:ee:ff");
+        let text = display(&HexId([0x01, 0x0A]));
+        assert_eq!(&text.bytes[..text.len], b"01:0a");
+        assert_eq!(display(&HexId([])).len, 0);
+    }
+}
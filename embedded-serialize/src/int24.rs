@@ -0,0 +1,135 @@
+//! 24-bit integers, written as exactly 3 big-endian bytes.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// An unsigned 24-bit integer held in a `u32`.
+/// Serializing a value above `U24::MAX` fails with `InvalidData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct U24(pub u32);
+
+impl U24 {
+    pub const MAX: u32 = (1 << 24) - 1;
+
+    /// Returns `None` if `value` does not fit in 24 bits.
+    pub fn new(value: u32) -> Option<Self> {
+        if value <= Self::MAX {
+            Some(U24(value))
+        } else {
+            None
+        }
+    }
+}
+
+/// A signed 24-bit integer held in an `i32`.
+/// Serializing a value outside `I24::MIN..=I24::MAX` fails with `InvalidData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct I24(pub i32);
+
+impl I24 {
+    pub const MIN: i32 = -(1 << 23);
+    pub const MAX: i32 = (1 << 23) - 1;
+
+    /// Returns `None` if `value` does not fit in 24 bits.
+    pub fn new(value: i32) -> Option<Self> {
+        if (Self::MIN..=Self::MAX).contains(&value) {
+            Some(I24(value))
+        } else {
+            None
+        }
+    }
+}
+
+/// Writes the low 3 bytes of `bits` in big-endian order.
+fn write_u24(bits: u32, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let dst = buf.get_mut(..3).ok_or(SerializeError::BufferTooSmall)?;
+    dst.copy_from_slice(&bits.to_be_bytes()[1..]);
+    Ok(3)
+}
+
+/// Reads 3 big-endian bytes into the low bits of a `u32`.
+fn read_u24(buf: &[u8]) -> Result<u32, DeserializeError> {
+    let src = buf.get(..3).ok_or(DeserializeError::BufferTooSmall)?;
+    Ok(u32::from_be_bytes([0, src[0], src[1], src[2]]))
+}
+
+impl Serialize for U24 {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let value = U24::new(self.0).ok_or(SerializeError::InvalidData)?;
+        write_u24(value.0, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        3
+    }
+}
+
+impl Deserialize for U24 {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((U24(read_u24(buf)?), 3))
+    }
+}
+
+impl Serialize for I24 {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let value = I24::new(self.0).ok_or(SerializeError::InvalidData)?;
+        write_u24(value.0 as u32, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        3
+    }
+}
+
+impl Deserialize for I24 {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        // Shift the sign bit of the 24-bit value into bit 31, then back down arithmetically.
+        let value = ((read_u24(buf)? << 8) as i32) >> 8;
+        Ok((I24(value), 3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i24_round_trips_through_three_bytes() {
+        let mut buf = [0u8; 4];
+        assert_eq!(I24(1_000_000).serialize(&mut buf).unwrap(), 3);
+        assert_eq!(buf[..3], [0x0F, 0x42, 0x40]);
+        assert_eq!(I24::deserialize(&buf).unwrap(), I24(1_000_000));
+
+        assert_eq!(I24(-2).serialize(&mut buf).unwrap(), 3);
+        assert_eq!(buf[..3], [0xFF, 0xFF, 0xFE]);
+        assert_eq!(I24::deserialize(&buf).unwrap(), I24(-2));
+
+        I24(I24::MIN).serialize(&mut buf).unwrap();
+        assert_eq!(I24::deserialize_consumed(&buf).unwrap(), (I24(I24::MIN), 3));
+    }
+
+    #[test]
+    fn u24_round_trip() {
+        let mut buf = [0u8; 3];
+        U24(U24::MAX).serialize(&mut buf).unwrap();
+        assert_eq!(buf, [0xFF; 3]);
+        assert_eq!(U24::deserialize(&buf).unwrap(), U24(U24::MAX));
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let mut buf = [0u8; 3];
+        assert_eq!(U24::new(1 << 24), None);
+        assert_eq!(I24::new(I24::MAX + 1), None);
+        assert!(matches!(U24(1 << 24).serialize(&mut buf), Err(SerializeError::InvalidData)));
+        assert!(matches!(I24(I24::MIN - 1).serialize(&mut buf), Err(SerializeError::InvalidData)));
+        assert!(matches!(I24::deserialize(&buf[..2]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
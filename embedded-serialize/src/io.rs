@@ -0,0 +1,135 @@
+//! Adapters between the serialization traits and `std::io`.
+
+use std::io::{self, Read, Write};
+use std::vec::Vec;
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+impl std::error::Error for SerializeError {}
+
+impl std::error::Error for DeserializeError {}
+
+impl From<SerializeError> for io::Error {
+    fn from(err: SerializeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, err)
+    }
+}
+
+impl From<DeserializeError> for io::Error {
+    fn from(err: DeserializeError) -> Self {
+        let kind = match err {
+            DeserializeError::BufferTooSmall => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+/// Serializes `value` into a newly allocated vector of its `serialized_size()`.
+/// An impl that needs more room than it reports fails with its `BufferTooSmall`.
+pub fn to_std_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerializeError> {
+    let mut buf = std::vec![0u8; value.serialized_size()];
+    let size = value.serialize(&mut buf)?;
+    buf.truncate(size);
+    Ok(buf)
+}
+
+/// Serializes `value` and writes it to `writer`.
+/// Returns the number of bytes written.
+pub fn serialize_to_write<T: Serialize + ?Sized, W: Write>(value: &T, writer: &mut W) -> io::Result<usize> {
+    let bytes = to_std_vec(value)?;
+    writer.write_all(&bytes)?;
+    Ok(bytes.len())
+}
+
+/// Reads exactly one value from `reader`.
+///
+/// Bytes are pulled one at a time until the value is complete, so nothing past
+/// the value is consumed; wrap unbuffered readers in a `BufReader`.
+pub fn deserialize_from_read<T: Deserialize, R: Read>(reader: &mut R) -> io::Result<T> {
+    let mut buf = Vec::new();
+    loop {
+        match T::deserialize_consumed(&buf) {
+            Ok((value, _)) => return Ok(value),
+            Err(DeserializeError::BufferTooSmall) => {
+                let mut byte = [0u8];
+                reader.read_exact(&mut byte)?;
+                buf.push(byte[0]);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Seek};
+
+    #[test]
+    fn cursor_round_trip() {
+        let mut cursor = Cursor::new(Vec::new());
+        assert_eq!(serialize_to_write(&0x0102_0304u32, &mut cursor).unwrap(), 4);
+        assert_eq!(serialize_to_write(&Some(7u8), &mut cursor).unwrap(), 2);
+        assert_eq!(cursor.get_ref().as_slice(), [1, 2, 3, 4, 1, 7]);
+
+        cursor.set_position(0);
+        assert_eq!(deserialize_from_read::<u32, _>(&mut cursor).unwrap(), 0x0102_0304);
+        assert_eq!(deserialize_from_read::<Option<u8>, _>(&mut cursor).unwrap(), Some(7));
+        assert_eq!(cursor.position(), 6);
+    }
+
+    #[test]
+    fn errors_convert_to_io_kinds() {
+        let err = deserialize_from_read::<u32, _>(&mut Cursor::new([1u8, 2])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = deserialize_from_read::<Option<u8>, _>(&mut Cursor::new([9u8])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err: io::Error = SerializeError::InvalidData.into();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn to_std_vec_grows_past_its_first_guess() {
+        let big = [0xA5u8; 100];
+        assert_eq!(to_std_vec(&big).unwrap(), big);
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let path = std::env::temp_dir().join(std::format!("embedded-serialize-io-{}", std::process::id()));
+        let mut file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        serialize_to_write(&[0x0102u16, 0x0304], &mut file).unwrap();
+        file.rewind().unwrap();
+        let decoded = deserialize_from_read::<[u16; 2], _>(&mut io::BufReader::new(&file));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded.unwrap(), [0x0102, 0x0304]);
+    }
+
+    /// Never fits, whatever it is given
+    struct Bottomless;
+
+    impl Serialize for Bottomless {
+        fn serialize(&self, _buf: &mut [u8]) -> Result<usize, SerializeError> {
+            Err(SerializeError::BufferTooSmall)
+        }
+
+        fn serialized_size(&self) -> usize {
+            8
+        }
+    }
+
+    #[test]
+    fn vector_is_sized_once() {
+        let bytes = to_std_vec(&[0x0102u16; 200]).unwrap();
+        assert_eq!(bytes.len(), 400);
+        assert_eq!(bytes.capacity(), 400);
+        assert!(matches!(to_std_vec(&Bottomless), Err(SerializeError::BufferTooSmall)));
+    }
+}
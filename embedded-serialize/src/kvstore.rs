@@ -0,0 +1,428 @@
+//! A key-value store for settings that are updated one at a time, such as over the air.
+//!
+//! An entry is a big-endian `u16` key, a one-byte length, and the serialized value. Keys
+//! are 16-bit hashes of setting names. Setting a value of the size already stored
+//! overwrites it in place; any other value is appended, and the newest entry of a key
+//! wins until compaction drops the ones it supersedes. Entries are kept whatever their
+//! key, so settings unknown to this build survive being loaded, changed and saved.
+//!
+//! A store serializes as a `u16` length followed by its compacted entries.
+
+use crate::schema::{hash_bytes, FNV_OFFSET};
+use crate::tlv::decode_value;
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Largest value a single entry can carry
+pub const MAX_VALUE_LEN: usize = u8::MAX as usize;
+
+/// Bytes of an entry before its value
+const ENTRY_HEADER_LEN: usize = 3;
+
+/// Name of a setting and the hash it is stored under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    name: &'static str,
+    hash: u16,
+}
+
+impl Key {
+    /// Hashes `name` with 32-bit FNV-1a, folded to 16 bits.
+    pub const fn new(name: &'static str) -> Self {
+        let hash = hash_bytes(FNV_OFFSET, name.as_bytes());
+        Key {
+            name,
+            hash: (hash >> 16) as u16 ^ hash as u16,
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub const fn hash(&self) -> u16 {
+        self.hash
+    }
+}
+
+/// Indices of the first two of `keys` with the same hash, if any.
+pub const fn find_collision(keys: &[Key]) -> Option<(usize, usize)> {
+    let mut first = 0;
+    while first < keys.len() {
+        let mut second = first + 1;
+        while second < keys.len() {
+            if keys[first].hash == keys[second].hash {
+                return Some((first, second));
+            }
+            second += 1;
+        }
+        first += 1;
+    }
+    None
+}
+
+/// The settings keys of a build, checked for hash collisions when registered.
+#[derive(Debug, Clone, Copy)]
+pub struct Registry {
+    keys: &'static [Key],
+}
+
+impl Registry {
+    /// Registers `keys`; evaluated in a `const`, a collision fails the build.
+    ///
+    /// # Panics
+    /// If two keys hash to the same value.
+    pub const fn new(keys: &'static [Key]) -> Self {
+        if find_collision(keys).is_some() {
+            panic!("two settings keys hash to the same value");
+        }
+        Registry { keys }
+    }
+
+    pub fn keys(&self) -> &'static [Key] {
+        self.keys
+    }
+
+    /// The registered key with `hash`, or `None` for a setting unknown to this build.
+    pub fn lookup(&self, hash: u16) -> Option<Key> {
+        self.keys.iter().copied().find(|key| key.hash == hash)
+    }
+}
+
+/// Splits off the entry at the start of `entries`.
+/// Returns its key, its value and its size, or `None` if it is truncated.
+fn split_entry(entries: &[u8]) -> Option<(u16, &[u8], usize)> {
+    match *entries {
+        [high, low, len, ref rest @ ..] if rest.len() >= len as usize => {
+            let size = ENTRY_HEADER_LEN + len as usize;
+            Some((u16::from_be_bytes([high, low]), &rest[..len as usize], size))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `entries` holds an entry for `hash`.
+fn contains(mut entries: &[u8], hash: u16) -> bool {
+    while let Some((key, _, size)) = split_entry(entries) {
+        if key == hash {
+            return true;
+        }
+        entries = &entries[size..];
+    }
+    false
+}
+
+/// Settings entries in a buffer of `N` bytes.
+pub struct KvStore<const N: usize = 256> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> KvStore<N> {
+    pub fn new() -> Self {
+        KvStore { bytes: [0; N], len: 0 }
+    }
+
+    /// Loads the entries in `entries`, for instance a blob received over the air.
+    /// Fails with `InvalidData` if the last entry is truncated.
+    pub fn from_entries(entries: &[u8]) -> Result<Self, DeserializeError> {
+        let mut store = Self::new();
+        store
+            .bytes
+            .get_mut(..entries.len())
+            .ok_or(DeserializeError::BufferTooSmall)?
+            .copy_from_slice(entries);
+        let mut rest = entries;
+        while !rest.is_empty() {
+            let (_, _, size) = split_entry(rest).ok_or(DeserializeError::InvalidData)?;
+            rest = &rest[size..];
+        }
+        store.len = entries.len();
+        Ok(store)
+    }
+
+    /// Number of bytes in use, superseded entries included.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The entries as stored, superseded ones included
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Position and length of the newest value stored under `hash`.
+    fn find(&self, hash: u16) -> Option<(usize, usize)> {
+        let mut found = None;
+        let mut offset = 0;
+        while let Some((key, value, size)) = split_entry(&self.bytes[offset..self.len]) {
+            if key == hash {
+                found = Some((offset + ENTRY_HEADER_LEN, value.len()));
+            }
+            offset += size;
+        }
+        found
+    }
+
+    /// The serialized value of the setting with `hash`, which may be one unknown to this build.
+    pub fn get_raw(&self, hash: u16) -> Option<&[u8]> {
+        self.find(hash).map(|(start, len)| &self.bytes[start..start + len])
+    }
+
+    /// Deserializes the value of `key`, which must fill its entry exactly.
+    pub fn get<T: Deserialize>(&self, key: Key) -> Result<Option<T>, DeserializeError> {
+        self.get_raw(key.hash).map(decode_value).transpose()
+    }
+
+    /// Stores `value` under `key`, compacting the store first if it is full.
+    /// The value is serialized aside first, so a failure leaves the store unchanged.
+    pub fn set<T: Serialize + ?Sized>(&mut self, key: Key, value: &T) -> Result<(), SerializeError> {
+        let mut scratch = [0u8; MAX_VALUE_LEN];
+        let size = value.serialize(&mut scratch)?;
+        let value = &scratch[..size];
+        if let Some((start, len)) = self.find(key.hash) {
+            if len == size {
+                self.bytes[start..start + len].copy_from_slice(value);
+                return Ok(());
+            }
+        }
+        match self.append(key.hash, value) {
+            Err(SerializeError::BufferTooSmall) => {
+                self.compact();
+                self.append(key.hash, value)
+            }
+            result => result,
+        }
+    }
+
+    fn append(&mut self, hash: u16, value: &[u8]) -> Result<(), SerializeError> {
+        let end = self.len + ENTRY_HEADER_LEN + value.len();
+        let entry = self.bytes.get_mut(self.len..end).ok_or(SerializeError::BufferTooSmall)?;
+        hash.serialize(entry)?;
+        entry[2] = value.len() as u8;
+        entry[ENTRY_HEADER_LEN..].copy_from_slice(value);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Iterates over the newest entry of each key, in storage order.
+    pub fn iter(&self) -> KvIter<'_> {
+        KvIter {
+            entries: self.as_bytes(),
+        }
+    }
+
+    /// Drops superseded entries.
+    pub fn compact(&mut self) {
+        let mut read = 0;
+        let mut write = 0;
+        while let Some((key, _, size)) = split_entry(&self.bytes[read..self.len]) {
+            let next = read + size;
+            if !contains(&self.bytes[next..self.len], key) {
+                self.bytes.copy_within(read..next, write);
+                write += size;
+            }
+            read = next;
+        }
+        self.len = write;
+    }
+
+    /// Writes the newest entry of each key into `out`, leaving the store as it is.
+    /// Returns the number of bytes written.
+    pub fn compact_into(&self, out: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut offset = 0;
+        for (key, value) in self.iter() {
+            let end = offset + ENTRY_HEADER_LEN + value.len();
+            let entry = out.get_mut(offset..end).ok_or(SerializeError::BufferTooSmall)?;
+            key.serialize(entry)?;
+            entry[2] = value.len() as u8;
+            entry[ENTRY_HEADER_LEN..].copy_from_slice(value);
+            offset = end;
+        }
+        Ok(offset)
+    }
+}
+
+impl<const N: usize> Default for KvStore<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterates over the `(key, value)` entries of a `KvStore` that no later entry supersedes.
+#[derive(Clone)]
+pub struct KvIter<'a> {
+    entries: &'a [u8],
+}
+
+impl<'a> Iterator for KvIter<'a> {
+    type Item = (u16, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((key, value, size)) = split_entry(self.entries) {
+            self.entries = &self.entries[size..];
+            if !contains(self.entries, key) {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
+
+impl<const N: usize> Serialize for KvStore<N> {
+    fn serialized_size(&self) -> usize {
+        2 + self.iter().map(|(_, value)| ENTRY_HEADER_LEN + value.len()).sum::<usize>()
+    }
+
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let entries = buf.get_mut(2..).ok_or(SerializeError::BufferTooSmall)?;
+        let size = self.compact_into(entries)?;
+        let len = u16::try_from(size).map_err(|_| SerializeError::InvalidData)?;
+        len.serialize(buf)?;
+        Ok(2 + size)
+    }
+}
+
+impl<const N: usize> Deserialize for KvStore<N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(store, _)| store)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let len = u16::deserialize(buf)? as usize;
+        let entries = buf.get(2..2 + len).ok_or(DeserializeError::BufferTooSmall)?;
+        Ok((Self::from_entries(entries)?, 2 + len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BRIGHTNESS: Key = Key::new("brightness");
+    const NAME: Key = Key::new("name");
+    const INTERVAL: Key = Key::new("interval");
+    static REGISTRY: Registry = Registry::new(&[BRIGHTNESS, NAME, INTERVAL]);
+
+    #[test]
+    fn same_size_update_is_in_place() {
+        let mut store = KvStore::<32>::new();
+        store.set(BRIGHTNESS, &40u8).unwrap();
+        store.set(INTERVAL, &1000u32).unwrap();
+        let len = store.len();
+        store.set(BRIGHTNESS, &90u8).unwrap();
+        assert_eq!(store.len(), len);
+        assert_eq!(store.get::<u8>(BRIGHTNESS).unwrap(), Some(90));
+        assert_eq!(store.get::<u32>(INTERVAL).unwrap(), Some(1000));
+        assert_eq!(store.get::<u8>(NAME).unwrap(), None);
+    }
+
+    #[test]
+    fn failed_update_keeps_the_old_value() {
+        use crate::int24::U24;
+
+        let mut store = KvStore::<32>::new();
+        store.set(INTERVAL, &[U24(1), U24(2)]).unwrap();
+        let before = store.as_bytes().len();
+        // The first element is written before the second is found out of range.
+        assert!(matches!(store.set(INTERVAL, &[U24(3), U24(1 << 24)]), Err(SerializeError::InvalidData)));
+        assert_eq!(store.as_bytes().len(), before);
+        assert_eq!(store.get::<[U24; 2]>(INTERVAL).unwrap(), Some([U24(1), U24(2)]));
+    }
+
+    #[test]
+    fn growing_a_value_supersedes_the_old_entry() {
+        let mut store = KvStore::<32>::new();
+        store.set(NAME, &[b'a'; 2]).unwrap();
+        store.set(INTERVAL, &5u16).unwrap();
+        store.set(NAME, &[b'b'; 6]).unwrap();
+        assert_eq!(store.len(), 5 + 5 + 9);
+        assert_eq!(store.get::<[u8; 6]>(NAME).unwrap(), Some([b'b'; 6]));
+        assert!(store.iter().map(|(key, _)| key).eq([INTERVAL.hash(), NAME.hash()]));
+
+        let mut out = [0u8; 32];
+        let size = store.compact_into(&mut out).unwrap();
+        assert_eq!(size, 14);
+        store.compact();
+        assert_eq!(store.as_bytes(), &out[..size]);
+    }
+
+    #[test]
+    fn full_store_compacts_before_appending() {
+        let mut store = KvStore::<13>::new();
+        store.set(NAME, &[1u8; 2]).unwrap();
+        store.set(NAME, &[2u8; 3]).unwrap();
+        assert_eq!(store.len(), 11);
+        // Only fits once the first entry is dropped.
+        store.set(NAME, &[3u8; 4]).unwrap();
+        assert_eq!(store.len(), 6 + 7);
+        assert_eq!(store.get::<[u8; 4]>(NAME).unwrap(), Some([3; 4]));
+        assert_eq!(store.iter().count(), 1);
+        assert!(matches!(store.set(INTERVAL, &0u32), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn unknown_keys_survive_a_read_modify_write() {
+        // Saved by a newer build with a setting this one does not know.
+        let unknown = Key::new("gps_rate");
+        assert!(REGISTRY.lookup(unknown.hash()).is_none());
+        let mut newer = KvStore::<32>::new();
+        newer.set(BRIGHTNESS, &10u8).unwrap();
+        newer.set(unknown, &[7u8, 8, 9]).unwrap();
+        let mut saved = [0u8; 40];
+        let size = newer.serialize(&mut saved).unwrap();
+
+        let mut store = KvStore::<32>::deserialize(&saved[..size]).unwrap();
+        store.set(BRIGHTNESS, &20u16).unwrap();
+        let size = store.serialize(&mut saved).unwrap();
+        assert_eq!(store.serialized_size(), size);
+
+        let store = KvStore::<32>::deserialize(&saved[..size]).unwrap();
+        assert_eq!(store.get_raw(unknown.hash()), Some(&[7u8, 8, 9][..]));
+        assert_eq!(store.get::<u16>(BRIGHTNESS).unwrap(), Some(20));
+        assert_eq!(REGISTRY.lookup(BRIGHTNESS.hash()).map(|key| key.name()), Some("brightness"));
+    }
+
+    #[test]
+    fn truncated_entries_are_rejected() {
+        assert!(matches!(KvStore::<32>::from_entries(&[0, 1, 4, 0]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(KvStore::<4>::from_entries(&[0; 5]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn hash_collisions_are_detected() {
+        let keys = [Key::new("setting_1"), Key::new("setting_32"), Key::new("setting_343")];
+        assert_eq!(keys[1].hash(), keys[2].hash());
+        assert_eq!(find_collision(&keys), Some((1, 2)));
+        assert_eq!(find_collision(REGISTRY.keys()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "hash to the same value")]
+    fn registering_colliding_keys_panics() {
+        static KEYS: [Key; 2] = [Key::new("setting_32"), Key::new("setting_343")];
+        Registry::new(&KEYS);
+    }
+
+    #[cfg(feature = "flash")]
+    #[test]
+    fn round_trip_through_flash_records() {
+        use crate::flash::{decode_record, encode_record};
+
+        let mut store = KvStore::<32>::new();
+        store.set(BRIGHTNESS, &50u8).unwrap();
+        store.set(INTERVAL, &250u32).unwrap();
+        store.set(BRIGHTNESS, &[1u8, 2]).unwrap();
+        let mut record = [0xFFu8; 64];
+        encode_record(9, &store, &mut record).unwrap();
+
+        let (seq, loaded) = decode_record::<KvStore<32>>(&record).unwrap();
+        assert_eq!(seq, 9);
+        assert_eq!(loaded.get::<[u8; 2]>(BRIGHTNESS).unwrap(), Some([1, 2]));
+        assert_eq!(loaded.get::<u32>(INTERVAL).unwrap(), Some(250));
+        assert!(loaded.iter().eq(store.iter()));
+    }
+}
@@ -0,0 +1,276 @@
+//! Fixed wire layouts and bulk byte-order conversion of received records.
+
+use core::mem::size_of;
+use core::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+};
+
+use crate::{Deserialize, DeserializeError};
+
+/// Types whose wire form always has the same size and integer layout.
+/// Derive it with `#[derive(FixedLayout)]` for structs of fixed-layout fields.
+///
+/// `SIZE` sizes buffers at compile time; `Serialize::serialized_size` gives the size of
+/// any value at run time.
+///
+/// ```
+/// use embedded_serialize::layout::FixedLayout;
+/// use embedded_serialize::Serialize;
+///
+/// let samples = [1.5f32, -2.0, 0.25];
+/// let mut dma = [0u8; <[f32; 3]>::SIZE];
+/// assert_eq!(samples.serialized_size(), dma.len());
+/// assert_eq!(samples.serialize(&mut dma).unwrap(), 12);
+/// ```
+pub trait FixedLayout {
+    /// Serialized size in bytes
+    const SIZE: usize;
+
+    /// Reverses the byte order of every multi-byte integer in one serialized record.
+    /// `record` must be exactly `SIZE` bytes long.
+    fn swap_bytes_in_place(record: &mut [u8]);
+}
+
+/// Fixed-layout types whose in-memory representation is exactly their wire form
+/// once converted to native byte order by `swap_endianness_in_place`.
+///
+/// # Safety
+/// `size_of::<Self>()` must equal `Self::SIZE`, the type must have no padding, and
+/// every bit pattern must be a valid value. For structs this needs `#[repr(C)]`
+/// with fields in wire order.
+pub unsafe trait Plain: FixedLayout {}
+
+macro_rules! impl_fixed_layout {
+    ($($ty:ty),*) => {
+        $(
+            impl FixedLayout for $ty {
+                const SIZE: usize = size_of::<$ty>();
+
+                fn swap_bytes_in_place(record: &mut [u8]) {
+                    record.reverse();
+                }
+            }
+
+            unsafe impl Plain for $ty {}
+        )*
+    };
+}
+
+impl_fixed_layout!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl FixedLayout for bool {
+    const SIZE: usize = 1;
+
+    fn swap_bytes_in_place(_record: &mut [u8]) {}
+}
+
+/// Fixed-size types with invalid bit patterns, which are therefore not `Plain`
+macro_rules! impl_fixed_layout_checked {
+    ($($ty:ty),*) => {
+        $(
+            impl FixedLayout for $ty {
+                const SIZE: usize = size_of::<$ty>();
+
+                fn swap_bytes_in_place(record: &mut [u8]) {
+                    record.reverse();
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_layout_checked!(
+    char, NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU128, NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64,
+    NonZeroI128
+);
+
+impl<T: FixedLayout, const N: usize> FixedLayout for [T; N] {
+    const SIZE: usize = T::SIZE * N;
+
+    fn swap_bytes_in_place(record: &mut [u8]) {
+        for item in record.chunks_exact_mut(T::SIZE) {
+            T::swap_bytes_in_place(item);
+        }
+    }
+}
+
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+/// Converts `count` consecutive records of `T` at the front of `buf` between the
+/// big-endian wire order and the native byte order. Applying it twice restores the input.
+pub fn swap_endianness_in_place<T: FixedLayout>(buf: &mut [u8], count: usize) -> Result<(), DeserializeError> {
+    let len = T::SIZE.checked_mul(count).ok_or(DeserializeError::BufferTooSmall)?;
+    let records = buf.get_mut(..len).ok_or(DeserializeError::BufferTooSmall)?;
+    if cfg!(target_endian = "little") && T::SIZE > 0 {
+        for record in records.chunks_exact_mut(T::SIZE) {
+            T::swap_bytes_in_place(record);
+        }
+    }
+    Ok(())
+}
+
+/// `deserialize_into_slice` for elements of a fixed layout, which checks the length of
+/// `buf` once up front.
+/// Returns the number of elements decoded and the number of bytes consumed.
+pub fn deserialize_fixed_into_slice<T: FixedLayout + Deserialize>(
+    buf: &[u8],
+    out: &mut [T],
+) -> Result<(usize, usize), DeserializeError> {
+    if T::SIZE == 0 {
+        return crate::deserialize_into_slice(buf, out);
+    }
+    let count = out.len().min(buf.len() / T::SIZE);
+    for (index, (slot, record)) in out[..count].iter_mut().zip(buf.chunks_exact(T::SIZE)).enumerate() {
+        *slot = T::deserialize(record).map_err(|err| match err {
+            DeserializeError::InvalidData => DeserializeError::InvalidDataAt(index),
+            err => err,
+        })?;
+    }
+    Ok((count, count * T::SIZE))
+}
+
+/// Views `count` records already converted by `swap_endianness_in_place` as native values.
+pub fn native_view<T: Plain>(buf: &[u8], count: usize) -> Result<&[T], DeserializeError> {
+    if size_of::<T>() != T::SIZE {
+        return Err(DeserializeError::InvalidData);
+    }
+    let len = T::SIZE.checked_mul(count).ok_or(DeserializeError::BufferTooSmall)?;
+    let records = buf.get(..len).ok_or(DeserializeError::BufferTooSmall)?;
+    if !records.as_ptr().cast::<T>().is_aligned() {
+        return Err(DeserializeError::Misaligned);
+    }
+    // SAFETY: the region is in bounds and aligned for `T`, and `Plain` guarantees
+    // `T` has no padding, matches its wire size and accepts any bit pattern.
+    Ok(unsafe { core::slice::from_raw_parts(records.as_ptr() as *const T, count) })
+}
+
+/// Width of an integer field whose byte order `swap_fields` reverses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    U16,
+    U32,
+    U64,
+}
+
+impl Width {
+    /// Size of the field in bytes
+    pub const fn size(self) -> usize {
+        match self {
+            Width::U16 => 2,
+            Width::U32 => 4,
+            Width::U64 => 8,
+        }
+    }
+}
+
+/// Reverses the byte order of the integer fields of `buf` at the given offsets, as a
+/// fixup for a record received little-endian before decoding it the usual way.
+/// Fails with `BufferTooSmall`, leaving `buf` unchanged, if a field runs past its end.
+pub fn swap_fields(buf: &mut [u8], fields: &[(usize, Width)]) -> Result<(), DeserializeError> {
+    if fields.iter().any(|&(offset, width)| offset.checked_add(width.size()).is_none_or(|end| end > buf.len())) {
+        return Err(DeserializeError::BufferTooSmall);
+    }
+    for &(offset, width) in fields {
+        buf[offset..offset + width.size()].reverse();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Serialize;
+
+    #[test]
+    fn swapped_fields_decode_big_endian() {
+        // id: u8, then two little-endian u16 fields and a u32.
+        let mut buf = [7, 0x34, 0x12, 0x78, 0x56, 0x04, 0x03, 0x02, 0x01];
+        swap_fields(&mut buf, &[(1, Width::U16), (3, Width::U16), (5, Width::U32)]).unwrap();
+        let (id, rest) = u8::deserialize_consumed(&buf).unwrap();
+        let (pair, size) = <[u16; 2]>::deserialize_consumed(&buf[rest..]).unwrap();
+        assert_eq!((id, pair), (7, [0x1234, 0x5678]));
+        assert_eq!(u32::deserialize(&buf[rest + size..]).unwrap(), 0x0102_0304);
+
+        let mut wide = 0x0102_0304_0506_0708u64.to_le_bytes();
+        swap_fields(&mut wide, &[(0, Width::U64)]).unwrap();
+        assert_eq!(u64::deserialize(&wide).unwrap(), 0x0102_0304_0506_0708);
+    }
+
+    #[test]
+    fn out_of_range_field_leaves_the_buffer_unchanged() {
+        let mut buf = [1, 2, 3, 4, 5];
+        assert!(matches!(
+            swap_fields(&mut buf, &[(0, Width::U16), (2, Width::U32)]),
+            Err(DeserializeError::BufferTooSmall)
+        ));
+        assert!(matches!(swap_fields(&mut buf, &[(usize::MAX, Width::U16)]), Err(DeserializeError::BufferTooSmall)));
+        assert_eq!(buf, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn fixed_batch_counts_whole_records() {
+        let mut buf = [0u8; 14];
+        [0x0102_0304u32, 5, 6].serialize(&mut buf).unwrap();
+        let mut out = [0u32; 2];
+        assert_eq!(deserialize_fixed_into_slice(&buf, &mut out).unwrap(), (2, 8));
+        assert_eq!(out, [0x0102_0304, 5]);
+
+        let mut out = [0u32; 8];
+        assert_eq!(deserialize_fixed_into_slice(&buf, &mut out).unwrap(), (3, 12));
+        assert_eq!(out[..3], [0x0102_0304, 5, 6]);
+    }
+
+    #[test]
+    fn fixed_batch_reports_a_corrupt_record() {
+        let buf = [1, 0, 1, 0, 1, 3, 1, 1];
+        let mut out = [[false; 2]; 4];
+        let err = deserialize_fixed_into_slice(&buf, &mut out).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidValue(3)));
+        assert_eq!(out[..2], [[true, false]; 2]);
+    }
+
+    /// Checks `SIZE` against what `serialize` actually writes.
+    fn assert_size<T: FixedLayout + Serialize>(value: T) {
+        let mut buf = [0u8; 64];
+        assert_eq!(value.serialize(&mut buf).unwrap(), T::SIZE);
+        assert_eq!(value.serialized_size(), T::SIZE);
+    }
+
+    #[test]
+    fn primitive_sizes_match_their_encoding() {
+        assert_size(0u8);
+        assert_size(0u16);
+        assert_size(0u32);
+        assert_size(0u64);
+        assert_size(0u128);
+        assert_size(-1i8);
+        assert_size(-1i16);
+        assert_size(-1i32);
+        assert_size(-1i64);
+        assert_size(-1i128);
+        assert_size(0.5f32);
+        assert_size(0.5f64);
+        assert_size(true);
+        assert_size('\u{e9}');
+        assert_size(NonZeroU16::new(1).unwrap());
+        assert_size(NonZeroI128::new(-1).unwrap());
+    }
+
+    #[test]
+    fn array_sizes_sum_their_elements() {
+        assert_size([0u16; 3]);
+        assert_size([[1.0f32; 3]; 2]);
+        assert_size([0u64; 0]);
+        assert_eq!(<[[u32; 4]; 2]>::SIZE, 32);
+    }
+
+    #[test]
+    fn checked_types_swap_like_their_integers() {
+        let mut record = [0x41, 0, 0, 0];
+        <char as FixedLayout>::swap_bytes_in_place(&mut record);
+        assert_eq!(record, [0, 0, 0, 0x41]);
+        let mut record = [1, 2];
+        <NonZeroU16 as FixedLayout>::swap_bytes_in_place(&mut record);
+        assert_eq!(record, [2, 1]);
+    }
+}
@@ -1,6 +1,10 @@
 #![no_std]
 
-#[cfg(feature = "esp32")]
+#[cfg(feature = "std")]
+extern crate std;
+
+// `arduino32` takes precedence, so enabling it works without turning off the default `esp32`.
+#[cfg(all(feature = "esp32", not(feature = "arduino32")))]
 pub mod platform {
     pub fn platform_info() -> &'static str {
         "ESP32 Platform"
@@ -13,13 +17,155 @@ pub mod platform {
         "Arduino32 Platform"
     }
 }
-use core::mem::size_of;
+use core::mem::{size_of, MaybeUninit};
+use core::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+};
+use core::ops::{RangeFrom, RangeTo};
+
+pub mod bcd;
+#[cfg(feature = "flash")]
+pub mod blackbox;
+pub mod bools;
+pub mod bytes;
+pub mod cobs;
+pub mod codec;
+pub mod compress;
+pub mod crc;
+pub mod decimal;
+pub mod ecc;
+pub mod endian;
+pub mod erased;
+pub mod fixed_str;
+pub mod fletcher;
+#[cfg(feature = "flash")]
+pub mod flash;
+pub mod frame;
+pub mod golden;
+pub mod gray;
+pub mod handle;
+pub mod hex_id;
+pub mod int24;
+pub mod kvstore;
+pub mod layout;
+pub mod magic;
+pub mod masked;
+pub mod matrix;
+pub mod nack;
+pub mod names;
+pub mod net;
+pub mod option;
+pub mod padded;
+#[cfg(any(target_has_atomic = "32", feature = "portable-atomic"))]
+pub mod pool;
+pub mod preamble;
+pub mod reserved;
+pub mod resume;
+pub mod ring;
+pub mod schema;
+pub mod text;
+pub mod timestamp;
+pub mod tlv;
+pub mod version;
+pub mod whitening;
+pub mod words;
+pub mod writer;
+#[cfg(feature = "heapless")]
+pub mod map;
+#[cfg(feature = "heapless")]
+pub mod queue;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+
+#[doc(hidden)]
+pub use core;
+
+/// Version of the wire format written by the impls in this crate and by derived types.
+///
+/// Within one version, every type encodes the same value to the same bytes, so devices
+/// built against different releases of this crate keep understanding each other. An
+/// encoding only changes together with an increment of this version, in the same change
+/// that updates the vectors in `golden`, whose doc tests fail otherwise. New types and
+/// new opt-in attributes leave existing encodings alone and do not bump it.
+pub const WIRE_FORMAT_VERSION: u16 = 1;
 
 /// Serialize data to bytes
 pub trait Serialize {
     /// Serializes the data into the provided buffer.
     /// Returns the number of bytes written or an error if the buffer is too small.
     fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError>;
+
+    /// Serializes the data into the provided buffer.
+    /// Returns the written prefix of `buf`, ready to hand to a transmit function.
+    fn serialize_ret<'a>(&self, buf: &'a mut [u8]) -> Result<&'a [u8], SerializeError> {
+        let size = self.serialize(buf)?;
+        Ok(&buf[..size])
+    }
+
+    /// Serializes the data into a buffer that may not be initialized yet.
+    /// Returns the written prefix of `buf`; every byte in it is initialized.
+    /// The default zeroes the `serialized_size()` bytes it needs, and no more of `buf`,
+    /// before serializing into them.
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        serialize_zeroed(self.serialized_size(), buf, |buf| self.serialize(buf))
+    }
+
+    /// Number of bytes `serialize` writes for this value.
+    ///
+    /// Every impl in this crate and every derived impl computes it exactly without
+    /// serializing, also for values that would fail to serialize. The default, for
+    /// hand-written impls, serializes into a scratch buffer of `SIZE_SCRATCH_LEN` bytes and
+    /// panics if that fails, so types whose values can be larger or fail to serialize
+    /// should override it.
+    fn serialized_size(&self) -> usize {
+        let mut scratch = [0u8; SIZE_SCRATCH_LEN];
+        self.serialize(&mut scratch)
+            .expect("value does not serialize into the size scratch buffer")
+    }
+}
+
+/// Size of the scratch buffer the default `Serialize::serialized_size` measures in
+pub const SIZE_SCRATCH_LEN: usize = 256;
+
+/// Reinterprets fully initialized bytes as `[u8]`.
+///
+/// # Safety
+/// Every byte of `buf` must be initialized.
+unsafe fn assume_init_bytes(buf: &mut [MaybeUninit<u8>]) -> &mut [u8] {
+    &mut *(buf as *mut [MaybeUninit<u8>] as *mut [u8])
+}
+
+/// Zeroes the first `size` bytes of `buf`, or all of it if shorter, and serializes into
+/// them with `serialize`.
+/// Returns the written prefix.
+pub(crate) fn serialize_zeroed(
+    size: usize,
+    buf: &mut [MaybeUninit<u8>],
+    serialize: impl FnOnce(&mut [u8]) -> Result<usize, SerializeError>,
+) -> Result<&mut [u8], SerializeError> {
+    let len = size.min(buf.len());
+    let buf = &mut buf[..len];
+    for byte in buf.iter_mut() {
+        byte.write(0);
+    }
+    // SAFETY: every byte of `buf` was initialized above.
+    let buf = unsafe { assume_init_bytes(buf) };
+    let size = serialize(buf)?;
+    Ok(&mut buf[..size])
+}
+
+/// Copies `bytes` to the front of `buf`, returning the now initialized prefix.
+fn write_uninit<'a>(bytes: &[u8], buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+    let dst = buf.get_mut(..bytes.len()).ok_or(SerializeError::BufferTooSmall)?;
+    for (dst, src) in dst.iter_mut().zip(bytes) {
+        dst.write(*src);
+    }
+    // SAFETY: every byte of `dst` was written above.
+    Ok(unsafe { assume_init_bytes(dst) })
 }
 
 /// Deserialize data from bytes
@@ -27,6 +173,39 @@ pub trait Deserialize: Sized {
     /// Deserializes the data from the provided buffer.
     /// Returns the instance of the type if successful or an error.
     fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError>;
+
+    /// Deserializes the data from the provided buffer.
+    /// Returns the instance together with the number of bytes consumed, which is what
+    /// places the next field of an outer struct, so it must be the wire size actually
+    /// read rather than the size of `Self` in memory.
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError>;
+}
+
+/// Deserialize data that may borrow from the input buffer
+pub trait DeserializeBorrowed<'de>: Sized {
+    /// Deserializes the data from the provided buffer, possibly borrowing from it.
+    /// Returns the instance together with the number of bytes consumed.
+    fn deserialize_borrowed(buf: &'de [u8]) -> Result<(Self, usize), DeserializeError>;
+}
+
+impl<'de, T: Deserialize> DeserializeBorrowed<'de> for T {
+    fn deserialize_borrowed(buf: &'de [u8]) -> Result<(Self, usize), DeserializeError> {
+        T::deserialize_consumed(buf)
+    }
+}
+
+/// Deserialize data that needs out-of-band context, such as a count or mode negotiated earlier
+pub trait DeserializeWith<C: ?Sized>: Sized {
+    /// Deserializes the data from the provided buffer using `context`.
+    /// Returns the instance together with the number of bytes consumed.
+    fn deserialize_with(context: &C, buf: &[u8]) -> Result<(Self, usize), DeserializeError>;
+}
+
+/// Context-free types ignore the context.
+impl<C: ?Sized, T: Deserialize> DeserializeWith<C> for T {
+    fn deserialize_with(_context: &C, buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        T::deserialize_consumed(buf)
+    }
 }
 
 /// Errors that can occur during serialization
@@ -34,6 +213,13 @@ pub trait Deserialize: Sized {
 pub enum SerializeError {
     /// Buffer provided is too small
     BufferTooSmall,
+    /// Buffer filled up while writing the element at this index of a slice
+    BufferTooSmallAt(usize),
+    /// The field or element at this index wrote past the length it reported,
+    /// caught by the `paranoid` feature
+    OverrunAt(usize),
+    /// Value cannot be represented in the wire format
+    InvalidData,
     /// Custom error variant for future extensions
     Custom(&'static str),
 }
@@ -45,27 +231,120 @@ pub enum DeserializeError {
     BufferTooSmall,
     /// Data is invalid or corrupted
     InvalidData,
+    /// The element or field at this index was rejected
+    InvalidDataAt(usize),
+    /// A tag that must be unique appeared more than once
+    DuplicateTag(u8),
+    /// A required tag was not present
+    MissingTag(u8),
+    /// Buffer is not aligned for the requested view
+    Misaligned,
+    /// A checksum did not match the data it covers
+    ChecksumMismatch,
+    /// Error correction detected more flipped bits than it can correct
+    Uncorrectable,
+    /// A text line carried a label other than the expected one
+    UnknownLabel,
+    /// An enum tag matched none of the variants. The name of the enum is only kept
+    /// with the `type-names` feature, to leave it out of flash otherwise.
+    UnknownDiscriminant {
+        #[cfg(feature = "type-names")]
+        type_name: &'static str,
+        value: u32,
+    },
+    /// A value outside the range of its type, such as a `bool` byte other than 0 or 1
+    InvalidValue(u32),
+    /// A record did not start with its magic bytes; holds the bytes found instead,
+    /// big-endian
+    BadMagic(u32),
+    /// A record was written in a protocol version below the minimum accepted; holds
+    /// the version found
+    VersionTooOld(u8),
     /// Custom error variant for future extensions
     Custom(&'static str),
 }
 
+impl DeserializeError {
+    /// `UnknownDiscriminant` for the tag `value` of the enum `type_name`, which is
+    /// dropped without the `type-names` feature.
+    pub const fn unknown_discriminant(type_name: &'static str, value: u32) -> Self {
+        #[cfg(not(feature = "type-names"))]
+        let _ = type_name;
+        DeserializeError::UnknownDiscriminant {
+            #[cfg(feature = "type-names")]
+            type_name,
+            value,
+        }
+    }
+}
+
+impl core::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SerializeError::BufferTooSmall => f.write_str("buffer too small"),
+            SerializeError::BufferTooSmallAt(index) => write!(f, "buffer too small at element {}", index),
+            SerializeError::OverrunAt(index) => write!(f, "overrun by element {}", index),
+            SerializeError::InvalidData => f.write_str("value cannot be represented"),
+            SerializeError::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl core::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeserializeError::BufferTooSmall => f.write_str("buffer too small"),
+            DeserializeError::InvalidData => f.write_str("invalid data"),
+            DeserializeError::InvalidDataAt(index) => write!(f, "invalid data at element {}", index),
+            DeserializeError::DuplicateTag(tag) => write!(f, "duplicate tag {}", tag),
+            DeserializeError::MissingTag(tag) => write!(f, "missing tag {}", tag),
+            DeserializeError::Misaligned => f.write_str("misaligned buffer"),
+            DeserializeError::ChecksumMismatch => f.write_str("checksum mismatch"),
+            DeserializeError::Uncorrectable => f.write_str("uncorrectable bit errors"),
+            DeserializeError::UnknownLabel => f.write_str("unknown line label"),
+            #[cfg(feature = "type-names")]
+            DeserializeError::UnknownDiscriminant { type_name, value } => {
+                write!(f, "unknown discriminant {} of {}", value, type_name)
+            }
+            #[cfg(not(feature = "type-names"))]
+            DeserializeError::UnknownDiscriminant { value } => write!(f, "unknown discriminant {}", value),
+            DeserializeError::InvalidValue(value) => write!(f, "invalid value {}", value),
+            DeserializeError::BadMagic(found) => write!(f, "bad magic {:#x}", found),
+            DeserializeError::VersionTooOld(version) => write!(f, "version {} too old", version),
+            DeserializeError::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
 impl Serialize for u8 {
     fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
-        if buf.len() < 1 {
+        if buf.is_empty() {
             return Err(SerializeError::BufferTooSmall);
         }
         buf[0] = *self;
         Ok(1)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&[*self], buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        size_of::<Self>()
+    }
 }
 
 impl Deserialize for u8 {
     fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
-        if buf.len() < 1 {
+        if buf.is_empty() {
             return Err(DeserializeError::BufferTooSmall);
         }
         Ok(buf[0])
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 1))
+    }
 }
 
 impl Serialize for u16 {
@@ -77,6 +356,14 @@ impl Serialize for u16 {
         buf[1] = *self as u8;
         Ok(2)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&self.to_be_bytes(), buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        size_of::<Self>()
+    }
 }
 
 impl Deserialize for u16 {
@@ -86,6 +373,10 @@ impl Deserialize for u16 {
         }
         Ok(((buf[0] as u16) << 8) | (buf[1] as u16))
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 2))
+    }
 }
 
 impl Serialize for u32 {
@@ -99,6 +390,14 @@ impl Serialize for u32 {
         buf[3] = *self as u8;
         Ok(4)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&self.to_be_bytes(), buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        size_of::<Self>()
+    }
 }
 
 impl Deserialize for u32 {
@@ -111,25 +410,41 @@ impl Deserialize for u32 {
             | ((buf[2] as u32) << 8)
             | (buf[3] as u32))
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 4))
+    }
 }
 
 impl Serialize for i8 {
     fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
-        if buf.len() < 1 {
+        if buf.is_empty() {
             return Err(SerializeError::BufferTooSmall);
         }
         buf[0] = *self as u8;
         Ok(1)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&[*self as u8], buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        size_of::<Self>()
+    }
 }
 
 impl Deserialize for i8 {
     fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
-        if buf.len() < 1 {
+        if buf.is_empty() {
             return Err(DeserializeError::BufferTooSmall);
         }
         Ok(buf[0] as i8)
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 1))
+    }
 }
 
 impl Serialize for i16 {
@@ -137,6 +452,14 @@ impl Serialize for i16 {
         let u_val: u16 = (*self) as u16;
         u_val.serialize(buf)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&self.to_be_bytes(), buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        size_of::<Self>()
+    }
 }
 
 impl Deserialize for i16 {
@@ -144,6 +467,10 @@ impl Deserialize for i16 {
         let u_val = u16::deserialize(buf)?;
         Ok(u_val as i16)
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 2))
+    }
 }
 
 
@@ -152,6 +479,14 @@ impl Serialize for i32 {
         let u_val: u32 = (*self) as u32;
         u_val.serialize(buf)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&self.to_be_bytes(), buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        size_of::<Self>()
+    }
 }
 
 impl Deserialize for i32 {
@@ -159,9 +494,116 @@ impl Deserialize for i32 {
         let u_val = u32::deserialize(buf)?;
         Ok(u_val as i32)
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 4))
+    }
 }
 
 
+/// Implements `Serialize` and `Deserialize` for the wide unsigned integers, written
+/// big-endian like `u32`.
+macro_rules! impl_wide_unsigned {
+    ($($ty:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+                    buf.get_mut(..size_of::<$ty>())
+                        .ok_or(SerializeError::BufferTooSmall)?
+                        .copy_from_slice(&self.to_be_bytes());
+                    Ok(size_of::<$ty>())
+                }
+
+                fn serialize_uninit<'a>(
+                    &self,
+                    buf: &'a mut [MaybeUninit<u8>],
+                ) -> Result<&'a mut [u8], SerializeError> {
+                    write_uninit(&self.to_be_bytes(), buf)
+                }
+
+                fn serialized_size(&self) -> usize {
+                    size_of::<Self>()
+                }
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+                    let bytes = buf.get(..size_of::<$ty>()).ok_or(DeserializeError::BufferTooSmall)?;
+                    let mut value = [0u8; size_of::<$ty>()];
+                    value.copy_from_slice(bytes);
+                    Ok(<$ty>::from_be_bytes(value))
+                }
+
+                fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+                    Ok((Self::deserialize(buf)?, size_of::<$ty>()))
+                }
+            }
+        )*
+    };
+}
+
+impl_wide_unsigned!(u64, u128);
+
+/// Implements `Serialize` and `Deserialize` for the wide signed integers, written as
+/// the unsigned integer of the same width like `i32`.
+///
+/// ```
+/// use embedded_serialize::{Deserialize, Serialize};
+///
+/// let mut buf = [0u8; 16];
+/// i64::MIN.serialize(&mut buf).unwrap();
+/// assert_eq!(buf[..8], [0x80, 0, 0, 0, 0, 0, 0, 0]);
+/// assert_eq!(i64::deserialize(&buf).unwrap(), i64::MIN);
+/// assert_eq!(u64::MAX.serialize(&mut buf).unwrap(), 8);
+/// assert_eq!(u64::deserialize_consumed(&buf).unwrap(), (u64::MAX, 8));
+/// assert_eq!((-2i128).serialize(&mut buf).unwrap(), 16);
+/// assert_eq!(buf[..], [[0xFF; 15].as_slice(), &[0xFE]].concat()[..]);
+/// assert_eq!(i128::deserialize_consumed(&buf).unwrap(), (-2, 16));
+/// assert!(u128::deserialize(&buf[..15]).is_err());
+///
+/// // A short buffer is left untouched.
+/// let value = 0x0102_0304_0506_0708_090A_0B0C_0D0E_0F10u128;
+/// assert!(value.serialize(&mut buf[..15]).is_err());
+/// assert_eq!(buf[0], 0xFF);
+/// value.serialize(&mut buf).unwrap();
+/// assert_eq!(u128::deserialize(&buf).unwrap(), value);
+/// ```
+macro_rules! impl_wide_signed {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+                    (*self as $unsigned).serialize(buf)
+                }
+
+                fn serialize_uninit<'a>(
+                    &self,
+                    buf: &'a mut [MaybeUninit<u8>],
+                ) -> Result<&'a mut [u8], SerializeError> {
+                    write_uninit(&self.to_be_bytes(), buf)
+                }
+
+                fn serialized_size(&self) -> usize {
+                    size_of::<Self>()
+                }
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+                    Ok(<$unsigned>::deserialize(buf)? as $ty)
+                }
+
+                fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+                    Ok((Self::deserialize(buf)?, size_of::<$ty>()))
+                }
+            }
+        )*
+    };
+}
+
+impl_wide_signed!(i64 => u64, i128 => u128);
+
+
 impl Serialize for bool {
     fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
         if buf.is_empty() {
@@ -170,6 +612,14 @@ impl Serialize for bool {
         buf[0] = if *self { 1 } else { 0 };
         Ok(1)
     }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        write_uninit(&[*self as u8], buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        1
+    }
 }
 
 impl Deserialize for bool {
@@ -180,35 +630,1127 @@ impl Deserialize for bool {
         match buf[0] {
             0 => Ok(false),
             1 => Ok(true),
-            _ => Err(DeserializeError::InvalidData),
+            byte => Err(DeserializeError::InvalidValue(byte as u32)),
         }
     }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        Ok((Self::deserialize(buf)?, 1))
+    }
 }
 
+/// Written as its scalar value, a `u32`.
+impl Serialize for char {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        (*self as u32).serialize(buf)
+    }
 
-impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        (*self as u32).serialize_uninit(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        4
+    }
+}
+
+/// Fails with `InvalidValue` on a surrogate or a value above `char::MAX`.
+impl Deserialize for char {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (value, size) = u32::deserialize_consumed(buf)?;
+        let value = char::from_u32(value).ok_or(DeserializeError::InvalidValue(value))?;
+        Ok((value, size))
+    }
+}
+
+/// Implements `Serialize` and `Deserialize` for floats, written as the big-endian
+/// integer of their IEEE 754 bits so that every value, NaN payloads included, round-trips
+/// exactly.
+///
+/// ```
+/// use embedded_serialize::{Deserialize, Serialize};
+///
+/// let mut buf = [0u8; 8];
+/// assert_eq!(1.0f32.serialize(&mut buf).unwrap(), 4);
+/// assert_eq!(buf[..4], [0x3F, 0x80, 0x00, 0x00]);
+/// for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.0, f32::from_bits(1)] {
+///     value.serialize(&mut buf).unwrap();
+///     assert_eq!(f32::deserialize(&buf).unwrap().to_bits(), value.to_bits());
+/// }
+/// for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0, f64::MIN_POSITIVE / 2.0] {
+///     assert_eq!(value.serialize(&mut buf).unwrap(), 8);
+///     assert_eq!(f64::deserialize(&buf).unwrap().to_bits(), value.to_bits());
+/// }
+///
+/// let accel = [0.5f32, -9.81, f32::NAN];
+/// let mut buf = [0u8; 12];
+/// accel.serialize(&mut buf).unwrap();
+/// let decoded = <[f32; 3]>::deserialize(&buf).unwrap();
+/// assert_eq!(decoded.map(f32::to_bits), accel.map(f32::to_bits));
+/// ```
+macro_rules! impl_float {
+    ($($float:ty => $bits:ty),*) => {
+        $(
+            impl Serialize for $float {
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+                    self.to_bits().serialize(buf)
+                }
+
+                fn serialize_uninit<'a>(
+                    &self,
+                    buf: &'a mut [MaybeUninit<u8>],
+                ) -> Result<&'a mut [u8], SerializeError> {
+                    write_uninit(&self.to_be_bytes(), buf)
+                }
+
+                fn serialized_size(&self) -> usize {
+                    size_of::<Self>()
+                }
+            }
+
+            impl Deserialize for $float {
+                fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+                    Ok(<$float>::from_bits(<$bits>::deserialize(buf)?))
+                }
+
+                fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+                    Ok((Self::deserialize(buf)?, size_of::<$float>()))
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32 => u32, f64 => u64);
+
+/// Implements `Serialize` and `Deserialize` for non-zero integers, written like the
+/// integer; decoding fails with `InvalidValue(0)` on zero.
+macro_rules! impl_nonzero {
+    ($($nonzero:ty => $int:ty),*) => {
+        $(
+            impl Serialize for $nonzero {
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+                    self.get().serialize(buf)
+                }
+
+                fn serialize_uninit<'a>(
+                    &self,
+                    buf: &'a mut [MaybeUninit<u8>],
+                ) -> Result<&'a mut [u8], SerializeError> {
+                    self.get().serialize_uninit(buf)
+                }
+
+                fn serialized_size(&self) -> usize {
+                    size_of::<$int>()
+                }
+            }
+
+            impl Deserialize for $nonzero {
+                fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+                    Self::deserialize_consumed(buf).map(|(value, _)| value)
+                }
+
+                fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+                    let (value, size) = <$int>::deserialize_consumed(buf)?;
+                    let value = <$nonzero>::new(value).ok_or(DeserializeError::InvalidValue(0))?;
+                    Ok((value, size))
+                }
+            }
+        )*
+    };
+}
+
+impl_nonzero!(
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64,
+    NonZeroU128 => u128,
+    NonZeroI8 => i8,
+    NonZeroI16 => i16,
+    NonZeroI32 => i32,
+    NonZeroI64 => i64,
+    NonZeroI128 => i128
+);
+
+/// Written as a presence byte (0 or 1), followed by the value when present.
+/// See `option::Sentinel` for an encoding without the extra byte.
+impl<T: Serialize> Serialize for Option<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let present = buf.first_mut().ok_or(SerializeError::BufferTooSmall)?;
+        match self {
+            None => {
+                *present = 0;
+                Ok(1)
+            }
+            Some(value) => {
+                *present = 1;
+                Ok(1 + value.serialize(&mut buf[1..])?)
+            }
+        }
+    }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        let mut writer = writer::UninitWriter::new(buf);
+        writer.write(&self.is_some())?;
+        if let Some(value) = self {
+            writer.write(value)?;
+        }
+        Ok(writer.finish())
+    }
+
+    fn serialized_size(&self) -> usize {
+        1 + self.as_ref().map_or(0, Serialize::serialized_size)
+    }
+}
+
+impl<T: Deserialize> Deserialize for Option<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        match buf.first() {
+            None => Err(DeserializeError::BufferTooSmall),
+            Some(0) => Ok((None, 1)),
+            Some(1) => {
+                let (value, size) = T::deserialize_consumed(&buf[1..])?;
+                Ok((Some(value), 1 + size))
+            }
+            Some(&byte) => Err(DeserializeError::InvalidValue(byte as u32)),
+        }
+    }
+}
+
+/// Written as its `start` bound.
+impl<T: Serialize> Serialize for RangeFrom<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.start.serialize(buf)
+    }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        self.start.serialize_uninit(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.start.serialized_size()
+    }
+}
+
+impl<T: Deserialize> Deserialize for RangeFrom<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(range, _)| range)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (start, size) = T::deserialize_consumed(buf)?;
+        Ok((start.., size))
+    }
+}
+
+/// Written as its `end` bound.
+impl<T: Serialize> Serialize for RangeTo<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.end.serialize(buf)
+    }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        self.end.serialize_uninit(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.end.serialized_size()
+    }
+}
+
+impl<T: Deserialize> Deserialize for RangeTo<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(range, _)| range)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (end, size) = T::deserialize_consumed(buf)?;
+        Ok((..end, size))
+    }
+}
+
+/// Byte the `paranoid` feature fills unwritten space with
+#[cfg(feature = "paranoid")]
+const CANARY: u8 = 0xA5;
+
+/// Serializes `value`, the field or element at `index` of a composite value.
+///
+/// With the `paranoid` feature, `buf` is first filled with a canary pattern, and if any
+/// byte past the reported length has changed afterwards this fails with
+/// `OverrunAt(index)`. That catches `Serialize` impls writing more than they report,
+/// at the cost of leaving the canary in the unused part of `buf`. Without the feature
+/// this is `value.serialize(buf)`.
+#[inline]
+pub fn serialize_guarded<T: Serialize + ?Sized>(value: &T, buf: &mut [u8], index: usize) -> Result<usize, SerializeError> {
+    #[cfg(feature = "paranoid")]
+    {
+        buf.fill(CANARY);
+        let size = value.serialize(buf)?;
+        match buf.get(size..) {
+            Some(rest) if rest.iter().all(|&byte| byte == CANARY) => Ok(size),
+            _ => Err(SerializeError::OverrunAt(index)),
+        }
+    }
+    #[cfg(not(feature = "paranoid"))]
+    {
+        let _ = index;
+        value.serialize(buf)
+    }
+}
+
+/// Serializes every element of `items` back to back, without a length prefix.
+/// Returns the number of bytes written.
+pub fn serialize_raw<T: Serialize>(items: &[T], buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let mut offset = 0;
+    for (index, item) in items.iter().enumerate() {
+        let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+        offset += serialize_guarded(item, rest, index)?;
+    }
+    Ok(offset)
+}
+
+/// Serializes every element of `items` back to back, without a length prefix.
+/// If the buffer fills up, the error names the element that did not fit.
+/// Returns the number of bytes written.
+pub fn serialize_all<T: Serialize>(items: &[T], buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let mut offset = 0;
+    for (index, item) in items.iter().enumerate() {
+        let size = buf
+            .get_mut(offset..)
+            .ok_or(SerializeError::BufferTooSmall)
+            .and_then(|rest| serialize_guarded(item, rest, index))
+            .map_err(|err| match err {
+                SerializeError::BufferTooSmall | SerializeError::BufferTooSmallAt(_) => {
+                    SerializeError::BufferTooSmallAt(index)
+                }
+                err => err,
+            })?;
+        offset += size;
+    }
+    Ok(offset)
+}
+
+impl<T: Serialize> Serialize for [T] {
     fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
-        let mut total = 0;
+        serialize_all(self, buf)
+    }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        let mut writer = writer::UninitWriter::new(buf);
         for item in self.iter() {
-            let size = item.serialize(&mut buf[total..])?;
-            total += size;
+            writer.write(item)?;
         }
-        Ok(total)
+        Ok(writer.finish())
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.iter().map(Serialize::serialized_size).sum()
+    }
+}
+
+/// Deserializes exactly `count` elements into the front of `out`, without reading a length prefix.
+/// Returns the number of bytes consumed.
+pub fn deserialize_raw_into<T: Deserialize>(
+    buf: &[u8],
+    out: &mut [T],
+    count: usize,
+) -> Result<usize, DeserializeError> {
+    let out = out.get_mut(..count).ok_or(DeserializeError::InvalidData)?;
+    let mut offset = 0;
+    for slot in out.iter_mut() {
+        let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+        let (item, size) = T::deserialize_consumed(rest)?;
+        *slot = item;
+        offset += size;
+    }
+    Ok(offset)
+}
+
+/// Deserializes elements back to back into `out` until it is full or `buf` runs out,
+/// treating a trailing partial element as the end of `buf`.
+/// An element that fails to decode ends the batch with its error, the elements before
+/// it left decoded in `out`; an `InvalidData` becomes `InvalidDataAt` its index.
+/// Fixed-layout elements are better decoded with `layout::deserialize_fixed_into_slice`,
+/// which checks the length of `buf` once up front.
+/// Returns the number of elements decoded and the number of bytes consumed.
+pub fn deserialize_into_slice<T: Deserialize>(buf: &[u8], out: &mut [T]) -> Result<(usize, usize), DeserializeError> {
+    let mut offset = 0;
+    for (index, slot) in out.iter_mut().enumerate() {
+        let item = buf
+            .get(offset..)
+            .ok_or(DeserializeError::BufferTooSmall)
+            .and_then(T::deserialize_consumed);
+        match item {
+            Ok((item, size)) => {
+                *slot = item;
+                offset += size;
+            }
+            Err(DeserializeError::BufferTooSmall) => return Ok((index, offset)),
+            Err(DeserializeError::InvalidData) => return Err(DeserializeError::InvalidDataAt(index)),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((out.len(), offset))
+}
+
+/// Deserializes exactly `count` elements, without reading a length prefix, for a field
+/// whose count is given by an earlier one. Fails with `InvalidData` if `count` exceeds `CAP`.
+/// Returns the elements and the number of bytes consumed.
+///
+/// Derived impls call this for `heapless::Vec` fields marked `#[es(count = "...")]`:
+///
+/// ```
+/// use embedded_serialize::{Deserialize, DeserializeError, Serialize, SerializeError};
+/// use embedded_serialize_derive::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct AdcFrame {
+///     n_channels: u8,
+///     sequence: u16,
+///     #[es(count = "n_channels")]
+///     channels: heapless::Vec<u16, 4>,
+/// }
+///
+/// let frame = [0x03, 0x00, 0x2A, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00];
+/// let decoded = AdcFrame::deserialize(&frame).unwrap();
+/// assert_eq!(decoded.channels, [0x0100, 0x0200, 0x0300]);
+///
+/// let mut buf = [0u8; 16];
+/// assert_eq!(decoded.serialize(&mut buf).unwrap(), frame.len());
+/// assert_eq!(buf[..frame.len()], frame);
+///
+/// // More channels than the `Vec` can hold
+/// let frame = [0x05, 0x00, 0x2A, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00, 0x05, 0x00];
+/// assert!(matches!(AdcFrame::deserialize(&frame), Err(DeserializeError::InvalidData)));
+///
+/// // A count that disagrees with the channels held
+/// let mismatched = AdcFrame { n_channels: 2, ..decoded };
+/// assert!(matches!(mismatched.serialize(&mut buf), Err(SerializeError::InvalidData)));
+/// ```
+#[cfg(feature = "heapless")]
+pub fn deserialize_counted_vec<T: Deserialize, const CAP: usize>(
+    buf: &[u8],
+    count: usize,
+) -> Result<(heapless::Vec<T, CAP>, usize), DeserializeError> {
+    if count > CAP {
+        return Err(DeserializeError::InvalidData);
+    }
+    let mut items = heapless::Vec::new();
+    let mut offset = 0;
+    for _ in 0..count {
+        let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+        let (item, size) = T::deserialize_consumed(rest)?;
+        // Cannot fail: `count <= CAP`.
+        let _ = items.push(item);
+        offset += size;
+    }
+    Ok((items, offset))
+}
+
+/// Deserializes elements back to back until `buf` is used up, for records that run to
+/// the end of a buffer without a count. Fails with `BufferTooSmall` if a partial element
+/// remains and with `InvalidData` if there are more than `CAP` elements or an element
+/// consumes no bytes.
+#[cfg(feature = "heapless")]
+pub fn deserialize_until_eof<T: Deserialize, const CAP: usize>(
+    buf: &[u8],
+) -> Result<heapless::Vec<T, CAP>, DeserializeError> {
+    let mut items = heapless::Vec::new();
+    let mut offset = 0;
+    while offset < buf.len() {
+        let (item, size) = T::deserialize_consumed(&buf[offset..])?;
+        if size == 0 {
+            return Err(DeserializeError::InvalidData);
+        }
+        items.push(item).map_err(|_| DeserializeError::InvalidData)?;
+        offset += size;
+    }
+    Ok(items)
+}
+
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        serialize_raw(self, buf)
+    }
+
+    fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+        self[..].serialize_uninit(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.iter().map(Serialize::serialized_size).sum()
     }
 }
 
+/// A buffer too short for all `N` elements fails with `BufferTooSmall`, also when an
+/// element reports consuming more bytes than it was given.
+///
+/// ```
+/// use embedded_serialize::{Deserialize, DeserializeError};
+///
+/// let buf = [0, 0, 0, 1, 0, 0];
+/// assert!(matches!(<[u32; 4]>::deserialize(&buf), Err(DeserializeError::BufferTooSmall)));
+/// ```
 impl<T: Deserialize, const N: usize> Deserialize for [T; N] {
     fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
-        let mut array: [core::mem::MaybeUninit<T>; N] =
-            unsafe { core::mem::MaybeUninit::uninit().assume_init() };
+        Self::deserialize_consumed(buf).map(|(array, _)| array)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let mut array = PartialArray::<T, N>::new();
         let mut offset = 0;
-        for i in 0..N {
-            let item = T::deserialize(&buf[offset..])?;
-            offset += size_of::<T>();
-            array[i] = core::mem::MaybeUninit::new(item);
-        }
-   
-        let array = unsafe { core::mem::transmute_copy::<_, [T; N]>(&array) };
-        Ok(array)
+        while array.initialized < N {
+            let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+            let (item, size) = T::deserialize_consumed(rest)?;
+            offset += size;
+            array.push(item);
+        }
+        Ok((array.into_inner(), offset))
+    }
+}
+
+/// Written like the array it points to, for borrowed fields such as a blob kept in flash.
+impl<const N: usize> Serialize for &[u8; N] {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        (**self).serialize(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        N
+    }
+}
+
+/// Points into the input instead of copying the `N` bytes.
+/// There is no owned `Deserialize` impl; a struct that must own its data uses `[u8; N]`.
+impl<'de, const N: usize> DeserializeBorrowed<'de> for &'de [u8; N] {
+    fn deserialize_borrowed(buf: &'de [u8]) -> Result<(Self, usize), DeserializeError> {
+        let bytes = buf.get(..N).ok_or(DeserializeError::BufferTooSmall)?;
+        let array = bytes.try_into().map_err(|_| DeserializeError::InvalidData)?;
+        Ok((array, N))
+    }
+}
+
+/// Deserializes an array, checking each element with `validate` as soon as it is decoded.
+/// Fails with `InvalidDataAt` holding the index of the first element `validate` rejects.
+/// Returns the array and the number of bytes consumed.
+pub fn deserialize_array_validated<T, F, const N: usize>(
+    buf: &[u8],
+    mut validate: F,
+) -> Result<([T; N], usize), DeserializeError>
+where
+    T: Deserialize,
+    F: FnMut(&T) -> bool,
+{
+    let mut array = PartialArray::<T, N>::new();
+    let mut offset = 0;
+    while array.initialized < N {
+        let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+        let (item, size) = T::deserialize_consumed(rest)?;
+        if !validate(&item) {
+            return Err(DeserializeError::InvalidDataAt(array.initialized));
+        }
+        offset += size;
+        array.push(item);
+    }
+    Ok((array.into_inner(), offset))
+}
+
+/// Deserializes a `W` and converts it with `map`, for fields derived with
+/// `#[es(map(...))]`. An `InvalidData` from `map` becomes `InvalidDataAt(index)`.
+/// Returns the value and the number of bytes consumed.
+pub fn deserialize_mapped<W, T, F>(buf: &[u8], index: usize, map: F) -> Result<(T, usize), DeserializeError>
+where
+    W: Deserialize,
+    F: FnOnce(W) -> Result<T, DeserializeError>,
+{
+    let (wire, size) = W::deserialize_consumed(buf)?;
+    match map(wire) {
+        Ok(value) => Ok((value, size)),
+        Err(DeserializeError::InvalidData) => Err(DeserializeError::InvalidDataAt(index)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Deserializes the `N` elements of an array one at a time, handing each to `sink` with
+/// its index instead of building the array, for arrays too large for the stack.
+/// An error from `sink` stops decoding and is returned.
+/// Returns the number of bytes consumed.
+pub fn deserialize_array_with<T, F, const N: usize>(buf: &[u8], mut sink: F) -> Result<usize, DeserializeError>
+where
+    T: Deserialize,
+    F: FnMut(usize, T) -> Result<(), DeserializeError>,
+{
+    let mut offset = 0;
+    for index in 0..N {
+        let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+        let (item, size) = T::deserialize_consumed(rest)?;
+        sink(index, item)?;
+        offset += size;
+    }
+    Ok(offset)
+}
+
+/// Serializes `N` elements that `source` produces one at a time from their index,
+/// written the same as a `[T; N]`.
+/// Returns the number of bytes written.
+pub fn serialize_array_from<T, F, const N: usize>(buf: &mut [u8], mut source: F) -> Result<usize, SerializeError>
+where
+    T: Serialize,
+    F: FnMut(usize) -> T,
+{
+    let mut offset = 0;
+    for index in 0..N {
+        let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+        offset += serialize_guarded(&source(index), rest, index)?;
+    }
+    Ok(offset)
+}
+
+/// An array filled front to back that drops only the elements written so far
+/// if decoding stops early.
+struct PartialArray<T, const N: usize> {
+    items: [MaybeUninit<T>; N],
+    initialized: usize,
+}
+
+impl<T, const N: usize> PartialArray<T, N> {
+    fn new() -> Self {
+        PartialArray {
+            items: [const { MaybeUninit::uninit() }; N],
+            initialized: 0,
+        }
+    }
+
+    fn push(&mut self, item: T) {
+        self.items[self.initialized].write(item);
+        self.initialized += 1;
+    }
+
+    fn into_inner(self) -> [T; N] {
+        assert_eq!(self.initialized, N);
+        let this = core::mem::ManuallyDrop::new(self);
+        // SAFETY: all `N` elements are initialized, and `this` is never dropped,
+        // so ownership of each element moves into the returned array exactly once.
+        unsafe { (&this.items as *const [MaybeUninit<T>; N] as *const [T; N]).read() }
+    }
+}
+
+impl<T, const N: usize> Drop for PartialArray<T, N> {
+    fn drop(&mut self) {
+        for item in &mut self.items[..self.initialized] {
+            // SAFETY: the first `initialized` elements were written by `push`.
+            unsafe { item.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn raw_slice_has_no_prefix() {
+        let mut buf = [0u8; 6];
+        assert_eq!(serialize_raw(&[0x0102u16, 0x0304, 0x0506], &mut buf).unwrap(), 6);
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6]);
+
+        let mut out = [0u16; 4];
+        assert_eq!(deserialize_raw_into(&buf, &mut out, 3).unwrap(), 6);
+        assert_eq!(out, [0x0102, 0x0304, 0x0506, 0]);
+    }
+
+    #[test]
+    fn raw_slice_rejects_short_buffers() {
+        let mut buf = [0u8; 5];
+        assert!(serialize_raw(&[1u16, 2, 3], &mut buf).is_err());
+
+        let mut out = [0u16; 3];
+        assert!(matches!(deserialize_raw_into(&buf, &mut out, 3), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    /// An element that counts its drops and fails to decode from 0xFF
+    #[derive(Debug)]
+    struct Tracked(u8);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    impl Deserialize for Tracked {
+        fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+            Self::deserialize_consumed(buf).map(|(item, _)| item)
+        }
+
+        fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+            match u8::deserialize_consumed(buf)? {
+                (0xFF, _) => Err(DeserializeError::InvalidData),
+                (byte, size) => Ok((Tracked(byte), size)),
+            }
+        }
+    }
+
+    /// Only this test uses `DROPS`, so the counts are not disturbed by tests running alongside.
+    #[test]
+    fn array_drops_exactly_the_decoded_elements() {
+        let array = <[Tracked; 3]>::deserialize(&[1, 2, 3]).unwrap();
+        assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+        assert_eq!(array.iter().map(|item| item.0).sum::<u8>(), 6);
+        drop(array);
+        assert_eq!(DROPS.swap(0, Ordering::Relaxed), 3);
+
+        assert!(matches!(<[Tracked; 3]>::deserialize(&[1, 2, 0xFF]), Err(DeserializeError::InvalidData)));
+        assert_eq!(DROPS.swap(0, Ordering::Relaxed), 2);
+
+        assert!(matches!(<[Tracked; 3]>::deserialize(&[1]), Err(DeserializeError::BufferTooSmall)));
+        assert_eq!(DROPS.swap(0, Ordering::Relaxed), 1);
+
+        // The rejected element is dropped along with those before it.
+        let result = deserialize_array_validated::<Tracked, _, 3>(&[1, 2, 3], |item| item.0 < 3);
+        assert!(matches!(result, Err(DeserializeError::InvalidDataAt(2))));
+        assert_eq!(DROPS.swap(0, Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn validation_failure_reports_the_index() {
+        let mut buf = [0u8; 8];
+        [215i16, 230, 1200, 225].serialize(&mut buf).unwrap();
+        let in_range = |celsius_tenths: &i16| (-400..=850).contains(celsius_tenths);
+        assert!(matches!(
+            deserialize_array_validated::<i16, _, 4>(&buf, in_range),
+            Err(DeserializeError::InvalidDataAt(2))
+        ));
+
+        buf[4..6].copy_from_slice(&220i16.to_be_bytes());
+        let mut checked = 0;
+        let (readings, size) = deserialize_array_validated::<i16, _, 4>(&buf, |reading| {
+            checked += 1;
+            in_range(reading)
+        })
+        .unwrap();
+        assert_eq!((readings, size, checked), ([215, 230, 220, 225], 8, 4));
+        assert!(matches!(deserialize_array_validated::<i16, _, 4>(&buf[..7], in_range), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn overflow_names_the_element() {
+        let records = [[1u16, 2], [3, 4], [5, 6], [7, 8], [9, 10]];
+        let mut buf = [0u8; 10];
+        assert!(matches!(serialize_all(&records, &mut buf), Err(SerializeError::BufferTooSmallAt(2))));
+        assert!(matches!(records[..].serialize(&mut buf), Err(SerializeError::BufferTooSmallAt(2))));
+        assert_eq!(serialize_all(&records[..2], &mut buf).unwrap(), 8);
+    }
+
+    #[test]
+    fn single_bound_ranges_round_trip() {
+        let mut buf = [0u8; 4];
+        assert_eq!((5u32..).serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0, 0, 0, 5]);
+        assert_eq!(RangeFrom::<u32>::deserialize(&buf).unwrap(), 5u32..);
+        assert_eq!((..10u32).serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0, 0, 0, 10]);
+        assert_eq!(RangeTo::<u32>::deserialize_consumed(&buf).unwrap(), (..10u32, 4));
+    }
+
+    #[test]
+    fn raw_count_larger_than_output_fails() {
+        let mut out = [0u8; 2];
+        assert!(matches!(deserialize_raw_into(&[1, 2, 3], &mut out, 3), Err(DeserializeError::InvalidData)));
+    }
+
+    #[test]
+    fn serialize_ret_returns_the_written_prefix() {
+        let value = [0x0102u16, 0x0304, 0x0506];
+        let mut expected = [0xEEu8; 8];
+        let size = value.serialize(&mut expected).unwrap();
+
+        let mut buf = [0xEEu8; 8];
+        let written = value.serialize_ret(&mut buf).unwrap();
+        assert_eq!(written.len(), size);
+        assert_eq!(written, &expected[..size]);
+        assert_eq!(written, [1, 2, 3, 4, 5, 6]);
+
+        assert!(matches!(value.serialize_ret(&mut buf[..4]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn streaming_matches_the_whole_array_path() {
+        const N: usize = 2048;
+        let samples: [u16; N] = core::array::from_fn(|index| (index as u16).wrapping_mul(0x9E37));
+
+        let mut whole = [0u8; 2 * N];
+        let mut streamed = [0u8; 2 * N];
+        assert_eq!(samples.serialize(&mut whole).unwrap(), 2 * N);
+        let size = serialize_array_from::<u16, _, N>(&mut streamed, |index| samples[index]).unwrap();
+        assert_eq!(size, 2 * N);
+        assert!(streamed == whole);
+
+        let decoded = <[u16; N]>::deserialize(&whole).unwrap();
+        let mut next = 0;
+        let size = deserialize_array_with::<u16, _, N>(&whole, |index, sample| {
+            assert_eq!(index, next);
+            assert_eq!(sample, decoded[index]);
+            next += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((size, next), (2 * N, N));
+    }
+
+    #[test]
+    fn streaming_stops_at_the_first_error() {
+        let mut buf = [0u8; 8];
+        serialize_array_from::<u16, _, 4>(&mut buf, |index| index as u16).unwrap();
+
+        let mut seen = 0;
+        let result = deserialize_array_with::<u16, _, 4>(&buf, |index, _| {
+            seen += 1;
+            if index == 1 {
+                Err(DeserializeError::InvalidDataAt(index))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(matches!(result, Err(DeserializeError::InvalidDataAt(1))));
+        assert_eq!(seen, 2);
+
+        assert!(matches!(deserialize_array_with::<u16, _, 4>(&buf[..7], |_, _| Ok(())), Err(DeserializeError::BufferTooSmall)));
+        assert!(serialize_array_from::<u16, _, 4>(&mut buf[..7], |index| index as u16).is_err());
+    }
+
+    #[test]
+    fn invalid_values_carry_the_raw_value() {
+        assert!(matches!(bool::deserialize(&[7]), Err(DeserializeError::InvalidValue(7))));
+        assert!(matches!(
+            core::num::NonZeroU16::deserialize(&[0, 0]),
+            Err(DeserializeError::InvalidValue(0))
+        ));
+        assert!(matches!(
+            char::deserialize(&0xD800u32.to_be_bytes()),
+            Err(DeserializeError::InvalidValue(0xD800))
+        ));
+        assert!(matches!(Option::<u8>::deserialize(&[2, 0]), Err(DeserializeError::InvalidValue(2))));
+        assert_eq!(core::num::NonZeroU16::deserialize(&[0, 5]).unwrap().get(), 5);
+    }
+
+    #[test]
+    fn unknown_discriminant_keeps_the_value() {
+        let err = DeserializeError::unknown_discriminant("Command", 0x42);
+        assert!(matches!(err, DeserializeError::UnknownDiscriminant { value: 0x42, .. }));
+        #[cfg(feature = "type-names")]
+        assert!(matches!(err, DeserializeError::UnknownDiscriminant { type_name: "Command", .. }));
+    }
+
+    #[test]
+    fn batch_stops_when_the_output_is_full() {
+        let mut buf = [0u8; 12];
+        [1u16, 2, 3, 4, 5, 6].serialize(&mut buf).unwrap();
+        let mut out = [0u16; 4];
+        assert_eq!(deserialize_into_slice(&buf, &mut out).unwrap(), (4, 8));
+        assert_eq!(out, [1, 2, 3, 4]);
+        // Continuing from the returned offset picks up the rest.
+        assert_eq!(deserialize_into_slice(&buf[8..], &mut out).unwrap(), (2, 4));
+        assert_eq!(out[..2], [5, 6]);
+    }
+
+    #[test]
+    fn batch_stops_when_the_input_runs_out() {
+        let mut out = [0u16; 4];
+        // Two whole elements and half of a third.
+        assert_eq!(deserialize_into_slice(&[0, 1, 0, 2, 0], &mut out).unwrap(), (2, 4));
+        assert_eq!(out, [1, 2, 0, 0]);
+        assert_eq!(deserialize_into_slice::<u16>(&[], &mut out).unwrap(), (0, 0));
+    }
+
+    /// An odd byte, rejecting even ones with `InvalidData`
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Odd(u8);
+
+    impl Deserialize for Odd {
+        fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+            Self::deserialize_consumed(buf).map(|(item, _)| item)
+        }
+
+        fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+            match u8::deserialize_consumed(buf)? {
+                (byte, size) if byte % 2 == 1 => Ok((Odd(byte), size)),
+                _ => Err(DeserializeError::InvalidData),
+            }
+        }
+    }
+
+    #[test]
+    fn corrupt_element_reports_the_progress_before_it() {
+        let mut out = [false; 4];
+        let err = deserialize_into_slice(&[1, 0, 7, 1], &mut out).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidValue(7)));
+        assert_eq!(out[..2], [true, false]);
+
+        let err = deserialize_into_slice(&[9], &mut out).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidValue(9)));
+
+        // An `InvalidData` gains the index of the element.
+        let mut out = [Odd(0); 3];
+        let err = deserialize_into_slice(&[1, 3, 4], &mut out).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidDataAt(2)));
+        assert_eq!(out[..2], [Odd(1), Odd(3)]);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn records_are_read_until_the_end() {
+        let mut buf = [0u8; 12];
+        [7u32, 0x0102_0304, u32::MAX].serialize(&mut buf).unwrap();
+        let records = deserialize_until_eof::<u32, 4>(&buf).unwrap();
+        assert_eq!(records[..], [7, 0x0102_0304, u32::MAX]);
+        assert!(deserialize_until_eof::<u32, 4>(&[]).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn uncounted_records_are_checked() {
+        let buf = [0u8; 14];
+        // Two bytes of a fourth record.
+        assert!(matches!(deserialize_until_eof::<u32, 4>(&buf), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(deserialize_until_eof::<u32, 2>(&buf[..12]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_until_eof::<[u8; 0], 4>(&buf), Err(DeserializeError::InvalidData)));
+    }
+
+    /// Claims to consume more bytes than it was given.
+    struct Overclaiming;
+
+    impl Deserialize for Overclaiming {
+        fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+            Self::deserialize_consumed(buf).map(|(item, _)| item)
+        }
+
+        fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+            Ok((Overclaiming, buf.len() + 1))
+        }
+    }
+
+    #[test]
+    fn short_array_buffers_fail_cleanly() {
+        let buf = [0, 0, 0, 1, 0, 0];
+        assert!(matches!(<[u32; 4]>::deserialize(&buf), Err(DeserializeError::BufferTooSmall)));
+
+        let mut full = [0u8; 16];
+        [1u32, 2, 3, 4].serialize(&mut full).unwrap();
+        for len in 0..full.len() {
+            assert!(matches!(<[u32; 4]>::deserialize(&full[..len]), Err(DeserializeError::BufferTooSmall)));
+        }
+        assert_eq!(<[u32; 4]>::deserialize(&full).unwrap(), [1, 2, 3, 4]);
+
+        assert!(matches!(<[Overclaiming; 2]>::deserialize(&[0; 4]), Err(DeserializeError::BufferTooSmall)));
+        let mut out = [0u32; 1];
+        assert!(matches!(deserialize_raw_into(&buf[..3], &mut out, 1), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn wide_integers_are_big_endian() {
+        let mut buf = [0u8; 16];
+        assert_eq!(u64::MAX.serialize(&mut buf).unwrap(), 8);
+        assert_eq!(buf[..8], [0xFF; 8]);
+        assert_eq!(u64::deserialize(&buf).unwrap(), u64::MAX);
+
+        assert_eq!(0x0102_0304_0506_0708u64.serialize(&mut buf).unwrap(), 8);
+        assert_eq!(buf[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(u128::MAX.serialize(&mut buf).unwrap(), 16);
+        assert_eq!(u128::deserialize_consumed(&buf).unwrap(), (u128::MAX, 16));
+    }
+
+    #[test]
+    fn wide_signed_integers_keep_their_sign() {
+        let mut buf = [0u8; 16];
+        i64::MIN.serialize(&mut buf).unwrap();
+        assert_eq!(buf[..8], [0x80, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(i64::deserialize(&buf).unwrap(), i64::MIN);
+
+        (-1i64).serialize(&mut buf).unwrap();
+        assert_eq!(buf[..8], [0xFF; 8]);
+        assert_eq!(i64::deserialize(&buf).unwrap(), -1);
+
+        for value in [-1i128, -2, i128::MIN, i64::MIN as i128, -0x0102_0304] {
+            assert_eq!(value.serialize(&mut buf).unwrap(), 16);
+            assert_eq!(buf, (value as u128).to_be_bytes());
+            assert_eq!(i128::deserialize(&buf).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn wide_integers_need_the_whole_width() {
+        let mut buf = [0u8; 15];
+        assert!(u64::MAX.serialize(&mut buf[..7]).is_err());
+        assert!(i128::MIN.serialize(&mut buf).is_err());
+        assert!(matches!(u64::deserialize(&buf[..7]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(i64::deserialize(&buf[..7]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn wide_serialize_leaves_short_buffers_untouched() {
+        let value = 0x0102_0304_0506_0708_090A_0B0C_0D0E_0F10u128;
+        for len in 0..16 {
+            let mut buf = [0xAAu8; 16];
+            assert!(value.serialize(&mut buf[..len]).is_err());
+            assert!((value as i128).serialize(&mut buf[..len]).is_err());
+            assert_eq!(buf, [0xAA; 16], "len {}", len);
+        }
+    }
+
+    #[test]
+    fn wide_deserialize_rejects_short_buffers() {
+        let buf = [0x80u8; 16];
+        for len in 0..16 {
+            assert!(matches!(u128::deserialize(&buf[..len]), Err(DeserializeError::BufferTooSmall)));
+            assert!(matches!(i128::deserialize_consumed(&buf[..len]), Err(DeserializeError::BufferTooSmall)));
+        }
+        assert_eq!(i128::deserialize(&buf).unwrap(), i128::from_be_bytes([0x80; 16]));
+    }
+
+    #[test]
+    fn wide_zero_is_an_invalid_non_zero_value() {
+        let zero = [0u8; 16];
+        assert!(matches!(NonZeroU128::deserialize(&zero), Err(DeserializeError::InvalidValue(0))));
+        assert!(matches!(NonZeroI128::deserialize(&zero), Err(DeserializeError::InvalidValue(0))));
+        assert!(matches!(NonZeroU64::deserialize(&zero), Err(DeserializeError::InvalidValue(0))));
+        assert!(matches!(NonZeroU128::deserialize(&zero[..15]), Err(DeserializeError::BufferTooSmall)));
+
+        let mut buf = [0u8; 16];
+        let value = NonZeroI128::new(-0x0102_0304_0506_0708_090A_0B0C_0D0E_0F10).unwrap();
+        assert_eq!(value.serialize(&mut buf).unwrap(), 16);
+        assert_eq!(NonZeroI128::deserialize_consumed(&buf).unwrap(), (value, 16));
+    }
+
+    #[test]
+    fn floats_use_the_integer_layout_of_their_bits() {
+        let mut buf = [0u8; 8];
+        assert_eq!(1.5f32.serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], 1.5f32.to_bits().to_be_bytes());
+        assert_eq!(f32::deserialize_consumed(&buf).unwrap(), (1.5, 4));
+
+        assert_eq!((-1.5f64).serialize(&mut buf).unwrap(), 8);
+        assert_eq!(buf, (-1.5f64).to_bits().to_be_bytes());
+        assert_eq!(f64::deserialize_consumed(&buf).unwrap(), (-1.5, 8));
+    }
+
+    #[test]
+    fn float_specials_round_trip_bit_for_bit() {
+        let mut buf = [0u8; 8];
+        for value in [f32::NAN, -f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -0.0, 0.0] {
+            value.serialize(&mut buf).unwrap();
+            assert_eq!(f32::deserialize(&buf).unwrap().to_bits(), value.to_bits());
+        }
+        for value in [f64::NAN, -f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0, 0.0] {
+            value.serialize(&mut buf).unwrap();
+            assert_eq!(f64::deserialize(&buf).unwrap().to_bits(), value.to_bits());
+        }
+    }
+
+    #[test]
+    fn floats_need_their_whole_width() {
+        let mut buf = [0u8; 7];
+        assert!(1.0f32.serialize(&mut buf[..3]).is_err());
+        assert!(1.0f64.serialize(&mut buf).is_err());
+        assert!(matches!(f32::deserialize(&buf[..3]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(f64::deserialize(&buf), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn float_subnormals_round_trip() {
+        let mut buf = [0u8; 8];
+        for value in [f32::from_bits(1), f32::from_bits(0x007F_FFFF), -f32::MIN_POSITIVE / 2.0] {
+            assert!(value.is_subnormal());
+            value.serialize(&mut buf).unwrap();
+            assert_eq!(buf[..4], value.to_bits().to_be_bytes());
+            assert_eq!(f32::deserialize(&buf).unwrap().to_bits(), value.to_bits());
+        }
+        for value in [f64::from_bits(1), f64::MIN_POSITIVE / 2.0, -f64::from_bits(0x000F_FFFF_FFFF_FFFF)] {
+            assert!(value.is_subnormal());
+            value.serialize(&mut buf).unwrap();
+            assert_eq!(f64::deserialize(&buf).unwrap().to_bits(), value.to_bits());
+        }
+    }
+
+    #[test]
+    fn float_nan_payloads_are_kept() {
+        let mut buf = [0u8; 8];
+        // Quiet and signalling NaNs of both signs, with payloads in the low mantissa bits.
+        for bits in [0x7FC0_0001u32, 0xFFC0_1234, 0x7F80_0001, 0xFFBF_FFFF] {
+            let value = f32::from_bits(bits);
+            assert!(value.is_nan());
+            value.serialize(&mut buf).unwrap();
+            assert_eq!(buf[..4], bits.to_be_bytes());
+            assert_eq!(f32::deserialize(&buf).unwrap().to_bits(), bits);
+        }
+        for bits in [0x7FF8_0000_0000_0001u64, 0xFFF0_0000_0000_0001, 0x7FF7_FFFF_FFFF_FFFF] {
+            let value = f64::from_bits(bits);
+            assert!(value.is_nan());
+            value.serialize(&mut buf).unwrap();
+            assert_eq!(buf, bits.to_be_bytes());
+            assert_eq!(f64::deserialize(&buf).unwrap().to_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn f32_arrays_round_trip() {
+        let accel = [0.5f32, -9.81, f32::from_bits(0x7FC0_0042)];
+        let mut buf = [0u8; 12];
+        assert_eq!(accel.serialize(&mut buf).unwrap(), 12);
+        assert_eq!(buf[4..8], (-9.81f32).to_bits().to_be_bytes());
+        let (decoded, consumed) = <[f32; 3]>::deserialize_consumed(&buf).unwrap();
+        assert_eq!(consumed, 12);
+        assert_eq!(decoded.map(f32::to_bits), accel.map(f32::to_bits));
+        assert!(matches!(<[f32; 3]>::deserialize(&buf[..11]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    /// Relies on the default `serialize_uninit`, recording how much of the buffer it got
+    struct Measured(core::cell::Cell<usize>);
+
+    impl Serialize for Measured {
+        fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+            self.0.set(buf.len());
+            0xABCDu16.serialize(buf)
+        }
+
+        fn serialized_size(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn default_uninit_initializes_only_what_it_writes() {
+        let value = Measured(core::cell::Cell::new(0));
+        let mut buf = [MaybeUninit::<u8>::uninit(); 64];
+        assert_eq!(value.serialize_uninit(&mut buf).unwrap(), [0xAB, 0xCD]);
+        assert_eq!(value.0.get(), 2);
+
+        let mut short = [MaybeUninit::<u8>::uninit(); 1];
+        assert!(value.serialize_uninit(&mut short).is_err());
+        assert_eq!(value.0.get(), 1);
+    }
+
+    #[test]
+    fn wrappers_serialize_uninit_like_their_inner_value() {
+        let mut buf = [MaybeUninit::<u8>::uninit(); 8];
+        assert_eq!('A'.serialize_uninit(&mut buf).unwrap(), [0, 0, 0, 0x41]);
+        let value = core::num::NonZeroU16::new(0x0102).unwrap();
+        assert_eq!(value.serialize_uninit(&mut buf).unwrap(), [1, 2]);
+        assert_eq!((5u16..).serialize_uninit(&mut buf).unwrap(), [0, 5]);
+        assert_eq!((..7u8).serialize_uninit(&mut buf).unwrap(), [7]);
+        assert!('A'.serialize_uninit(&mut buf[..3]).is_err());
     }
 }
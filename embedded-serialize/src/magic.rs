@@ -0,0 +1,85 @@
+//! Magic bytes opening every record of a type, declared on the type with
+//! `#[es(magic = [0xAA, 0x55])]`.
+//!
+//! The derived `Serialize` writes the magic before anything else, the tag of an enum
+//! included, and counts it in `serialized_size`. The derived `Deserialize` checks and
+//! consumes it, failing with `BadMagic` holding the bytes found instead. Magic is at most
+//! `MAX_MAGIC_LEN` bytes long, and is not supported on views or `FixedLayout` types.
+//!
+//! ```
+//! use embedded_serialize::codec::{Codec, FramedCrcCodec};
+//! use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+//! use embedded_serialize_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! #[es(magic = [0xAA, 0x55])]
+//! enum Record {
+//!     #[es(tag = 3)]
+//!     Sample(u16),
+//! }
+//!
+//! let mut buf = [0u8; 8];
+//! let record = Record::Sample(0x0102);
+//! assert_eq!(record.serialized_size(), 5);
+//! assert_eq!(record.serialize(&mut buf).unwrap(), 5);
+//! assert_eq!(buf[..5], [0xAA, 0x55, 0x03, 0x01, 0x02]);
+//! assert_eq!(Record::deserialize_consumed(&buf[..5]).unwrap(), (record, 5));
+//!
+//! let corrupted = [0xAA, 0x56, 0x03, 0x01, 0x02];
+//! assert!(matches!(Record::deserialize(&corrupted), Err(DeserializeError::BadMagic(0xAA56))));
+//!
+//! // The magic travels inside the payload of a frame.
+//! let mut codec = FramedCrcCodec::<Record>::new();
+//! let mut frame = [0u8; 16];
+//! let size = codec.encode(&Record::Sample(7), &mut frame).unwrap();
+//! assert_eq!(frame[..4], [0x00, 0x05, 0xAA, 0x55]);
+//! assert_eq!(codec.feed(&frame[..size]).unwrap(), Some(Record::Sample(7)));
+//! ```
+
+use crate::{DeserializeError, SerializeError};
+
+/// Longest magic, so that `BadMagic` can hold the bytes found in a `u32`
+pub const MAX_MAGIC_LEN: usize = 4;
+
+/// Writes `magic` at the start of `buf`.
+/// Returns the number of bytes written.
+pub fn write_magic(magic: &[u8], buf: &mut [u8]) -> Result<usize, SerializeError> {
+    buf.get_mut(..magic.len())
+        .ok_or(SerializeError::BufferTooSmall)?
+        .copy_from_slice(magic);
+    Ok(magic.len())
+}
+
+/// Checks that `buf` starts with `magic`, failing with `BadMagic` holding the bytes found
+/// instead. Returns the number of bytes consumed.
+pub fn check_magic(magic: &[u8], buf: &[u8]) -> Result<usize, DeserializeError> {
+    let found = buf.get(..magic.len()).ok_or(DeserializeError::BufferTooSmall)?;
+    if found != magic {
+        let found = found.iter().fold(0u32, |value, &byte| value << 8 | byte as u32);
+        return Err(DeserializeError::BadMagic(found));
+    }
+    Ok(magic.len())
+}
+
+/// Writes `magic` followed by whatever `body` writes into the rest of `buf`.
+/// Returns the number of bytes written, magic included.
+pub fn serialize_after_magic(
+    magic: &[u8],
+    buf: &mut [u8],
+    body: impl FnOnce(&mut [u8]) -> Result<usize, SerializeError>,
+) -> Result<usize, SerializeError> {
+    let size = write_magic(magic, buf)?;
+    Ok(size + body(&mut buf[size..])?)
+}
+
+/// Checks and consumes `magic`, then decodes the rest of `buf` with `body`.
+/// Returns the value and the number of bytes consumed, magic included.
+pub fn deserialize_after_magic<'de, T>(
+    magic: &[u8],
+    buf: &'de [u8],
+    body: impl FnOnce(&'de [u8]) -> Result<(T, usize), DeserializeError>,
+) -> Result<(T, usize), DeserializeError> {
+    let size = check_magic(magic, buf)?;
+    let (value, consumed) = body(&buf[size..])?;
+    Ok((value, size + consumed))
+}
@@ -0,0 +1,251 @@
+//! Map serialization: a big-endian `u16` entry count followed by each key and its value.
+//!
+//! `LinearMap` and `IndexMap` (so `FnvIndexMap`) share this form, so one side of a link
+//! can encode whichever map suits it and the other decode into its own:
+//!
+//! ```
+//! use embedded_serialize::{Deserialize, Serialize};
+//! use heapless::{FnvIndexMap, LinearMap};
+//!
+//! let mut calibration = LinearMap::<u8, u16, 8>::new();
+//! calibration.insert(1, 100).unwrap();
+//! calibration.insert(2, 250).unwrap();
+//! let mut buf = [0u8; 16];
+//! let size = calibration.serialize(&mut buf).unwrap();
+//!
+//! let decoded = FnvIndexMap::<u8, u16, 8>::deserialize(&buf[..size]).unwrap();
+//! assert_eq!(decoded.get(&2), Some(&250));
+//! assert_eq!(decoded.len(), 2);
+//! ```
+
+use core::hash::{BuildHasher, Hash};
+
+use heapless::{IndexMap, LinearMap};
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+impl<K: Serialize + Eq, V: Serialize, const N: usize> Serialize for LinearMap<K, V, N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        serialize_entries(self.len(), self.iter(), buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        entries_size(self.iter())
+    }
+}
+
+impl<K: Serialize, V: Serialize, S, const N: usize> Serialize for IndexMap<K, V, S, N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        serialize_entries(self.len(), self.iter(), buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        entries_size(self.iter())
+    }
+}
+
+fn serialize_entries<'a, K: Serialize + 'a, V: Serialize + 'a>(
+    len: usize,
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    let count = u16::try_from(len).map_err(|_| SerializeError::InvalidData)?;
+    let mut offset = count.serialize(buf)?;
+    for (key, value) in entries {
+        let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+        offset += key.serialize(rest)?;
+        let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+        offset += value.serialize(rest)?;
+    }
+    Ok(offset)
+}
+
+fn entries_size<'a, K: Serialize + 'a, V: Serialize + 'a>(
+    entries: impl Iterator<Item = (&'a K, &'a V)>,
+) -> usize {
+    2 + entries
+        .map(|(key, value)| key.serialized_size() + value.serialized_size())
+        .sum::<usize>()
+}
+
+/// Later entries replace earlier ones with the same key;
+/// see `deserialize_linear_map_strict` to reject them instead.
+impl<K: Deserialize + Eq, V: Deserialize, const N: usize> Deserialize for LinearMap<K, V, N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(map, _)| map)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        deserialize_linear_map(buf, false)
+    }
+}
+
+/// Deserializes a map like its `Deserialize` impl, but fails with `InvalidData` if a key repeats.
+/// Returns the map and the number of bytes consumed.
+pub fn deserialize_linear_map_strict<K: Deserialize + Eq, V: Deserialize, const N: usize>(
+    buf: &[u8],
+) -> Result<(LinearMap<K, V, N>, usize), DeserializeError> {
+    deserialize_linear_map(buf, true)
+}
+
+fn deserialize_linear_map<K: Deserialize + Eq, V: Deserialize, const N: usize>(
+    buf: &[u8],
+    strict: bool,
+) -> Result<(LinearMap<K, V, N>, usize), DeserializeError> {
+    let mut map = LinearMap::new();
+    let size = deserialize_entries(buf, N, strict, |key, value| {
+        map.insert(key, value).map_err(|_| ())
+    })?;
+    Ok((map, size))
+}
+
+/// Later entries replace earlier ones with the same key;
+/// see `deserialize_index_map_strict` to reject them instead.
+impl<K, V, S, const N: usize> Deserialize for IndexMap<K, V, S, N>
+where
+    K: Deserialize + Eq + Hash,
+    V: Deserialize,
+    S: BuildHasher + Default,
+{
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(map, _)| map)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        deserialize_index_map(buf, false)
+    }
+}
+
+/// Deserializes a map like its `Deserialize` impl, but fails with `InvalidData` if a key repeats.
+/// Returns the map and the number of bytes consumed.
+pub fn deserialize_index_map_strict<K, V, S, const N: usize>(
+    buf: &[u8],
+) -> Result<(IndexMap<K, V, S, N>, usize), DeserializeError>
+where
+    K: Deserialize + Eq + Hash,
+    V: Deserialize,
+    S: BuildHasher + Default,
+{
+    deserialize_index_map(buf, true)
+}
+
+fn deserialize_index_map<K, V, S, const N: usize>(
+    buf: &[u8],
+    strict: bool,
+) -> Result<(IndexMap<K, V, S, N>, usize), DeserializeError>
+where
+    K: Deserialize + Eq + Hash,
+    V: Deserialize,
+    S: BuildHasher + Default,
+{
+    let mut map = IndexMap::default();
+    let size = deserialize_entries(buf, N, strict, |key, value| {
+        map.insert(key, value).map_err(|_| ())
+    })?;
+    Ok((map, size))
+}
+
+/// Reads up to `capacity` entries, passing each to `insert`, which returns the value it
+/// replaced. Returns the number of bytes consumed.
+fn deserialize_entries<K: Deserialize, V: Deserialize>(
+    buf: &[u8],
+    capacity: usize,
+    strict: bool,
+    mut insert: impl FnMut(K, V) -> Result<Option<V>, ()>,
+) -> Result<usize, DeserializeError> {
+    let (count, mut offset) = u16::deserialize_consumed(buf)?;
+    if count as usize > capacity {
+        return Err(DeserializeError::InvalidData);
+    }
+    for _ in 0..count {
+        let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+        let (key, size) = K::deserialize_consumed(rest)?;
+        offset += size;
+        let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+        let (value, size) = V::deserialize_consumed(rest)?;
+        offset += size;
+        // Fewer distinct keys than `count <= capacity` always fit.
+        let replaced = insert(key, value).map_err(|_| DeserializeError::InvalidData)?;
+        if strict && replaced.is_some() {
+            return Err(DeserializeError::InvalidData);
+        }
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::FnvIndexMap;
+
+    #[test]
+    fn two_entry_map_round_trip() {
+        let mut map = LinearMap::<u8, u16, 4>::new();
+        map.insert(1, 0x0102).unwrap();
+        map.insert(7, 0x0304).unwrap();
+        let mut buf = [0u8; 16];
+        let size = map.serialize(&mut buf).unwrap();
+        assert_eq!(buf[..size], [0, 2, 1, 0x01, 0x02, 7, 0x03, 0x04]);
+        assert_eq!(map.serialized_size(), size);
+        assert_eq!(LinearMap::<u8, u16, 4>::deserialize_consumed(&buf).unwrap(), (map, size));
+    }
+
+    #[test]
+    fn count_over_capacity_is_rejected() {
+        let buf = [0, 3, 1, 10, 2, 20, 3, 30];
+        assert!(matches!(LinearMap::<u8, u8, 2>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        assert_eq!(LinearMap::<u8, u8, 3>::deserialize(&buf).unwrap().len(), 3);
+        assert!(matches!(LinearMap::<u8, u8, 3>::deserialize(&buf[..7]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn duplicate_keys_replace_unless_strict() {
+        let buf = [0, 2, 5, 10, 5, 20];
+        let map = LinearMap::<u8, u8, 2>::deserialize(&buf).unwrap();
+        assert_eq!((map.len(), map.get(&5)), (1, Some(&20)));
+        assert!(matches!(deserialize_linear_map_strict::<u8, u8, 2>(&buf), Err(DeserializeError::InvalidData)));
+    }
+
+    #[test]
+    fn index_map_round_trip() {
+        let mut map = FnvIndexMap::<u8, u16, 4>::new();
+        map.insert(1, 0x0102).unwrap();
+        map.insert(7, 0x0304).unwrap();
+        let mut buf = [0u8; 16];
+        let size = map.serialize(&mut buf).unwrap();
+        assert_eq!(buf[..size], [0, 2, 1, 0x01, 0x02, 7, 0x03, 0x04]);
+        assert_eq!(map.serialized_size(), size);
+        assert_eq!(FnvIndexMap::<u8, u16, 4>::deserialize_consumed(&buf).unwrap(), (map, size));
+    }
+
+    #[test]
+    fn index_map_count_and_duplicates_are_checked() {
+        let buf = [0, 3, 1, 10, 2, 20, 3, 30];
+        assert!(matches!(FnvIndexMap::<u8, u8, 2>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        assert_eq!(FnvIndexMap::<u8, u8, 4>::deserialize(&buf).unwrap().len(), 3);
+        assert!(matches!(FnvIndexMap::<u8, u8, 4>::deserialize(&buf[..7]), Err(DeserializeError::BufferTooSmall)));
+
+        let buf = [0, 2, 5, 10, 5, 20];
+        let map = FnvIndexMap::<u8, u8, 2>::deserialize(&buf).unwrap();
+        assert_eq!((map.len(), map.get(&5)), (1, Some(&20)));
+        let strict: Result<(FnvIndexMap<u8, u8, 2>, usize), _> = deserialize_index_map_strict(&buf);
+        assert!(matches!(strict, Err(DeserializeError::InvalidData)));
+    }
+
+    #[test]
+    fn linear_and_index_maps_are_interchangeable() {
+        let mut linear = LinearMap::<u8, u16, 8>::new();
+        for (key, value) in [(3, 300), (1, 100), (2, 250)] {
+            linear.insert(key, value).unwrap();
+        }
+        let mut buf = [0u8; 16];
+        let size = linear.serialize(&mut buf).unwrap();
+        let index = FnvIndexMap::<u8, u16, 8>::deserialize(&buf[..size]).unwrap();
+        assert!(index.iter().eq(linear.iter()));
+
+        let mut again = [0u8; 16];
+        assert_eq!(index.serialize(&mut again).unwrap(), size);
+        assert_eq!(again, buf);
+        assert_eq!(LinearMap::<u8, u16, 8>::deserialize(&again[..size]).unwrap(), linear);
+    }
+}
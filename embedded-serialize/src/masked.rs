@@ -0,0 +1,103 @@
+//! Arrays of optional values written as a validity bitmap followed by only the present values.
+//!
+//! The bitmap takes `ceil(N / 8)` bytes; bit `i % 8` (least significant first) of byte
+//! `i / 8` is set when element `i` is present. Unused trailing bits must be zero.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Serializes `items` as a validity bitmap followed by the present values in order.
+pub fn serialize_masked<T: Serialize, const N: usize>(
+    items: &[Option<T>; N],
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    let mask_len = N.div_ceil(8);
+    let mask = buf.get_mut(..mask_len).ok_or(SerializeError::BufferTooSmall)?;
+    mask.fill(0);
+    for (index, item) in items.iter().enumerate() {
+        if item.is_some() {
+            mask[index / 8] |= 1 << (index % 8);
+        }
+    }
+    let mut offset = mask_len;
+    for item in items.iter().flatten() {
+        let rest = buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?;
+        offset += item.serialize(rest)?;
+    }
+    Ok(offset)
+}
+
+/// Deserializes a validity bitmap and the present values, leaving absent elements `None`.
+/// Returns the array and the number of bytes consumed.
+pub fn deserialize_masked<T: Deserialize, const N: usize>(
+    buf: &[u8],
+) -> Result<([Option<T>; N], usize), DeserializeError> {
+    let mask_len = N.div_ceil(8);
+    let mask = buf.get(..mask_len).ok_or(DeserializeError::BufferTooSmall)?;
+    if !N.is_multiple_of(8) && mask[mask_len - 1] >> (N % 8) != 0 {
+        return Err(DeserializeError::InvalidData);
+    }
+    let mut items: [Option<T>; N] = core::array::from_fn(|_| None);
+    let mut offset = mask_len;
+    for (index, slot) in items.iter_mut().enumerate() {
+        if mask[index / 8] & (1 << (index % 8)) != 0 {
+            let rest = buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?;
+            let (item, size) = T::deserialize_consumed(rest)?;
+            *slot = Some(item);
+            offset += size;
+        }
+    }
+    Ok((items, offset))
+}
+
+/// An array of optional values using the bitmap encoding, usable as a derived struct field
+#[derive(Debug, Clone, PartialEq)]
+pub struct Masked<T, const N: usize>(pub [Option<T>; N]);
+
+impl<T: Serialize, const N: usize> Serialize for Masked<T, N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        serialize_masked(&self.0, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        N.div_ceil(8) + self.0.iter().flatten().map(Serialize::serialized_size).sum::<usize>()
+    }
+}
+
+impl<T: Deserialize, const N: usize> Deserialize for Masked<T, N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(masked, _)| masked)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        deserialize_masked(buf).map(|(items, size)| (Masked(items), size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_0_2_5_valid() {
+        let mut readings = [None; 8];
+        readings[0] = Some(1.0f32);
+        readings[2] = Some(-2.5);
+        readings[5] = Some(0.5);
+        let mut buf = [0u8; 16];
+        assert_eq!(serialize_masked(&readings, &mut buf).unwrap(), 13);
+        assert_eq!(buf[0], 0b0010_0101);
+        assert_eq!(buf[1..5], 1.0f32.to_be_bytes());
+        assert_eq!(buf[5..9], (-2.5f32).to_be_bytes());
+        assert_eq!(buf[9..13], 0.5f32.to_be_bytes());
+
+        assert_eq!(deserialize_masked::<f32, 8>(&buf).unwrap(), (readings, 13));
+        assert_eq!(Masked::<f32, 8>::deserialize(&buf).unwrap(), Masked(readings));
+    }
+
+    #[test]
+    fn unused_mask_bits_and_short_buffers_fail() {
+        assert!(matches!(deserialize_masked::<u8, 3>(&[0b1000]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(deserialize_masked::<u8, 3>(&[0b011, 7]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(serialize_masked(&[Some(1u16)], &mut [0u8; 2]), Err(SerializeError::BufferTooSmall)));
+    }
+}
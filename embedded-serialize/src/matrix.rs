@@ -0,0 +1,85 @@
+//! Small `f32` matrices, such as transforms, written with their shape so that a receiver
+//! expecting another shape rejects them instead of misreading the elements.
+//!
+//! The wire format is a `u8` row count, a `u8` column count, then the elements in
+//! row-major order as big-endian IEEE 754 bits.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// An `R`x`C` matrix of `f32`, stored row-major.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix<const R: usize, const C: usize>(pub [[f32; C]; R]);
+
+impl<const R: usize, const C: usize> Matrix<R, C> {
+    /// Serialized size: the shape followed by `R * C` elements
+    pub const SIZE: usize = 2 + 4 * R * C;
+
+    /// The shape as written on the wire, or `None` if a dimension does not fit in a `u8`.
+    fn shape() -> Option<[u8; 2]> {
+        Some([u8::try_from(R).ok()?, u8::try_from(C).ok()?])
+    }
+}
+
+impl<const R: usize, const C: usize> Serialize for Matrix<R, C> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let shape = Self::shape().ok_or(SerializeError::InvalidData)?;
+        let dst = buf.get_mut(..Self::SIZE).ok_or(SerializeError::BufferTooSmall)?;
+        dst[..2].copy_from_slice(&shape);
+        for (chunk, value) in dst[2..].chunks_exact_mut(4).zip(self.0.iter().flatten()) {
+            chunk.copy_from_slice(&value.to_bits().to_be_bytes());
+        }
+        Ok(Self::SIZE)
+    }
+
+    fn serialized_size(&self) -> usize {
+        Self::SIZE
+    }
+}
+
+impl<const R: usize, const C: usize> Deserialize for Matrix<R, C> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(matrix, _)| matrix)
+    }
+
+    /// Fails with `InvalidData` if the shape on the wire is not `R`x`C`.
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let shape = buf.get(..2).ok_or(DeserializeError::BufferTooSmall)?;
+        if Self::shape() != Some([shape[0], shape[1]]) {
+            return Err(DeserializeError::InvalidData);
+        }
+        let src = buf.get(2..Self::SIZE).ok_or(DeserializeError::BufferTooSmall)?;
+        let mut matrix = [[0.0; C]; R];
+        for (value, chunk) in matrix.iter_mut().flatten().zip(src.chunks_exact(4)) {
+            *value = f32::from_bits(u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        }
+        Ok((Matrix(matrix), Self::SIZE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROTATION: Matrix<3, 3> = Matrix([[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.5]]);
+
+    #[test]
+    fn three_by_three_round_trip() {
+        let mut buf = [0u8; 40];
+        assert_eq!(ROTATION.serialize(&mut buf).unwrap(), 38);
+        assert_eq!(buf[..2], [3, 3]);
+        // Row-major: the second element is row 0, column 1.
+        assert_eq!(buf[6..10], (-1.0f32).to_be_bytes());
+        assert_eq!(buf[34..38], 1.5f32.to_be_bytes());
+        assert_eq!(Matrix::<3, 3>::deserialize_consumed(&buf).unwrap(), (ROTATION, 38));
+    }
+
+    #[test]
+    fn wrong_shape_header_is_rejected() {
+        let mut buf = [0u8; 40];
+        Matrix([[1.0f32; 3]; 2]).serialize(&mut buf).unwrap();
+        assert!(matches!(Matrix::<3, 2>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        assert!(matches!(Matrix::<2, 3>::deserialize(&buf[..25]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(ROTATION.serialize(&mut buf[..37]), Err(SerializeError::BufferTooSmall)));
+        assert!(matches!(Matrix([[0.0f32; 0]; 256]).serialize(&mut buf), Err(SerializeError::InvalidData)));
+    }
+}
@@ -0,0 +1,291 @@
+//! Error codes that fit on the wire, for telling a sender why its message was rejected.
+//!
+//! Codes below `0x80` belong to this crate and are assigned to `SerializeError` and
+//! `DeserializeError` variants as listed on `ErrorCode`. The assignment is append-only:
+//! a code keeps its meaning forever and new variants take the next free code, so peers
+//! built against different versions agree on every code they both know.
+//! Codes from `0x80` up are left to the application.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A one-byte reason for rejecting a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// `0x01`: `BufferTooSmall` and `BufferTooSmallAt`
+    BufferTooSmall,
+    /// `0x02`: `InvalidData` and `InvalidDataAt`
+    InvalidData,
+    /// `0x03`: `DuplicateTag`
+    DuplicateTag,
+    /// `0x04`: `MissingTag`
+    MissingTag,
+    /// `0x05`: `Misaligned`
+    Misaligned,
+    /// `0x06`: `ChecksumMismatch`
+    ChecksumMismatch,
+    /// `0x07`: `Uncorrectable`
+    Uncorrectable,
+    /// `0x08`: `UnknownLabel`
+    UnknownLabel,
+    /// `0x09`: `Custom`, whose message stays on the device
+    Custom,
+    /// `0x0A`: `OverrunAt`
+    Overrun,
+    /// `0x0B`: `UnknownDiscriminant`
+    UnknownDiscriminant,
+    /// `0x0C`: `InvalidValue`
+    InvalidValue,
+    /// `0x0D`: `BadMagic`
+    BadMagic,
+    /// `0x0E`: `VersionTooOld`
+    VersionTooOld,
+    /// A code below `0x80` that this build does not know, such as one added by a newer peer
+    Reserved(u8),
+    /// An application-defined code, `0x80` or above
+    Application(u8),
+}
+
+/// First code left to the application
+pub const APPLICATION_CODES: u8 = 0x80;
+
+impl ErrorCode {
+    /// The code on the wire.
+    /// `Reserved` and `Application` values outside their ranges have none.
+    pub fn code(self) -> Option<u8> {
+        let code = match self {
+            ErrorCode::BufferTooSmall => 0x01,
+            ErrorCode::InvalidData => 0x02,
+            ErrorCode::DuplicateTag => 0x03,
+            ErrorCode::MissingTag => 0x04,
+            ErrorCode::Misaligned => 0x05,
+            ErrorCode::ChecksumMismatch => 0x06,
+            ErrorCode::Uncorrectable => 0x07,
+            ErrorCode::UnknownLabel => 0x08,
+            ErrorCode::Custom => 0x09,
+            ErrorCode::Overrun => 0x0A,
+            ErrorCode::UnknownDiscriminant => 0x0B,
+            ErrorCode::InvalidValue => 0x0C,
+            ErrorCode::BadMagic => 0x0D,
+            ErrorCode::VersionTooOld => 0x0E,
+            ErrorCode::Reserved(code) if Self::from_code(code) == self => code,
+            ErrorCode::Application(code) if code >= APPLICATION_CODES => code,
+            ErrorCode::Reserved(_) | ErrorCode::Application(_) => return None,
+        };
+        Some(code)
+    }
+
+    /// The error code for a byte received on the wire.
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0x01 => ErrorCode::BufferTooSmall,
+            0x02 => ErrorCode::InvalidData,
+            0x03 => ErrorCode::DuplicateTag,
+            0x04 => ErrorCode::MissingTag,
+            0x05 => ErrorCode::Misaligned,
+            0x06 => ErrorCode::ChecksumMismatch,
+            0x07 => ErrorCode::Uncorrectable,
+            0x08 => ErrorCode::UnknownLabel,
+            0x09 => ErrorCode::Custom,
+            0x0A => ErrorCode::Overrun,
+            0x0B => ErrorCode::UnknownDiscriminant,
+            0x0C => ErrorCode::InvalidValue,
+            0x0D => ErrorCode::BadMagic,
+            0x0E => ErrorCode::VersionTooOld,
+            code if code >= APPLICATION_CODES => ErrorCode::Application(code),
+            code => ErrorCode::Reserved(code),
+        }
+    }
+}
+
+impl From<SerializeError> for ErrorCode {
+    fn from(err: SerializeError) -> Self {
+        match err {
+            SerializeError::BufferTooSmall | SerializeError::BufferTooSmallAt(_) => ErrorCode::BufferTooSmall,
+            SerializeError::InvalidData => ErrorCode::InvalidData,
+            SerializeError::OverrunAt(_) => ErrorCode::Overrun,
+            SerializeError::Custom(_) => ErrorCode::Custom,
+        }
+    }
+}
+
+impl From<DeserializeError> for ErrorCode {
+    fn from(err: DeserializeError) -> Self {
+        match err {
+            DeserializeError::BufferTooSmall => ErrorCode::BufferTooSmall,
+            DeserializeError::InvalidData | DeserializeError::InvalidDataAt(_) => ErrorCode::InvalidData,
+            DeserializeError::DuplicateTag(_) => ErrorCode::DuplicateTag,
+            DeserializeError::MissingTag(_) => ErrorCode::MissingTag,
+            DeserializeError::Misaligned => ErrorCode::Misaligned,
+            DeserializeError::ChecksumMismatch => ErrorCode::ChecksumMismatch,
+            DeserializeError::Uncorrectable => ErrorCode::Uncorrectable,
+            DeserializeError::UnknownLabel => ErrorCode::UnknownLabel,
+            DeserializeError::UnknownDiscriminant { .. } => ErrorCode::UnknownDiscriminant,
+            DeserializeError::InvalidValue(_) => ErrorCode::InvalidValue,
+            DeserializeError::BadMagic(_) => ErrorCode::BadMagic,
+            DeserializeError::VersionTooOld(_) => ErrorCode::VersionTooOld,
+            DeserializeError::Custom(_) => ErrorCode::Custom,
+        }
+    }
+}
+
+/// Fails with `InvalidData` for a value that has no code.
+impl Serialize for ErrorCode {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.code().ok_or(SerializeError::InvalidData)?.serialize(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        1
+    }
+}
+
+impl Deserialize for ErrorCode {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(code, _)| code)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (code, size) = u8::deserialize_consumed(buf)?;
+        Ok((ErrorCode::from_code(code), size))
+    }
+}
+
+/// Negative acknowledgement: the reason a message was rejected, and a detail whose
+/// meaning depends on the code.
+/// Converted from a `DeserializeError`, the detail is the tag of `DuplicateTag` and
+/// `MissingTag`, the index of `InvalidDataAt`, the value of `UnknownDiscriminant` and
+/// `InvalidValue`, the bytes found by `BadMagic` (saturated) and the version of
+/// `VersionTooOld`, and 0 otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nack {
+    pub code: ErrorCode,
+    pub detail: u16,
+}
+
+impl From<DeserializeError> for Nack {
+    fn from(err: DeserializeError) -> Self {
+        let detail = match err {
+            DeserializeError::DuplicateTag(tag) | DeserializeError::MissingTag(tag) => tag as u16,
+            DeserializeError::VersionTooOld(version) => version as u16,
+            DeserializeError::InvalidDataAt(index) => u16::try_from(index).unwrap_or(u16::MAX),
+            DeserializeError::UnknownDiscriminant { value, .. }
+            | DeserializeError::InvalidValue(value)
+            | DeserializeError::BadMagic(value) => {
+                u16::try_from(value).unwrap_or(u16::MAX)
+            }
+            _ => 0,
+        };
+        Nack {
+            code: err.into(),
+            detail,
+        }
+    }
+}
+
+impl Serialize for Nack {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let dst = buf.get_mut(..3).ok_or(SerializeError::BufferTooSmall)?;
+        let size = self.code.serialize(dst)?;
+        Ok(size + self.detail.serialize(&mut dst[size..])?)
+    }
+
+    fn serialized_size(&self) -> usize {
+        3
+    }
+}
+
+impl Deserialize for Nack {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(nack, _)| nack)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (code, size) = ErrorCode::deserialize_consumed(buf)?;
+        let (detail, detail_size) = u16::deserialize_consumed(&buf[size..])?;
+        Ok((Nack { code, detail }, size + detail_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_of<E: Into<ErrorCode>>(err: E) -> u8 {
+        err.into().code().unwrap()
+    }
+
+    #[test]
+    fn every_serialize_error_has_its_code() {
+        assert_eq!(code_of(SerializeError::BufferTooSmall), 0x01);
+        assert_eq!(code_of(SerializeError::BufferTooSmallAt(3)), 0x01);
+        assert_eq!(code_of(SerializeError::InvalidData), 0x02);
+        assert_eq!(code_of(SerializeError::Custom("full")), 0x09);
+        assert_eq!(code_of(SerializeError::OverrunAt(1)), 0x0A);
+    }
+
+    #[test]
+    fn every_deserialize_error_has_its_code() {
+        assert_eq!(code_of(DeserializeError::BufferTooSmall), 0x01);
+        assert_eq!(code_of(DeserializeError::InvalidData), 0x02);
+        assert_eq!(code_of(DeserializeError::InvalidDataAt(4)), 0x02);
+        assert_eq!(code_of(DeserializeError::DuplicateTag(1)), 0x03);
+        assert_eq!(code_of(DeserializeError::MissingTag(1)), 0x04);
+        assert_eq!(code_of(DeserializeError::Misaligned), 0x05);
+        assert_eq!(code_of(DeserializeError::ChecksumMismatch), 0x06);
+        assert_eq!(code_of(DeserializeError::Uncorrectable), 0x07);
+        assert_eq!(code_of(DeserializeError::UnknownLabel), 0x08);
+        assert_eq!(code_of(DeserializeError::Custom("bad")), 0x09);
+        assert_eq!(code_of(DeserializeError::unknown_discriminant("Command", 7)), 0x0B);
+        assert_eq!(code_of(DeserializeError::InvalidValue(2)), 0x0C);
+        assert_eq!(code_of(DeserializeError::BadMagic(0xCAFE)), 0x0D);
+        assert_eq!(code_of(DeserializeError::VersionTooOld(1)), 0x0E);
+    }
+
+    #[test]
+    fn every_byte_maps_back_to_itself() {
+        for code in 0..=u8::MAX {
+            let error = ErrorCode::from_code(code);
+            assert_eq!(error.code(), Some(code));
+            assert_eq!(matches!(error, ErrorCode::Application(_)), code >= APPLICATION_CODES);
+        }
+        assert_eq!(ErrorCode::Reserved(0x02).code(), None);
+        assert_eq!(ErrorCode::Application(0x10).code(), None);
+        assert!(matches!(ErrorCode::Application(0x10).serialize(&mut [0u8; 1]), Err(SerializeError::InvalidData)));
+    }
+
+    #[test]
+    fn nack_round_trip() {
+        let nack = Nack::from(DeserializeError::unknown_discriminant("Command", 0x0123));
+        assert_eq!(nack, Nack { code: ErrorCode::UnknownDiscriminant, detail: 0x0123 });
+        let mut buf = [0u8; 4];
+        assert_eq!(nack.serialize(&mut buf).unwrap(), 3);
+        assert_eq!(buf[..3], [0x0B, 0x01, 0x23]);
+        assert_eq!(Nack::deserialize_consumed(&buf).unwrap(), (nack, 3));
+        assert!(matches!(Nack::deserialize(&buf[..2]), Err(DeserializeError::BufferTooSmall)));
+
+        let nack = Nack { code: ErrorCode::Application(0x90), detail: 7 };
+        nack.serialize(&mut buf).unwrap();
+        assert_eq!(Nack::deserialize(&buf).unwrap(), nack);
+    }
+
+    #[test]
+    fn nack_details() {
+        assert_eq!(Nack::from(DeserializeError::MissingTag(5)).detail, 5);
+        assert_eq!(Nack::from(DeserializeError::InvalidDataAt(70_000)).detail, u16::MAX);
+        assert_eq!(Nack::from(DeserializeError::BadMagic(0x1_0000)).detail, u16::MAX);
+        assert_eq!(Nack::from(DeserializeError::VersionTooOld(2)).detail, 2);
+        assert_eq!(Nack::from(DeserializeError::ChecksumMismatch).detail, 0);
+    }
+
+    #[test]
+    fn codec_decode_failure_becomes_a_nack() {
+        use crate::codec::{Codec, FramedCrcCodec};
+
+        let mut codec = FramedCrcCodec::<u16, u16, 32>::new();
+        let mut frame = [0u8; 16];
+        let size = codec.encode(&0x0102, &mut frame).unwrap();
+        frame[size / 2] ^= 0x01;
+        let err = codec.feed(&frame[..size]).unwrap_err();
+        assert_eq!(Nack::from(err).code, ErrorCode::ChecksumMismatch);
+    }
+}
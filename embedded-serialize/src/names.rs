@@ -0,0 +1,19 @@
+//! Variant name tables for enums, for turning wire tags into readable diagnostics.
+
+/// Names and tags of an enum's variants.
+/// Implemented by `#[derive(Deserialize)]` on enums when the derive's `names` feature is enabled.
+pub trait VariantNames {
+    /// Every variant's name with its wire tag, in declaration order
+    const VARIANT_NAMES: &'static [(&'static str, u32)];
+
+    /// Name of the variant `self` is.
+    fn variant_name(&self) -> &'static str;
+
+    /// Name of the variant with tag `discriminant`, or `None` if no variant has it.
+    fn name_of_discriminant(discriminant: u32) -> Option<&'static str> {
+        Self::VARIANT_NAMES
+            .iter()
+            .find(|(_, tag)| *tag == discriminant)
+            .map(|(name, _)| *name)
+    }
+}
@@ -0,0 +1,131 @@
+//! IP addresses, written as their octets in network order.
+//!
+//! An `IpAddr` is a tag byte, 4 for IPv4 or 6 for IPv6, followed by the address.
+
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+const TAG_V4: u8 = 4;
+const TAG_V6: u8 = 6;
+
+impl Serialize for Ipv4Addr {
+    fn serialized_size(&self) -> usize {
+        4
+    }
+
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.octets().serialize(buf)
+    }
+}
+
+impl Deserialize for Ipv4Addr {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(addr, _)| addr)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (octets, size) = <[u8; 4]>::deserialize_consumed(buf)?;
+        Ok((Ipv4Addr::from(octets), size))
+    }
+}
+
+impl Serialize for Ipv6Addr {
+    fn serialized_size(&self) -> usize {
+        16
+    }
+
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.octets().serialize(buf)
+    }
+}
+
+impl Deserialize for Ipv6Addr {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(addr, _)| addr)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (octets, size) = <[u8; 16]>::deserialize_consumed(buf)?;
+        Ok((Ipv6Addr::from(octets), size))
+    }
+}
+
+impl Serialize for IpAddr {
+    fn serialized_size(&self) -> usize {
+        match self {
+            IpAddr::V4(_) => 1 + 4,
+            IpAddr::V6(_) => 1 + 16,
+        }
+    }
+
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let (tag, body) = buf.split_first_mut().ok_or(SerializeError::BufferTooSmall)?;
+        let size = match self {
+            IpAddr::V4(addr) => {
+                *tag = TAG_V4;
+                addr.serialize(body)?
+            }
+            IpAddr::V6(addr) => {
+                *tag = TAG_V6;
+                addr.serialize(body)?
+            }
+        };
+        Ok(1 + size)
+    }
+}
+
+/// Fails with `UnknownDiscriminant` on a tag other than 4 or 6.
+impl Deserialize for IpAddr {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(addr, _)| addr)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (&tag, body) = buf.split_first().ok_or(DeserializeError::BufferTooSmall)?;
+        let (addr, size) = match tag {
+            TAG_V4 => Ipv4Addr::deserialize_consumed(body).map(|(addr, size)| (IpAddr::V4(addr), size))?,
+            TAG_V6 => Ipv6Addr::deserialize_consumed(body).map(|(addr, size)| (IpAddr::V6(addr), size))?,
+            tag => return Err(DeserializeError::unknown_discriminant("IpAddr", tag as u32)),
+        };
+        Ok((addr, 1 + size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v4_round_trip() {
+        let addr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+        let mut buf = [0u8; 17];
+        assert_eq!(addr.serialize(&mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], [4, 192, 168, 1, 20]);
+        assert_eq!(addr.serialized_size(), 5);
+        assert_eq!(IpAddr::deserialize_consumed(&buf).unwrap(), (addr, 5));
+    }
+
+    #[test]
+    fn v6_round_trip() {
+        let addr = IpAddr::V6(Ipv6Addr::new(0xFE80, 0, 0, 0, 0x0202, 0xB3FF, 0xFE1E, 0x8329));
+        let mut buf = [0u8; 17];
+        assert_eq!(addr.serialize(&mut buf).unwrap(), 17);
+        assert_eq!(buf[..3], [6, 0xFE, 0x80]);
+        assert_eq!(buf[15..], [0x83, 0x29]);
+        assert_eq!(addr.serialized_size(), 17);
+        assert_eq!(IpAddr::deserialize_consumed(&buf).unwrap(), (addr, 17));
+    }
+
+    #[test]
+    fn invalid_tag_and_short_buffers_are_rejected() {
+        assert!(matches!(
+            IpAddr::deserialize(&[5, 10, 0, 0, 1]),
+            Err(DeserializeError::UnknownDiscriminant { value: 5, .. })
+        ));
+        assert!(matches!(IpAddr::deserialize(&[6, 0, 0, 0, 0]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(IpAddr::deserialize(&[]), Err(DeserializeError::BufferTooSmall)));
+        let addr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert!(matches!(addr.serialize(&mut [0u8; 4]), Err(SerializeError::BufferTooSmall)));
+    }
+}
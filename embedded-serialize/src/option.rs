@@ -0,0 +1,231 @@
+//! Alternative wire encodings for `Option`.
+//!
+//! The `Option` impls write a presence byte before the value. Protocols that reserve one
+//! value of the type to mean "absent" can use `Sentinel` instead and save that byte,
+//! either through the `SentinelOption` wrapper or the derive field attribute
+//! `#[es(option = "sentinel", sentinel = 0xFFFF)]`.
+
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A way of writing `Option<T>` on the wire
+pub trait OptionEncoding<T> {
+    /// Serializes `value` into the provided buffer.
+    /// Returns the number of bytes written.
+    fn serialize_option(value: &Option<T>, buf: &mut [u8]) -> Result<usize, SerializeError>;
+
+    /// Serializes `value` into a buffer that may not be initialized yet.
+    /// Returns the written prefix of `buf`; every byte in it is initialized.
+    /// The default zeroes the `serialized_size_option` bytes it needs first, like
+    /// `Serialize::serialize_uninit`.
+    fn serialize_option_uninit<'a>(
+        value: &Option<T>,
+        buf: &'a mut [MaybeUninit<u8>],
+    ) -> Result<&'a mut [u8], SerializeError> {
+        crate::serialize_zeroed(Self::serialized_size_option(value), buf, |buf| Self::serialize_option(value, buf))
+    }
+
+    /// Number of bytes `serialize_option` writes for `value`.
+    fn serialized_size_option(value: &Option<T>) -> usize;
+
+    /// Deserializes an optional value from the provided buffer.
+    /// Returns the value and the number of bytes consumed.
+    fn deserialize_option(buf: &[u8]) -> Result<(Option<T>, usize), DeserializeError>;
+}
+
+/// A presence byte (0 or 1) followed by the value when present, as in the `Option` impls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PresenceByte;
+
+impl<T: Serialize + Deserialize> OptionEncoding<T> for PresenceByte {
+    fn serialize_option(value: &Option<T>, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        value.serialize(buf)
+    }
+
+    fn serialize_option_uninit<'a>(
+        value: &Option<T>,
+        buf: &'a mut [MaybeUninit<u8>],
+    ) -> Result<&'a mut [u8], SerializeError> {
+        value.serialize_uninit(buf)
+    }
+
+    fn serialized_size_option(value: &Option<T>) -> usize {
+        value.serialized_size()
+    }
+
+    fn deserialize_option(buf: &[u8]) -> Result<(Option<T>, usize), DeserializeError> {
+        Option::<T>::deserialize_consumed(buf)
+    }
+}
+
+/// The value alone, with `S` standing for `None`.
+/// `S` is converted to `T` by value; a sentinel `T` cannot hold is rejected with `InvalidData`,
+/// as is `Some(S)`, which would otherwise decode as `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sentinel<const S: u64>;
+
+impl<const S: u64> Sentinel<S> {
+    fn value<T: TryFrom<u64>>() -> Option<T> {
+        T::try_from(S).ok()
+    }
+}
+
+impl<T, const S: u64> OptionEncoding<T> for Sentinel<S>
+where
+    T: Serialize + Deserialize + PartialEq + TryFrom<u64>,
+{
+    fn serialize_option(value: &Option<T>, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let sentinel = Self::value::<T>().ok_or(SerializeError::InvalidData)?;
+        match value {
+            None => sentinel.serialize(buf),
+            Some(value) if *value == sentinel => Err(SerializeError::InvalidData),
+            Some(value) => value.serialize(buf),
+        }
+    }
+
+    fn serialize_option_uninit<'a>(
+        value: &Option<T>,
+        buf: &'a mut [MaybeUninit<u8>],
+    ) -> Result<&'a mut [u8], SerializeError> {
+        let sentinel = Self::value::<T>().ok_or(SerializeError::InvalidData)?;
+        match value {
+            None => sentinel.serialize_uninit(buf),
+            Some(value) if *value == sentinel => Err(SerializeError::InvalidData),
+            Some(value) => value.serialize_uninit(buf),
+        }
+    }
+
+    /// The size of the sentinel for `None`, or 0 if `T` cannot hold it.
+    fn serialized_size_option(value: &Option<T>) -> usize {
+        match value {
+            Some(value) => value.serialized_size(),
+            None => Self::value::<T>().map_or(0, |sentinel| sentinel.serialized_size()),
+        }
+    }
+
+    fn deserialize_option(buf: &[u8]) -> Result<(Option<T>, usize), DeserializeError> {
+        let sentinel = Self::value::<T>().ok_or(DeserializeError::InvalidData)?;
+        let (value, size) = T::deserialize_consumed(buf)?;
+        if value == sentinel {
+            Ok((None, size))
+        } else {
+            Ok((Some(value), size))
+        }
+    }
+}
+
+/// An `Option` written with the encoding `E`
+pub struct EncodedOption<T, E> {
+    pub value: Option<T>,
+    encoding: PhantomData<E>,
+}
+
+impl<T, E> EncodedOption<T, E> {
+    pub fn new(value: Option<T>) -> Self {
+        EncodedOption {
+            value,
+            encoding: PhantomData,
+        }
+    }
+}
+
+/// An `Option` written as the value alone, with `S` standing for `None`
+pub type SentinelOption<T, const S: u64> = EncodedOption<T, Sentinel<S>>;
+
+impl<T: Clone, E> Clone for EncodedOption<T, E> {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<T: Copy, E> Copy for EncodedOption<T, E> {}
+
+impl<T: PartialEq, E> PartialEq for EncodedOption<T, E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq, E> Eq for EncodedOption<T, E> {}
+
+impl<T: core::fmt::Debug, E> core::fmt::Debug for EncodedOption<T, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T, E> From<Option<T>> for EncodedOption<T, E> {
+    fn from(value: Option<T>) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T, E: OptionEncoding<T>> Serialize for EncodedOption<T, E> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        E::serialize_option(&self.value, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        E::serialized_size_option(&self.value)
+    }
+}
+
+impl<T, E: OptionEncoding<T>> Deserialize for EncodedOption<T, E> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(value, _)| value)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        E::deserialize_option(buf).map(|(value, size)| (Self::new(value), size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Reading = SentinelOption<u16, 0xFFFF>;
+
+    #[test]
+    fn presence_byte_round_trip() {
+        let mut buf = [0u8; 3];
+        assert_eq!(PresenceByte::serialize_option(&Some(0x0102u16), &mut buf).unwrap(), 3);
+        assert_eq!(buf, [1, 0x01, 0x02]);
+        assert_eq!(PresenceByte::deserialize_option(&buf).unwrap(), (Some(0x0102u16), 3));
+        assert_eq!(PresenceByte::serialize_option(&None::<u16>, &mut buf).unwrap(), 1);
+        assert_eq!(PresenceByte::deserialize_option(&buf).unwrap(), (None::<u16>, 1));
+    }
+
+    #[test]
+    fn sentinel_round_trip() {
+        let mut buf = [0u8; 2];
+        assert_eq!(Reading::new(Some(0x0102)).serialize(&mut buf).unwrap(), 2);
+        assert_eq!(buf, [0x01, 0x02]);
+        assert_eq!(Reading::deserialize(&buf).unwrap(), Reading::new(Some(0x0102)));
+        assert_eq!(Reading::new(None).serialize(&mut buf).unwrap(), 2);
+        assert_eq!(buf, [0xFF, 0xFF]);
+        assert_eq!(Reading::deserialize(&buf).unwrap(), Reading::new(None));
+    }
+
+    #[test]
+    fn some_sentinel_is_an_error() {
+        let mut buf = [0u8; 2];
+        assert!(matches!(Reading::new(Some(0xFFFF)).serialize(&mut buf), Err(SerializeError::InvalidData)));
+        // A sentinel the type cannot hold fails both ways.
+        assert!(matches!(
+            SentinelOption::<u8, 0x100>::new(None).serialize(&mut buf),
+            Err(SerializeError::InvalidData)
+        ));
+        assert!(matches!(SentinelOption::<u8, 0x100>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+    }
+
+    #[test]
+    fn sentinel_saves_the_presence_byte() {
+        let mut buf = [0u8; 4];
+        let presence = EncodedOption::<u16, PresenceByte>::new(Some(7)).serialize(&mut buf).unwrap();
+        let sentinel = Reading::new(Some(7)).serialize(&mut buf).unwrap();
+        assert_eq!((presence, sentinel), (3, 2));
+    }
+}
@@ -0,0 +1,96 @@
+//! Values padded to a whole number of fixed-size blocks, for block cipher layers.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A value followed by PKCS#7 padding up to the next multiple of `B` bytes.
+///
+/// Between 1 and `B` padding bytes are always added, each holding the padding length,
+/// so a value already a multiple of `B` long gets a whole block of padding.
+/// `B` must be between 1 and 255.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockPadded<const B: usize, T>(pub T);
+
+impl<const B: usize, T> BlockPadded<B, T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Padding length after `len` bytes of value.
+    fn padding(len: usize) -> usize {
+        B - len % B
+    }
+}
+
+impl<const B: usize, T: Serialize> Serialize for BlockPadded<B, T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        if !(1..=255).contains(&B) {
+            return Err(SerializeError::InvalidData);
+        }
+        let size = self.0.serialize(buf)?;
+        let padding = Self::padding(size);
+        let dst = buf.get_mut(size..size + padding).ok_or(SerializeError::BufferTooSmall)?;
+        dst.fill(padding as u8);
+        Ok(size + padding)
+    }
+
+    /// Without padding if `B` is out of range, as `serialize` fails then.
+    fn serialized_size(&self) -> usize {
+        let size = self.0.serialized_size();
+        match B {
+            1..=255 => size + Self::padding(size),
+            _ => size,
+        }
+    }
+}
+
+impl<const B: usize, T: Deserialize> Deserialize for BlockPadded<B, T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(padded, _)| padded)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        if !(1..=255).contains(&B) {
+            return Err(DeserializeError::InvalidData);
+        }
+        let (value, size) = T::deserialize_consumed(buf)?;
+        let padding = Self::padding(size);
+        let src = buf.get(size..size + padding).ok_or(DeserializeError::BufferTooSmall)?;
+        if src.iter().any(|&byte| byte as usize != padding) {
+            return Err(DeserializeError::InvalidData);
+        }
+        Ok((BlockPadded(value), size + padding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ten_bytes_pad_to_sixteen() {
+        let payload = *b"0123456789";
+        let mut buf = [0u8; 32];
+        assert_eq!(BlockPadded::<16, _>(payload).serialize(&mut buf).unwrap(), 16);
+        assert_eq!(buf[..10], payload);
+        assert_eq!(buf[10..16], [6; 6]);
+        let (padded, size) = BlockPadded::<16, [u8; 10]>::deserialize_consumed(&buf).unwrap();
+        assert_eq!((padded.into_inner(), size), (payload, 16));
+    }
+
+    #[test]
+    fn whole_block_gets_a_block_of_padding() {
+        let mut buf = [0u8; 16];
+        assert_eq!(BlockPadded::<8, _>(0u64).serialize(&mut buf).unwrap(), 16);
+        assert_eq!(buf[8..], [8; 8]);
+    }
+
+    #[test]
+    fn bad_padding_is_rejected() {
+        let mut buf = [0u8; 8];
+        BlockPadded::<8, _>(0x0102u16).serialize(&mut buf).unwrap();
+        buf[7] = 5;
+        assert!(matches!(BlockPadded::<8, u16>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        assert!(matches!(BlockPadded::<8, u16>::deserialize(&buf[..7]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(BlockPadded::<0, _>(1u8).serialize(&mut buf), Err(SerializeError::InvalidData)));
+    }
+}
@@ -0,0 +1,215 @@
+//! A fixed pool of frame buffers that can be taken from interrupt handlers, so that a
+//! message can be serialized there without a buffer on the handler's stack.
+//!
+//! Buffers are handed out by setting bits in an atomic bitmap with compare-and-swap,
+//! which never blocks: when every buffer is in use, acquiring fails immediately.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+#[cfg(not(feature = "portable-atomic"))]
+use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "portable-atomic")]
+use portable_atomic::{AtomicU32, Ordering};
+
+use crate::{Serialize, SerializeError};
+
+/// `N` buffers of `LEN` bytes, for a `static`. `N` is at most 32.
+pub struct StaticFramePool<const N: usize, const LEN: usize> {
+    buffers: [UnsafeCell<[u8; LEN]>; N],
+    /// Bit `i` is set while buffer `i` is leased
+    leased: AtomicU32,
+}
+
+// SAFETY: a buffer is only reachable through the one lease whose bit in `leased` was set
+// by a successful compare-and-swap, and the bit is cleared only when that lease drops.
+unsafe impl<const N: usize, const LEN: usize> Sync for StaticFramePool<N, LEN> {}
+
+impl<const N: usize, const LEN: usize> StaticFramePool<N, LEN> {
+    const FITS_BITMAP: () = assert!(N <= 32, "a frame pool holds at most 32 buffers");
+
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_BITMAP;
+        StaticFramePool {
+            buffers: [const { UnsafeCell::new([0; LEN]) }; N],
+            leased: AtomicU32::new(0),
+        }
+    }
+
+    /// Takes a free buffer, or returns `None` if all of them are leased.
+    /// The buffer keeps whatever the previous lease left in it.
+    pub fn acquire(&self) -> Option<FrameLease<'_, N, LEN>> {
+        let mut leased = self.leased.load(Ordering::Relaxed);
+        loop {
+            let index = (!leased).trailing_zeros() as usize;
+            if index >= N {
+                return None;
+            }
+            match self.leased.compare_exchange_weak(
+                leased,
+                leased | 1 << index,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(FrameLease { pool: self, index, len: 0 }),
+                Err(current) => leased = current,
+            }
+        }
+    }
+
+    /// Number of buffers currently leased.
+    pub fn leased(&self) -> usize {
+        self.leased.load(Ordering::Relaxed).count_ones() as usize
+    }
+}
+
+impl<const N: usize, const LEN: usize> Default for StaticFramePool<N, LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Exclusive use of one buffer of a `StaticFramePool`, returned to the pool on drop.
+/// Dereferences to the whole buffer; `as_bytes` covers the used length only.
+pub struct FrameLease<'a, const N: usize, const LEN: usize> {
+    pool: &'a StaticFramePool<N, LEN>,
+    index: usize,
+    len: usize,
+}
+
+impl<const N: usize, const LEN: usize> FrameLease<'_, N, LEN> {
+    /// Number of bytes in use.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sets the number of bytes in use.
+    ///
+    /// # Panics
+    /// If `len` exceeds the buffer size.
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= LEN, "length exceeds the frame buffer");
+        self.len = len;
+    }
+
+    /// The bytes in use
+    pub fn as_bytes(&self) -> &[u8] {
+        &self[..self.len]
+    }
+}
+
+impl<const N: usize, const LEN: usize> Deref for FrameLease<'_, N, LEN> {
+    type Target = [u8; LEN];
+
+    fn deref(&self) -> &[u8; LEN] {
+        // SAFETY: this lease holds the buffer's bit, so nothing else accesses it.
+        unsafe { &*self.pool.buffers[self.index].get() }
+    }
+}
+
+impl<const N: usize, const LEN: usize> DerefMut for FrameLease<'_, N, LEN> {
+    fn deref_mut(&mut self) -> &mut [u8; LEN] {
+        // SAFETY: this lease holds the buffer's bit, so nothing else accesses it.
+        unsafe { &mut *self.pool.buffers[self.index].get() }
+    }
+}
+
+impl<const N: usize, const LEN: usize> Drop for FrameLease<'_, N, LEN> {
+    fn drop(&mut self) {
+        self.pool.leased.fetch_and(!(1 << self.index), Ordering::Release);
+    }
+}
+
+/// Errors from serializing into a leased buffer
+#[derive(Debug)]
+pub enum LeaseError {
+    /// Every buffer is leased
+    Exhausted,
+    /// The message does not serialize into one buffer
+    Serialize(SerializeError),
+}
+
+/// Serializes `msg` into a buffer taken from `pool`.
+/// Returns the lease with its length set to the serialized size; on failure the buffer
+/// goes back to the pool.
+pub fn serialize_leased<'a, T: Serialize + ?Sized, const N: usize, const LEN: usize>(
+    pool: &'a StaticFramePool<N, LEN>,
+    msg: &T,
+) -> Result<FrameLease<'a, N, LEN>, LeaseError> {
+    let mut lease = pool.acquire().ok_or(LeaseError::Exhausted)?;
+    let len = msg.serialize(&mut lease[..]).map_err(LeaseError::Serialize)?;
+    lease.set_len(len);
+    Ok(lease)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhaustion_fails_and_dropped_leases_return() {
+        let pool = StaticFramePool::<2, 8>::new();
+        let first = pool.acquire().unwrap();
+        let second = pool.acquire().unwrap();
+        assert_ne!(first.index, second.index);
+        assert!(pool.acquire().is_none());
+        assert_eq!(pool.leased(), 2);
+        drop(first);
+        assert_eq!(pool.leased(), 1);
+        let third = pool.acquire().unwrap();
+        drop((second, third));
+        assert_eq!(pool.leased(), 0);
+    }
+
+    #[test]
+    fn serialized_message_records_its_length() {
+        let pool = StaticFramePool::<1, 4>::new();
+        let lease = serialize_leased(&pool, &0x0102u16).unwrap();
+        assert_eq!((lease.len(), lease.as_bytes()), (2, &[1u8, 2][..]));
+        assert!(matches!(serialize_leased(&pool, &1u8), Err(LeaseError::Exhausted)));
+        drop(lease);
+
+        // A failed serialization gives the buffer back.
+        assert!(matches!(
+            serialize_leased(&pool, &0u64),
+            Err(LeaseError::Serialize(SerializeError::BufferTooSmall))
+        ));
+        assert_eq!(pool.leased(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn no_buffer_is_handed_out_twice_across_threads() {
+        use core::sync::atomic::AtomicBool;
+
+        use crate::Deserialize;
+
+        static POOL: StaticFramePool<3, 8> = StaticFramePool::new();
+        static IN_USE: [AtomicBool; 3] = [const { AtomicBool::new(false) }; 3];
+
+        let workers: [_; 2] = core::array::from_fn(|worker| {
+            std::thread::spawn(move || {
+                for round in 0..2000u32 {
+                    let Some(mut lease) = POOL.acquire() else {
+                        std::thread::yield_now();
+                        continue;
+                    };
+                    assert!(!IN_USE[lease.index].swap(true, Ordering::SeqCst));
+                    let stamp = [worker as u32, round];
+                    let len = stamp.serialize(&mut lease[..]).unwrap();
+                    std::thread::yield_now();
+                    assert_eq!(<[u32; 2]>::deserialize(&lease[..len]).unwrap(), stamp);
+                    IN_USE[lease.index].store(false, Ordering::SeqCst);
+                }
+            })
+        });
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(POOL.leased(), 0);
+    }
+}
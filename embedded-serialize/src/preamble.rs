@@ -0,0 +1,57 @@
+//! Preambles that radios send ahead of a frame, as runs of one byte value such as `0x55`,
+//! for their receivers to lock onto.
+//!
+//! ```
+//! use embedded_serialize::preamble::deserialize_after_preamble;
+//!
+//! let received = [0x55, 0x55, 0x55, 0x01, 0x02, 0x03, 0x04];
+//! let (value, consumed) = deserialize_after_preamble::<u32>(&received, 0x55).unwrap();
+//! assert_eq!(value, 0x0102_0304);
+//! assert_eq!(consumed, 7);
+//! ```
+
+use crate::{Deserialize, DeserializeError};
+
+/// Number of bytes at the front of `buf` equal to `preamble_byte`.
+pub fn preamble_len(buf: &[u8], preamble_byte: u8) -> usize {
+    buf.iter().take_while(|&&byte| byte == preamble_byte).count()
+}
+
+/// Skips the bytes equal to `preamble_byte` at the front of `buf`, however many there
+/// are, then decodes a value from the first byte after them. A value whose first byte
+/// equals `preamble_byte` cannot be told apart from the preamble.
+/// Returns the value and the number of bytes consumed, preamble included.
+pub fn deserialize_after_preamble<T: Deserialize>(
+    buf: &[u8],
+    preamble_byte: u8,
+) -> Result<(T, usize), DeserializeError> {
+    let skipped = preamble_len(buf, preamble_byte);
+    let (value, consumed) = T::deserialize_consumed(&buf[skipped..])?;
+    Ok((value, skipped + consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn three_preamble_bytes_are_skipped() {
+        let received = [0x55, 0x55, 0x55, 0xDE, 0xAD, 0xBE, 0xEF, 0x55];
+        assert_eq!(preamble_len(&received, 0x55), 3);
+        assert_eq!(deserialize_after_preamble::<u32>(&received, 0x55).unwrap(), (0xDEAD_BEEF, 7));
+    }
+
+    #[test]
+    fn no_preamble_decodes_from_the_start() {
+        assert_eq!(deserialize_after_preamble::<u16>(&[0x12, 0x34], 0x55).unwrap(), (0x1234, 2));
+    }
+
+    #[test]
+    fn a_frame_of_only_preamble_is_too_short() {
+        assert!(matches!(deserialize_after_preamble::<u32>(&[0x55; 6], 0x55), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(
+            deserialize_after_preamble::<u32>(&[0x55, 0x55, 0x01, 0x02], 0x55),
+            Err(DeserializeError::BufferTooSmall)
+        ));
+    }
+}
@@ -0,0 +1,292 @@
+//! Queues of serialized frames between an interrupt handler and the main loop,
+//! built on `heapless::spsc::Queue`, and the contents of such queues, for persisting
+//! pending work across a sleep.
+//!
+//! A `Queue` is written as a `u16` count followed by its items from front to back, and
+//! decoded by enqueueing them in that order; a count above the capacity is rejected with
+//! `InvalidData`. Both need the whole queue, not a split producer or consumer, so the
+//! borrow checker ensures that neither end is in use meanwhile.
+//!
+//! ```
+//! use embedded_serialize::{Deserialize, Serialize};
+//! use heapless::spsc::Queue;
+//!
+//! let mut jobs: Queue<u16, 4> = Queue::new();
+//! for job in [0x0A, 0x0B, 0x0C] {
+//!     jobs.enqueue(job).unwrap();
+//! }
+//! let mut buf = [0u8; 16];
+//! let size = jobs.serialize(&mut buf).unwrap();
+//! assert_eq!(buf[..size], [0, 3, 0, 0x0A, 0, 0x0B, 0, 0x0C]);
+//!
+//! let mut restored = Queue::<u16, 4>::deserialize(&buf[..size]).unwrap();
+//! assert_eq!(restored.dequeue(), Some(0x0A));
+//! assert_eq!(restored.dequeue(), Some(0x0B));
+//! assert_eq!(restored.dequeue(), Some(0x0C));
+//! assert!(Queue::<u16, 3>::deserialize(&buf[..size]).is_err());
+//! ```
+
+use heapless::spsc::{Consumer, Producer, Queue};
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// One serialized message of at most `FRAME` bytes
+#[derive(Debug, Clone)]
+pub struct Frame<const FRAME: usize> {
+    bytes: [u8; FRAME],
+    len: usize,
+}
+
+impl<const FRAME: usize> Frame<FRAME> {
+    /// Serializes `msg` into a new frame.
+    pub fn serialize<T: Serialize + ?Sized>(msg: &T) -> Result<Self, SerializeError> {
+        let mut bytes = [0u8; FRAME];
+        let len = msg.serialize(&mut bytes)?;
+        Ok(Frame { bytes, len })
+    }
+
+    /// Deserializes the message in this frame.
+    pub fn deserialize<T: Deserialize>(&self) -> Result<T, DeserializeError> {
+        T::deserialize(self.as_bytes())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Errors from enqueueing a message
+#[derive(Debug)]
+pub enum EnqueueError {
+    /// The message does not serialize into one frame
+    Serialize(SerializeError),
+    /// Every slot is taken
+    Full,
+}
+
+/// A single-producer single-consumer queue of serialized messages.
+///
+/// Holds up to `CAP - 1` frames of up to `FRAME` bytes each. Messages are serialized into
+/// a frame on the producer's stack and copied into the queue. Split it into a producer
+/// and a consumer to use the two ends from different contexts, such as an interrupt
+/// handler and the main loop.
+pub struct FrameQueue<const CAP: usize, const FRAME: usize> {
+    queue: Queue<Frame<FRAME>, CAP>,
+}
+
+impl<const CAP: usize, const FRAME: usize> FrameQueue<CAP, FRAME> {
+    pub const fn new() -> Self {
+        FrameQueue { queue: Queue::new() }
+    }
+
+    /// Serializes `msg` and appends it to the queue.
+    pub fn enqueue_serialized<T: Serialize + ?Sized>(&mut self, msg: &T) -> Result<(), EnqueueError> {
+        let frame = Frame::serialize(msg).map_err(EnqueueError::Serialize)?;
+        self.queue.enqueue(frame).map_err(|_| EnqueueError::Full)
+    }
+
+    /// Removes the oldest message and deserializes it, or returns `None` if the queue is empty.
+    /// A message that fails to deserialize is still removed.
+    pub fn dequeue_deserialized<T: Deserialize>(&mut self) -> Result<Option<T>, DeserializeError> {
+        self.queue.dequeue().map(|frame| frame.deserialize()).transpose()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Splits the queue into its producer and consumer ends.
+    pub fn split(&mut self) -> (FrameProducer<'_, CAP, FRAME>, FrameConsumer<'_, CAP, FRAME>) {
+        let (producer, consumer) = self.queue.split();
+        (FrameProducer { producer }, FrameConsumer { consumer })
+    }
+}
+
+impl<const CAP: usize, const FRAME: usize> Default for FrameQueue<CAP, FRAME> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The enqueueing end of a split `FrameQueue`
+pub struct FrameProducer<'a, const CAP: usize, const FRAME: usize> {
+    producer: Producer<'a, Frame<FRAME>, CAP>,
+}
+
+impl<const CAP: usize, const FRAME: usize> FrameProducer<'_, CAP, FRAME> {
+    /// Serializes `msg` and appends it to the queue.
+    pub fn enqueue_serialized<T: Serialize + ?Sized>(&mut self, msg: &T) -> Result<(), EnqueueError> {
+        let frame = Frame::serialize(msg).map_err(EnqueueError::Serialize)?;
+        self.producer.enqueue(frame).map_err(|_| EnqueueError::Full)
+    }
+
+    pub fn ready(&self) -> bool {
+        self.producer.ready()
+    }
+}
+
+/// The dequeueing end of a split `FrameQueue`
+pub struct FrameConsumer<'a, const CAP: usize, const FRAME: usize> {
+    consumer: Consumer<'a, Frame<FRAME>, CAP>,
+}
+
+impl<const CAP: usize, const FRAME: usize> FrameConsumer<'_, CAP, FRAME> {
+    /// Removes the oldest message and deserializes it, or returns `None` if the queue is empty.
+    /// A message that fails to deserialize is still removed.
+    pub fn dequeue_deserialized<T: Deserialize>(&mut self) -> Result<Option<T>, DeserializeError> {
+        self.consumer.dequeue().map(|frame| frame.deserialize()).transpose()
+    }
+
+    pub fn ready(&self) -> bool {
+        self.consumer.ready()
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for Queue<T, N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let count = u16::try_from(self.len()).map_err(|_| SerializeError::InvalidData)?;
+        let mut offset = count.serialize(buf)?;
+        for item in self.iter() {
+            offset += item.serialize(buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?)?;
+        }
+        Ok(offset)
+    }
+
+    fn serialized_size(&self) -> usize {
+        2 + self.iter().map(Serialize::serialized_size).sum::<usize>()
+    }
+}
+
+impl<T: Deserialize, const N: usize> Deserialize for Queue<T, N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(queue, _)| queue)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (count, mut offset) = u16::deserialize_consumed(buf)?;
+        let mut queue = Queue::new();
+        if count as usize > queue.capacity() {
+            return Err(DeserializeError::InvalidData);
+        }
+        for _ in 0..count {
+            let (item, size) = T::deserialize_consumed(buf.get(offset..).ok_or(DeserializeError::BufferTooSmall)?)?;
+            offset += size;
+            // `count` is within the capacity, so there is always room.
+            queue.enqueue(item).map_err(|_| DeserializeError::InvalidData)?;
+        }
+        Ok((queue, offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_come_out_in_order() {
+        let mut queue = FrameQueue::<4, 8>::new();
+        queue.enqueue_serialized(&0x0102u16).unwrap();
+        queue.enqueue_serialized(&0x0304u16).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dequeue_deserialized::<u16>().unwrap(), Some(0x0102));
+        assert_eq!(queue.dequeue_deserialized::<u16>().unwrap(), Some(0x0304));
+        assert_eq!(queue.dequeue_deserialized::<u16>().unwrap(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn full_queue_and_oversize_message_are_rejected() {
+        let mut queue = FrameQueue::<3, 4>::new();
+        let (mut producer, mut consumer) = queue.split();
+        assert!(matches!(
+            producer.enqueue_serialized(&0u64),
+            Err(EnqueueError::Serialize(SerializeError::BufferTooSmall))
+        ));
+        producer.enqueue_serialized(&1u32).unwrap();
+        producer.enqueue_serialized(&2u32).unwrap();
+        assert!(!producer.ready());
+        assert!(matches!(producer.enqueue_serialized(&3u32), Err(EnqueueError::Full)));
+
+        // A frame that does not decode as the requested type is still removed.
+        assert!(matches!(consumer.dequeue_deserialized::<u64>(), Err(DeserializeError::BufferTooSmall)));
+        assert_eq!(consumer.dequeue_deserialized::<u32>().unwrap(), Some(2));
+        assert!(!consumer.ready());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn producer_and_consumer_on_separate_threads() {
+        const COUNT: u32 = 1000;
+        let mut queue = FrameQueue::<4, 8>::new();
+        let (mut producer, mut consumer) = queue.split();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for value in 0..COUNT {
+                    while let Err(err) = producer.enqueue_serialized(&[value, !value]) {
+                        assert!(matches!(err, EnqueueError::Full));
+                        std::thread::yield_now();
+                    }
+                }
+            });
+            scope.spawn(move || {
+                let mut expected = 0;
+                while expected < COUNT {
+                    match consumer.dequeue_deserialized::<[u32; 2]>().unwrap() {
+                        Some(message) => {
+                            assert_eq!(message, [expected, !expected]);
+                            expected += 1;
+                        }
+                        None => std::thread::yield_now(),
+                    }
+                }
+            });
+        });
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn contents_keep_their_fifo_order() {
+        let mut jobs: Queue<u32, 4> = Queue::new();
+        // Move the head along first, so that the items wrap around the end of the ring.
+        for job in [1, 2, 3] {
+            jobs.enqueue(job).unwrap();
+        }
+        jobs.dequeue().unwrap();
+        jobs.dequeue().unwrap();
+        jobs.enqueue(4).unwrap();
+        jobs.enqueue(5).unwrap();
+
+        let mut buf = [0u8; 16];
+        assert_eq!(jobs.serialize(&mut buf).unwrap(), 14);
+        assert_eq!(jobs.serialized_size(), 14);
+        let (mut restored, consumed) = Queue::<u32, 4>::deserialize_consumed(&buf).unwrap();
+        assert_eq!(consumed, 14);
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored.dequeue(), Some(3));
+        assert_eq!(restored.dequeue(), Some(4));
+        assert_eq!(restored.dequeue(), Some(5));
+        assert_eq!(restored.dequeue(), None);
+    }
+
+    #[test]
+    fn empty_queue_is_just_a_count() {
+        let jobs: Queue<u16, 2> = Queue::new();
+        let mut buf = [0xFFu8; 2];
+        assert_eq!(jobs.serialize(&mut buf).unwrap(), 2);
+        assert_eq!(buf, [0, 0]);
+        assert!(Queue::<u16, 2>::deserialize(&buf).unwrap().is_empty());
+    }
+
+    #[test]
+    fn counts_above_capacity_and_missing_items_are_rejected() {
+        // Three items fit a `Queue<_, 4>`, whose capacity is one less than `N`.
+        let buf = [0, 3, 0, 1, 0, 2, 0, 3];
+        assert!(matches!(Queue::<u16, 3>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        assert!(matches!(Queue::<u16, 4>::deserialize(&buf[..7]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(Queue::<u16, 4>::deserialize(&[0, 0xFF]), Err(DeserializeError::InvalidData)));
+    }
+}
@@ -0,0 +1,155 @@
+//! Reserved bytes of a protocol, such as "bytes 6–9: reserved, write as zero", as struct
+//! fields that hold nothing.
+//!
+//! Both markers write `N` zero bytes. `Reserved` skips whatever a peer wrote there, so a
+//! later revision may put data in them; `ReservedZero` rejects a byte other than zero
+//! with `InvalidValue` holding it.
+//!
+//! ```
+//! use embedded_serialize::reserved::{Reserved, ReservedZero};
+//! use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+//! use embedded_serialize_derive::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! #[es(view)]
+//! struct Header {
+//!     version: u8,
+//!     reserved: Reserved<2>,
+//!     length: u16,
+//!     must_be_zero: ReservedZero<1>,
+//!     flags: u8,
+//! }
+//!
+//! let header = Header {
+//!     version: 1,
+//!     reserved: Reserved,
+//!     length: 0x0102,
+//!     must_be_zero: ReservedZero,
+//!     flags: 0x80,
+//! };
+//! let mut buf = [0xFFu8; 7];
+//! assert_eq!(header.serialize(&mut buf).unwrap(), 7);
+//! assert_eq!(buf, [0x01, 0x00, 0x00, 0x01, 0x02, 0x00, 0x80]);
+//!
+//! let view = HeaderView::new(&buf).unwrap();
+//! assert_eq!(view.length().unwrap(), 0x0102);
+//! assert_eq!(view.flags().unwrap(), 0x80);
+//!
+//! buf[1] = 0x55;
+//! assert_eq!(Header::deserialize(&buf).unwrap(), header);
+//! buf[5] = 0x55;
+//! assert!(matches!(Header::deserialize(&buf), Err(DeserializeError::InvalidValue(0x55))));
+//! ```
+
+use crate::layout::FixedLayout;
+use crate::schema::{Describe, Schema};
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// `N` reserved bytes, written as zeros and skipped when decoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Reserved<const N: usize>;
+
+/// `N` reserved bytes, written as zeros and required to be zero when decoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ReservedZero<const N: usize>;
+
+fn write_zeros<const N: usize>(buf: &mut [u8]) -> Result<usize, SerializeError> {
+    buf.get_mut(..N).ok_or(SerializeError::BufferTooSmall)?.fill(0);
+    Ok(N)
+}
+
+impl<const N: usize> Serialize for Reserved<N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_zeros::<N>(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Deserialize for Reserved<N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(reserved, _)| reserved)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        buf.get(..N).ok_or(DeserializeError::BufferTooSmall)?;
+        Ok((Reserved, N))
+    }
+}
+
+impl<const N: usize> Serialize for ReservedZero<N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_zeros::<N>(buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Deserialize for ReservedZero<N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(reserved, _)| reserved)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let bytes = buf.get(..N).ok_or(DeserializeError::BufferTooSmall)?;
+        match bytes.iter().find(|&&byte| byte != 0) {
+            Some(&byte) => Err(DeserializeError::InvalidValue(byte as u32)),
+            None => Ok((ReservedZero, N)),
+        }
+    }
+}
+
+impl<const N: usize> FixedLayout for Reserved<N> {
+    const SIZE: usize = N;
+
+    fn swap_bytes_in_place(_record: &mut [u8]) {}
+}
+
+impl<const N: usize> FixedLayout for ReservedZero<N> {
+    const SIZE: usize = N;
+
+    fn swap_bytes_in_place(_record: &mut [u8]) {}
+}
+
+impl<const N: usize> Describe for Reserved<N> {
+    const SCHEMA: &'static Schema = &Schema::Array { item: &Schema::U8, len: N };
+}
+
+impl<const N: usize> Describe for ReservedZero<N> {
+    const SCHEMA: &'static Schema = &Schema::Array { item: &Schema::U8, len: N };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zeros_are_written() {
+        let mut buf = [0xFFu8; 6];
+        assert_eq!(Reserved::<4>.serialize(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0, 0, 0, 0, 0xFF, 0xFF]);
+        assert_eq!(ReservedZero::<2>.serialize(&mut buf[4..]).unwrap(), 2);
+        assert_eq!(buf, [0; 6]);
+        assert_eq!((Reserved::<4>.serialized_size(), ReservedZero::<2>.serialized_size()), (4, 2));
+        assert!(matches!(Reserved::<4>.serialize(&mut buf[..3]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn reserved_skips_anything() {
+        assert_eq!(Reserved::<3>::deserialize_consumed(&[1, 2, 3, 4]).unwrap(), (Reserved, 3));
+        assert!(matches!(Reserved::<3>::deserialize(&[0, 0]), Err(DeserializeError::BufferTooSmall)));
+        assert_eq!(Reserved::<0>::deserialize_consumed(&[]).unwrap(), (Reserved, 0));
+    }
+
+    #[test]
+    fn reserved_zero_verifies() {
+        assert_eq!(ReservedZero::<3>::deserialize_consumed(&[0, 0, 0, 9]).unwrap(), (ReservedZero, 3));
+        assert!(matches!(ReservedZero::<3>::deserialize(&[0, 0x40, 0x41]), Err(DeserializeError::InvalidValue(0x40))));
+        // Only the reserved bytes are checked.
+        assert!(matches!(ReservedZero::<3>::deserialize(&[0, 0]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
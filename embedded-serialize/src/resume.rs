@@ -0,0 +1,88 @@
+//! Serialization that can pause when the output fills up and resume later.
+
+use crate::SerializeError;
+
+/// A value that can be serialized one top-level field at a time.
+/// Implemented by `#[derive(Serialize)]`.
+pub trait SerializeFields {
+    /// Number of fields written on the wire
+    const FIELD_COUNT: usize;
+
+    /// Serializes field `index` into the provided buffer.
+    /// Returns the number of bytes written.
+    fn serialize_field(&self, index: usize, buf: &mut [u8]) -> Result<usize, SerializeError>;
+}
+
+/// Outcome of one `serialize_chunk` call, carrying the bytes written by that call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// The output filled up before the value was complete
+    More(usize),
+    /// The value has been written completely
+    Done(usize),
+}
+
+impl Progress {
+    /// Bytes written by the call.
+    pub fn written(&self) -> usize {
+        match *self {
+            Progress::More(written) | Progress::Done(written) => written,
+        }
+    }
+}
+
+/// Writes a value across any number of output chunks.
+///
+/// Each field is serialized into a scratch buffer of `S` bytes, so `S` must fit
+/// the largest field; the cursor is the field index plus the offset into it.
+pub struct ResumableSerializer<T, const S: usize> {
+    value: T,
+    field: usize,
+    offset: usize,
+    scratch: [u8; S],
+    scratch_len: usize,
+}
+
+impl<T: SerializeFields, const S: usize> ResumableSerializer<T, S> {
+    pub fn new(value: T) -> Self {
+        ResumableSerializer {
+            value,
+            field: 0,
+            offset: 0,
+            scratch: [0; S],
+            scratch_len: 0,
+        }
+    }
+
+    /// Writes as much of the value as fits into `buf`, continuing where the last call stopped.
+    pub fn serialize_chunk(&mut self, buf: &mut [u8]) -> Result<Progress, SerializeError> {
+        let mut written = 0;
+        loop {
+            if self.offset == self.scratch_len && self.field < T::FIELD_COUNT {
+                self.scratch_len = self.value.serialize_field(self.field, &mut self.scratch)?;
+                self.field += 1;
+                self.offset = 0;
+                continue;
+            }
+            if self.offset == self.scratch_len {
+                return Ok(Progress::Done(written));
+            }
+            if written == buf.len() {
+                return Ok(Progress::More(written));
+            }
+            let size = (self.scratch_len - self.offset).min(buf.len() - written);
+            buf[written..written + size].copy_from_slice(&self.scratch[self.offset..self.offset + size]);
+            self.offset += size;
+            written += size;
+        }
+    }
+
+    /// Returns true once the whole value has been written.
+    pub fn is_done(&self) -> bool {
+        self.field == T::FIELD_COUNT && self.offset == self.scratch_len
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
@@ -0,0 +1,147 @@
+//! Snapshots of a ring buffer's whole state, for persisting a queue across a reset.
+//!
+//! A snapshot is written as the head and tail indices, each a big-endian `u16`,
+//! followed by all `N` slots, used or not. Decoding rejects an index that is not below
+//! `N` with `InvalidData` before anything is rebuilt from it.
+//!
+//! ```
+//! use embedded_serialize::ring::RingSnapshot;
+//! use embedded_serialize::{Deserialize, DeserializeError, Serialize};
+//!
+//! // Two readings queued, read from slot 1 onwards and written at slot 3 next.
+//! let ring = RingSnapshot::new([0u16, 0x0A0B, 0x0C0D, 0], 1, 3).unwrap();
+//! let mut buf = [0u8; 16];
+//! let size = ring.serialize(&mut buf).unwrap();
+//! assert_eq!(buf[..size], [0, 1, 0, 3, 0, 0, 0x0A, 0x0B, 0x0C, 0x0D, 0, 0]);
+//!
+//! let restored = RingSnapshot::<u16, 4>::deserialize(&buf[..size]).unwrap();
+//! assert_eq!(restored, ring);
+//! assert_eq!(restored.len(), 2);
+//!
+//! // A tail past the last slot
+//! buf[3] = 4;
+//! assert!(matches!(RingSnapshot::<u16, 4>::deserialize(&buf[..size]), Err(DeserializeError::InvalidData)));
+//! ```
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// The backing slots of a ring buffer of `N` slots, with the index `head` of the next
+/// slot to read and `tail` of the next slot to write. The ring is empty when they are
+/// equal, so it holds at most `N - 1` elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingSnapshot<T, const N: usize> {
+    slots: [T; N],
+    head: u16,
+    tail: u16,
+}
+
+impl<T, const N: usize> RingSnapshot<T, N> {
+    /// A snapshot of `slots` with the given indices, or `None` unless both are below `N`.
+    pub fn new(slots: [T; N], head: usize, tail: usize) -> Option<Self> {
+        if head >= N || tail >= N {
+            return None;
+        }
+        Some(RingSnapshot {
+            slots,
+            head: u16::try_from(head).ok()?,
+            tail: u16::try_from(tail).ok()?,
+        })
+    }
+
+    pub fn slots(&self) -> &[T; N] {
+        &self.slots
+    }
+
+    pub fn head(&self) -> usize {
+        self.head as usize
+    }
+
+    pub fn tail(&self) -> usize {
+        self.tail as usize
+    }
+
+    /// Number of elements between `head` and `tail`.
+    pub fn len(&self) -> usize {
+        (self.tail() + N - self.head()) % N
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head == self.tail
+    }
+
+    /// The slots, head and tail, for rebuilding the ring buffer.
+    pub fn into_parts(self) -> ([T; N], usize, usize) {
+        let (head, tail) = (self.head(), self.tail());
+        (self.slots, head, tail)
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for RingSnapshot<T, N> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let mut offset = self.head.serialize(buf)?;
+        offset += self.tail.serialize(buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?)?;
+        offset += self.slots.serialize(buf.get_mut(offset..).ok_or(SerializeError::BufferTooSmall)?)?;
+        Ok(offset)
+    }
+
+    fn serialized_size(&self) -> usize {
+        4 + self.slots.serialized_size()
+    }
+}
+
+impl<T: Deserialize, const N: usize> Deserialize for RingSnapshot<T, N> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(ring, _)| ring)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let head = u16::deserialize(buf)? as usize;
+        let tail = u16::deserialize(buf.get(2..).ok_or(DeserializeError::BufferTooSmall)?)? as usize;
+        if head >= N || tail >= N {
+            return Err(DeserializeError::InvalidData);
+        }
+        let (slots, size) = <[T; N]>::deserialize_consumed(&buf[4..])?;
+        let ring = RingSnapshot::new(slots, head, tail).ok_or(DeserializeError::InvalidData)?;
+        Ok((ring, 4 + size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_ring_round_trips() {
+        // Written at slots 3, 4 and 0, with slot 1 next.
+        let ring = RingSnapshot::new([30u8, 0, 0, 10, 20], 3, 1).unwrap();
+        assert_eq!(ring.len(), 3);
+        let mut buf = [0u8; 16];
+        let size = ring.serialize(&mut buf).unwrap();
+        assert_eq!(buf[..size], [0, 3, 0, 1, 30, 0, 0, 10, 20]);
+        assert_eq!(ring.serialized_size(), size);
+
+        let (restored, consumed) = RingSnapshot::<u8, 5>::deserialize_consumed(&buf).unwrap();
+        assert_eq!((consumed, restored.len()), (size, 3));
+        assert_eq!(restored.into_parts(), ([30, 0, 0, 10, 20], 3, 1));
+
+        let empty = RingSnapshot::new([0u8; 5], 2, 2).unwrap();
+        assert!(empty.is_empty());
+        empty.serialize(&mut buf).unwrap();
+        assert!(RingSnapshot::<u8, 5>::deserialize(&buf).unwrap().is_empty());
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        assert!(RingSnapshot::new([0u8; 4], 0, 4).is_none());
+        assert!(RingSnapshot::new([0u8; 4], 4, 0).is_none());
+
+        let buf = [0, 0, 0, 4, 1, 2, 3, 4];
+        assert!(matches!(RingSnapshot::<u8, 4>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        let buf = [0x01, 0x00, 0, 0, 1, 2, 3, 4];
+        assert!(matches!(RingSnapshot::<u8, 4>::deserialize(&buf), Err(DeserializeError::InvalidData)));
+        // The indices are checked before the slots are read.
+        assert!(matches!(RingSnapshot::<u8, 4>::deserialize(&[0, 0, 0, 9]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(RingSnapshot::<u8, 4>::deserialize(&[0, 0, 0, 1, 7]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(RingSnapshot::<u8, 4>::deserialize(&[0, 0, 0]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,191 @@
+//! Static descriptions of wire formats, for generating matching code in other languages.
+
+use crate::fixed_str::FixedStr;
+
+/// Wire format of a type
+#[derive(Debug)]
+pub enum Schema {
+    U8,
+    U16,
+    U32,
+    I8,
+    I16,
+    I32,
+    Bool,
+    /// `len` items back to back
+    Array { item: &'static Schema, len: usize },
+    /// The first `count` items of an array of `max`, back to back, where `count` names
+    /// an earlier field of the same struct holding the number of items
+    Counted { item: &'static Schema, max: usize, count: &'static str },
+    /// A `FixedStr` of `len` bytes
+    FixedStr { len: usize },
+    /// Fields back to back in declaration order
+    Struct { name: &'static str, fields: &'static [Field] },
+    /// A `u8` tag followed by the fields of the variant it selects
+    Enum { name: &'static str, variants: &'static [Variant] },
+    /// A format that cannot be described field by field, such as a variable-length one
+    Opaque { name: &'static str },
+}
+
+/// A named field of a struct or enum variant
+#[derive(Debug)]
+pub struct Field {
+    pub name: &'static str,
+    pub schema: &'static Schema,
+}
+
+/// An enum variant and its wire tag
+#[derive(Debug)]
+pub struct Variant {
+    pub name: &'static str,
+    pub tag: u32,
+    pub fields: &'static [Field],
+}
+
+impl Schema {
+    /// Serialized size, or `None` if it depends on the value.
+    pub const fn fixed_size(&self) -> Option<usize> {
+        match self {
+            Schema::U8 | Schema::I8 | Schema::Bool => Some(1),
+            Schema::U16 | Schema::I16 => Some(2),
+            Schema::U32 | Schema::I32 => Some(4),
+            Schema::Array { item, len } => match item.fixed_size() {
+                Some(size) => Some(size * *len),
+                None => None,
+            },
+            Schema::FixedStr { len } => Some(*len),
+            Schema::Counted { .. } => None,
+            Schema::Struct { fields, .. } => fields_size(fields),
+            Schema::Enum { variants, .. } => {
+                // Only enums whose variants are all the same size have a fixed size.
+                let mut size = None;
+                let mut index = 0;
+                while index < variants.len() {
+                    let variant = match fields_size(variants[index].fields) {
+                        Some(variant) => variant,
+                        None => return None,
+                    };
+                    match size {
+                        Some(size) if size != variant => return None,
+                        _ => size = Some(variant),
+                    }
+                    index += 1;
+                }
+                match size {
+                    Some(size) => Some(1 + size),
+                    None => Some(1),
+                }
+            }
+            Schema::Opaque { .. } => None,
+        }
+    }
+
+    /// 32-bit FNV-1a hash of the format, covering its structure and every name in it.
+    /// Two builds that agree on the hash agree on the wire format, so exchanging it at
+    /// startup catches peers built from different definitions, including with different
+    /// `cfg`-gated fields.
+    pub const fn hash(&self) -> u32 {
+        self.hash_into(FNV_OFFSET)
+    }
+
+    const fn hash_into(&self, state: u32) -> u32 {
+        match self {
+            Schema::U8 => hash_u32(state, 0),
+            Schema::U16 => hash_u32(state, 1),
+            Schema::U32 => hash_u32(state, 2),
+            Schema::I8 => hash_u32(state, 3),
+            Schema::I16 => hash_u32(state, 4),
+            Schema::I32 => hash_u32(state, 5),
+            Schema::Bool => hash_u32(state, 6),
+            Schema::Array { item, len } => item.hash_into(hash_u32(hash_u32(state, 7), *len as u32)),
+            Schema::Counted { item, max, count } => {
+                let state = hash_str(hash_u32(hash_u32(state, 8), *max as u32), count);
+                item.hash_into(state)
+            }
+            Schema::FixedStr { len } => hash_u32(hash_u32(state, 9), *len as u32),
+            Schema::Struct { name, fields } => hash_fields(hash_str(hash_u32(state, 10), name), fields),
+            Schema::Enum { name, variants } => {
+                let mut state = hash_u32(hash_str(hash_u32(state, 11), name), variants.len() as u32);
+                let mut index = 0;
+                while index < variants.len() {
+                    let variant = &variants[index];
+                    state = hash_u32(hash_str(state, variant.name), variant.tag);
+                    state = hash_fields(state, variant.fields);
+                    index += 1;
+                }
+                state
+            }
+            Schema::Opaque { name } => hash_str(hash_u32(state, 12), name),
+        }
+    }
+}
+
+pub(crate) const FNV_OFFSET: u32 = 0x811C_9DC5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+pub(crate) const fn hash_bytes(mut state: u32, bytes: &[u8]) -> u32 {
+    let mut index = 0;
+    while index < bytes.len() {
+        state = (state ^ bytes[index] as u32).wrapping_mul(FNV_PRIME);
+        index += 1;
+    }
+    state
+}
+
+const fn hash_u32(state: u32, value: u32) -> u32 {
+    hash_bytes(state, &value.to_be_bytes())
+}
+
+/// Hashes the length before the bytes, so that adjacent names cannot run together.
+const fn hash_str(state: u32, value: &str) -> u32 {
+    hash_bytes(hash_u32(state, value.len() as u32), value.as_bytes())
+}
+
+const fn hash_fields(mut state: u32, fields: &[Field]) -> u32 {
+    state = hash_u32(state, fields.len() as u32);
+    let mut index = 0;
+    while index < fields.len() {
+        state = fields[index].schema.hash_into(hash_str(state, fields[index].name));
+        index += 1;
+    }
+    state
+}
+
+const fn fields_size(fields: &[Field]) -> Option<usize> {
+    let mut total = 0;
+    let mut index = 0;
+    while index < fields.len() {
+        match fields[index].schema.fixed_size() {
+            Some(size) => total += size,
+            None => return None,
+        }
+        index += 1;
+    }
+    Some(total)
+}
+
+/// Types with a static description of their wire format.
+/// Derive it with `#[derive(Describe)]` for structs and enums of describable fields.
+pub trait Describe {
+    const SCHEMA: &'static Schema;
+}
+
+macro_rules! impl_describe {
+    ($($ty:ty => $schema:ident),*) => {
+        $(
+            impl Describe for $ty {
+                const SCHEMA: &'static Schema = &Schema::$schema;
+            }
+        )*
+    };
+}
+
+impl_describe!(u8 => U8, u16 => U16, u32 => U32, i8 => I8, i16 => I16, i32 => I32, bool => Bool);
+
+impl<T: Describe, const N: usize> Describe for [T; N] {
+    const SCHEMA: &'static Schema = &Schema::Array { item: T::SCHEMA, len: N };
+}
+
+impl<const N: usize> Describe for FixedStr<N> {
+    const SCHEMA: &'static Schema = &Schema::FixedStr { len: N };
+}
@@ -0,0 +1,4 @@
+//! Human-readable framings, for debug consoles and serial terminals.
+
+pub mod kv;
+pub mod line;
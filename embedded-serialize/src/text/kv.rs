@@ -0,0 +1,156 @@
+//! A small key-value text form of enums for gateways that speak JSON, such as
+//! `{"type":"Start","speed":5,"reverse":false}`.
+//!
+//! An object names its variant under a leading `"type"` key, followed by the variant's
+//! fields as integers or booleans. Strings other than keys and the variant name, escapes,
+//! nesting and floating-point numbers are not supported. Whitespace between tokens is
+//! accepted on input and never written. Derive it with `#[es(text_kv)]` on an enum,
+//! which generates `write_kv` and `parse_kv`.
+
+use core::fmt::{self, Write};
+
+use crate::DeserializeError;
+
+/// Field values of the key-value text form
+pub trait KvValue: Sized {
+    fn write_value(&self, w: &mut dyn Write) -> fmt::Result;
+
+    /// Parses the text of a value, failing with `InvalidData` if it is malformed.
+    fn parse_value(text: &str) -> Result<Self, DeserializeError>;
+}
+
+macro_rules! impl_kv_value {
+    ($($ty:ty),*) => {
+        $(
+            impl KvValue for $ty {
+                fn write_value(&self, w: &mut dyn Write) -> fmt::Result {
+                    write!(w, "{}", self)
+                }
+
+                fn parse_value(text: &str) -> Result<Self, DeserializeError> {
+                    text.parse().map_err(|_| DeserializeError::InvalidData)
+                }
+            }
+        )*
+    };
+}
+
+impl_kv_value!(u8, u16, u32, u64, i8, i16, i32, i64, bool);
+
+/// Writes the start of the object of variant `name`, up to its first field.
+pub fn write_start(w: &mut dyn Write, name: &str) -> fmt::Result {
+    write!(w, "{{\"type\":\"{}\"", name)
+}
+
+/// Writes the field `key` with `value`.
+pub fn write_field<T: KvValue>(w: &mut dyn Write, key: &str, value: &T) -> fmt::Result {
+    write!(w, ",\"{}\":", key)?;
+    value.write_value(w)
+}
+
+/// Writes the end of an object.
+pub fn write_end(w: &mut dyn Write) -> fmt::Result {
+    w.write_char('}')
+}
+
+/// Reads the fields of an object in the order they appear.
+#[derive(Debug, Clone)]
+pub struct KvReader<'a> {
+    rest: &'a str,
+}
+
+impl<'a> KvReader<'a> {
+    /// Starts reading the object in `text`.
+    /// Returns the reader and the variant name given by the leading `"type"` key.
+    pub fn new(text: &'a str) -> Result<(Self, &'a str), DeserializeError> {
+        let mut reader = KvReader { rest: text };
+        reader.expect('{')?;
+        if reader.string()? != "type" {
+            return Err(DeserializeError::InvalidData);
+        }
+        reader.expect(':')?;
+        let name = reader.string()?;
+        Ok((reader, name))
+    }
+
+    /// The next key and the text of its value, or `None` once the object has ended.
+    /// Fails with `InvalidData` if anything follows the object.
+    pub fn next_field(&mut self) -> Result<Option<(&'a str, &'a str)>, DeserializeError> {
+        self.skip_whitespace();
+        if let Some(rest) = self.rest.strip_prefix('}') {
+            self.rest = rest;
+            self.skip_whitespace();
+            return match self.rest.is_empty() {
+                true => Ok(None),
+                false => Err(DeserializeError::InvalidData),
+            };
+        }
+        self.expect(',')?;
+        let key = self.string()?;
+        self.expect(':')?;
+        self.skip_whitespace();
+        let value = if self.rest.starts_with('"') {
+            let start = self.rest;
+            let inner = self.string()?;
+            &start[..inner.len() + 2]
+        } else {
+            let end = self
+                .rest
+                .find(|c: char| c == ',' || c == '}' || c.is_ascii_whitespace())
+                .ok_or(DeserializeError::InvalidData)?;
+            let (value, rest) = self.rest.split_at(end);
+            self.rest = rest;
+            value
+        };
+        if value.is_empty() {
+            return Err(DeserializeError::InvalidData);
+        }
+        Ok(Some((key, value)))
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    }
+
+    fn expect(&mut self, token: char) -> Result<(), DeserializeError> {
+        self.skip_whitespace();
+        self.rest = self.rest.strip_prefix(token).ok_or(DeserializeError::InvalidData)?;
+        Ok(())
+    }
+
+    /// Reads a quoted string without escapes and returns its contents.
+    fn string(&mut self) -> Result<&'a str, DeserializeError> {
+        self.expect('"')?;
+        let end = self.rest.find(['"', '\\']).ok_or(DeserializeError::InvalidData)?;
+        let (contents, rest) = self.rest.split_at(end);
+        self.rest = rest.strip_prefix('"').ok_or(DeserializeError::InvalidData)?;
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_read_in_order() {
+        let (mut reader, name) = KvReader::new("{\"type\":\"Set\", \"level\" : -3,\"label\":\"a b\",\"on\":true}").unwrap();
+        assert_eq!(name, "Set");
+        assert_eq!(reader.next_field().unwrap(), Some(("level", "-3")));
+        assert_eq!(reader.next_field().unwrap(), Some(("label", "\"a b\"")));
+        assert_eq!(reader.next_field().unwrap(), Some(("on", "true")));
+        assert_eq!(reader.next_field().unwrap(), None);
+        assert_eq!(i8::parse_value("-3").unwrap(), -3);
+        assert!(bool::parse_value("true").unwrap());
+    }
+
+    #[test]
+    fn escapes_and_empty_values_are_rejected() {
+        assert!(matches!(KvReader::new("{\"type\":\"a\\\"b\"}"), Err(DeserializeError::InvalidData)));
+        let (mut reader, _) = KvReader::new("{\"type\":\"Set\",\"level\":}").unwrap();
+        assert!(matches!(reader.next_field(), Err(DeserializeError::InvalidData)));
+        let (mut reader, _) = KvReader::new("{\"type\":\"Set\"").unwrap();
+        assert!(matches!(reader.next_field(), Err(DeserializeError::InvalidData)));
+        assert!(matches!(u8::parse_value("1.5"), Err(DeserializeError::InvalidData)));
+    }
+}
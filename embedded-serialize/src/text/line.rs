@@ -0,0 +1,175 @@
+//! NMEA-style lines: `$LABEL,<hex payload>*<checksum>\r\n`.
+//!
+//! The payload is the serialized value in uppercase hex, and the checksum is the XOR of
+//! every byte between `$` and `*`, as two uppercase hex digits. On input, hex digits of
+//! either case are accepted and the line terminator is optional.
+
+use core::marker::PhantomData;
+
+use crate::codec::{decode_hex_in_place, hex_value, Codec, RxBuffer, HEX_DIGITS};
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, byte| sum ^ byte)
+}
+
+/// Writes `value` as a line with the given label.
+/// The label must not contain `$`, `,`, `*`, `\r` or `\n`.
+/// Returns the number of bytes written.
+pub fn encode_line<T: Serialize + ?Sized>(label: &str, value: &T, out: &mut [u8]) -> Result<usize, SerializeError> {
+    let label = label.as_bytes();
+    if label.iter().any(|byte| b"$,*\r\n".contains(byte)) {
+        return Err(SerializeError::InvalidData);
+    }
+    let start = label.len() + 2;
+    let header = out.get_mut(..start).ok_or(SerializeError::BufferTooSmall)?;
+    header[0] = b'$';
+    header[1..start - 1].copy_from_slice(label);
+    header[start - 1] = b',';
+
+    // Serialize in place, then expand to hex from the back so no byte is overwritten
+    // before it is read.
+    let size = value.serialize(&mut out[start..])?;
+    let end = start + 2 * size;
+    let line = out.get_mut(..end + 5).ok_or(SerializeError::BufferTooSmall)?;
+    for index in (0..size).rev() {
+        let byte = line[start + index];
+        line[start + 2 * index] = HEX_DIGITS[(byte >> 4) as usize];
+        line[start + 2 * index + 1] = HEX_DIGITS[(byte & 0xF) as usize];
+    }
+    let sum = checksum(&line[1..end]);
+    line[end..].copy_from_slice(&[
+        b'*',
+        HEX_DIGITS[(sum >> 4) as usize],
+        HEX_DIGITS[(sum & 0xF) as usize],
+        b'\r',
+        b'\n',
+    ]);
+    Ok(line.len())
+}
+
+/// Decodes a line carrying `label`, failing with `ChecksumMismatch` if the checksum does
+/// not match and `UnknownLabel` if the line has another label.
+/// The payload is hex-decoded in place, so `line` is overwritten.
+pub fn decode_line<T: Deserialize>(line: &mut [u8], label: &str) -> Result<T, DeserializeError> {
+    let mut end = line.len();
+    while end > 0 && matches!(line[end - 1], b'\r' | b'\n') {
+        end -= 1;
+    }
+    let line = &mut line[..end];
+    if line.first() != Some(&b'$') || end < 4 || line[end - 3] != b'*' {
+        return Err(DeserializeError::InvalidData);
+    }
+    let expected = hex_value(line[end - 2])? << 4 | hex_value(line[end - 1])?;
+    let body = &mut line[1..end - 3];
+    if checksum(body) != expected {
+        return Err(DeserializeError::ChecksumMismatch);
+    }
+    let comma = body.iter().position(|&byte| byte == b',').ok_or(DeserializeError::InvalidData)?;
+    let (found, payload) = body.split_at_mut(comma);
+    if found != label.as_bytes() {
+        return Err(DeserializeError::UnknownLabel);
+    }
+    let payload = &mut payload[1..];
+    let len = decode_hex_in_place(payload)?;
+    T::deserialize(&payload[..len])
+}
+
+/// Lines with one label, each terminated by `\n`; lines may arrive split across
+/// any number of `feed` calls.
+/// `N` bounds the receive buffer and so the longest line.
+pub struct LineCodec<Tx, Rx = Tx, const N: usize = 256> {
+    label: &'static str,
+    rx: RxBuffer<N>,
+    messages: PhantomData<fn(&Tx) -> Rx>,
+}
+
+impl<Tx, Rx, const N: usize> LineCodec<Tx, Rx, N> {
+    pub fn new(label: &'static str) -> Self {
+        LineCodec {
+            label,
+            rx: RxBuffer::new(),
+            messages: PhantomData,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+}
+
+impl<Tx: Serialize, Rx: Deserialize, const N: usize> Codec for LineCodec<Tx, Rx, N> {
+    type Tx = Tx;
+    type Rx = Rx;
+
+    fn encode(&mut self, msg: &Tx, out: &mut [u8]) -> Result<usize, SerializeError> {
+        encode_line(self.label, msg, out)
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Result<Option<Rx>, DeserializeError> {
+        self.rx.extend(bytes)?;
+        let end = match self.rx.find_frame(b'\n') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let result = decode_line(&mut self.rx.bytes[..end], self.label);
+        self.rx.consume(end + 1);
+        result.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_round_trip() {
+        let mut out = [0u8; 32];
+        let size = encode_line("TELEM", &0x1234u16, &mut out).unwrap();
+        assert_eq!(&out[..size], b"$TELEM,1234*7D\r\n");
+        assert_eq!(decode_line::<u16>(&mut out[..size], "TELEM").unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn lowercase_hex_and_missing_terminator_are_accepted() {
+        let mut line = *b"$TELEM,00abcdef*7e";
+        assert_eq!(decode_line::<u32>(&mut line, "TELEM").unwrap(), 0x00AB_CDEF);
+        let mut line = *b"$TELEM,00ABCDEF*7E\n";
+        assert_eq!(decode_line::<u32>(&mut line, "TELEM").unwrap(), 0x00AB_CDEF);
+    }
+
+    #[test]
+    fn checksum_mismatch_and_unknown_label_are_distinct() {
+        let mut line = *b"$TELEM,1235*7D\r\n";
+        assert!(matches!(decode_line::<u16>(&mut line, "TELEM"), Err(DeserializeError::ChecksumMismatch)));
+
+        let mut out = [0u8; 32];
+        let size = encode_line("STATUS", &0x1234u16, &mut out).unwrap();
+        assert!(matches!(decode_line::<u16>(&mut out[..size], "TELEM"), Err(DeserializeError::UnknownLabel)));
+
+        let mut line = *b"TELEM,1234*7D\r\n";
+        assert!(matches!(decode_line::<u16>(&mut line, "TELEM"), Err(DeserializeError::InvalidData)));
+    }
+
+    #[test]
+    fn reserved_label_characters_are_rejected() {
+        let mut out = [0u8; 32];
+        assert!(matches!(encode_line("TE,LEM", &1u8, &mut out), Err(SerializeError::InvalidData)));
+        assert!(matches!(encode_line("TELEM", &1u32, &mut out[..14]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn line_split_across_feeds() {
+        let mut codec = LineCodec::<u16, u16, 64>::new("TELEM");
+        let mut out = [0u8; 32];
+        let size = codec.encode(&0xBEEF, &mut out).unwrap();
+        let (first, second) = out[..size].split_at(9);
+        assert_eq!(codec.feed(first).unwrap(), None);
+        assert_eq!(codec.feed(second).unwrap(), Some(0xBEEF));
+        assert_eq!(codec.feed(&[]).unwrap(), None);
+
+        // A bad line is reported once, and the next line still decodes.
+        assert!(matches!(codec.feed(b"$TELEM,BEEF*00\r\n"), Err(DeserializeError::ChecksumMismatch)));
+        assert_eq!(codec.feed(&out[..size]).unwrap(), Some(0xBEEF));
+    }
+}
@@ -0,0 +1,364 @@
+//! Values carrying a capture timestamp, written as a big-endian `u64` of microseconds
+//! before the value itself, `Ticks` of a monotonic timebase, and streams of increasing
+//! timestamps written as LEB128 deltas.
+
+use crate::layout::FixedLayout;
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// A source of the current time
+pub trait Clock {
+    /// Current time in microseconds since an epoch of the clock's choosing.
+    fn now_micros(&self) -> u64;
+}
+
+impl<F: Fn() -> u64> Clock for F {
+    fn now_micros(&self) -> u64 {
+        self()
+    }
+}
+
+/// A value together with the time it was captured.
+/// Decoding reads the timestamp from the wire and needs no clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamped<T> {
+    timestamp: u64,
+    value: T,
+}
+
+impl<T> Timestamped<T> {
+    pub fn new(timestamp: u64, value: T) -> Self {
+        Timestamped { timestamp, value }
+    }
+
+    /// Stamps `value` with the current time of `clock`.
+    pub fn now<C: Clock + ?Sized>(clock: &C, value: T) -> Self {
+        Self::new(clock.now_micros(), value)
+    }
+
+    /// Capture time in microseconds.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Microseconds elapsed between capture and `now`, or 0 if `now` is earlier.
+    pub fn age(&self, now: u64) -> u64 {
+        now.saturating_sub(self.timestamp)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+/// Writes the timestamp and then `value`.
+fn write_stamped<T: Serialize + ?Sized>(timestamp: u64, value: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let stamp = buf.get_mut(..8).ok_or(SerializeError::BufferTooSmall)?;
+    stamp.copy_from_slice(&timestamp.to_be_bytes());
+    Ok(8 + value.serialize(&mut buf[8..])?)
+}
+
+/// Serializes `value` prefixed with the current time of `clock`, read as the value is written.
+/// The output decodes as a `Timestamped<T>`.
+/// Returns the number of bytes written.
+pub fn serialize_timestamped<T: Serialize + ?Sized, C: Clock + ?Sized>(
+    clock: &C,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<usize, SerializeError> {
+    write_stamped(clock.now_micros(), value, buf)
+}
+
+impl<T: Serialize> Serialize for Timestamped<T> {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        write_stamped(self.timestamp, &self.value, buf)
+    }
+
+    fn serialized_size(&self) -> usize {
+        8 + self.value.serialized_size()
+    }
+}
+
+impl<T: Deserialize> Deserialize for Timestamped<T> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(stamped, _)| stamped)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let stamp = buf.get(..8).ok_or(DeserializeError::BufferTooSmall)?;
+        let mut raw = [0; 8];
+        raw.copy_from_slice(stamp);
+        let (value, size) = T::deserialize_consumed(&buf[8..])?;
+        Ok((Timestamped::new(u64::from_be_bytes(raw), value), 8 + size))
+    }
+}
+
+/// A monotonic tick count of a timebase running at `HZ` ticks per second, written as a
+/// big-endian `u64`. `HZ` must not be zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Ticks<const HZ: u32>(pub u64);
+
+impl<const HZ: u32> Ticks<HZ> {
+    pub const fn new(ticks: u64) -> Self {
+        Ticks(ticks)
+    }
+
+    pub const fn ticks(&self) -> u64 {
+        self.0
+    }
+
+    /// The tick count `millis` milliseconds after the epoch, rounded down, or `None` if
+    /// it does not fit in a `u64`.
+    pub const fn from_millis(millis: u64) -> Option<Self> {
+        let ticks = millis as u128 * HZ as u128 / 1000;
+        if ticks > u64::MAX as u128 {
+            return None;
+        }
+        Some(Ticks(ticks as u64))
+    }
+
+    /// Milliseconds since the epoch, rounded down and saturating at `u64::MAX`.
+    pub const fn as_millis(&self) -> u64 {
+        let millis = self.0 as u128 * 1000 / HZ as u128;
+        if millis > u64::MAX as u128 {
+            return u64::MAX;
+        }
+        millis as u64
+    }
+}
+
+impl<const HZ: u32> Serialize for Ticks<HZ> {
+    fn serialized_size(&self) -> usize {
+        8
+    }
+
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        self.0.to_be_bytes().serialize(buf)
+    }
+}
+
+impl<const HZ: u32> Deserialize for Ticks<HZ> {
+    fn deserialize(buf: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_consumed(buf).map(|(ticks, _)| ticks)
+    }
+
+    fn deserialize_consumed(buf: &[u8]) -> Result<(Self, usize), DeserializeError> {
+        let (raw, size) = <[u8; 8]>::deserialize_consumed(buf)?;
+        Ok((Ticks(u64::from_be_bytes(raw)), size))
+    }
+}
+
+impl<const HZ: u32> FixedLayout for Ticks<HZ> {
+    const SIZE: usize = 8;
+
+    fn swap_bytes_in_place(record: &mut [u8]) {
+        record.reverse();
+    }
+}
+
+/// Longest LEB128 encoding of a `u64`
+const MAX_LEB128_LEN: usize = 10;
+
+/// Writes `value` as unsigned LEB128: seven bits per byte, least significant first, with
+/// the high bit set on every byte but the last.
+fn write_leb128(mut value: u64, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let mut len = 0;
+    loop {
+        let byte = buf.get_mut(len).ok_or(SerializeError::BufferTooSmall)?;
+        len += 1;
+        if value < 0x80 {
+            *byte = value as u8;
+            return Ok(len);
+        }
+        *byte = value as u8 | 0x80;
+        value >>= 7;
+    }
+}
+
+/// Reads an unsigned LEB128 value. Fails with `InvalidData` on one that overflows a `u64`.
+fn read_leb128(buf: &[u8]) -> Result<(u64, usize), DeserializeError> {
+    let mut value = 0u64;
+    for (index, &byte) in buf.iter().take(MAX_LEB128_LEN).enumerate() {
+        let bits = (byte & 0x7F) as u64;
+        if index == MAX_LEB128_LEN - 1 && bits > 1 {
+            return Err(DeserializeError::InvalidData);
+        }
+        value |= bits << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+    if buf.len() < MAX_LEB128_LEN {
+        Err(DeserializeError::BufferTooSmall)
+    } else {
+        Err(DeserializeError::InvalidData)
+    }
+}
+
+/// Writes each timestamp of an increasing sequence as the LEB128 delta from the one
+/// before it, starting from a base both ends agree on.
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampDeltaEncoder {
+    last: u64,
+}
+
+impl TimestampDeltaEncoder {
+    pub fn new(base: u64) -> Self {
+        TimestampDeltaEncoder { last: base }
+    }
+
+    /// The last timestamp written, or the base.
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Writes the delta from the last timestamp to `timestamp`.
+    /// Fails with `InvalidData` if `timestamp` is earlier than the last one.
+    /// Returns the number of bytes written.
+    pub fn encode(&mut self, timestamp: u64, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        let delta = timestamp.checked_sub(self.last).ok_or(SerializeError::InvalidData)?;
+        let size = write_leb128(delta, buf)?;
+        self.last = timestamp;
+        Ok(size)
+    }
+}
+
+/// Reads timestamps written by a `TimestampDeltaEncoder` with the same base.
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampDeltaDecoder {
+    last: u64,
+}
+
+impl TimestampDeltaDecoder {
+    pub fn new(base: u64) -> Self {
+        TimestampDeltaDecoder { last: base }
+    }
+
+    /// The last timestamp read, or the base.
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+
+    /// Reads the next delta and adds it to the last timestamp.
+    /// Fails with `InvalidData` if the sum overflows.
+    /// Returns the timestamp and the number of bytes consumed.
+    pub fn decode(&mut self, buf: &[u8]) -> Result<(u64, usize), DeserializeError> {
+        let (delta, size) = read_leb128(buf)?;
+        self.last = self.last.checked_add(delta).ok_or(DeserializeError::InvalidData)?;
+        Ok((self.last, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    /// A clock that advances by 10µs on every read
+    struct MockClock(Cell<u64>);
+
+    impl Clock for MockClock {
+        fn now_micros(&self) -> u64 {
+            let now = self.0.get();
+            self.0.set(now + 10);
+            now
+        }
+    }
+
+    #[test]
+    fn timestamp_is_prepended() {
+        let clock = MockClock(Cell::new(0x0102_0304_0506_0708));
+        let mut buf = [0u8; 16];
+        assert_eq!(serialize_timestamped(&clock, &0xABCDu16, &mut buf).unwrap(), 10);
+        assert_eq!(buf[..10], [1, 2, 3, 4, 5, 6, 7, 8, 0xAB, 0xCD]);
+
+        let stamped = Timestamped::now(&clock, 0xABCDu16);
+        assert_eq!(stamped.timestamp(), 0x0102_0304_0506_0712);
+        assert_eq!(stamped.serialize(&mut buf).unwrap(), 10);
+        assert_eq!(buf[..8], 0x0102_0304_0506_0712u64.to_be_bytes());
+    }
+
+    #[test]
+    fn decoding_needs_no_clock() {
+        let buf = [0, 0, 0, 0, 0, 0, 0x03, 0xE8, 0x7F];
+        let (stamped, size) = Timestamped::<u8>::deserialize_consumed(&buf).unwrap();
+        assert_eq!((stamped.timestamp(), *stamped.get_ref(), size), (1000, 0x7F, 9));
+        assert_eq!((stamped.age(1500), stamped.age(900)), (500, 0));
+        assert_eq!(stamped.into_inner(), 0x7F);
+    }
+
+    #[test]
+    fn short_buffers_fail() {
+        assert!(matches!(Timestamped::<u8>::deserialize(&[0; 8]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(Timestamped::new(1, 2u8).serialize(&mut [0; 8]), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn ticks_are_written_raw_and_convert_to_millis() {
+        let ticks = Ticks::<1000>::new(0x0102_0304_0506_0708);
+        let mut buf = [0u8; 8];
+        assert_eq!(ticks.serialize(&mut buf).unwrap(), 8);
+        assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(Ticks::<1000>::deserialize_consumed(&buf).unwrap(), (ticks, 8));
+        assert_eq!(ticks.as_millis(), 0x0102_0304_0506_0708);
+        assert_eq!(Ticks::<1000>::from_millis(1234), Some(Ticks(1234)));
+    }
+
+    #[test]
+    fn other_rates_round_down_and_saturate() {
+        assert_eq!(Ticks::<32768>::new(32768 * 3 + 100).as_millis(), 3003);
+        assert_eq!(Ticks::<32768>::from_millis(1500).unwrap().ticks(), 49152);
+        assert_eq!(Ticks::<100>::new(7).as_millis(), 70);
+        assert_eq!(Ticks::<1>::new(u64::MAX).as_millis(), u64::MAX);
+        assert_eq!(Ticks::<1_000_000>::from_millis(u64::MAX), None);
+        assert!(matches!(Ticks::<1000>::deserialize(&[0; 7]), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn delta_stream_round_trips() {
+        let base = 1_700_000_000_000u64;
+        let timestamps = [base, base + 1, base + 127, base + 128, base + 20_000, base + 20_000, u64::MAX];
+        let mut encoder = TimestampDeltaEncoder::new(base);
+        let mut buf = [0u8; 32];
+        let mut len = 0;
+        for &timestamp in &timestamps {
+            len += encoder.encode(timestamp, &mut buf[len..]).unwrap();
+        }
+        assert_eq!(encoder.last(), u64::MAX);
+        // Deltas 0, 1, 126, 1, 19872, 0 and the rest up to u64::MAX.
+        assert_eq!(buf[..8], [0x00, 0x01, 0x7E, 0x01, 0xA0, 0x9B, 0x01, 0x00]);
+        assert_eq!(len, 8 + MAX_LEB128_LEN);
+
+        let mut decoder = TimestampDeltaDecoder::new(base);
+        let mut offset = 0;
+        for &timestamp in &timestamps {
+            let (decoded, size) = decoder.decode(&buf[offset..len]).unwrap();
+            assert_eq!(decoded, timestamp);
+            offset += size;
+        }
+        assert_eq!(offset, len);
+    }
+
+    #[test]
+    fn earlier_timestamp_is_rejected() {
+        let mut encoder = TimestampDeltaEncoder::new(1000);
+        let mut buf = [0u8; 4];
+        assert_eq!(encoder.encode(1500, &mut buf).unwrap(), 2);
+        assert!(matches!(encoder.encode(1499, &mut buf), Err(SerializeError::InvalidData)));
+        assert_eq!(encoder.last(), 1500);
+        assert!(matches!(TimestampDeltaEncoder::new(5).encode(4, &mut buf), Err(SerializeError::InvalidData)));
+    }
+
+    #[test]
+    fn malformed_deltas_are_rejected() {
+        let mut decoder = TimestampDeltaDecoder::new(u64::MAX - 1);
+        assert!(matches!(decoder.decode(&[0x02]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(decoder.decode(&[0x80, 0x80]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(decoder.decode(&[0xFF; 10]), Err(DeserializeError::InvalidData)));
+        assert!(matches!(decoder.decode(&[]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(TimestampDeltaEncoder::new(0).encode(200, &mut [0u8; 1]), Err(SerializeError::BufferTooSmall)));
+    }
+}
@@ -0,0 +1,191 @@
+//! Tag-length-value encoding: a one-byte tag, a one-byte length, then the value.
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Largest value a single entry can carry
+pub const MAX_VALUE_LEN: usize = u8::MAX as usize;
+
+/// Appends TLV entries to a buffer
+pub struct TlvWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> TlvWriter<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        TlvWriter { buf, len: 0 }
+    }
+
+    /// Appends an entry holding `bytes` verbatim.
+    pub fn push(&mut self, tag: u8, bytes: &[u8]) -> Result<(), SerializeError> {
+        if bytes.len() > MAX_VALUE_LEN {
+            return Err(SerializeError::InvalidData);
+        }
+        let end = self.len + 2 + bytes.len();
+        let entry = self.buf.get_mut(self.len..end).ok_or(SerializeError::BufferTooSmall)?;
+        entry[0] = tag;
+        entry[1] = bytes.len() as u8;
+        entry[2..].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Appends an entry holding the serialized form of `value`.
+    pub fn push_value<T: Serialize>(&mut self, tag: u8, value: &T) -> Result<(), SerializeError> {
+        let rest = self.buf.get_mut(self.len..).ok_or(SerializeError::BufferTooSmall)?;
+        if rest.len() < 2 {
+            return Err(SerializeError::BufferTooSmall);
+        }
+        let value_end = rest.len().min(2 + MAX_VALUE_LEN);
+        let size = value.serialize(&mut rest[2..value_end])?;
+        rest[0] = tag;
+        rest[1] = size as u8;
+        self.len += 2 + size;
+        Ok(())
+    }
+
+    /// Number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the encoded entries.
+    pub fn finish(self) -> &'a [u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// Iterates over the `(tag, value)` entries of a TLV blob in wire order.
+///
+/// An entry whose length runs past the end of the buffer yields an error and ends the iteration.
+#[derive(Clone)]
+pub struct TlvIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> TlvIter<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        TlvIter { buf }
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Result<(u8, &'a [u8]), DeserializeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self.buf {
+            [] => None,
+            [tag, len, ref rest @ ..] if rest.len() >= len as usize => {
+                let (value, rest) = rest.split_at(len as usize);
+                self.buf = rest;
+                Some(Ok((tag, value)))
+            }
+            _ => {
+                self.buf = &[];
+                Some(Err(DeserializeError::BufferTooSmall))
+            }
+        }
+    }
+}
+
+/// Returns the value of the first entry with `tag`, ignoring any later duplicates.
+pub fn find_first(buf: &[u8], tag: u8) -> Result<Option<&[u8]>, DeserializeError> {
+    for entry in TlvIter::new(buf) {
+        let (entry_tag, value) = entry?;
+        if entry_tag == tag {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the value of the entry with `tag`, or `DuplicateTag` if it occurs more than once.
+/// The whole blob is validated.
+pub fn find(buf: &[u8], tag: u8) -> Result<Option<&[u8]>, DeserializeError> {
+    let mut found = None;
+    for entry in TlvIter::new(buf) {
+        let (entry_tag, value) = entry?;
+        if entry_tag == tag {
+            if found.is_some() {
+                return Err(DeserializeError::DuplicateTag(tag));
+            }
+            found = Some(value);
+        }
+    }
+    Ok(found)
+}
+
+/// Deserializes the value of the entry with `tag`, which must fill the entry exactly.
+pub fn get<T: Deserialize>(buf: &[u8], tag: u8) -> Result<Option<T>, DeserializeError> {
+    find(buf, tag)?.map(decode_value).transpose()
+}
+
+/// Deserializes an entry's value, which must be consumed exactly.
+pub fn decode_value<T: Deserialize>(value: &[u8]) -> Result<T, DeserializeError> {
+    let (item, size) = T::deserialize_consumed(value)?;
+    if size != value.len() {
+        return Err(DeserializeError::InvalidData);
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_entry_round_trip() {
+        let mut buf = [0u8; 16];
+        let mut writer = TlvWriter::new(&mut buf);
+        writer.push(1, &[0xAA, 0xBB]).unwrap();
+        writer.push_value(2, &0x0102u16).unwrap();
+        writer.push(1, &[0xCC]).unwrap();
+        let blob = writer.finish();
+        assert_eq!(blob, [1, 2, 0xAA, 0xBB, 2, 2, 0x01, 0x02, 1, 1, 0xCC]);
+
+        let mut entries = TlvIter::new(blob);
+        for expected in [(1, 2), (2, 2), (1, 1)] {
+            let (tag, value) = entries.next().unwrap().unwrap();
+            assert_eq!((tag, value.len()), expected);
+        }
+        assert!(entries.next().is_none());
+        assert_eq!(find_first(blob, 1).unwrap(), Some(&[0xAA, 0xBB][..]));
+        assert!(matches!(find(blob, 1), Err(DeserializeError::DuplicateTag(1))));
+        assert_eq!(get::<u16>(blob, 2).unwrap(), Some(0x0102));
+        assert_eq!(get::<u16>(blob, 3).unwrap(), None);
+    }
+
+    #[test]
+    fn length_overrunning_the_buffer_fails() {
+        let blob = [1, 1, 0xAA, 2, 5, 0, 0];
+        let mut entries = TlvIter::new(&blob);
+        assert_eq!(entries.next().unwrap().unwrap(), (1, &[0xAA][..]));
+        assert!(matches!(entries.next(), Some(Err(DeserializeError::BufferTooSmall))));
+        assert!(entries.next().is_none());
+        assert!(matches!(find(&blob, 9), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn empty_value() {
+        let mut buf = [0u8; 2];
+        let mut writer = TlvWriter::new(&mut buf);
+        writer.push(7, &[]).unwrap();
+        assert_eq!(writer.finish(), [7, 0]);
+        assert_eq!(find(&buf, 7).unwrap(), Some(&[][..]));
+    }
+
+    #[test]
+    fn value_at_the_length_limit() {
+        let value = [0x5Au8; 256];
+        let mut buf = [0u8; 300];
+        let mut writer = TlvWriter::new(&mut buf);
+        writer.push(1, &value[..255]).unwrap();
+        assert!(matches!(writer.push(2, &value), Err(SerializeError::InvalidData)));
+        assert_eq!(writer.len(), 257);
+        assert_eq!(find(&buf[..257], 1).unwrap(), Some(&value[..255]));
+    }
+}
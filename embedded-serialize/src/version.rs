@@ -0,0 +1,71 @@
+//! A protocol version byte leading each frame, for refusing peers that run firmware
+//! older than a floor.
+//!
+//! ```
+//! use embedded_serialize::version::{deserialize_min_version, serialize_versioned};
+//! use embedded_serialize::DeserializeError;
+//!
+//! let mut frame = [0u8; 3];
+//! assert_eq!(serialize_versioned(1, &0x0102u16, &mut frame).unwrap(), 3);
+//! assert_eq!(frame, [0x01, 0x01, 0x02]);
+//! assert!(matches!(deserialize_min_version::<u16>(&frame, 2), Err(DeserializeError::VersionTooOld(1))));
+//!
+//! serialize_versioned(2, &0x0102u16, &mut frame).unwrap();
+//! assert_eq!(deserialize_min_version::<u16>(&frame, 2).unwrap(), 0x0102);
+//! ```
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Writes `version` followed by `value`.
+/// Returns the number of bytes written, version included.
+pub fn serialize_versioned<T: Serialize>(version: u8, value: &T, buf: &mut [u8]) -> Result<usize, SerializeError> {
+    let size = version.serialize(buf)?;
+    Ok(size + value.serialize(&mut buf[size..])?)
+}
+
+/// Reads the version byte at the front of `buf`, failing with `VersionTooOld` if it is
+/// below `min` before anything else is decoded, then decodes the body after it.
+pub fn deserialize_min_version<T: Deserialize>(buf: &[u8], min: u8) -> Result<T, DeserializeError> {
+    deserialize_min_version_consumed(buf, min).map(|(value, _)| value)
+}
+
+/// Like `deserialize_min_version`, also returning the number of bytes consumed,
+/// version included.
+pub fn deserialize_min_version_consumed<T: Deserialize>(
+    buf: &[u8],
+    min: u8,
+) -> Result<(T, usize), DeserializeError> {
+    let (version, size) = u8::deserialize_consumed(buf)?;
+    if version < min {
+        return Err(DeserializeError::VersionTooOld(version));
+    }
+    let (value, consumed) = T::deserialize_consumed(&buf[size..])?;
+    Ok((value, size + consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_frame_is_refused_under_a_v2_floor() {
+        let mut frame = [0u8; 5];
+        serialize_versioned(1, &0xDEAD_BEEFu32, &mut frame).unwrap();
+        assert!(matches!(deserialize_min_version::<u32>(&frame, 2), Err(DeserializeError::VersionTooOld(1))));
+    }
+
+    #[test]
+    fn floor_is_checked_before_the_body() {
+        // The body is truncated, but the version alone is enough to refuse it.
+        assert!(matches!(deserialize_min_version::<u32>(&[1, 0xAA], 2), Err(DeserializeError::VersionTooOld(1))));
+    }
+
+    #[test]
+    fn v2_and_newer_frames_are_accepted() {
+        let mut frame = [0u8; 5];
+        assert_eq!(serialize_versioned(2, &0xDEAD_BEEFu32, &mut frame).unwrap(), 5);
+        assert_eq!(deserialize_min_version::<u32>(&frame, 2).unwrap(), 0xDEAD_BEEF);
+        serialize_versioned(3, &7u32, &mut frame).unwrap();
+        assert_eq!(deserialize_min_version_consumed::<u32>(&frame, 2).unwrap(), (7, 5));
+    }
+}
@@ -0,0 +1,162 @@
+//! Data whitening for radio links, which XORs a payload with the output of a linear
+//! feedback shift register so that long runs of `0x00` or `0xFF` cannot upset the
+//! receiver's clock recovery.
+//!
+//! The register is the 7-bit one of Bluetooth LE and of Nordic radios such as the
+//! nRF51/nRF52 (`DATAWHITEIV`), with polynomial `x^7 + x^4 + 1`, applied to the least
+//! significant bit of each byte first. Its output repeats every 127 bits and never holds
+//! the same bit for more than 7 in a row, so a whitened run of equal bytes has no run of
+//! more than 7 equal bits. Whitening twice with the same seed restores the input.
+//!
+//! Both ends must agree on the seed of each frame. Derive it from the channel with
+//! `ble_seed`, or from anything else both ends know, such as a sequence number, as long as
+//! its low seven bits are not all zero: a zero register whitens nothing.
+//!
+//! ```
+//! use embedded_serialize::whitening::{ble_seed, dewhiten, whiten};
+//!
+//! // Bluetooth LE advertising channel 37 whitening a run of zeros, as computed by the
+//! // reference `btLeWhiten` used to drive nRF24L01 radios as BLE beacons.
+//! let mut payload = [0u8; 4];
+//! whiten(&mut payload, ble_seed(37));
+//! assert_eq!(payload, [0x8d, 0xd2, 0x57, 0xa1]);
+//! dewhiten(&mut payload, ble_seed(37));
+//! assert_eq!(payload, [0; 4]);
+//!
+//! // No whitened byte of a long run of zeros is 0x00 or 0xFF.
+//! let mut run = [0u8; 64];
+//! whiten(&mut run, ble_seed(37));
+//! assert!(run.iter().all(|&byte| byte != 0x00 && byte != 0xFF));
+//! ```
+
+/// The Bluetooth LE seed of `channel` (0 to 39): the channel index with the register's
+/// top bit set.
+pub const fn ble_seed(channel: u8) -> u16 {
+    (channel & 0x3F) as u16 | 0x40
+}
+
+/// XORs `buf` with the whitening sequence started from the low seven bits of `seed`.
+pub fn whiten(buf: &mut [u8], seed: u16) {
+    let mut lfsr = (seed & 0x7F) as u8;
+    for byte in buf {
+        for bit in 0..8 {
+            if lfsr & 1 != 0 {
+                lfsr ^= 0x88;
+                *byte ^= 1 << bit;
+            }
+            lfsr >>= 1;
+        }
+    }
+}
+
+/// Restores a buffer whitened with `seed`; the same operation as `whiten`.
+pub fn dewhiten(buf: &mut [u8], seed: u16) {
+    whiten(buf, seed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Codec, FramedCrcCodec};
+    use crate::DeserializeError;
+
+    /// Whitening as the Bluetooth Core specification draws it: positions 1 to 7, position 1
+    /// preset to 1 and positions 2 to 7 to the channel index, most significant bit first.
+    fn spec_whiten(buf: &mut [u8], channel: u8) {
+        let mut position = [false; 8];
+        position[1] = true;
+        for bit in 0..6 {
+            position[2 + bit] = channel >> (5 - bit) & 1 != 0;
+        }
+        for byte in buf {
+            for bit in 0..8 {
+                let out = position[7];
+                if out {
+                    *byte ^= 1 << bit;
+                }
+                for index in (2..8).rev() {
+                    position[index] = position[index - 1];
+                }
+                position[1] = out;
+                position[5] ^= out;
+            }
+        }
+    }
+
+    #[test]
+    fn dewhitening_restores_the_input() {
+        let original: [u8; 40] = core::array::from_fn(|index| (index * 37) as u8);
+        for seed in [1, 0x25, 0x7F, ble_seed(0), ble_seed(39), 0xFF01] {
+            let mut buf = original;
+            whiten(&mut buf, seed);
+            assert_ne!(buf, original);
+            dewhiten(&mut buf, seed);
+            assert_eq!(buf, original);
+        }
+    }
+
+    #[test]
+    fn matches_the_specification_register() {
+        for channel in 0..40 {
+            let mut ours = [0u8; 32];
+            let mut spec = [0u8; 32];
+            whiten(&mut ours, ble_seed(channel));
+            spec_whiten(&mut spec, channel);
+            assert_eq!(ours, spec, "channel {}", channel);
+        }
+
+        let mut payload = [0u8; 4];
+        whiten(&mut payload, ble_seed(37));
+        assert_eq!(payload, [0x8d, 0xd2, 0x57, 0xa1]);
+    }
+
+    #[test]
+    fn runs_of_equal_bytes_have_short_bit_runs() {
+        for (fill, seed) in [(0x00, ble_seed(37)), (0xFF, ble_seed(2)), (0x00, 1)] {
+            let mut run = [fill; 64];
+            whiten(&mut run, seed);
+            let mut longest = 0;
+            let mut current = 0;
+            let mut previous = None;
+            for index in 0..run.len() * 8 {
+                let bit = run[index / 8] >> (index % 8) & 1;
+                current = if previous == Some(bit) { current + 1 } else { 1 };
+                longest = longest.max(current);
+                previous = Some(bit);
+            }
+            assert!(longest <= 7, "run of {} bits", longest);
+        }
+    }
+
+    #[test]
+    fn zero_seed_whitens_nothing() {
+        let mut buf = [0x5Au8; 8];
+        whiten(&mut buf, 0x80);
+        assert_eq!(buf, [0x5A; 8]);
+    }
+
+    #[test]
+    fn codec_whitens_whole_frames() {
+        let mut plain = FramedCrcCodec::<u32>::new();
+        let mut radio = FramedCrcCodec::<u32>::with_whitening(ble_seed(12));
+        let mut clear = [0u8; 16];
+        let mut white = [0u8; 16];
+        let size = plain.encode(&0x0102_0304, &mut clear).unwrap();
+        assert_eq!(radio.encode(&0x0102_0304, &mut white).unwrap(), size);
+        let mut expected = clear;
+        whiten(&mut expected[..size], ble_seed(12));
+        assert_eq!(white[..size], expected[..size]);
+
+        // Split across feeds, including inside the length header.
+        assert_eq!(radio.feed(&white[..1]).unwrap(), None);
+        assert_eq!(radio.feed(&white[1..5]).unwrap(), None);
+        assert_eq!(radio.feed(&white[5..size]).unwrap(), Some(0x0102_0304));
+
+        radio.set_whitening(None);
+        assert_eq!(radio.feed(&clear[..size]).unwrap(), Some(0x0102_0304));
+
+        let mut hopped = FramedCrcCodec::<u32>::with_whitening(ble_seed(13));
+        // The wrong seed garbles the length into one longer than the receive buffer.
+        assert!(matches!(hopped.feed(&white[..size]), Err(DeserializeError::BufferTooSmall)));
+    }
+}
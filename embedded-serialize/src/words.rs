@@ -0,0 +1,175 @@
+//! Word-oriented memory: deserialization from memory that must be read with aligned
+//! 32-bit loads, such as memory-mapped (XIP) flash, and serialization into 16-bit words
+//! for SPI peripherals that DMA whole words.
+//!
+//! The source is only ever read one whole aligned `u32` at a time. Its bytes are copied,
+//! in memory order, into a stack buffer that the normal deserialization path then reads.
+//!
+//! `serialize_words16` groups the serialized bytes in pairs, the first byte of each pair
+//! in the high half of its word, so that a peripheral shifting each word out MSB-first
+//! sends the bytes in their serialized order whatever the CPU's endianness. An odd last
+//! byte is padded with a zero low half.
+//!
+//! ```
+//! use embedded_serialize::words::serialize_words16;
+//! use embedded_serialize_derive::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Command {
+//!     register: u8,
+//!     value: u16,
+//! }
+//!
+//! let mut words = [0xFFFFu16; 4];
+//! let len = serialize_words16(&Command { register: 0x2A, value: 0x1234 }, &mut words).unwrap();
+//! assert_eq!(words[..len], [0x2A12, 0x3400]);
+//! ```
+
+use core::{ptr, slice};
+
+use crate::{Deserialize, DeserializeError, Serialize, SerializeError};
+
+/// Copies `out.len()` bytes starting `offset` bytes into `src` into `out`,
+/// using only aligned word loads.
+pub fn copy_from_words(src: &[u32], offset: usize, out: &mut [u8]) -> Result<(), DeserializeError> {
+    let end = offset.checked_add(out.len()).ok_or(DeserializeError::BufferTooSmall)?;
+    if end > src.len() * 4 {
+        return Err(DeserializeError::BufferTooSmall);
+    }
+    let mut position = offset;
+    while position < end {
+        // SAFETY: `position / 4 < src.len()` as `position < end <= src.len() * 4`, and
+        // indexing a `&[u32]` gives an aligned pointer. The volatile read keeps the load
+        // from being split into byte loads.
+        let word = unsafe { ptr::read_volatile(&src[position / 4]) };
+        let bytes = word.to_ne_bytes();
+        let start = position % 4;
+        let len = (4 - start).min(end - position);
+        let dst = position - offset;
+        out[dst..dst + len].copy_from_slice(&bytes[start..start + len]);
+        position += len;
+    }
+    Ok(())
+}
+
+/// Deserializes a value from the `byte_len` bytes starting `offset` bytes into `src`,
+/// using only aligned word loads. `N` bounds `byte_len` and is the size of the stack
+/// buffer the bytes are assembled in.
+/// Returns the value and the number of bytes consumed.
+pub fn deserialize_from_words<T: Deserialize, const N: usize>(
+    src: &[u32],
+    offset: usize,
+    byte_len: usize,
+) -> Result<(T, usize), DeserializeError> {
+    let mut buf = [0u8; N];
+    let buf = buf.get_mut(..byte_len).ok_or(DeserializeError::BufferTooSmall)?;
+    copy_from_words(src, offset, buf)?;
+    T::deserialize_consumed(buf)
+}
+
+/// Serializes `value` into `words`, two bytes per word with the first in the high half,
+/// padding an odd last byte with zero.
+/// Returns the number of words written.
+pub fn serialize_words16<T: Serialize + ?Sized>(value: &T, words: &mut [u16]) -> Result<usize, SerializeError> {
+    // SAFETY: the bytes cover exactly the memory of `words`, which is borrowed mutably
+    // for as long as they live; `u8` has no alignment requirement and every bit
+    // pattern is a valid `u16`.
+    let bytes = unsafe { slice::from_raw_parts_mut(words.as_mut_ptr().cast::<u8>(), words.len() * 2) };
+    let size = value.serialize(bytes)?;
+    let len = size.div_ceil(2);
+    bytes[size..len * 2].fill(0);
+    for word in &mut words[..len] {
+        *word = u16::from_be(*word);
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 16] = [
+        0x10, 0x21, 0x32, 0x43, 0x54, 0x65, 0x76, 0x87, 0x98, 0xA9, 0xBA, 0xCB, 0xDC, 0xED, 0xFE, 0x0F,
+    ];
+
+    /// `BYTES` laid out in memory as words, as it would be in mapped flash
+    fn words() -> [u32; 4] {
+        core::array::from_fn(|index| u32::from_ne_bytes(BYTES[index * 4..index * 4 + 4].try_into().unwrap()))
+    }
+
+    #[test]
+    fn copies_every_offset_and_length() {
+        let src = words();
+        for offset in 0..BYTES.len() {
+            for len in 0..=BYTES.len() - offset {
+                let mut out = [0u8; 16];
+                copy_from_words(&src, offset, &mut out[..len]).unwrap();
+                assert_eq!(out[..len], BYTES[offset..offset + len], "offset {} len {}", offset, len);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_plain_deserialization_at_unaligned_offsets() {
+        let src = words();
+        for offset in 0..4 {
+            // 7 bytes: not a multiple of the word size, and spanning up to three words.
+            let expected = <[u8; 7]>::deserialize_consumed(&BYTES[offset..offset + 7]).unwrap();
+            assert_eq!(deserialize_from_words::<[u8; 7], 16>(&src, offset, 7).unwrap(), expected);
+
+            let expected = u32::deserialize_consumed(&BYTES[offset..]).unwrap();
+            assert_eq!(deserialize_from_words::<u32, 16>(&src, offset, 5).unwrap(), expected);
+
+            let expected = <[u16; 3]>::deserialize_consumed(&BYTES[offset + 1..]).unwrap();
+            assert_eq!(deserialize_from_words::<[u16; 3], 8>(&src, offset + 1, 6).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn reads_past_the_source_or_scratch_fail() {
+        let src = words();
+        assert!(matches!(copy_from_words(&src, 13, &mut [0u8; 4]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(copy_from_words(&src, usize::MAX, &mut [0u8; 1]), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(deserialize_from_words::<u32, 4>(&src, 0, 5), Err(DeserializeError::BufferTooSmall)));
+        assert!(matches!(deserialize_from_words::<u32, 16>(&src, 1, 3), Err(DeserializeError::BufferTooSmall)));
+    }
+
+    /// A 3-byte command, leaving half of its second word for padding
+    struct Command {
+        register: u8,
+        value: u16,
+    }
+
+    impl Serialize for Command {
+        fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+            let size = self.register.serialize(buf)?;
+            Ok(size + self.value.serialize(&mut buf[size..])?)
+        }
+    }
+
+    #[test]
+    fn odd_lengths_are_padded_to_a_whole_word() {
+        let mut words = [0xFFFFu16; 3];
+        assert_eq!(serialize_words16(&Command { register: 0x2A, value: 0x1234 }, &mut words).unwrap(), 2);
+        assert_eq!(words, [0x2A12, 0x3400, 0xFFFF]);
+    }
+
+    #[test]
+    fn bytes_are_paired_in_serialized_order() {
+        let mut words = [0u16; 8];
+        assert_eq!(serialize_words16(&BYTES, &mut words).unwrap(), 8);
+        for (index, word) in words.iter().enumerate() {
+            assert_eq!(word.to_be_bytes(), BYTES[index * 2..index * 2 + 2], "word {}", index);
+        }
+
+        assert_eq!(serialize_words16(&0xA1B2_C3D4u32, &mut words).unwrap(), 2);
+        assert_eq!(words[..2], [0xA1B2, 0xC3D4]);
+    }
+
+    #[test]
+    fn too_few_words_fail() {
+        let mut words = [0u16; 1];
+        assert!(serialize_words16(&Command { register: 1, value: 2 }, &mut words).is_err());
+        assert_eq!(serialize_words16(&[0u8; 0], &mut words).unwrap(), 0);
+    }
+}
@@ -0,0 +1,404 @@
+//! A cursor over an output buffer for building frames from several parts, with length
+//! prefixes patched in once their section is written and checkpoints for abandoning a
+//! section that turns out not to fit.
+//!
+//! Length prefixes and checkpoints are both sections, and sections nest: each must be
+//! ended, committed or rolled back before any section opened before it. A rollback
+//! discards every section opened since its checkpoint, and ending a discarded length
+//! prefix fails with `InvalidData` instead of patching bytes that now belong to
+//! something else.
+//!
+//! `UninitWriter` appends to a buffer that may not be initialized yet, initializing only
+//! the bytes it writes; derived types implement `Serialize::serialize_uninit` with it.
+
+use core::mem::MaybeUninit;
+
+use crate::{assume_init_bytes, Serialize, SerializeError};
+
+/// A section opened by `ByteWriter` and not yet closed
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    id: u32,
+    start: usize,
+}
+
+/// Reserves a big-endian `u16` for the length of the bytes written after it,
+/// filled in by `ByteWriter::end_len_prefix`.
+#[derive(Debug)]
+#[must_use]
+pub struct LenPrefix {
+    section: Section,
+}
+
+/// A position `ByteWriter::rollback` can return to.
+#[derive(Debug)]
+#[must_use]
+pub struct Checkpoint {
+    section: Section,
+}
+
+/// Writes values and raw bytes one after another into a buffer.
+/// `DEPTH` bounds how many sections can be open at once; opening one more fails with
+/// `InvalidData`.
+pub struct ByteWriter<'a, const DEPTH: usize = 8> {
+    buf: &'a mut [u8],
+    len: usize,
+    open: [Section; DEPTH],
+    depth: usize,
+    next_id: u32,
+}
+
+impl<'a, const DEPTH: usize> ByteWriter<'a, DEPTH> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        ByteWriter {
+            buf,
+            len: 0,
+            open: [Section { id: 0, start: 0 }; DEPTH],
+            depth: 0,
+            next_id: 0,
+        }
+    }
+
+    /// Number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bytes written so far
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns the written bytes.
+    /// Sections still open are left as they are; an unpatched length prefix reads as 0.
+    pub fn finish(self) -> &'a [u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Appends the serialized form of `value`.
+    /// Returns the number of bytes written.
+    pub fn write<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<usize, SerializeError> {
+        let rest = self.buf.get_mut(self.len..).ok_or(SerializeError::BufferTooSmall)?;
+        let size = value.serialize(rest)?;
+        self.len += size;
+        Ok(size)
+    }
+
+    /// Appends `bytes` verbatim.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerializeError> {
+        let end = self.len + bytes.len();
+        let dst = self.buf.get_mut(self.len..end).ok_or(SerializeError::BufferTooSmall)?;
+        dst.copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Reserves a `u16` length prefix for the bytes written until `end_len_prefix`.
+    pub fn begin_len_prefix(&mut self) -> Result<LenPrefix, SerializeError> {
+        let start = self.len;
+        self.write_bytes(&[0, 0])?;
+        match self.open_section(start) {
+            Ok(section) => Ok(LenPrefix { section }),
+            Err(err) => {
+                self.len = start;
+                Err(err)
+            }
+        }
+    }
+
+    /// Writes the number of bytes written since `prefix` was reserved into it.
+    /// Fails with `InvalidData` if `prefix` is not the innermost open section, which
+    /// includes a prefix discarded by a rollback, or if the length exceeds `u16::MAX`.
+    pub fn end_len_prefix(&mut self, prefix: LenPrefix) -> Result<(), SerializeError> {
+        self.check_innermost(&prefix.section)?;
+        let start = prefix.section.start;
+        let len = u16::try_from(self.len - (start + 2)).map_err(|_| SerializeError::InvalidData)?;
+        len.serialize(&mut self.buf[start..])?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Marks the current position, to be returned to by `rollback` or kept by `commit`.
+    pub fn checkpoint(&mut self) -> Result<Checkpoint, SerializeError> {
+        self.open_section(self.len).map(|section| Checkpoint { section })
+    }
+
+    /// Keeps everything written since `checkpoint`. Writes nothing; it closes the section
+    /// so that nesting is checked the same way as for a rollback.
+    /// Fails with `InvalidData` if `checkpoint` is not the innermost open section.
+    pub fn commit(&mut self, checkpoint: Checkpoint) -> Result<(), SerializeError> {
+        self.check_innermost(&checkpoint.section)?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Discards everything written since `checkpoint`, together with every section
+    /// opened since. The discarded bytes are left in the buffer.
+    /// Fails with `InvalidData` if `checkpoint` is no longer open.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) -> Result<(), SerializeError> {
+        let index = self.open[..self.depth]
+            .iter()
+            .rposition(|open| open.id == checkpoint.section.id)
+            .ok_or(SerializeError::InvalidData)?;
+        self.depth = index;
+        self.len = checkpoint.section.start;
+        Ok(())
+    }
+
+    /// Same as `rollback`, also zeroing the discarded bytes.
+    pub fn rollback_zeroed(&mut self, checkpoint: Checkpoint) -> Result<(), SerializeError> {
+        let end = self.len;
+        let start = checkpoint.section.start;
+        self.rollback(checkpoint)?;
+        self.buf[start..end].fill(0);
+        Ok(())
+    }
+
+    fn open_section(&mut self, start: usize) -> Result<Section, SerializeError> {
+        let slot = self.open.get_mut(self.depth).ok_or(SerializeError::InvalidData)?;
+        let section = Section {
+            id: self.next_id,
+            start,
+        };
+        *slot = section;
+        self.depth += 1;
+        self.next_id = self.next_id.wrapping_add(1);
+        Ok(section)
+    }
+
+    /// Fails with `InvalidData` unless `section` is the innermost open section.
+    fn check_innermost(&self, section: &Section) -> Result<(), SerializeError> {
+        match self.depth.checked_sub(1).map(|top| self.open[top].id) {
+            Some(id) if id == section.id => Ok(()),
+            _ => Err(SerializeError::InvalidData),
+        }
+    }
+}
+
+/// Writes values one after another into a buffer that may not be initialized yet.
+/// Only the bytes written are initialized; the rest of the buffer is never touched.
+pub struct UninitWriter<'a> {
+    buf: &'a mut [MaybeUninit<u8>],
+    /// Length of the initialized prefix of `buf`
+    len: usize,
+}
+
+impl<'a> UninitWriter<'a> {
+    pub fn new(buf: &'a mut [MaybeUninit<u8>]) -> Self {
+        UninitWriter { buf, len: 0 }
+    }
+
+    /// Number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bytes written so far
+    pub fn written(&self) -> &[u8] {
+        let written = &self.buf[..self.len];
+        // SAFETY: `buf[..len]` is initialized.
+        unsafe { &*(written as *const [MaybeUninit<u8>] as *const [u8]) }
+    }
+
+    /// Returns the written bytes.
+    pub fn finish(self) -> &'a mut [u8] {
+        // SAFETY: `buf[..len]` is initialized.
+        unsafe { assume_init_bytes(&mut self.buf[..self.len]) }
+    }
+
+    /// Appends the serialized form of `value` through its `serialize_uninit`.
+    /// Fails with `InvalidData` if that returns anything but a prefix of the space it was given.
+    pub fn write<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<usize, SerializeError> {
+        self.write_with(|rest| value.serialize_uninit(rest))
+    }
+
+    /// Appends the bytes `f` initializes at the front of the space after those written,
+    /// returning them. Fails with `InvalidData` if `f` returns anything but such a prefix.
+    pub fn write_with<F>(&mut self, f: F) -> Result<usize, SerializeError>
+    where
+        F: for<'b> FnOnce(&'b mut [MaybeUninit<u8>]) -> Result<&'b mut [u8], SerializeError>,
+    {
+        let rest = self.buf.get_mut(self.len..).ok_or(SerializeError::BufferTooSmall)?;
+        let (start, capacity) = (rest.as_ptr() as *const u8, rest.len());
+        let written = f(rest)?;
+        // Bytes behind a `&mut [u8]` are initialized, so a prefix of `rest` returned as one is.
+        if !core::ptr::eq(written.as_ptr(), start) || written.len() > capacity {
+            return Err(SerializeError::InvalidData);
+        }
+        self.len += written.len();
+        Ok(written.len())
+    }
+
+    /// Zeroes the next `len` bytes and appends the first bytes of them that `f` reports
+    /// writing, for encoders that only write to initialized buffers.
+    pub fn write_zeroed<F>(&mut self, len: usize, f: F) -> Result<usize, SerializeError>
+    where
+        F: FnOnce(&mut [u8]) -> Result<usize, SerializeError>,
+    {
+        let end = self.len.checked_add(len).ok_or(SerializeError::BufferTooSmall)?;
+        let dst = self.buf.get_mut(self.len..end).ok_or(SerializeError::BufferTooSmall)?;
+        for byte in dst.iter_mut() {
+            byte.write(0);
+        }
+        // SAFETY: every byte of `dst` was written above.
+        let dst = unsafe { assume_init_bytes(dst) };
+        let size = f(dst)?;
+        if size > len {
+            return Err(SerializeError::InvalidData);
+        }
+        self.len += size;
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a slice that is not the front of the buffer it was given.
+    struct Misplaced;
+
+    impl Serialize for Misplaced {
+        fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+            buf.get_mut(..1).ok_or(SerializeError::BufferTooSmall)?.fill(0);
+            Ok(1)
+        }
+
+        fn serialize_uninit<'a>(&self, buf: &'a mut [MaybeUninit<u8>]) -> Result<&'a mut [u8], SerializeError> {
+            let tail = buf.get_mut(1..2).ok_or(SerializeError::BufferTooSmall)?;
+            tail[0].write(0);
+            // SAFETY: the byte was written above.
+            Ok(unsafe { assume_init_bytes(tail) })
+        }
+
+        fn serialized_size(&self) -> usize {
+            1
+        }
+    }
+
+    /// Writes a header and three length-prefixed sections, rolling back the middle one
+    /// if `abandon_middle` is set, or leaving it out entirely if `include_middle` is not.
+    fn frame(buf: &mut [u8], include_middle: bool, abandon_middle: bool) -> usize {
+        let mut writer = ByteWriter::<4>::new(buf);
+        writer.write(&0xA5u8).unwrap();
+        for (index, section) in [[1u8, 2, 3], [4, 5, 6], [7, 8, 9]].iter().enumerate() {
+            if index == 1 && !include_middle {
+                continue;
+            }
+            let checkpoint = writer.checkpoint().unwrap();
+            let prefix = writer.begin_len_prefix().unwrap();
+            writer.write(section).unwrap();
+            if index == 1 && abandon_middle {
+                writer.rollback(checkpoint).unwrap();
+                // The prefix was discarded with its section.
+                assert!(matches!(writer.end_len_prefix(prefix), Err(SerializeError::InvalidData)));
+                continue;
+            }
+            writer.end_len_prefix(prefix).unwrap();
+            writer.commit(checkpoint).unwrap();
+        }
+        writer.len()
+    }
+
+    #[test]
+    fn rolled_back_section_leaves_no_trace() {
+        let mut rolled_back = [0u8; 32];
+        let size = frame(&mut rolled_back, true, true);
+        let mut without = [0u8; 32];
+        assert_eq!(frame(&mut without, false, false), size);
+        assert_eq!(rolled_back[..size], without[..size]);
+        assert_eq!(rolled_back[..size], [0xA5, 0, 3, 1, 2, 3, 0, 3, 7, 8, 9]);
+
+        let mut full = [0u8; 32];
+        assert_eq!(frame(&mut full, true, false), 16);
+    }
+
+    #[test]
+    fn rollback_zeroed_clears_the_discarded_bytes() {
+        let mut buf = [0u8; 8];
+        let mut writer = ByteWriter::<2>::new(&mut buf);
+        writer.write(&1u8).unwrap();
+        let checkpoint = writer.checkpoint().unwrap();
+        writer.write(&0xFFFF_FFFFu32).unwrap();
+        writer.rollback_zeroed(checkpoint).unwrap();
+        assert_eq!(writer.written(), [1]);
+        assert_eq!(buf, [1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn sections_must_close_innermost_first() {
+        let mut buf = [0u8; 8];
+        let mut writer = ByteWriter::<2>::new(&mut buf);
+        let outer = writer.checkpoint().unwrap();
+        let inner = writer.begin_len_prefix().unwrap();
+        assert!(matches!(writer.commit(outer), Err(SerializeError::InvalidData)));
+        // Opening a third section exceeds `DEPTH` and writes nothing.
+        assert!(matches!(writer.begin_len_prefix(), Err(SerializeError::InvalidData)));
+        assert_eq!(writer.len(), 2);
+        writer.end_len_prefix(inner).unwrap();
+        assert!(writer.checkpoint().is_ok());
+    }
+
+    #[test]
+    fn uninit_writer_initializes_only_what_it_writes() {
+        let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+        let mut writer = UninitWriter::new(&mut buf);
+        writer.write(&0x0102u16).unwrap();
+        writer.write(&[3u8, 4]).unwrap();
+        assert_eq!(writer.written(), [1, 2, 3, 4]);
+        writer.write_zeroed(3, |bytes| {
+            bytes[0] = 5;
+            Ok(1)
+        })
+        .unwrap();
+        assert_eq!(writer.len(), 5);
+        assert_eq!(writer.finish(), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn uninit_writer_rejects_misplaced_output() {
+        let mut buf = [MaybeUninit::<u8>::uninit(); 4];
+        let mut writer = UninitWriter::new(&mut buf);
+        assert!(matches!(writer.write(&Misplaced), Err(SerializeError::InvalidData)));
+        assert!(writer.is_empty());
+        assert!(matches!(writer.write_zeroed(2, |_| Ok(3)), Err(SerializeError::InvalidData)));
+        assert!(matches!(writer.write_zeroed(5, |_| Ok(0)), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn uninit_fast_paths_match_serialize() {
+        let mut buf = [MaybeUninit::<u8>::uninit(); 16];
+        assert_eq!(0x0102_0304u32.serialize_uninit(&mut buf).unwrap(), [1, 2, 3, 4]);
+        assert_eq!([0x0102u16, 0x0304].serialize_uninit(&mut buf).unwrap(), [1, 2, 3, 4]);
+        assert_eq!(Some(7u8).serialize_uninit(&mut buf).unwrap(), [1, 7]);
+        let mut short = [MaybeUninit::<u8>::uninit(); 3];
+        assert!(matches!(7u32.serialize_uninit(&mut short), Err(SerializeError::BufferTooSmall)));
+    }
+
+    #[test]
+    fn position_is_kept_across_writes() {
+        let mut buf = [0u8; 16];
+        let mut writer = ByteWriter::<1>::new(&mut buf);
+        let sizes = [
+            writer.write(&0xA5u8).unwrap(),
+            writer.write(&0x0102_0304u32).unwrap(),
+            writer.write(&[0x0506u16, 0x0708]).unwrap(),
+        ];
+        assert_eq!(sizes, [1, 4, 4]);
+        assert_eq!(writer.len(), sizes.iter().sum::<usize>());
+        assert_eq!(writer.written(), [0xA5, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // A failed write leaves the position where it was.
+        assert!(matches!(writer.write(&[0u8; 8]), Err(SerializeError::BufferTooSmall)));
+        assert_eq!(writer.len(), 9);
+        assert_eq!(writer.finish().len(), 9);
+    }
+}
@@ -0,0 +1,66 @@
+//! Round-trips a representative message under whatever features the crate was built
+//! with. `scripts/check-features.sh` runs it for every feature combination.
+
+use embedded_serialize::{Deserialize, Serialize};
+use embedded_serialize_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Mode {
+    Idle,
+    Sampling { rate: u16 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Telemetry {
+    node: u8,
+    temperature: i16,
+    uptime: u64,
+    valid: bool,
+    samples: [u16; 3],
+    limit: Option<u32>,
+    mode: Mode,
+    ratio: f32,
+}
+
+#[test]
+fn telemetry_round_trip() {
+    let telemetry = Telemetry {
+        node: 7,
+        temperature: -120,
+        uptime: 1 << 40,
+        valid: true,
+        samples: [1, 2, 3],
+        limit: Some(500),
+        mode: Mode::Sampling { rate: 100 },
+        ratio: 0.25,
+    };
+    let mut buf = [0u8; 64];
+    let size = telemetry.serialize(&mut buf).unwrap();
+    assert_eq!(size, 1 + 2 + 8 + 1 + 6 + 5 + 3 + 4);
+    assert_eq!(Telemetry::deserialize_consumed(&buf).unwrap(), (telemetry, size));
+}
+
+#[cfg(feature = "heapless")]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Batch {
+    len: u8,
+    #[es(count = "len")]
+    items: heapless::Vec<u16, 4>,
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn heapless_vec_round_trip() {
+    let batch = Batch { len: 2, items: heapless::Vec::from_slice(&[1, 2]).unwrap() };
+    let mut buf = [0u8; 16];
+    let size = batch.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [2, 0, 1, 0, 2]);
+    assert_eq!(Batch::deserialize(&buf[..size]).unwrap(), batch);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn std_vec_round_trip() {
+    let bytes = embedded_serialize::io::to_std_vec(&[1u16, 2, 3]).unwrap();
+    assert_eq!(<[u16; 3]>::deserialize(&bytes).unwrap(), [1, 2, 3]);
+}
@@ -0,0 +1,63 @@
+//! Overrun detection by the `paranoid` feature, and output identical to the default
+//! build when nothing overruns. `scripts/check-features.sh` runs it with and without
+//! the feature.
+
+use embedded_serialize::{serialize_raw, Serialize, SerializeError};
+use embedded_serialize_derive::Serialize;
+
+/// Writes two bytes but reports one, as a buggy hand-written impl might
+struct Scribbler;
+
+impl Serialize for Scribbler {
+    fn serialize(&self, buf: &mut [u8]) -> Result<usize, SerializeError> {
+        buf.get_mut(..2).ok_or(SerializeError::BufferTooSmall)?.copy_from_slice(&[0x11, 0x22]);
+        Ok(1)
+    }
+
+    fn serialized_size(&self) -> usize {
+        1
+    }
+}
+
+#[derive(Serialize)]
+struct Packet {
+    id: u8,
+    payload: Scribbler,
+    crc: u16,
+}
+
+#[derive(Serialize)]
+struct Clean {
+    id: u8,
+    values: [u16; 2],
+    flag: bool,
+}
+
+#[cfg(feature = "paranoid")]
+#[test]
+fn overrun_is_caught_with_the_field_index() {
+    let mut buf = [0u8; 16];
+    let packet = Packet { id: 1, payload: Scribbler, crc: 0xBEEF };
+    assert!(matches!(packet.serialize(&mut buf), Err(SerializeError::OverrunAt(1))));
+    assert!(matches!(serialize_raw(&[Scribbler, Scribbler], &mut buf), Err(SerializeError::OverrunAt(0))));
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn overrun_goes_unnoticed_without_the_feature() {
+    let mut buf = [0u8; 16];
+    let packet = Packet { id: 1, payload: Scribbler, crc: 0xBEEF };
+    // The CRC overwrites the stray byte, hiding the bug.
+    assert_eq!(packet.serialize(&mut buf).unwrap(), 4);
+    assert_eq!(buf[..4], [1, 0x11, 0xBE, 0xEF]);
+    assert_eq!(serialize_raw(&[Scribbler, Scribbler], &mut buf).unwrap(), 2);
+}
+
+#[test]
+fn clean_output_matches_the_default_build() {
+    let mut buf = [0u8; 16];
+    let size = Clean { id: 7, values: [0x0102, 0x0304], flag: true }.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..size], [7, 1, 2, 3, 4, 1]);
+    let size = serialize_raw(&[0x0506u16, 0x0708], &mut buf).unwrap();
+    assert_eq!(buf[..size], [5, 6, 7, 8]);
+}
@@ -0,0 +1,57 @@
+#!/bin/sh
+# Builds and lints embedded-serialize under each meaningful feature combination, and
+# round-trips a representative message (tests/features.rs), checks overrun detection
+# (tests/paranoid.rs) and runs the derive crate's tests under each.
+# Run from anywhere in the repository; exits non-zero on the first broken combination.
+set -eu
+
+cd "$(dirname "$0")/.."
+
+check() {
+    echo "== features: ${1:-<none>} $2"
+    cargo clippy --quiet -p embedded-serialize --all-targets $2 --features "$1" -- -D warnings
+    cargo test --quiet -p embedded-serialize --test features --test paranoid $2 --features "$1"
+    derive_tests "$1" "$2"
+}
+
+# The derive crate depends on embedded-serialize with its default features, so
+# `--no-default-features` cannot take them away here; the listed features are added.
+derive_tests() {
+    if [ "$2" = --all-features ]; then
+        set -- "$ALL_FEATURES" --all-features
+    fi
+    derive_features=""
+    for feature in $1; do
+        derive_features="$derive_features embedded-serialize/$feature"
+    done
+    cargo test --quiet -p embedded-serialize-derive $2 --features "$derive_features"
+}
+
+ALL_FEATURES="esp32 arduino32 cbor std heapless flash codegen paranoid type-names portable-atomic"
+
+for features in "" esp32 arduino32 "esp32 arduino32"; do
+    check "$features" --no-default-features
+done
+
+for features in heapless std cbor flash codegen paranoid type-names portable-atomic "heapless std" "std codegen" "heapless flash"; do
+    check "$features" --no-default-features
+    check "$features" ""
+done
+
+check "" --all-features
+
+# The frame pool, including its two-thread test, on portable-atomic's types.
+echo "== pool: portable-atomic std"
+cargo test --quiet -p embedded-serialize --lib --features "portable-atomic std" pool::
+
+# Cortex-M0+ has no compare-and-swap, so the frame pool needs portable-atomic there and
+# is left out without it.
+if rustup target list --installed 2>/dev/null | grep -qx thumbv6m-none-eabi; then
+    echo "== target: thumbv6m-none-eabi"
+    cargo build --quiet -p embedded-serialize --target thumbv6m-none-eabi
+    echo "== target: thumbv6m-none-eabi portable-atomic"
+    RUSTFLAGS="--cfg portable_atomic_unsafe_assume_single_core" \
+        cargo build --quiet -p embedded-serialize --target thumbv6m-none-eabi --features portable-atomic
+fi
+cargo clippy --quiet -p embedded-serialize-derive --all-targets --all-features -- -D warnings
+cargo build --quiet --workspace
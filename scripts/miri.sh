@@ -0,0 +1,9 @@
+#!/bin/sh
+# Runs the tests covering unsafe code under Miri, which fails on any read of uninitialized
+# memory, double drop or leak. Needs a nightly toolchain with the miri component.
+set -eu
+
+cd "$(dirname "$0")/.."
+
+cargo +nightly miri test -p embedded-serialize --lib
+cargo +nightly miri test -p embedded-serialize-derive --test uninit --test layout